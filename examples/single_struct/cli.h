@@ -1,6 +1,11 @@
+// Generated by cli-generator 0.1.0. Do not edit by hand.
+// Spec: examples/single_struct/cli.in
+// Spec hash: fnv1a-64:acee368d9ee4d334
+
 #ifndef _CLI_H_
 #define _CLI_H_
 
+#include <cctype>
 #include <cstdint>
 #include <cstdlib>
 #include <cstring>
@@ -8,6 +13,28 @@
 #include <cerrno>
 #include <string>
 #include <vector>
+#include <charconv>
+
+inline bool equals_ignore_case(const char* lhs, const char* rhs) {
+    for (; *lhs != '\0' && *rhs != '\0'; ++lhs, ++rhs) {
+        if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {
+            return false;
+        }
+    }
+    return *lhs == *rhs;
+}
+
+inline bool parse_bool_value(const char* value, bool* res) {
+    if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {
+        *res = true;
+        return true;
+    }
+    if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {
+        *res = false;
+        return true;
+    }
+    return false;
+}
 
 struct Cli {
     bool some;
@@ -26,7 +53,7 @@ struct Cli {
         printf("\tstr: %s\n", this->str.c_str());
         printf("\tmany_values: [\n");
         for (size_t i = 0; i != this->many_values.size(); ++i) {
-            printf("\t%d,\n", this->many_values[i]);
+            printf("\t%u,\n", this->many_values[i]);
         }
         printf("\t]\n");
         printf("}\n");
@@ -55,6 +82,7 @@ struct Cli {
             "--verbose",
             "-p",
             "--param",
+            "--omg",
             "-f",
             "--float-value",
             "--str",
@@ -62,7 +90,7 @@ struct Cli {
             "--many-values",
         };
 
-        for (size_t i = 0; i != 11; ++i) {
+        for (size_t i = 0; i != 12; ++i) {
             if (strcmp(arg, valid_options[i]) == 0) {
                 return true;
             }
@@ -80,15 +108,31 @@ struct Cli {
 
         Cli res = {};
         for (int i = 0; i != argc; ++i, ++args) {
-            char *arg = args[0];
+            const char *arg = args[0];
             if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {
                 Cli::help();
-            } else if (strcmp(arg, "-s") == 0 || strcmp(arg, "--some") == 0) {
+            } else if (strcmp(arg, "-s") == 0 || strncmp(arg, "-s=", 3) == 0 || strcmp(arg, "--some") == 0 || strncmp(arg, "--some=", 7) == 0) {
                 bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
                 res.some = arg_res;
                 mandatory_fields_seen[0] = true;
-            } else if (strcmp(arg, "-v") == 0 || strcmp(arg, "--verbose") == 0) {
+            } else if (strcmp(arg, "-v") == 0 || strncmp(arg, "-v=", 3) == 0 || strcmp(arg, "--verbose") == 0 || strncmp(arg, "--verbose=", 10) == 0) {
                 bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
                 res.verbose = arg_res;
                 mandatory_fields_seen[1] = true;
             } else if (strcmp(arg, "-p") == 0 || strcmp(arg, "--param") == 0 || strcmp(arg, "--omg") == 0) {
@@ -99,13 +143,15 @@ struct Cli {
                     exit(1);
                 }
                 char* arg_value = args[0];
-                int16_t arg_res = static_cast<int16_t>(std::strtoll(arg_value, nullptr, 10));
+                errno = 0;
+                long long arg_number = std::strtoll(arg_value, nullptr, 10);
+                int16_t arg_res = static_cast<int16_t>(arg_number);
 
-                if (errno == ERANGE) {
+                if (errno == ERANGE || arg_number < INT16_MIN || arg_number > INT16_MAX) {
                     printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
                     exit(1);
                 }
-                if (arg_res == 0 && strcmp(arg, "0") != 0) {
+                if (arg_res == 0 && strcmp(arg_value, "0") != 0) {
                     printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
                     exit(1);
                 }
@@ -119,14 +165,15 @@ struct Cli {
                     exit(1);
                 }
                 char* arg_value = args[0];
-                float arg_res = static_cast<float>(std::strtof(arg_value, nullptr));
+                float arg_res = 0;
+                std::from_chars_result conversion = std::from_chars(arg_value, arg_value + strlen(arg_value), arg_res);
 
-                if (errno == ERANGE) {
-                    printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
+                if (conversion.ec == std::errc::result_out_of_range) {
+                    printf("Value '%s' of option '%s' out of range for floating point type", arg_value, arg);
                     exit(1);
                 }
-                if (arg_res == 0 && strcmp(arg, "0") != 0) {
-                    printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
+                if (conversion.ec != std::errc() || *conversion.ptr != '\0') {
+                    printf("Value '%s' of option '%s' is not a valid floating point number", arg_value, arg);
                     exit(1);
                 }
                 res.float_value = arg_res;
@@ -149,13 +196,15 @@ struct Cli {
                     exit(1);
                 }
                 char* arg_value = args[0];
-                uint32_t arg_res = static_cast<uint32_t>(std::strtoll(arg_value, nullptr, 10));
+                errno = 0;
+                long long arg_number = std::strtoll(arg_value, nullptr, 10);
+                uint32_t arg_res = static_cast<uint32_t>(arg_number);
 
-                if (errno == ERANGE) {
+                if (errno == ERANGE || arg_number < 0 || arg_number > UINT32_MAX) {
                     printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
                     exit(1);
                 }
-                if (arg_res == 0 && strcmp(arg, "0") != 0) {
+                if (arg_res == 0 && strcmp(arg_value, "0") != 0) {
                     printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
                     exit(1);
                 }
@@ -0,0 +1,422 @@
+//! Specs built from code rather than parsed, for programs that already know their options, e.g.
+//! a build script turning its own configuration into a CLI:
+//!
+//! ```
+//! use cli_generator::{check, generate, FieldType, GenOptions, SpecBuilder, Target};
+//!
+//! let spec = SpecBuilder::new()
+//!     .strukt("Args", |s| {
+//!         s.main()
+//!             .field("output", FieldType::String, |f| f.long().short('o'))
+//!     })
+//!     .build()
+//!     .unwrap();
+//! let metadata = check(&spec).unwrap();
+//! let files = generate(&spec, &metadata, Target::Cpp, &GenOptions::default()).unwrap();
+//! assert!(files[0].contents.contains("struct Args"));
+//! ```
+//!
+//! A built spec goes through the same checks and backends as a parsed one. It has no source for
+//! its diagnostics to show, so its spans point into a line describing each struct, field and
+//! attribute instead, e.g. `struct Args, field output`, and its diagnostics are rendered with that
+//! description.
+
+use std::borrow::Cow;
+
+use logos::Span;
+
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::parse::{resolve_field_attributes, Parser};
+use crate::types::{Attribute, AttributeType, Field, FieldType, Spec, Struct};
+
+/// Builds a [`Spec`] struct by struct, in declaration order.
+#[derive(Debug, Default, Clone)]
+pub struct SpecBuilder {
+    structs: Vec<StructBuilder>,
+}
+
+/// Builds a struct of a [`SpecBuilder`], field by field.
+#[derive(Debug, Clone)]
+pub struct StructBuilder {
+    name: String,
+    attributes: Vec<(AttributeType, Option<String>)>,
+    fields: Vec<FieldBuilder>,
+}
+
+/// Builds a field of a [`StructBuilder`].
+#[derive(Debug, Clone)]
+pub struct FieldBuilder {
+    name: String,
+    ty: FieldType,
+    attributes: Vec<(AttributeType, Option<String>)>,
+}
+
+impl SpecBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the struct `name`, built by `build`.
+    pub fn strukt(
+        mut self,
+        name: &str,
+        build: impl FnOnce(StructBuilder) -> StructBuilder,
+    ) -> Self {
+        self.structs.push(build(StructBuilder {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            fields: Vec::new(),
+        }));
+        self
+    }
+
+    /// The spec, or diagnostics about the names and attribute values that couldn't be written in
+    /// a spec. Whether the spec is valid is left to [`check`](crate::check), like for a parsed one.
+    pub fn build(self) -> Result<Spec<'static>, Diagnostics> {
+        let mut source = Source::default();
+        let mut errors = Vec::new();
+        let mut structs = Vec::new();
+
+        for strukt in self.structs {
+            let location = format!("struct {}", strukt.name);
+            let name_span = source.line(&location);
+            if !is_struct_name(&strukt.name) {
+                errors.push(invalid_name(&name_span, "struct", &strukt.name));
+            }
+
+            let attributes = source.attributes(&location, strukt.attributes, &mut errors);
+            let attributes_span = match (attributes.first(), attributes.last()) {
+                (Some(first), Some(last)) => Some(first.span.start..last.span.end),
+                _ => None,
+            };
+            let body_start = source.text.len();
+
+            let mut fields = Vec::new();
+            for field in strukt.fields {
+                let location = format!("{location}, field {}", field.name);
+                let name_span = source.line(&location);
+                if !is_field_name(&field.name) {
+                    errors.push(invalid_name(&name_span, "field", &field.name));
+                }
+                let type_span = source.line(&format!("{location}, type {}", field.ty));
+                let attributes = source.attributes(&location, field.attributes, &mut errors);
+
+                fields.push(Field {
+                    attributes: resolve_field_attributes(&field.name, attributes),
+                    name: field.name,
+                    ty: field.ty,
+                    span: name_span.start..source.text.len(),
+                    name_span,
+                    type_span,
+                });
+            }
+
+            structs.push(Struct {
+                attributes,
+                fields,
+                name: strukt.name,
+                name_span,
+                attributes_span,
+                body_span: body_start..source.text.len(),
+            });
+        }
+
+        if !errors.is_empty() {
+            return Err(Diagnostics::new(
+                errors
+                    .into_iter()
+                    .map(|error| error.locate(&source.text))
+                    .collect(),
+            ));
+        }
+
+        Ok(Spec {
+            structs,
+            source: Cow::Owned(source.text),
+            format_directive: None,
+            is_built: true,
+        })
+    }
+}
+
+impl StructBuilder {
+    /// Adds the field `name` of type `ty`, built by `build`.
+    pub fn field(
+        mut self,
+        name: &str,
+        ty: FieldType,
+        build: impl FnOnce(FieldBuilder) -> FieldBuilder,
+    ) -> Self {
+        self.fields.push(build(FieldBuilder {
+            name: name.to_string(),
+            ty,
+            attributes: Vec::new(),
+        }));
+        self
+    }
+
+    /// Adds the attribute `ty`, with its value written as in a spec, e.g. `Some("\"upper\"")` for
+    /// `env_case = "upper"` or `Some("2")` for `exit_code = 2`.
+    pub fn attribute(mut self, ty: AttributeType, value: Option<&str>) -> Self {
+        self.attributes.push((ty, value.map(String::from)));
+        self
+    }
+
+    /// Makes the struct the one the command line is parsed into.
+    pub fn main(self) -> Self {
+        self.attribute(AttributeType::Main, None)
+    }
+
+    /// Makes the struct a subcommand of the structs it's a field of.
+    pub fn subcommand(self) -> Self {
+        self.attribute(AttributeType::SubCommand, None)
+    }
+
+    /// Sets the description of the program printed by `--help`.
+    pub fn about(self, about: &str) -> Self {
+        self.attribute(AttributeType::About, Some(&quote(about)))
+    }
+}
+
+impl FieldBuilder {
+    /// Adds the attribute `ty`, with its value written as in a spec, e.g. `Some("\"PORT\"")` for
+    /// `env = "PORT"` or `Some("1")` for `min = 1`.
+    pub fn attribute(mut self, ty: AttributeType, value: Option<&str>) -> Self {
+        self.attributes.push((ty, value.map(String::from)));
+        self
+    }
+
+    /// Gives the field a long option named after it.
+    pub fn long(self) -> Self {
+        self.attribute(AttributeType::Long, None)
+    }
+
+    /// Gives the field the short option `-letter`.
+    pub fn short(self, letter: char) -> Self {
+        self.attribute(AttributeType::Short, Some(&letter.to_string()))
+    }
+
+    /// Gives the field another long option, `--alias`.
+    pub fn alias(self, alias: &str) -> Self {
+        self.attribute(AttributeType::Alias, Some(alias))
+    }
+
+    /// Sets the help of the field.
+    pub fn doc(self, doc: &str) -> Self {
+        self.attribute(AttributeType::Doc, Some(&quote(doc)))
+    }
+
+    /// Lets the field be left out, leaving it zero or empty.
+    pub fn default(self) -> Self {
+        self.attribute(AttributeType::Default, None)
+    }
+
+    /// Lets the field be left out, giving it `value`.
+    pub fn default_value_t(self, value: &str) -> Self {
+        self.attribute(AttributeType::DefaultValueT, Some(&quote(value)))
+    }
+
+    /// Makes the options of the struct the field is of options of the field's struct.
+    pub fn flatten(self) -> Self {
+        self.attribute(AttributeType::Flatten, None)
+    }
+}
+
+/// The text the spans of a built spec point into, a line describing each struct, field and
+/// attribute.
+#[derive(Default)]
+struct Source {
+    text: String,
+}
+
+impl Source {
+    /// Adds the line `description`, returning its span.
+    fn line(&mut self, description: &str) -> Span {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        let start = self.text.len();
+        self.text.push_str(description);
+
+        start..self.text.len()
+    }
+
+    /// The attributes of the struct or field at `location`, parsed from their value the way they
+    /// would be in a spec. The ones that can't be are reported in `errors` instead.
+    fn attributes(
+        &mut self,
+        location: &str,
+        attributes: Vec<(AttributeType, Option<String>)>,
+        errors: &mut Vec<Diagnostic>,
+    ) -> Vec<Attribute> {
+        let mut parsed = Vec::new();
+        for (ty, value) in attributes {
+            let literal = ty.to_literal();
+            let span = self.line(&format!("{location}, attribute {literal}"));
+            let written = match &value {
+                Some(value) => format!("{literal} = {value}"),
+                None => literal.to_string(),
+            };
+
+            match parse_attribute(&written, &span, &span) {
+                Ok(attribute) => parsed.push(attribute),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        parsed
+    }
+}
+
+/// Parses the attribute `written` as it is in a spec, e.g. `long = port`, giving it `span` and
+/// `value_span`. What keeps it from being parsed is reported at `value_span`, as it is most likely
+/// the value that is wrong.
+pub(crate) fn parse_attribute(
+    written: &str,
+    span: &Span,
+    value_span: &Span,
+) -> Result<Attribute, Diagnostic> {
+    let source = format!("#[{written}]\nstruct S {{}}\n");
+    let spec = Parser::new(&source).parse().map_err(|error| Diagnostic {
+        span: value_span.clone(),
+        info: None,
+        ..error.errors.into_iter().next().unwrap()
+    })?;
+
+    let mut attributes = spec.structs.into_iter().next().unwrap().attributes;
+    match (attributes.len(), attributes.pop()) {
+        (1, Some(attribute)) => Ok(Attribute {
+            span: span.clone(),
+            value_span: attribute.value_span.as_ref().map(|_| value_span.clone()),
+            ..attribute
+        }),
+        _ => Err(Diagnostic::new(
+            "Parser error",
+            value_span,
+            "Expected the value of a single attribute",
+        )),
+    }
+}
+
+/// The string literal of `text`, with the characters that can't be in one escaped.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+pub(crate) fn is_struct_name(name: &str) -> bool {
+    Parser::new(&format!("struct {name} {{}}\n"))
+        .parse()
+        .is_ok_and(|spec| spec.structs.len() == 1 && spec.structs[0].name == name)
+}
+
+pub(crate) fn is_field_name(name: &str) -> bool {
+    Parser::new(&format!("struct S {{\n    {name}: bool,\n}}\n"))
+        .parse()
+        .is_ok_and(|spec| {
+            spec.structs[0].fields.len() == 1 && spec.structs[0].fields[0].name == name
+        })
+}
+
+pub(crate) fn invalid_name(span: &Span, kind: &str, name: &str) -> Diagnostic {
+    Diagnostic::new(
+        "Parse error",
+        span,
+        &format!("`{name}` can't be the name of a {kind}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_point_at_a_description_of_what_they_are_about() {
+        let spec = SpecBuilder::new()
+            .strukt("Args", |s| {
+                s.main()
+                    .field("output", FieldType::String, |f| f.long().short('o'))
+            })
+            .build()
+            .unwrap();
+
+        let strukt = &spec.structs[0];
+        let field = &strukt.fields[0];
+        assert_eq!(&spec.source[strukt.name_span.clone()], "struct Args");
+        assert_eq!(
+            &spec.source[strukt.attributes[0].span.clone()],
+            "struct Args, attribute main"
+        );
+        assert_eq!(&spec.source[field.name_span()], "struct Args, field output");
+        assert_eq!(
+            &spec.source[field.type_span()],
+            "struct Args, field output, type string"
+        );
+        assert_eq!(
+            &spec.source[field.attributes[1].span.clone()],
+            "struct Args, field output, attribute short"
+        );
+        assert_eq!(field.long_value().as_deref(), Some("output"));
+        assert_eq!(field.short_value().as_deref(), Some("o"));
+    }
+
+    #[test]
+    fn values_are_parsed_like_in_a_spec() {
+        let spec = SpecBuilder::new()
+            .strukt("Args", |s| {
+                s.field("a", FieldType::U32, |f| {
+                    f.doc("Say \"hi\"\n")
+                        .attribute(AttributeType::ShortAndLong, Some("\"x/ex\""))
+                })
+            })
+            .build()
+            .unwrap();
+
+        let field = &spec.structs[0].fields[0];
+        assert_eq!(field.doc(), Some("Say \\\"hi\\\"\\n"));
+        assert_eq!(field.short_value().as_deref(), Some("x"));
+        assert_eq!(field.long_value().as_deref(), Some("ex"));
+    }
+
+    #[test]
+    fn values_that_cannot_be_written_in_a_spec_are_reported() {
+        let diagnostics = SpecBuilder::new()
+            .strukt("Args", |s| {
+                s.attribute(AttributeType::ExitCode, None).field(
+                    "not a name",
+                    FieldType::Bool,
+                    |f| f.attribute(AttributeType::Min, Some("\"1\"")),
+                )
+            })
+            .build()
+            .unwrap_err();
+
+        let rendered = diagnostics.render("");
+        assert_eq!(diagnostics.len(), 3, "{rendered}");
+        assert!(
+            rendered
+                .contains("in programmatically constructed spec, struct Args, attribute exit_code"),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("`not a name` can't be the name of a field"),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("struct Args, field not a name, attribute min"),
+            "{rendered}"
+        );
+    }
+}
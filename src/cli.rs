@@ -1,11 +1,223 @@
-use clap::Parser;
+use crate::log::Verbosity;
+use clap::{ArgGroup, Parser, Subcommand};
+use cli_generator::internal::completions::Shell;
+use cli_generator::internal::{ColorChoice, ErrorFormat, InputFormat, OutputFormat};
+use cli_generator::{CppStandard, LineEnding, Target, TargetOs};
 
 #[derive(Debug, Parser)]
+#[clap(
+    group(ArgGroup::new("outputs").args(["output", "out_dir"])),
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub(crate) struct Cli {
-    /// The input file to generate cli from
-    #[clap(short, long)]
-    pub input: String,
-    /// The output path to store the generated cli
-    #[clap(short, long)]
-    pub output: String,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    /// Only print the errors
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Also print how long every phase takes for every input, how many structs, fields and
+    /// options the spec has and how many bytes are written
+    #[clap(short, long, global = true)]
+    pub verbose: bool,
+    /// When to color the diagnostics. `auto` colors them when stderr is a terminal, unless the
+    /// `NO_COLOR` environment variable is set, or when `CLICOLOR_FORCE` is set
+    #[clap(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+    /// The input file to generate cli from, or `-` to read it from stdin. Can be given several
+    /// times to generate from several inputs at once
+    #[clap(
+        short,
+        long,
+        required_unless_present_any = ["cmake_helper", "list_targets", "list_emitters"]
+    )]
+    pub input: Vec<String>,
+    /// How the input is written. `json` reads a spec serialized as JSON, e.g. by a tool generating
+    /// specs or by `--emit-spec-json`, which is checked and generated from like a parsed one
+    #[clap(long, value_enum, default_value_t = InputFormat::Dsl)]
+    pub input_format: InputFormat,
+    /// The output path to store the generated cli. With `--target c` this is the header, and the
+    /// source is written next to it with a `.c` extension. `-` writes it to stdout instead. Given
+    /// once for every input, in the same order
+    #[clap(
+        short,
+        long,
+        required_unless_present_any = [
+            "cmake_helper",
+            "check",
+            "out_dir",
+            "emit_completions",
+            "emit_spec_json",
+            "emit_dot",
+            "dump_tokens",
+            "dump_ast",
+            "list_targets",
+            "list_emitters"
+        ],
+        requires = "input"
+    )]
+    pub output: Vec<String>,
+    /// The directory to store the generated cli of every input in, named after the input
+    #[clap(
+        long,
+        value_name = "DIR",
+        conflicts_with = "output",
+        requires = "input"
+    )]
+    pub out_dir: Option<String>,
+    /// The TOML file setting the options that are not given on the command line, with keys spelled
+    /// like the flags, e.g. `cpp-std = "c++20"`. Defaults to the `cli-generator.toml` next to the
+    /// first input, if there is one
+    #[clap(long, value_name = "PATH")]
+    pub config: Option<String>,
+    /// The language of the generated code
+    #[clap(long, value_enum, default_value_t = Target::Cpp)]
+    pub target: Target,
+    /// What to generate from the spec. The JSON Schema is written to the output as is, whatever the
+    /// target
+    #[clap(long, value_enum, default_value_t = OutputFormat::Code)]
+    pub output_format: OutputFormat,
+    /// The package of the generated Go code
+    #[clap(long, default_value = "main")]
+    pub go_package: String,
+    /// The C++ standard the generated code targets. Before C++17, `Optional` fields are a
+    /// `cli_optional` defined in the header rather than a `std::optional`
+    #[clap(long, value_enum, default_value_t = CppStandard::Cpp17)]
+    pub cpp_std: CppStandard,
+    /// The operating system the generated code targets
+    #[clap(long, value_enum, default_value_t = TargetOs::Unix)]
+    pub target_os: TargetOs,
+    /// Make the generated fields private and expose them through const getters
+    #[clap(long)]
+    pub accessors: bool,
+    /// Declare the generated `parse()` methods `noexcept`, for the structs without struct or
+    /// `parse_fn` fields
+    #[clap(long)]
+    pub cpp_noexcept: bool,
+    /// Document the generated structs, members and methods with Doxygen comments, taken from the
+    /// `about` and `doc` attributes
+    #[clap(long)]
+    pub emit_doxygen: bool,
+    /// Skip the validation of regex attributes, for targets without `<regex>` support
+    #[clap(long)]
+    pub no_regex_validation: bool,
+    /// Check that the values of the `directory` fields are existing directories, with
+    /// `std::filesystem` in the generated C++
+    #[clap(long)]
+    pub validate_directory: bool,
+    /// Only declare the methods of the C++ structs in the header, and define them in a source file
+    /// written next to it with a `.cpp` extension
+    #[clap(long)]
+    pub split_implementation: bool,
+    /// Generate a `to_command_line()` method for every C++ struct, rebuilding the options it
+    /// would be parsed from
+    #[clap(long)]
+    pub with_to_command_line: bool,
+    /// The number of spaces a level of indentation is made of in the generated code. Go code is
+    /// always indented with tabs, like gofmt does
+    #[clap(long, default_value_t = 4)]
+    pub indent: usize,
+    /// Indent the generated code with tabs instead of spaces
+    #[clap(long, conflicts_with = "indent")]
+    pub indent_tabs: bool,
+    /// The line ending of the generated code, except for Go which always ends lines with LF
+    #[clap(long, value_enum, default_value_t = LineEnding::Lf)]
+    pub line_ending: LineEnding,
+    /// How the errors found in the spec are printed to stderr. JSON diagnostics are printed as a
+    /// single array per input
+    #[clap(long, value_enum, default_value_t = ErrorFormat::Human)]
+    pub error_format: ErrorFormat,
+    /// Only check whether the output is up to date instead of writing it, failing if it is not.
+    /// Without an output, only the spec is checked. Exits with 2 on parse errors, 3 on semantic
+    /// errors and 4 on I/O errors
+    #[clap(long)]
+    pub check: bool,
+    /// Print a unified diff of the changes to the output
+    #[clap(long)]
+    pub diff: bool,
+    /// Leave the generation time out of the banner, for reproducible builds
+    #[clap(long)]
+    pub no_timestamp: bool,
+    /// Only check whether the output was generated from the current version of the input,
+    /// failing if it was not
+    #[clap(long, requires = "outputs")]
+    pub verify: bool,
+    /// Write a CMake include file defining a `generate_cli` function that runs this binary
+    #[clap(long, value_name = "PATH")]
+    pub cmake_helper: Option<String>,
+    /// Write a completion script for the given shell, completing the options of the main struct
+    #[clap(long, value_enum, requires_all = ["input", "completions_out"])]
+    pub emit_completions: Option<Shell>,
+    /// The path to store the completion script
+    #[clap(long, value_name = "PATH", requires = "emit_completions")]
+    pub completions_out: Option<String>,
+    /// The command the completion script completes, the lowercase name of the main struct by
+    /// default
+    #[clap(long, requires = "emit_completions")]
+    pub completions_command: Option<String>,
+    /// Write the parsed spec as JSON, with the spans of everything as lines and columns
+    #[clap(long, value_name = "PATH", requires = "input")]
+    pub emit_spec_json: Option<String>,
+    /// Write a Graphviz digraph of the structs and the struct typed fields relating them
+    #[clap(
+        long,
+        visible_alias = "dot-output",
+        value_name = "PATH",
+        requires = "input"
+    )]
+    pub emit_dot: Option<String>,
+    /// Print every token of the spec with its span and text, then exit without generating
+    /// anything
+    #[clap(long, requires = "input", conflicts_with = "dump_ast")]
+    pub dump_tokens: bool,
+    /// Print the structs, fields and attributes the spec is parsed into with their spans, then exit
+    /// without generating anything
+    #[clap(long, requires = "input")]
+    pub dump_ast: bool,
+    /// Write a C++ program testing the generated parser with command lines derived from the spec,
+    /// which includes the output by its name. It needs a POSIX system to run
+    #[clap(long, value_name = "PATH", requires = "outputs")]
+    pub emit_tests: Option<String>,
+    /// Write a CMake snippet that runs the generator again with the same arguments whenever the
+    /// spec changes
+    #[clap(long, value_name = "PATH", requires = "input")]
+    pub emit_cmake: Option<String>,
+    /// Print the name of every target language, one per line, then exit
+    #[clap(long, conflicts_with_all = ["input", "list_emitters"])]
+    pub list_targets: bool,
+    /// Print the name of every `--emit-*` output besides the generated code, one per line, then
+    /// exit
+    #[clap(long, conflicts_with = "input")]
+    pub list_emitters: bool,
+}
+
+impl Cli {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Rewrite a spec in the canonical style: one field per line, indented by 4 spaces, with the
+    /// attributes of a field on the line before it, `short`, `long` and `alias` first
+    Fmt {
+        /// The spec to format
+        file: String,
+        /// Print the formatted spec to stdout instead of rewriting the file
+        #[clap(long, conflicts_with = "check")]
+        stdout: bool,
+        /// Only check whether the spec is formatted, failing if it is not
+        #[clap(long)]
+        check: bool,
+    },
+    /// Run a language server over stdin and stdout, publishing the diagnostics of the specs opened
+    /// in an editor and listing their structs and fields for its outline
+    Lsp,
 }
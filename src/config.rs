@@ -0,0 +1,182 @@
+use crate::cli::Cli;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use cli_generator::internal::{ErrorFormat, OutputFormat};
+use cli_generator::{CppStandard, LineEnding, Target, TargetOs};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The name of the config file looked up next to the input.
+const CONFIG_FILE_NAME: &str = "cli-generator.toml";
+
+/// Parses the values of the options taking one of a few values the same way the command line
+/// does, e.g. `cpp-std = "c++20"`.
+fn value_enum<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| T::from_str(&value, false).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// The generation options of a config file, whose keys are spelled like the flags of the command
+/// line without their `--`, e.g. `cpp-std = "c++20"` or `accessors = true`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Config {
+    #[serde(default, deserialize_with = "value_enum")]
+    target: Option<Target>,
+    #[serde(default, deserialize_with = "value_enum")]
+    output_format: Option<OutputFormat>,
+    go_package: Option<String>,
+    #[serde(default, deserialize_with = "value_enum")]
+    cpp_std: Option<CppStandard>,
+    #[serde(default, deserialize_with = "value_enum")]
+    target_os: Option<TargetOs>,
+    accessors: Option<bool>,
+    cpp_noexcept: Option<bool>,
+    emit_doxygen: Option<bool>,
+    no_regex_validation: Option<bool>,
+    validate_directory: Option<bool>,
+    with_to_command_line: Option<bool>,
+    split_implementation: Option<bool>,
+    indent: Option<usize>,
+    indent_tabs: Option<bool>,
+    #[serde(default, deserialize_with = "value_enum")]
+    line_ending: Option<LineEnding>,
+    #[serde(default, deserialize_with = "value_enum")]
+    error_format: Option<ErrorFormat>,
+    no_timestamp: Option<bool>,
+    /// The keys that are not options, which are warned about rather than rejected.
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Config {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        toml::from_str(source).map_err(|err| err.message().to_string())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("error: cannot read '{}': {err}", path.display()))?;
+        Self::parse(&source)
+            .map_err(|err| format!("error: invalid config '{}': {err}", path.display()))
+    }
+
+    pub fn unknown_keys(&self) -> impl Iterator<Item = &str> {
+        self.unknown.keys().map(String::as_str)
+    }
+
+    /// Sets the options of the file on `options`, except for the ones given on the command line
+    /// as told by `matches`, which take precedence.
+    pub fn merge_into(self, options: &mut Cli, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        macro_rules! merge {
+            ($($option:ident),*) => {
+                $(
+                    if let Some(value) = self.$option {
+                        if !given(stringify!($option)) {
+                            options.$option = value;
+                        }
+                    }
+                )*
+            };
+        }
+
+        merge!(
+            target,
+            output_format,
+            go_package,
+            cpp_std,
+            target_os,
+            accessors,
+            cpp_noexcept,
+            emit_doxygen,
+            no_regex_validation,
+            validate_directory,
+            with_to_command_line,
+            split_implementation,
+            line_ending,
+            error_format,
+            no_timestamp
+        );
+
+        // Either way of indenting on the command line overrides both of the file.
+        if !given("indent") && !given("indent_tabs") {
+            merge!(indent, indent_tabs);
+        }
+    }
+}
+
+/// The config file of the options: the one given by `--config`, or else the one next to the first
+/// input read from a file, if there is one.
+pub(crate) fn config_path(options: &Cli) -> Option<PathBuf> {
+    if let Some(config) = &options.config {
+        return Some(PathBuf::from(config));
+    }
+
+    let input = options.input.iter().find(|input| *input != "-")?;
+    let path = Path::new(input).with_file_name(CONFIG_FILE_NAME);
+    path.is_file().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    /// The options of the command line `args`, with the ones of `config` merged into them.
+    fn options(args: &[&str], config: &str) -> Cli {
+        let matches = Cli::command()
+            .try_get_matches_from(
+                ["cli-generator", "-i", "spec.cli", "-o", "cli.h"]
+                    .iter()
+                    .chain(args),
+            )
+            .unwrap();
+        let mut options = Cli::from_arg_matches(&matches).unwrap();
+        Config::parse(config)
+            .unwrap()
+            .merge_into(&mut options, &matches);
+        options
+    }
+
+    #[test]
+    fn options_of_the_command_line_take_precedence_over_the_file() {
+        let config = r#"
+            cpp-std = "c++20"
+            accessors = true
+            indent = 2
+            go-package = "cli"
+        "#;
+
+        let merged = options(&[], config);
+        assert_eq!(merged.cpp_std, CppStandard::Cpp20);
+        assert!(merged.accessors);
+        assert_eq!(merged.indent, 2);
+        assert_eq!(merged.go_package, "cli");
+
+        let merged = options(&["--cpp-std", "c++11", "--indent-tabs"], config);
+        assert_eq!(merged.cpp_std, CppStandard::Cpp11);
+        assert!(merged.accessors);
+        assert!(merged.indent_tabs);
+        assert_eq!(merged.indent, 4);
+
+        // Options left out of the file keep their default value.
+        assert_eq!(options(&[], "").cpp_std, CppStandard::Cpp17);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_and_unknown_keys_are_kept() {
+        assert!(Config::parse(r#"cpp-std = "c++98""#).is_err());
+        assert!(Config::parse("accessors = 1").is_err());
+
+        let config = Config::parse("namespace = \"app\"\naccessors = true").unwrap();
+        assert_eq!(config.unknown_keys().collect::<Vec<_>>(), ["namespace"]);
+    }
+}
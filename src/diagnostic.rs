@@ -0,0 +1,590 @@
+use crate::source_map::SourceMap;
+use logos::Span;
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+use std::fmt;
+
+/// How diagnostics are printed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorFormat {
+    /// Reports showing the lines of the spec around the problem.
+    #[default]
+    Human,
+    /// A JSON array of every diagnostic, with its lines and columns.
+    Json,
+}
+
+/// Whether diagnostics are colored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Colored when stderr is a terminal, unless `NO_COLOR` is set. `CLICOLOR_FORCE` colors them
+    /// anyway.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color the diagnostics printed to stderr.
+    pub fn colors_stderr(self) -> bool {
+        use std::io::IsTerminal;
+
+        self.colors(std::io::stderr().is_terminal(), |name| {
+            std::env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0")
+        })
+    }
+
+    /// Whether to color, `is_set` telling whether an environment variable is set to something
+    /// other than an empty string or `0`.
+    fn colors(self, is_terminal: bool, is_set: impl Fn(&str) -> bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if is_set("NO_COLOR") => false,
+            ColorChoice::Auto => is_terminal || is_set("CLICOLOR_FORCE"),
+        }
+    }
+}
+
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// `text` without its ANSI escape sequences, e.g. `\x1b[1;31m`.
+fn strip_ansi_escapes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+        } else if chars.next() == Some('[') {
+            // Parameters up to the final byte of the sequence, e.g. `m`.
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+
+    stripped
+}
+
+/// Colors a plain rendered diagnostic: the label in red, the markers under the spec along with
+/// their message in red for the error and in blue for the info, and the help in bold. Warnings
+/// are yellow rather than red.
+fn colorize(rendered: &str) -> String {
+    let severity_color = if rendered.starts_with("warning") {
+        BOLD_YELLOW
+    } else {
+        BOLD_RED
+    };
+    let mut colored = rendered
+        .lines()
+        .map(|line| {
+            for severity in ["error", "warning"] {
+                if let Some(label) = line.strip_prefix(severity) {
+                    return format!("{severity_color}{severity}{RESET}{BOLD}{label}{RESET}");
+                }
+            }
+            if let Some((gutter, help)) = line.split_once("= help") {
+                return format!("{gutter}{BOLD}= help{RESET}{help}");
+            }
+
+            // `  |     ^^^^ Unexpected token`, the gutter being made of the line number and `|`.
+            let Some((gutter, annotation)) = line.split_once('|') else {
+                return line.to_string();
+            };
+            let marker_start = annotation.len() - annotation.trim_start().len();
+            let color = match annotation[marker_start..].chars().next() {
+                Some('^') => severity_color,
+                Some('-') => BOLD_BLUE,
+                _ => return line.to_string(),
+            };
+            let (indentation, marker) = annotation.split_at(marker_start);
+            format!("{gutter}|{indentation}{color}{marker}{RESET}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if rendered.ends_with('\n') {
+        colored.push('\n');
+    }
+
+    colored
+}
+
+/// A position in the spec, both counted from 1. Columns count characters rather than bytes, see
+/// `SourceMap::position`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A part of the spec, `end` being the position right after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SourceSpan {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// E.g. `3:5-3:9`.
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}:{}",
+            self.start.line, self.start.column, self.end.line, self.end.column
+        )
+    }
+}
+
+/// The lines around a span, along with the number of the first of them.
+fn get_context(source_map: &SourceMap, span: &Span) -> (usize, Span) {
+    // A span at the very end of a source ending with a newline is shown on its last line.
+    let line = min(source_map.line(span.start), source_map.line_count());
+
+    let previous_line = max(1, line - 1);
+    let previous_line_span = source_map.line_span(previous_line);
+
+    let next_line = min(line + 1, source_map.line_count());
+    let next_line_span = source_map.line_span(next_line);
+
+    (
+        previous_line,
+        Span::from(previous_line_span.start..next_line_span.end),
+    )
+}
+
+/// How serious a diagnostic is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The spec can't be used as it is.
+    #[default]
+    Error,
+    /// The spec is used anyway, but likely doesn't do what was meant.
+    Warning,
+}
+
+/// A problem found in a spec, pointing at the part of it that is wrong. It is rendered with chic
+/// for people and as JSON for tools such as editors.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// Whether the diagnostic keeps the spec from being used.
+    pub severity: Severity,
+    /// The kind of problem, e.g. `Multiple field definition`.
+    pub label: String,
+    /// The byte range of the spec the diagnostic is about.
+    pub span: Span,
+    /// What is wrong.
+    pub message: String,
+    /// How to fix the problem, when there's an obvious way.
+    pub help: Option<String>,
+    /// Another part of the spec involved, e.g. the first definition of a field defined twice.
+    /// Boxed to keep the results of the parser small.
+    pub info: Option<Box<(Span, String)>>,
+    /// What the spans are about in a spec built with `SpecBuilder`, which has no source to show.
+    pub(crate) location: Option<Box<Location>>,
+}
+
+/// The descriptions of what a diagnostic of a built spec is about, e.g. `struct Args, field
+/// output`, for its span and for its info.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Location {
+    span: String,
+    info: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic about `span` alone.
+    pub fn new(label: &str, span: &Span, message: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            label: label.to_string(),
+            span: span.clone(),
+            message: message.to_string(),
+            help: None,
+            info: None,
+            location: None,
+        }
+    }
+
+    /// A diagnostic about `span` that also points at `info_span`, e.g. the first definition of
+    /// something defined twice.
+    pub fn with_info(
+        label: &str,
+        span: &Span,
+        message: &str,
+        info_span: &Span,
+        info_message: &str,
+    ) -> Self {
+        Self {
+            info: Some(Box::new((info_span.clone(), info_message.to_string()))),
+            ..Self::new(label, span, message)
+        }
+    }
+
+    /// Adds how to fix the problem.
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    /// Sets how serious the diagnostic is, an error unless told otherwise.
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Describes the spans with the lines of the built spec `source` they are on, see `SpecBuilder`.
+    pub(crate) fn locate(mut self, source: &str) -> Self {
+        let line = |span: &Span| {
+            let start = span.start.min(source.len());
+            let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+            let line_end = source[start..]
+                .find('\n')
+                .map_or(source.len(), |newline| start + newline);
+            source[line_start..line_end].to_string()
+        };
+
+        self.location = Some(Box::new(Location {
+            span: line(&self.span),
+            info: self.info.as_deref().map(|(span, _)| line(span)),
+        }));
+        self
+    }
+
+    /// Renders the diagnostic like `render`, colored when `color` is set.
+    pub fn render_colored(&self, source: &str, color: bool) -> String {
+        let rendered = self.render(source);
+        if color {
+            colorize(&rendered)
+        } else {
+            rendered
+        }
+    }
+
+    /// Renders the diagnostic with the lines of `source` around its span, and around its info, as
+    /// plain text.
+    pub fn render(&self, source: &str) -> String {
+        self.render_with_source_map(&SourceMap::new(source))
+    }
+
+    /// Renders the diagnostic like `render`, with the lines of the source already found.
+    pub(crate) fn render_with_source_map(&self, source_map: &SourceMap) -> String {
+        if let Some(location) = self.location.as_deref() {
+            return self.render_location(location);
+        }
+
+        // chic may color the diagnostics itself, colors are added by `render_colored` instead.
+        let rendered = strip_ansi_escapes(&self.render_with_chic(source_map));
+        match self.severity {
+            Severity::Error => rendered,
+            // chic reports everything as an error, warnings only differ by their title.
+            Severity::Warning => rendered.replacen("error", "warning", 1),
+        }
+    }
+
+    /// Renders a diagnostic of a built spec with what it's about in place of the lines of the
+    /// spec, e.g. `in programmatically constructed spec, struct Args, field output`.
+    fn render_location(&self, location: &Location) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let mut rendered = format!(
+            "{severity}: {}\n  --> in programmatically constructed spec, {}\n   = {}\n",
+            self.label, location.span, self.message
+        );
+        if let (Some((_, message)), Some(info)) = (self.info.as_deref(), &location.info) {
+            rendered.push_str(&format!("   = info: {message}, at {info}\n"));
+        }
+        if let Some(help) = &self.help {
+            rendered.push_str(&format!("   = help: {help}\n"));
+        }
+
+        rendered
+    }
+
+    fn render_with_chic(&self, source_map: &SourceMap) -> String {
+        let source = source_map.source();
+        let (line_start, context_span) = get_context(source_map, &self.span);
+        let error_start = self.span.start - context_span.start;
+        let error_end = self.span.end - context_span.start;
+        let context = &source[context_span.start..context_span.end];
+
+        let Some((info_span, info_message)) = self.info.as_deref() else {
+            let error = chic::Error::new(&self.label).error(
+                line_start,
+                error_start,
+                error_end,
+                context,
+                &self.message,
+            );
+            return match &self.help {
+                Some(help) => error.help(help).to_string(),
+                None => error.to_string(),
+            };
+        };
+
+        let (info_line_start, info_context_span) = get_context(source_map, info_span);
+        let report = chic::Report::new_error(&self.label)
+            .error(line_start, error_start, error_end, context, &self.message)
+            .info(
+                info_line_start,
+                info_span.start - info_context_span.start,
+                info_span.end - info_context_span.start,
+                &source[info_context_span.start..info_context_span.end],
+                info_message,
+            );
+        match &self.help {
+            Some(help) => report.help(help).to_string(),
+            None => report.to_string(),
+        }
+    }
+}
+
+/// The diagnostics of a spec that can't be parsed, isn't valid or can't be generated for a target,
+/// in the order they were found. There is always at least one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub(crate) fn new(diagnostics: Vec<Diagnostic>) -> Self {
+        Self(diagnostics)
+    }
+
+    /// Renders every diagnostic like `Diagnostic::render`, separated by newlines.
+    pub fn render(&self, source: &str) -> String {
+        let source_map = SourceMap::new(source);
+        self.0
+            .iter()
+            .map(|diagnostic| diagnostic.render_with_source_map(&source_map))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The diagnostics, to be rendered along with the spec they are about.
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.0
+    }
+}
+
+impl std::ops::Deref for Diagnostics {
+    type Target = [Diagnostic];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// The label and message of every diagnostic on a line of its own, e.g. `Multiple field
+/// definition: Redefinition of field`. `render` shows the spec around them instead.
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, diagnostic) in self.0.iter().enumerate() {
+            if index != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}", diagnostic.label, diagnostic.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// The other part of the spec a diagnostic points at.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct InfoJson {
+    pub message: String,
+    pub span: SourceSpan,
+}
+
+/// A diagnostic as JSON, e.g. `{"file": "cli.spec", "severity": "error", "label": "Semantic
+/// error", "message": ..., "help": null, "span": ..., "info": null}`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DiagnosticJson {
+    pub file: String,
+    pub severity: Severity,
+    pub label: String,
+    pub message: String,
+    pub help: Option<String>,
+    pub span: SourceSpan,
+    pub info: Option<InfoJson>,
+}
+
+impl DiagnosticJson {
+    pub fn new(file: &str, source_map: &SourceMap, diagnostic: &Diagnostic) -> Self {
+        Self {
+            file: file.to_string(),
+            severity: diagnostic.severity,
+            label: diagnostic.label.clone(),
+            message: diagnostic.message.clone(),
+            help: diagnostic.help.clone(),
+            span: source_map.source_span(&diagnostic.span),
+            info: diagnostic.info.as_deref().map(|(span, message)| InfoJson {
+                message: message.clone(),
+                span: source_map.source_span(span),
+            }),
+        }
+    }
+}
+
+/// The diagnostics of `file` as a single line JSON array.
+pub fn diagnostics_json(file: &str, source: &str, diagnostics: &[Diagnostic]) -> String {
+    let source_map = SourceMap::new(source);
+    let diagnostics = diagnostics
+        .iter()
+        .map(|diagnostic| DiagnosticJson::new(file, &source_map, diagnostic))
+        .collect::<Vec<_>>();
+
+    serde_json::to_string(&diagnostics).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    fn json(source: &str) -> Vec<DiagnosticJson> {
+        let mut parser = Parser::new(source);
+        let diagnostics = match parser.parse() {
+            Ok(spec) => check_semantics(&spec).err().unwrap_or_default(),
+            Err(error) => error.errors,
+        };
+
+        serde_json::from_str(&diagnostics_json("cli.spec", source, &diagnostics)).unwrap()
+    }
+
+    fn span(start: (usize, usize), end: (usize, usize)) -> SourceSpan {
+        SourceSpan {
+            start: Position {
+                line: start.0,
+                column: start.1,
+            },
+            end: Position {
+                line: end.0,
+                column: end.1,
+            },
+        }
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_token() {
+        let diagnostics = json("#[main]\nstruct Cli {\n    verbose bool,\n}\n");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "cli.spec");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].span, span((3, 13), (3, 17)));
+        assert_eq!(diagnostics[0].info, None);
+    }
+
+    #[test]
+    fn semantic_errors_point_at_both_definitions() {
+        let diagnostics =
+            json("#[main]\nstruct Cli {\n    #[long] jobs: u32,\n    #[long] jobs: u32,\n}\n");
+
+        // The long name given twice is reported along with the field.
+        assert_eq!(
+            diagnostics,
+            vec![
+                DiagnosticJson {
+                    file: String::from("cli.spec"),
+                    severity: Severity::Error,
+                    label: String::from("Multiple field definition"),
+                    message: String::from("Redefinition of field"),
+                    help: None,
+                    span: span((4, 13), (4, 17)),
+                    info: Some(InfoJson {
+                        message: String::from("Has already been defined here"),
+                        span: span((3, 13), (3, 17)),
+                    }),
+                },
+                DiagnosticJson {
+                    file: String::from("cli.spec"),
+                    severity: Severity::Error,
+                    label: String::from("Invalid field attribute usage"),
+                    message: String::from(
+                        "There's already a field with the same long name or alias"
+                    ),
+                    help: None,
+                    span: span((4, 7), (4, 11)),
+                    info: Some(InfoJson {
+                        message: String::from("Field with same long or alias value"),
+                        span: span((3, 13), (3, 17)),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rendered_diagnostics_show_the_lines_around_them() {
+        let source = "#[main]\nstruct Cli {\n    verbose bool,\n}\n";
+        let mut parser = Parser::new(source);
+        let rendered = parser.parse().unwrap_err().errors[0].render(source);
+
+        assert!(rendered.contains("2 | struct Cli {"));
+        assert!(rendered.contains("3 |     verbose bool,"));
+        assert!(rendered.contains("4 | }"));
+    }
+
+    #[test]
+    fn rendered_diagnostics_of_crlf_specs_show_the_lines_around_them() {
+        let source = "#[main]\r\nstruct Cli {\r\n    verbose bool,\r\n}\r\n";
+        let mut parser = Parser::new(source);
+        let errors = parser.parse().unwrap_err().errors;
+        let last = errors.last().unwrap().render(source);
+
+        assert!(last.contains("3 |     verbose bool,\r\n"));
+        assert!(last.contains("4 | }"));
+    }
+
+    #[test]
+    fn diagnostics_are_only_colored_when_asked_for() {
+        let source = "#[main]\nstruct Cli {\n    #[long] jobs: u32,\n    #[long] jobs: u32,\n}\n";
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let diagnostic = &check_semantics(&spec).unwrap_err()[0];
+
+        let plain = diagnostic.render_colored(source, false);
+        assert!(!plain.contains('\x1b'));
+        let colored = diagnostic.render_colored(source, true);
+        assert!(colored.contains(&format!("{BOLD_RED}error{RESET}")));
+        assert!(colored.contains(&format!("{BOLD_RED}^^^^ Redefinition of field{RESET}")));
+        assert_eq!(strip_ansi_escapes(&colored), plain);
+    }
+
+    #[test]
+    fn auto_colors_follow_the_terminal_and_the_environment() {
+        let colors = |choice: ColorChoice, is_terminal, set: &[&str]| {
+            choice.colors(is_terminal, |name| set.contains(&name))
+        };
+
+        assert!(colors(ColorChoice::Auto, true, &[]));
+        assert!(!colors(ColorChoice::Auto, false, &[]));
+        assert!(!colors(ColorChoice::Auto, true, &["NO_COLOR"]));
+        assert!(colors(ColorChoice::Auto, false, &["CLICOLOR_FORCE"]));
+        assert!(colors(ColorChoice::Always, false, &["NO_COLOR"]));
+        assert!(!colors(ColorChoice::Never, true, &["CLICOLOR_FORCE"]));
+    }
+}
@@ -0,0 +1,191 @@
+/// The number of unchanged lines shown around every change.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineChange<'s> {
+    Equal(&'s str),
+    Removed(&'s str),
+    Added(&'s str),
+}
+
+/// Line by line changes from `old` to `new`. The common prefix and suffix are skipped before
+/// computing the longest common subsequence of the rest, which keeps small edits of large headers
+/// cheap.
+fn line_changes<'s>(old: &[&'s str], new: &[&'s str]) -> Vec<LineChange<'s>> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of old_middle[i..] and
+    // new_middle[j..].
+    let mut lcs = vec![vec![0usize; new_middle.len() + 1]; old_middle.len() + 1];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lcs[i][j] = if old_middle[i] == new_middle[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = old[..prefix]
+        .iter()
+        .map(|line| LineChange::Equal(line))
+        .collect::<Vec<_>>();
+
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            changes.push(LineChange::Equal(old_middle[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_middle.len() && (j == new_middle.len() || lcs[i + 1][j] >= lcs[i][j + 1])
+        {
+            changes.push(LineChange::Removed(old_middle[i]));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(new_middle[j]));
+            j += 1;
+        }
+    }
+
+    changes.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| LineChange::Equal(line)),
+    );
+
+    changes
+}
+
+/// Formats the changes from `old` to `new` as a unified diff. The result is empty when both are
+/// equal.
+pub(crate) fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let changes = line_changes(&old_lines, &new_lines);
+
+    let changed = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, change)| !matches!(change, LineChange::Equal(_)))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Changes that are close enough to share their context end up in the same hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(changes.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut res = format!("--- {old_name}\n+++ {new_name}\n");
+
+    // Line numbers of both sides at the start of the current hunk.
+    let (mut old_line, mut new_line) = (0, 0);
+    let mut position = 0;
+    for (start, end) in hunks {
+        for change in &changes[position..start] {
+            match change {
+                LineChange::Equal(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                LineChange::Removed(_) => old_line += 1,
+                LineChange::Added(_) => new_line += 1,
+            }
+        }
+
+        let hunk = &changes[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|change| !matches!(change, LineChange::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|change| !matches!(change, LineChange::Removed(_)))
+            .count();
+
+        // Empty ranges point at the line before them, as in GNU diff.
+        let old_start = if old_count == 0 {
+            old_line
+        } else {
+            old_line + 1
+        };
+        let new_start = if new_count == 0 {
+            new_line
+        } else {
+            new_line + 1
+        };
+        res.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        ));
+
+        for change in hunk {
+            let (marker, line) = match change {
+                LineChange::Equal(line) => (' ', line),
+                LineChange::Removed(line) => ('-', line),
+                LineChange::Added(line) => ('+', line),
+            };
+            res.push(marker);
+            res.push_str(line);
+            res.push('\n');
+        }
+
+        old_line += old_count;
+        new_line += new_count;
+        position = end;
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_inputs_have_no_diff() {
+        let text = "a\nb\nc\n";
+        assert_eq!(unified_diff(text, text, "old", "new"), "");
+    }
+
+    #[test]
+    fn changes_are_grouped_into_hunks_with_context() {
+        let old = (1..=20).map(|i| format!("{i}\n")).collect::<String>();
+        let new = (1..=20)
+            .filter(|&i| i != 18)
+            .map(|i| match i {
+                2 => String::from("two\n"),
+                _ => format!("{i}\n"),
+            })
+            .collect::<String>();
+
+        assert_eq!(
+            unified_diff(&old, &new, "old", "new"),
+            "--- old\n+++ new\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -15,6 +15,5 @@\n 15\n 16\n 17\n-18\n 19\n 20\n"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::types::{Attribute, AttributeType, Field, Spec};
+use std::fmt::Write;
+
+/// The indentation of the fields of a struct.
+const INDENTATION: &str = "    ";
+
+/// Where an attribute goes in its list: the kind of struct, then the options of a field, then
+/// every other attribute in the order it was given.
+fn attribute_rank(ty: AttributeType) -> usize {
+    match ty {
+        AttributeType::Main | AttributeType::SubCommand => 0,
+        AttributeType::Short => 1,
+        AttributeType::Long => 2,
+        AttributeType::Alias => 3,
+        _ => 4,
+    }
+}
+
+/// Whether the value of a `short` or `long` attribute is the one it gets when it is left out,
+/// i.e. the first letter or the name of the field.
+fn has_default_value(attribute: &Attribute, field: &Field) -> bool {
+    let default = match attribute.ty {
+        AttributeType::Short => field.name.chars().next().map(String::from),
+        AttributeType::Long => Some(field.name.clone()),
+        _ => None,
+    };
+
+    default.is_some() && attribute.value == default
+}
+
+/// Writes the attributes of a struct, or of `field`, on their own line.
+fn write_attributes(
+    formatted: &mut String,
+    attributes: &[Attribute],
+    field: Option<&Field>,
+    indentation: &str,
+) {
+    if attributes.is_empty() {
+        return;
+    }
+
+    let mut attributes = attributes.iter().collect::<Vec<_>>();
+    attributes.sort_by_key(|attribute| attribute_rank(attribute.ty));
+
+    let attributes = attributes
+        .into_iter()
+        .map(|attribute| match field {
+            Some(field) if has_default_value(attribute, field) => {
+                attribute.ty.to_literal().to_string()
+            }
+            _ => attribute.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    writeln!(formatted, "{indentation}#[{}]", attributes.join(", ")).unwrap();
+}
+
+/// The spec printed back in the style of `cli-generator fmt`: structs separated by an empty line,
+/// their fields on their own line indented by 4 spaces, each after a line with its attributes,
+/// and `short` and `long` written without their value when it is the default one. A format
+/// directive is kept on the first line. Formatting a formatted spec leaves it as is.
+pub fn format_spec(spec: &Spec) -> String {
+    let mut formatted = String::new();
+    if let Some(version) = spec.format_directive {
+        writeln!(formatted, "#!cli-spec {version}\n").unwrap();
+    }
+
+    for (index, strukt) in spec.structs.iter().enumerate() {
+        if index != 0 {
+            formatted.push('\n');
+        }
+        write_attributes(&mut formatted, &strukt.attributes, None, "");
+        writeln!(formatted, "struct {} {{", strukt.name).unwrap();
+        for field in &strukt.fields {
+            write_attributes(&mut formatted, &field.attributes, Some(field), INDENTATION);
+            writeln!(formatted, "{INDENTATION}{}: {},", field.name, field.ty).unwrap();
+        }
+        formatted.push_str("}\n");
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+
+    fn format(source: &str) -> String {
+        let mut parser = Parser::new(source);
+        format_spec(&parser.parse().unwrap())
+    }
+
+    #[test]
+    fn specs_are_formatted_in_the_canonical_style() {
+        let source = r#"#[about = "A \"tool\"", main] struct Cli { #[doc = "Port", long = port, short = x] port: u16, #[flatten] net: Optional<Net> }
+struct Net { host: string
+}"#;
+
+        assert_eq!(
+            format(source),
+            r#"#[main, about = "A \"tool\""]
+struct Cli {
+    #[short = x, long, doc = "Port"]
+    port: u16,
+    #[flatten]
+    net: Optional<Net>,
+}
+
+struct Net {
+    host: string,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn formatting_twice_is_formatting_once() {
+        for source in [
+            include_str!("../tests/specs/comprehensive.cli"),
+            include_str!("../tests/specs/c99.cli"),
+        ] {
+            let formatted = format(source);
+            assert_eq!(format(&formatted), formatted);
+        }
+    }
+
+    #[test]
+    fn format_directives_are_kept() {
+        assert_eq!(
+            format("#!cli-spec 1\nstruct Cli { #[long] port: u16 }"),
+            "#!cli-spec 1\n\nstruct Cli {\n    #[long]\n    port: u16,\n}\n"
+        );
+    }
+}
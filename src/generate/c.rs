@@ -0,0 +1,1589 @@
+use crate::diagnostic::Diagnostic;
+use crate::generate::cpp::{
+    banner_lines, deprecation_warning, field_option_name, help_lines, option_match_condition,
+    struct_options, MandatoryFields,
+};
+use crate::generate::{left_pad, Backend, GenOptions, OutputFile, Provenance};
+use crate::semantic::check_c_support;
+use crate::types::{AttributeType, Field, FieldType, Spec, SpecMetadata, Struct};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// The C type of a single value. `Vec` and `Optional` fields are made of several members, see
+/// `CSourceBuilder::write_struct_field`.
+fn field_type_to_c_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::String => "const char*".to_string(),
+        FieldType::I16 => "int16_t".to_string(),
+        FieldType::U16 => "uint16_t".to_string(),
+        FieldType::I32 => "int32_t".to_string(),
+        FieldType::U32 => "uint32_t".to_string(),
+        FieldType::I64 => "int64_t".to_string(),
+        FieldType::U64 => "uint64_t".to_string(),
+        FieldType::F32 => "float".to_string(),
+        FieldType::F64 => "double".to_string(),
+        FieldType::Bool => "bool".to_string(),
+        FieldType::Struct(strukt) => strukt.to_string(),
+        FieldType::Vec(_) | FieldType::Optional(_) | FieldType::Error => unreachable!(),
+    }
+}
+
+/// The `printf` conversion of a single value, using the `<inttypes.h>` macros so that it is right
+/// whatever the fixed width types are defined as. The macros are spliced into the format literal.
+fn printf_conversion(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::String | FieldType::Bool => "%s",
+        FieldType::I16 => r#"%" PRId16 ""#,
+        FieldType::U16 => r#"%" PRIu16 ""#,
+        FieldType::I32 => r#"%" PRId32 ""#,
+        FieldType::U32 => r#"%" PRIu32 ""#,
+        FieldType::I64 => r#"%" PRId64 ""#,
+        FieldType::U64 => r#"%" PRIu64 ""#,
+        FieldType::F32 | FieldType::F64 => "%f",
+        _ => unreachable!(),
+    }
+}
+
+/// The prefix of the functions generated for a struct, e.g. `server_config` for `ServerConfig`.
+fn function_prefix(struct_name: &str) -> String {
+    let chars = struct_name.chars().collect::<Vec<_>>();
+    let mut prefix = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let after_lowercase =
+                chars[i - 1].is_ascii_lowercase() || chars[i - 1].is_ascii_digit();
+            let starts_word = chars[i - 1].is_ascii_uppercase()
+                && chars
+                    .get(i + 1)
+                    .is_some_and(|next| next.is_ascii_lowercase());
+            if after_lowercase || starts_word {
+                prefix.push('_');
+            }
+        }
+        prefix.push(c.to_ascii_lowercase());
+    }
+
+    prefix
+}
+
+/// Whether the struct holds heap allocated memory (a `Vec`), directly or through its fields, and
+/// so gets a `_free` function.
+fn owns_heap_memory<'s>(
+    strukt: &'s Struct,
+    spec_metadata: &SpecMetadata<'s>,
+    visited: &mut HashSet<&'s str>,
+) -> bool {
+    if !visited.insert(strukt.name.as_str()) {
+        return false;
+    }
+
+    strukt.fields.iter().any(|field| match &field.ty {
+        FieldType::Vec(_) => true,
+        ty => match ty.value_type() {
+            FieldType::Struct(name) => spec_metadata
+                .identifier_to_struct
+                .get(name.as_str())
+                .is_some_and(|inner| owns_heap_memory(inner, spec_metadata, visited)),
+            _ => false,
+        },
+    })
+}
+
+/// The helpers of the source file, which are only written when the parsing code uses them so
+/// that the result compiles cleanly with `-Wunused-function`.
+#[derive(Debug, Default, Clone, Copy)]
+struct UsedHelpers {
+    set_error: bool,
+    equals_ignore_case: bool,
+    starts_with_ignore_case: bool,
+    parse_bool_value: bool,
+    parse_signed: bool,
+    parse_unsigned: bool,
+    parse_float: bool,
+    parse_double: bool,
+    grow_array: bool,
+}
+
+impl UsedHelpers {
+    #[inline]
+    fn parses_numbers(&self) -> bool {
+        self.parse_signed || self.parse_unsigned || self.parse_float || self.parse_double
+    }
+}
+
+/// A struct flattened through an `Optional` field, see `cpp::OptionalGroup`. In C presence is
+/// tracked through the `has_` members instead of `std::optional`.
+struct OptionalGroup<'s> {
+    /// The `has_` members of the enclosing groups, from the outermost to this one.
+    flags: Vec<String>,
+    mandatory_fields: MandatoryFields<'s>,
+}
+
+/// The prefix of the arrays tracking the mandatory fields of an optional group, e.g. `net_` for
+/// `out->net` and `net_proxy_` for `out->net.proxy`.
+fn optional_group_prefix(path: &str) -> String {
+    format!("{}_", path.trim_start_matches("out->").replace('.', "_"))
+}
+
+/// A branch of the `if`/`else if`/`else` chain that matches the arguments against the options.
+struct MatchArm {
+    condition: String,
+    body: String,
+}
+
+#[derive(Debug, Default)]
+struct CSourceBuilder {
+    buffer: String,
+    /// The number of indentation levels, see `GenOptions::indent` for their width.
+    indentation: usize,
+    options: GenOptions,
+    helpers: UsedHelpers,
+}
+
+macro_rules! c_source_builder_writeln {
+    ($self:expr) => {{
+        writeln!($self.buffer).unwrap();
+    }};
+    ($self:expr, $($arg:tt)*) => {{
+        left_pad($self.options.indent, $self.indentation, &mut $self.buffer).unwrap();
+        writeln!($self.buffer, $($arg)*).unwrap();
+    }};
+}
+
+macro_rules! c_source_builder_write {
+    ($self:expr, $($arg:tt)*) => {{
+        left_pad($self.options.indent, $self.indentation, &mut $self.buffer).unwrap();
+        write!($self.buffer, $($arg)*).unwrap()
+    }};
+}
+
+/// Continues the current line, without indenting.
+macro_rules! c_source_builder_append {
+    ($self:expr, $($arg:tt)*) => {{
+        write!($self.buffer, $($arg)*).unwrap()
+    }};
+}
+
+/// Continues and ends the current line, without indenting.
+macro_rules! c_source_builder_appendln {
+    ($self:expr, $($arg:tt)*) => {{
+        writeln!($self.buffer, $($arg)*).unwrap()
+    }};
+}
+
+impl CSourceBuilder {
+    pub fn new(options: GenOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn push_indentation_level(&mut self) {
+        self.indentation += 1;
+    }
+
+    #[inline]
+    pub fn pop_indentation_level(&mut self) {
+        self.indentation = self.indentation.saturating_sub(1);
+    }
+
+    #[inline]
+    pub fn result(self) -> String {
+        match self.options.line_ending.as_str() {
+            "\n" => self.buffer,
+            line_ending => self.buffer.replace('\n', line_ending),
+        }
+    }
+
+    /// Runs `write` against an empty buffer one indentation level deeper and returns what it
+    /// wrote, so that blocks can be assembled before being written out.
+    fn capture(&mut self, write: impl FnOnce(&mut Self)) -> String {
+        let previous_buffer = std::mem::take(&mut self.buffer);
+        self.push_indentation_level();
+        write(self);
+        self.pop_indentation_level();
+        std::mem::replace(&mut self.buffer, previous_buffer)
+    }
+
+    #[inline]
+    pub fn write_banner(&mut self, provenance: &Provenance) {
+        for line in banner_lines(provenance) {
+            c_source_builder_writeln!(self, "{line}");
+        }
+        c_source_builder_writeln!(self);
+    }
+
+    /// Writes `body` as a block closing with `}`, the opening line having been written already.
+    fn write_block(&mut self, body: impl FnOnce(&mut Self)) {
+        self.push_indentation_level();
+        body(self);
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}");
+    }
+
+    fn write_error(&mut self, format: &str, args: &[&str]) {
+        self.helpers.set_error = true;
+        c_source_builder_write!(self, r#"set_error(errbuf, errlen, "{format}""#);
+        for arg in args {
+            c_source_builder_append!(self, ", {arg}");
+        }
+        c_source_builder_appendln!(self, ");");
+        c_source_builder_writeln!(self, "return false;");
+    }
+
+    /// Reports an invalid `arg_value` for the option `arg`. The value of secret fields is never
+    /// echoed back, so that it doesn't end up in terminals or logs.
+    fn write_invalid_value_error(&mut self, reason: &str, secret: bool) {
+        if secret {
+            self.write_error(&format!("Value of option '%s' {reason}"), &["arg"]);
+        } else {
+            self.write_error(
+                &format!("Value '%s' of option '%s' {reason}"),
+                &["arg_value", "arg"],
+            );
+        }
+    }
+
+    /// The comment at the top of the header, spelling out who owns what.
+    fn write_ownership_comment(&mut self, uses_heap: bool) {
+        c_source_builder_writeln!(self, "/*");
+        c_source_builder_writeln!(self, " * Memory ownership:");
+        c_source_builder_writeln!(
+            self,
+            " * - String fields point into the argv given to the parse functions, which has to"
+        );
+        c_source_builder_writeln!(self, " *   outlive the parsed structs.");
+        if uses_heap {
+            c_source_builder_writeln!(
+                self,
+                " * - Vec fields own a heap allocated array of `len` items. It is released by the"
+            );
+            c_source_builder_writeln!(
+                self,
+                " *   `_free` function of the struct, which must be called after every call to a"
+            );
+            c_source_builder_writeln!(self, " *   parse function, whether it succeeded or not.");
+        }
+        c_source_builder_writeln!(
+            self,
+            " * - On failure, the parse functions write a NUL terminated message of at most"
+        );
+        c_source_builder_writeln!(
+            self,
+            " *   `errlen` bytes to `errbuf`, which may be NULL, and return false."
+        );
+        c_source_builder_writeln!(
+            self,
+            " * - `-h`/`--help` prints the help of the struct and exits the process."
+        );
+        c_source_builder_writeln!(self, " */");
+        c_source_builder_writeln!(self);
+    }
+
+    /// `Vec` fields are a growable array along with its length and capacity, and `Optional`
+    /// fields are preceded by a `has_` flag telling whether they were given.
+    fn write_struct_field(&mut self, field: &Field) {
+        let name = &field.name;
+        self.push_indentation_level();
+        match &field.ty {
+            FieldType::Vec(inner) => {
+                c_source_builder_writeln!(self, "struct {{");
+                self.push_indentation_level();
+                c_source_builder_writeln!(self, "{}* items;", field_type_to_c_type(inner));
+                c_source_builder_writeln!(self, "size_t len;");
+                c_source_builder_writeln!(self, "size_t capacity;");
+                self.pop_indentation_level();
+                c_source_builder_writeln!(self, "}} {name};");
+            }
+            FieldType::Optional(inner) => {
+                c_source_builder_writeln!(self, "bool has_{name};");
+                c_source_builder_writeln!(self, "{} {name};", field_type_to_c_type(inner));
+            }
+            ty => {
+                c_source_builder_writeln!(self, "{} {name};", field_type_to_c_type(ty));
+            }
+        }
+        self.pop_indentation_level();
+    }
+
+    fn write_struct_definition(&mut self, strukt: &Struct) {
+        let struct_name = &strukt.name;
+        c_source_builder_writeln!(self, "typedef struct {struct_name} {{");
+        for field in &strukt.fields {
+            self.write_struct_field(field);
+        }
+        c_source_builder_writeln!(self, "}} {struct_name};\n");
+    }
+
+    fn write_struct_prototypes(&mut self, strukt: &Struct, owns_heap: bool) {
+        let struct_name = &strukt.name;
+        let prefix = function_prefix(struct_name);
+
+        // Doc hidden structs are only parsed as part of the structs that flatten them.
+        if !strukt.is_doc_hidden() {
+            c_source_builder_writeln!(
+                self,
+                "bool {prefix}_parse(int argc, char** argv, {struct_name}* out, char* errbuf, size_t errlen);"
+            );
+            c_source_builder_writeln!(self, "void {prefix}_help(void);");
+        }
+        c_source_builder_writeln!(
+            self,
+            "void {prefix}_print_debug(const {struct_name}* self);"
+        );
+        if owns_heap {
+            c_source_builder_writeln!(self, "void {prefix}_free({struct_name}* self);");
+        }
+        c_source_builder_writeln!(self);
+    }
+
+    fn write_helper_functions(&mut self) {
+        let helpers = self.helpers;
+
+        if helpers.set_error {
+            c_source_builder_writeln!(
+                self,
+                "static void set_error(char* errbuf, size_t errlen, const char* format, ...) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "va_list args;\n");
+                c_source_builder_writeln!(this, "if (errbuf == NULL || errlen == 0) {{");
+                this.write_block(|this| c_source_builder_writeln!(this, "return;"));
+                c_source_builder_writeln!(this);
+                c_source_builder_writeln!(this, "va_start(args, format);");
+                c_source_builder_writeln!(this, "vsnprintf(errbuf, errlen, format, args);");
+                c_source_builder_writeln!(this, "va_end(args);");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if helpers.equals_ignore_case || helpers.parse_bool_value {
+            c_source_builder_writeln!(
+                self,
+                "static bool equals_ignore_case(const char* lhs, const char* rhs) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(
+                    this,
+                    "for (; *lhs != '\\0' && *rhs != '\\0'; ++lhs, ++rhs) {{"
+                );
+                this.write_block(|this| {
+                    c_source_builder_writeln!(
+                        this,
+                        "if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {{"
+                    );
+                    this.write_block(|this| c_source_builder_writeln!(this, "return false;"));
+                });
+                c_source_builder_writeln!(this, "return *lhs == *rhs;");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if helpers.starts_with_ignore_case {
+            c_source_builder_writeln!(
+                self,
+                "static bool starts_with_ignore_case(const char* str, const char* prefix) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "for (; *prefix != '\\0'; ++str, ++prefix) {{");
+                this.write_block(|this| {
+                    c_source_builder_writeln!(
+                        this,
+                        "if (tolower((unsigned char)*str) != tolower((unsigned char)*prefix)) {{"
+                    );
+                    this.write_block(|this| c_source_builder_writeln!(this, "return false;"));
+                });
+                c_source_builder_writeln!(this, "return true;");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if helpers.parse_bool_value {
+            c_source_builder_writeln!(
+                self,
+                "static bool parse_bool_value(const char* value, bool* res) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(
+                    this,
+                    r#"if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {{"#
+                );
+                this.write_block(|this| {
+                    c_source_builder_writeln!(this, "*res = true;");
+                    c_source_builder_writeln!(this, "return true;");
+                });
+                c_source_builder_writeln!(
+                    this,
+                    r#"if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {{"#
+                );
+                this.write_block(|this| {
+                    c_source_builder_writeln!(this, "*res = false;");
+                    c_source_builder_writeln!(this, "return true;");
+                });
+                c_source_builder_writeln!(this, "return false;");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if helpers.parses_numbers() {
+            c_source_builder_writeln!(self, "typedef enum {{");
+            self.push_indentation_level();
+            c_source_builder_writeln!(self, "PARSE_OK,");
+            c_source_builder_writeln!(self, "PARSE_INVALID,");
+            c_source_builder_writeln!(self, "PARSE_OUT_OF_RANGE");
+            self.pop_indentation_level();
+            c_source_builder_writeln!(self, "}} parse_result;\n");
+        }
+
+        if helpers.parse_signed {
+            c_source_builder_writeln!(
+                self,
+                "static parse_result parse_signed(const char* value, long long min, long long max, long long* res) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "char* end = NULL;\n");
+                c_source_builder_writeln!(this, "errno = 0;");
+                c_source_builder_writeln!(this, "*res = strtoll(value, &end, 10);");
+                c_source_builder_writeln!(this, r#"if (end == value || *end != '\0') {{"#);
+                this.write_block(|this| c_source_builder_writeln!(this, "return PARSE_INVALID;"));
+                c_source_builder_writeln!(
+                    this,
+                    "if (errno == ERANGE || *res < min || *res > max) {{"
+                );
+                this.write_block(|this| {
+                    c_source_builder_writeln!(this, "return PARSE_OUT_OF_RANGE;")
+                });
+                c_source_builder_writeln!(this, "return PARSE_OK;");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if helpers.parse_unsigned {
+            c_source_builder_writeln!(
+                self,
+                "static parse_result parse_unsigned(const char* value, unsigned long long max, unsigned long long* res) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "char* end = NULL;\n");
+                // `strtoull` happily negates negative values instead of rejecting them.
+                c_source_builder_writeln!(this, "if (strchr(value, '-') != NULL) {{");
+                this.write_block(|this| c_source_builder_writeln!(this, "return PARSE_INVALID;"));
+                c_source_builder_writeln!(this, "errno = 0;");
+                c_source_builder_writeln!(this, "*res = strtoull(value, &end, 10);");
+                c_source_builder_writeln!(this, r#"if (end == value || *end != '\0') {{"#);
+                this.write_block(|this| c_source_builder_writeln!(this, "return PARSE_INVALID;"));
+                c_source_builder_writeln!(this, "if (errno == ERANGE || *res > max) {{");
+                this.write_block(|this| {
+                    c_source_builder_writeln!(this, "return PARSE_OUT_OF_RANGE;")
+                });
+                c_source_builder_writeln!(this, "return PARSE_OK;");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        for (used, name, c_type, conversion) in [
+            (helpers.parse_float, "parse_float", "float", "strtof"),
+            (helpers.parse_double, "parse_double", "double", "strtod"),
+        ] {
+            if !used {
+                continue;
+            }
+
+            c_source_builder_writeln!(
+                self,
+                "static parse_result {name}(const char* value, {c_type}* res) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "char* end = NULL;\n");
+                c_source_builder_writeln!(this, "errno = 0;");
+                c_source_builder_writeln!(this, "*res = {conversion}(value, &end);");
+                c_source_builder_writeln!(this, r#"if (end == value || *end != '\0') {{"#);
+                this.write_block(|this| c_source_builder_writeln!(this, "return PARSE_INVALID;"));
+                c_source_builder_writeln!(this, "if (errno == ERANGE) {{");
+                this.write_block(|this| {
+                    c_source_builder_writeln!(this, "return PARSE_OUT_OF_RANGE;")
+                });
+                c_source_builder_writeln!(this, "return PARSE_OK;");
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if helpers.grow_array {
+            c_source_builder_writeln!(
+                self,
+                "static void* grow_array(void* items, size_t* capacity, size_t item_size) {{"
+            );
+            self.write_block(|this| {
+                c_source_builder_writeln!(
+                    this,
+                    "size_t new_capacity = *capacity == 0 ? 4 : *capacity * 2;"
+                );
+                c_source_builder_writeln!(
+                    this,
+                    "void* new_items = realloc(items, new_capacity * item_size);\n"
+                );
+                c_source_builder_writeln!(this, "if (new_items != NULL) {{");
+                this.write_block(|this| {
+                    c_source_builder_writeln!(this, "*capacity = new_capacity;")
+                });
+                c_source_builder_writeln!(this, "return new_items;");
+            });
+            c_source_builder_writeln!(self);
+        }
+    }
+
+    /// Warns on stderr that the option of a deprecated field was given, like the C++ backend.
+    fn write_deprecation_warning(&mut self, field: &Field) {
+        let (warning, removal) = deprecation_warning(field);
+        c_source_builder_writeln!(self, r#"fprintf(stderr, "{warning}\n", arg);"#);
+        if let Some(removal) = removal {
+            c_source_builder_writeln!(self, r#"fprintf(stderr, "{removal}\n");"#);
+        }
+    }
+
+    /// Appends `value` to the `Vec` member at `path`, growing it when full.
+    fn write_push(&mut self, path: &str, value: &str) {
+        self.helpers.grow_array = true;
+
+        c_source_builder_writeln!(self, "if ({path}.len == {path}.capacity) {{");
+        self.write_block(|this| {
+            c_source_builder_writeln!(
+                this,
+                "void* items = grow_array({path}.items, &{path}.capacity, sizeof({path}.items[0]));"
+            );
+            c_source_builder_writeln!(this, "if (items == NULL) {{");
+            this.write_block(|this| this.write_error("Out of memory", &[]));
+            c_source_builder_writeln!(this, "{path}.items = items;");
+        });
+        c_source_builder_writeln!(self, "{path}.items[{path}.len++] = {value};");
+    }
+
+    /// Checks the parsed value against the `min`/`max` attributes of the field, if any.
+    fn write_range_check(&mut self, field: &Field, field_type: &FieldType, secret: bool) {
+        let literal = |value: &str| match field_type {
+            FieldType::I64 => format!("{value}LL"),
+            FieldType::U64 => format!("{value}ULL"),
+            _ => value.to_string(),
+        };
+        let min = field.attribute_value(AttributeType::Min);
+        let max = field.attribute_value(AttributeType::Max);
+
+        let (condition, reason) = match (min, max) {
+            (Some(min), Some(max)) => (
+                format!("arg_res < {} || arg_res > {}", literal(min), literal(max)),
+                format!("is not between {min} and {max}"),
+            ),
+            (Some(min), None) => (
+                format!("arg_res < {}", literal(min)),
+                format!("is less than {min}"),
+            ),
+            (None, Some(max)) => (
+                format!("arg_res > {}", literal(max)),
+                format!("is greater than {max}"),
+            ),
+            (None, None) => return,
+        };
+
+        c_source_builder_writeln!(self, "if ({condition}) {{");
+        self.write_block(|this| this.write_invalid_value_error(&reason, secret));
+    }
+
+    /// Integers are parsed into the widest type of their signedness and then checked against the
+    /// limits of the field type. Floating point values go through `strtof`/`strtod`, so unlike the
+    /// C++ backend they follow the `LC_NUMERIC` locale of the process.
+    fn write_parse_numeric_field(&mut self, field: &Field, field_type: &FieldType) {
+        let secret = field.has_attribute(AttributeType::Secret);
+        let c_type = field_type_to_c_type(field_type);
+
+        let is_integer = !matches!(field_type, FieldType::F32 | FieldType::F64);
+        let conversion = match field_type {
+            FieldType::I16 | FieldType::I32 | FieldType::I64 => {
+                self.helpers.parse_signed = true;
+                let limits = match field_type {
+                    FieldType::I16 => "INT16_MIN, INT16_MAX",
+                    FieldType::I32 => "INT32_MIN, INT32_MAX",
+                    _ => "INT64_MIN, INT64_MAX",
+                };
+                c_source_builder_writeln!(self, "long long arg_parsed = 0;");
+                format!("parse_signed(arg_value, {limits}, &arg_parsed)")
+            }
+            FieldType::U16 | FieldType::U32 | FieldType::U64 => {
+                self.helpers.parse_unsigned = true;
+                let max = match field_type {
+                    FieldType::U16 => "UINT16_MAX",
+                    FieldType::U32 => "UINT32_MAX",
+                    _ => "UINT64_MAX",
+                };
+                c_source_builder_writeln!(self, "unsigned long long arg_parsed = 0;");
+                format!("parse_unsigned(arg_value, {max}, &arg_parsed)")
+            }
+            FieldType::F32 => {
+                self.helpers.parse_float = true;
+                c_source_builder_writeln!(self, "{c_type} arg_res = 0;");
+                String::from("parse_float(arg_value, &arg_res)")
+            }
+            FieldType::F64 => {
+                self.helpers.parse_double = true;
+                c_source_builder_writeln!(self, "{c_type} arg_res = 0;");
+                String::from("parse_double(arg_value, &arg_res)")
+            }
+            _ => unreachable!(),
+        };
+        let (out_of_range, invalid) = if is_integer {
+            ("out of range for integer type", "is not a valid integer")
+        } else {
+            (
+                "out of range for floating point type",
+                "is not a valid floating point number",
+            )
+        };
+
+        c_source_builder_writeln!(self, "parse_result conversion = {conversion};");
+        c_source_builder_writeln!(self, "if (conversion == PARSE_OUT_OF_RANGE) {{");
+        self.write_block(|this| this.write_invalid_value_error(out_of_range, secret));
+        c_source_builder_writeln!(self, "if (conversion == PARSE_INVALID) {{");
+        self.write_block(|this| this.write_invalid_value_error(invalid, secret));
+        if is_integer {
+            c_source_builder_writeln!(self, "{c_type} arg_res = ({c_type})arg_parsed;");
+        }
+
+        self.write_range_check(field, field_type, secret);
+    }
+
+    /// Bool options are set by their mere presence, but an explicit value can also be attached
+    /// (`--flag=false`). Explicit bool fields additionally consume the next argument as value.
+    fn write_parse_bool_field(&mut self, explicit: bool, value_separator: &str) {
+        self.helpers.parse_bool_value = true;
+
+        c_source_builder_writeln!(self, "bool arg_res = true;");
+        c_source_builder_writeln!(
+            self,
+            "const char* bool_value = strchr(arg, '{value_separator}');"
+        );
+        c_source_builder_writeln!(self, "if (bool_value != NULL) {{");
+        self.push_indentation_level();
+        c_source_builder_writeln!(self, "++bool_value;");
+        self.pop_indentation_level();
+        if explicit {
+            c_source_builder_writeln!(self, "}} else {{");
+            self.push_indentation_level();
+            c_source_builder_writeln!(self, "if (i + 1 == argc) {{");
+            self.write_block(|this| {
+                this.write_error(
+                    "Expected value for option '%s' but no value was provided",
+                    &["arg"],
+                )
+            });
+            c_source_builder_writeln!(self, "bool_value = argv[++i];");
+            self.pop_indentation_level();
+        }
+        c_source_builder_writeln!(self, "}}");
+
+        c_source_builder_writeln!(
+            self,
+            "if (bool_value != NULL && !parse_bool_value(bool_value, &arg_res)) {{"
+        );
+        self.write_block(|this| {
+            this.write_error(
+                "Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)",
+                &["bool_value", "arg"],
+            )
+        });
+    }
+
+    /// Writes the parsing of a single value of `field` into a local `arg_res`.
+    fn write_parse_value(
+        &mut self,
+        strukt: &Struct,
+        field: &Field,
+        spec_metadata: &SpecMetadata,
+        uses_is_option: &mut bool,
+    ) {
+        let field_type = field.ty.value_type();
+
+        if !matches!(field_type, FieldType::Bool) {
+            let is_string = matches!(field_type, FieldType::String);
+            if is_string {
+                c_source_builder_writeln!(self, "if (i + 1 == argc) {{");
+            } else {
+                *uses_is_option = true;
+                c_source_builder_writeln!(
+                    self,
+                    "if (i + 1 == argc || {}_is_option(argv[i + 1])) {{",
+                    function_prefix(&strukt.name)
+                );
+            }
+            self.write_block(|this| {
+                this.write_error(
+                    "Expected value for option '%s' but no value was provided",
+                    &["arg"],
+                )
+            });
+            if matches!(field_type, FieldType::Struct(_)) {
+                // The struct parses the arguments from its value on.
+                c_source_builder_writeln!(self, "++i;");
+            } else {
+                c_source_builder_writeln!(self, "const char* arg_value = argv[++i];");
+            }
+        }
+
+        match field_type {
+            FieldType::String => {
+                c_source_builder_writeln!(self, "const char* arg_res = arg_value;");
+            }
+            FieldType::I16
+            | FieldType::U16
+            | FieldType::I32
+            | FieldType::U32
+            | FieldType::I64
+            | FieldType::U64
+            | FieldType::F32
+            | FieldType::F64 => {
+                self.write_parse_numeric_field(field, field_type);
+            }
+            FieldType::Bool => {
+                let value_separator = strukt.option_syntax().value_separator;
+                self.write_parse_bool_field(field.is_explicit_bool(), value_separator);
+            }
+            FieldType::Struct(struct_name) => {
+                let inner = spec_metadata.identifier_to_struct[struct_name.as_str()];
+                let prefix = function_prefix(struct_name);
+                c_source_builder_writeln!(self, "{struct_name} arg_res;");
+                c_source_builder_writeln!(
+                    self,
+                    "if (!{prefix}_parse(argc - i, argv + i, &arg_res, errbuf, errlen)) {{"
+                );
+                self.write_block(|this| {
+                    if owns_heap_memory(inner, spec_metadata, &mut HashSet::new()) {
+                        c_source_builder_writeln!(this, "{prefix}_free(&arg_res);");
+                    }
+                    c_source_builder_writeln!(this, "return false;");
+                });
+            }
+            FieldType::Vec(_) | FieldType::Optional(_) | FieldType::Error => unreachable!(),
+        }
+    }
+
+    /// Collects the arms of the fields, descending into flattened structs. `path` is where the
+    /// members of `fields` live (e.g. `out->` or `out->network.`) and `optional_flags` are the
+    /// `has_` members of the optional groups enclosing them.
+    #[allow(clippy::too_many_arguments)]
+    fn collect_parse_arms(
+        &mut self,
+        strukt: &Struct,
+        fields: &[Field],
+        spec_metadata: &SpecMetadata,
+        path: &str,
+        optional_flags: &mut Vec<String>,
+        mandatory_fields: &MandatoryFields,
+        uses_is_option: &mut bool,
+    ) -> Vec<MatchArm> {
+        let syntax = strukt.option_syntax();
+        let mut arms = Vec::new();
+
+        for field in fields {
+            let name = &field.name;
+
+            if field.has_attribute(AttributeType::Flatten) {
+                let FieldType::Struct(struct_name) = field.ty.value_type() else {
+                    unreachable!()
+                };
+                let flatten_type = spec_metadata.identifier_to_struct[struct_name.as_str()];
+                let inner_path = format!("{path}{name}.");
+
+                if matches!(field.ty, FieldType::Optional(_)) {
+                    let group_mandatory_fields = MandatoryFields::new(
+                        optional_group_prefix(&format!("{path}{name}")),
+                        flatten_type,
+                    );
+
+                    optional_flags.push(format!("{path}has_{name}"));
+                    arms.extend(self.collect_parse_arms(
+                        strukt,
+                        &flatten_type.fields,
+                        spec_metadata,
+                        &inner_path,
+                        optional_flags,
+                        &group_mandatory_fields,
+                        uses_is_option,
+                    ));
+                    optional_flags.pop();
+                } else {
+                    arms.extend(self.collect_parse_arms(
+                        strukt,
+                        &flatten_type.fields,
+                        spec_metadata,
+                        &inner_path,
+                        optional_flags,
+                        mandatory_fields,
+                        uses_is_option,
+                    ));
+                }
+                continue;
+            }
+
+            let options = spec_metadata
+                .struct_metadata(strukt)
+                .options
+                .iter()
+                .filter(|option| std::ptr::eq(option.field, field))
+                .map(|option| (option.name.clone(), option.case_insensitive))
+                .collect::<Vec<_>>();
+
+            if options.is_empty() {
+                continue;
+            }
+
+            let is_bool = matches!(field.ty.value_type(), FieldType::Bool);
+            let condition = options
+                .into_iter()
+                .map(|(option, case_insensitive)| {
+                    self.helpers.equals_ignore_case |= case_insensitive;
+                    let condition = option_match_condition(&option, case_insensitive);
+                    if !is_bool {
+                        condition
+                    } else if case_insensitive {
+                        self.helpers.starts_with_ignore_case = true;
+                        format!(
+                            r#"{condition} || starts_with_ignore_case(arg, "{option}{}")"#,
+                            syntax.value_separator
+                        )
+                    } else {
+                        format!(
+                            r#"{condition} || strncmp(arg, "{option}{}", {}) == 0"#,
+                            syntax.value_separator,
+                            option.len() + syntax.value_separator.len()
+                        )
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" || ");
+
+            let body = self.capture(|this| {
+                if field.is_deprecated() {
+                    this.write_deprecation_warning(field);
+                }
+                this.write_parse_value(strukt, field, spec_metadata, uses_is_option);
+
+                for flag in optional_flags.iter() {
+                    c_source_builder_writeln!(this, "{flag} = true;");
+                }
+
+                match &field.ty {
+                    FieldType::Vec(_) => this.write_push(&format!("{path}{name}"), "arg_res"),
+                    FieldType::Optional(_) => {
+                        c_source_builder_writeln!(this, "{path}has_{name} = true;");
+                        c_source_builder_writeln!(this, "{path}{name} = arg_res;");
+                    }
+                    _ => c_source_builder_writeln!(this, "{path}{name} = arg_res;"),
+                }
+
+                if let Some(index) = mandatory_fields.index_of(field) {
+                    c_source_builder_writeln!(
+                        this,
+                        "{}mandatory_fields_seen[{index}] = true;",
+                        mandatory_fields.prefix
+                    );
+                }
+            });
+            arms.push(MatchArm { condition, body });
+        }
+
+        arms
+    }
+
+    /// Finds the optional groups in the same order and with the same paths that
+    /// `collect_parse_arms` walks the flattened structs.
+    fn collect_optional_groups<'s>(
+        fields: &'s [Field],
+        spec_metadata: &SpecMetadata<'s>,
+        path: &str,
+        flags: &mut Vec<String>,
+        groups: &mut Vec<OptionalGroup<'s>>,
+    ) {
+        for field in fields
+            .iter()
+            .filter(|field| field.has_attribute(AttributeType::Flatten))
+        {
+            let FieldType::Struct(struct_name) = field.ty.value_type() else {
+                continue;
+            };
+            let flatten_type = spec_metadata.identifier_to_struct[struct_name.as_str()];
+            let name = &field.name;
+            let inner_path = format!("{path}{name}.");
+
+            if matches!(field.ty, FieldType::Optional(_)) {
+                flags.push(format!("{path}has_{name}"));
+                groups.push(OptionalGroup {
+                    flags: flags.clone(),
+                    mandatory_fields: MandatoryFields::new(
+                        optional_group_prefix(&format!("{path}{name}")),
+                        flatten_type,
+                    ),
+                });
+                Self::collect_optional_groups(
+                    &flatten_type.fields,
+                    spec_metadata,
+                    &inner_path,
+                    flags,
+                    groups,
+                );
+                flags.pop();
+            } else {
+                Self::collect_optional_groups(
+                    &flatten_type.fields,
+                    spec_metadata,
+                    &inner_path,
+                    flags,
+                    groups,
+                );
+            }
+        }
+    }
+
+    /// String members start out empty rather than NULL, so that they can always be printed.
+    fn write_string_initialization(
+        &mut self,
+        fields: &[Field],
+        spec_metadata: &SpecMetadata,
+        path: &str,
+    ) {
+        for field in fields {
+            let name = &field.name;
+            match &field.ty {
+                FieldType::Vec(_) => {}
+                ty => match ty.value_type() {
+                    FieldType::String => c_source_builder_writeln!(self, r#"{path}{name} = "";"#),
+                    FieldType::Struct(struct_name) => {
+                        let inner = spec_metadata.identifier_to_struct[struct_name.as_str()];
+                        self.write_string_initialization(
+                            &inner.fields,
+                            spec_metadata,
+                            &format!("{path}{name}."),
+                        );
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn write_mandatory_fields_declaration(&mut self, mandatory_fields: &MandatoryFields) {
+        let prefix = &mandatory_fields.prefix;
+        let count = mandatory_fields.fields.len();
+
+        // Empty arrays are not valid C.
+        if count == 0 {
+            return;
+        }
+
+        c_source_builder_write!(
+            self,
+            "static const char* const {prefix}mandatory_field_names[] = {{"
+        );
+        for field in &mandatory_fields.fields {
+            c_source_builder_append!(self, r#" "{}","#, field.name);
+        }
+        c_source_builder_appendln!(self, " }};");
+        c_source_builder_writeln!(
+            self,
+            "bool {prefix}mandatory_fields_seen[{count}] = {{ false }};"
+        );
+    }
+
+    fn write_mandatory_fields_check(
+        &mut self,
+        mandatory_fields: &MandatoryFields,
+        long_prefix: &str,
+    ) {
+        let prefix = &mandatory_fields.prefix;
+        let count = mandatory_fields.fields.len();
+
+        if count == 0 {
+            return;
+        }
+
+        c_source_builder_writeln!(self, "for (size_t i = 0; i != {count}; ++i) {{");
+        self.write_block(|this| {
+            c_source_builder_writeln!(this, "if (!{prefix}mandatory_fields_seen[i]) {{");
+            this.write_block(|this| {
+                this.write_error(
+                    &format!("{long_prefix}%s was required but it was not provided"),
+                    &[&format!("{prefix}mandatory_field_names[i]")],
+                )
+            });
+        });
+    }
+
+    /// Vec fields constrained with `min_occurs`/`max_occurs` have their number of values checked
+    /// once all the arguments are parsed.
+    fn write_occurrence_checks(&mut self, strukt: &Struct, field: &Field) {
+        let option_name = field_option_name(strukt, field);
+        let name = &field.name;
+
+        if let Some(min) = field.min_occurs().filter(|&min| min > 0) {
+            c_source_builder_writeln!(self, "if (out->{name}.len < {min}) {{");
+            self.write_block(|this| {
+                this.write_error(
+                    &format!(
+                        "Expected at least {min} occurrences of {option_name} but %zu were provided"
+                    ),
+                    &[&format!("out->{name}.len")],
+                )
+            });
+        }
+
+        if let Some(max) = field.max_occurs() {
+            c_source_builder_writeln!(self, "if (out->{name}.len > {max}) {{");
+            self.write_block(|this| {
+                this.write_error(
+                    &format!(
+                        "Expected at most {max} occurrences of {option_name} but %zu were provided"
+                    ),
+                    &[&format!("out->{name}.len")],
+                )
+            });
+        }
+    }
+
+    fn write_if_else_chain(&mut self, arms: &[MatchArm], else_body: &str) {
+        for (i, arm) in arms.iter().enumerate() {
+            if i == 0 {
+                c_source_builder_writeln!(self, "if ({}) {{", arm.condition);
+            } else {
+                c_source_builder_writeln!(self, "}} else if ({}) {{", arm.condition);
+            }
+            self.buffer.push_str(&arm.body);
+        }
+
+        c_source_builder_writeln!(self, "}} else {{");
+        self.buffer.push_str(else_body);
+        c_source_builder_writeln!(self, "}}");
+    }
+
+    fn write_is_option_function(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let prefix = function_prefix(&strukt.name);
+        let (case_insensitive_options, options): (Vec<_>, Vec<_>) =
+            struct_options(strukt, spec_metadata)
+                .into_iter()
+                .partition(|(_, case_insensitive)| *case_insensitive);
+
+        c_source_builder_writeln!(self, "static bool {prefix}_is_option(const char* arg) {{");
+        self.push_indentation_level();
+
+        let groups = [
+            (
+                "valid_options",
+                options,
+                "strcmp(arg, valid_options[i]) == 0",
+            ),
+            (
+                "case_insensitive_options",
+                case_insensitive_options,
+                "equals_ignore_case(arg, case_insensitive_options[i])",
+            ),
+        ];
+        let mut has_options = false;
+        for (array_name, options, condition) in groups {
+            if options.is_empty() {
+                continue;
+            }
+            has_options = true;
+            self.helpers.equals_ignore_case |= array_name == "case_insensitive_options";
+
+            c_source_builder_writeln!(self, "static const char* const {array_name}[] = {{");
+            self.push_indentation_level();
+            let num_options = options.len();
+            for (option, _) in options {
+                c_source_builder_writeln!(self, r#""{option}","#);
+            }
+            self.pop_indentation_level();
+            c_source_builder_writeln!(self, "}};\n");
+
+            c_source_builder_writeln!(self, "for (size_t i = 0; i != {num_options}; ++i) {{");
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "if ({condition}) {{");
+                this.write_block(|this| c_source_builder_writeln!(this, "return true;"));
+            });
+            c_source_builder_writeln!(self);
+        }
+
+        if !has_options {
+            c_source_builder_writeln!(self, "(void)arg;");
+        }
+        c_source_builder_writeln!(self, "return false;");
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}\n");
+    }
+
+    fn write_help_function(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let lines = help_lines(strukt, spec_metadata);
+
+        c_source_builder_writeln!(self, "void {}_help(void) {{", function_prefix(&strukt.name));
+        self.push_indentation_level();
+        c_source_builder_writeln!(self, "printf(");
+        self.push_indentation_level();
+        for (i, line) in lines.iter().enumerate() {
+            let end = if i + 1 == lines.len() { ");" } else { "" };
+            c_source_builder_writeln!(self, r#""{line}\n"{end}"#);
+        }
+        self.pop_indentation_level();
+        if let Some(after_help) = strukt.after_help() {
+            c_source_builder_writeln!(self, r#"printf("%s", "{after_help}");"#);
+        }
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}\n");
+    }
+
+    fn write_parse_function(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let struct_name = &strukt.name;
+        let prefix = function_prefix(struct_name);
+        let syntax = strukt.option_syntax();
+
+        let mandatory_fields = MandatoryFields::new(String::new(), strukt);
+        let mut optional_groups = Vec::new();
+        Self::collect_optional_groups(
+            &strukt.fields,
+            spec_metadata,
+            "out->",
+            &mut Vec::new(),
+            &mut optional_groups,
+        );
+
+        // The function is written aside, since whether it needs `is_option` is only known once the
+        // arms are collected.
+        let previous_buffer = std::mem::take(&mut self.buffer);
+        let mut uses_is_option = false;
+        c_source_builder_writeln!(
+            self,
+            "bool {prefix}_parse(int argc, char** argv, {struct_name}* out, char* errbuf, size_t errlen) {{"
+        );
+        self.push_indentation_level();
+
+        self.write_mandatory_fields_declaration(&mandatory_fields);
+        for group in &optional_groups {
+            self.write_mandatory_fields_declaration(&group.mandatory_fields);
+        }
+
+        c_source_builder_writeln!(self, "memset(out, 0, sizeof(*out));");
+        self.write_string_initialization(&strukt.fields, spec_metadata, "out->");
+        c_source_builder_writeln!(self);
+
+        // The program name is skipped by the main struct.
+        let start = usize::from(strukt.has_attribute(AttributeType::Main));
+        c_source_builder_writeln!(self, "for (int i = {start}; i < argc; ++i) {{");
+        self.push_indentation_level();
+        c_source_builder_writeln!(self, "const char* arg = argv[i];");
+
+        if let Some(trailing_field) = strukt.trailing_field() {
+            let separator = strukt.separator();
+            c_source_builder_writeln!(self, r#"if (strcmp(arg, "{separator}") == 0) {{"#);
+            self.write_block(|this| {
+                c_source_builder_writeln!(this, "for (++i; i < argc; ++i) {{");
+                this.write_block(|this| {
+                    this.write_push(&format!("out->{}", trailing_field.name), "argv[i]")
+                });
+                c_source_builder_writeln!(this, "break;");
+            });
+        }
+
+        let mut arms = vec![MatchArm {
+            condition: format!(
+                r#"strcmp("{}", arg) == 0 || strcmp("{}", arg) == 0"#,
+                syntax.short_option("h"),
+                syntax.long_option("help")
+            ),
+            body: self.capture(|this| {
+                c_source_builder_writeln!(this, "{prefix}_help();");
+                c_source_builder_writeln!(this, "exit(0);");
+            }),
+        }];
+        arms.extend(self.collect_parse_arms(
+            strukt,
+            &strukt.fields,
+            spec_metadata,
+            "out->",
+            &mut Vec::new(),
+            &mandatory_fields,
+            &mut uses_is_option,
+        ));
+
+        let unknown_option_body =
+            self.capture(|this| this.write_error("Unknown option '%s'", &["arg"]));
+        self.write_if_else_chain(&arms, &unknown_option_body);
+
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}\n");
+
+        self.write_mandatory_fields_check(&mandatory_fields, syntax.long_prefix);
+        for group in &optional_groups {
+            if group.mandatory_fields.fields.is_empty() {
+                continue;
+            }
+            c_source_builder_writeln!(self, "if ({}) {{", group.flags.join(" && "));
+            self.write_block(|this| {
+                this.write_mandatory_fields_check(&group.mandatory_fields, syntax.long_prefix)
+            });
+        }
+
+        for field in &strukt.fields {
+            self.write_occurrence_checks(strukt, field);
+        }
+
+        c_source_builder_writeln!(self, "return true;");
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}\n");
+        let function = std::mem::replace(&mut self.buffer, previous_buffer);
+
+        if uses_is_option {
+            self.write_is_option_function(strukt, spec_metadata);
+        }
+        self.buffer.push_str(&function);
+    }
+
+    fn write_print_debug_function(&mut self, strukt: &Struct) {
+        /// Prints a single value, between the `label` and `suffix` of the format.
+        fn print_statement(
+            label: &str,
+            suffix: &str,
+            field_type: &FieldType,
+            value: &str,
+        ) -> String {
+            match field_type {
+                FieldType::Struct(name) => {
+                    format!("{}_print_debug(&{value});", function_prefix(name))
+                }
+                FieldType::Bool => {
+                    format!(r#"printf("{label}%s{suffix}", {value} ? "true" : "false");"#)
+                }
+                ty => format!(
+                    r#"printf("{label}{}{suffix}", {value});"#,
+                    printf_conversion(ty)
+                ),
+            }
+        }
+
+        let struct_name = &strukt.name;
+        c_source_builder_writeln!(
+            self,
+            "void {}_print_debug(const {struct_name}* self) {{",
+            function_prefix(struct_name)
+        );
+        self.push_indentation_level();
+
+        c_source_builder_writeln!(self, r#"printf("{struct_name} {{\n");"#);
+        for field in &strukt.fields {
+            let name = &field.name;
+            match &field.ty {
+                _ if field.has_attribute(AttributeType::Secret) => {
+                    c_source_builder_writeln!(self, r#"printf("\t{name}: ********\n");"#);
+                }
+                FieldType::Vec(inner) => {
+                    c_source_builder_writeln!(self, r#"printf("\t{name}: [\n");"#);
+                    c_source_builder_writeln!(
+                        self,
+                        "for (size_t i = 0; i != self->{name}.len; ++i) {{"
+                    );
+                    let statement =
+                        print_statement("\\t", ",\\n", inner, &format!("self->{name}.items[i]"));
+                    self.write_block(|this| c_source_builder_writeln!(this, "{statement}"));
+                    c_source_builder_writeln!(self, r#"printf("\t]\n");"#);
+                }
+                FieldType::Optional(inner) => {
+                    let statement = print_statement(
+                        &format!("\\t{name}: "),
+                        "\\n",
+                        inner,
+                        &format!("self->{name}"),
+                    );
+                    c_source_builder_writeln!(self, "if (self->has_{name}) {{");
+                    self.push_indentation_level();
+                    c_source_builder_writeln!(self, "{statement}");
+                    self.pop_indentation_level();
+                    c_source_builder_writeln!(self, "}} else {{");
+                    self.write_block(|this| {
+                        c_source_builder_writeln!(this, r#"printf("\t{name}: none\n");"#)
+                    });
+                }
+                ty => {
+                    let statement = print_statement(
+                        &format!("\\t{name}: "),
+                        "\\n",
+                        ty,
+                        &format!("self->{name}"),
+                    );
+                    c_source_builder_writeln!(self, "{statement}");
+                }
+            }
+        }
+        c_source_builder_writeln!(self, r#"printf("}}\n");"#);
+
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}\n");
+    }
+
+    fn write_free_function<'s>(&mut self, strukt: &'s Struct, spec_metadata: &SpecMetadata<'s>) {
+        let struct_name = &strukt.name;
+        let inner_owns_heap = |ty: &FieldType| match ty {
+            FieldType::Struct(name) => owns_heap_memory(
+                spec_metadata.identifier_to_struct[name.as_str()],
+                spec_metadata,
+                &mut HashSet::new(),
+            ),
+            _ => false,
+        };
+
+        c_source_builder_writeln!(
+            self,
+            "void {}_free({struct_name}* self) {{",
+            function_prefix(struct_name)
+        );
+        self.push_indentation_level();
+        for field in &strukt.fields {
+            let name = &field.name;
+            match &field.ty {
+                FieldType::Vec(inner) => {
+                    if let FieldType::Struct(inner_name) = inner.as_ref() {
+                        if inner_owns_heap(inner) {
+                            c_source_builder_writeln!(
+                                self,
+                                "for (size_t i = 0; i != self->{name}.len; ++i) {{"
+                            );
+                            self.write_block(|this| {
+                                c_source_builder_writeln!(
+                                    this,
+                                    "{}_free(&self->{name}.items[i]);",
+                                    function_prefix(inner_name)
+                                )
+                            });
+                        }
+                    }
+                    c_source_builder_writeln!(self, "free(self->{name}.items);");
+                    c_source_builder_writeln!(self, "self->{name}.items = NULL;");
+                    c_source_builder_writeln!(self, "self->{name}.len = 0;");
+                    c_source_builder_writeln!(self, "self->{name}.capacity = 0;");
+                }
+                FieldType::Optional(inner) if inner_owns_heap(inner) => {
+                    let FieldType::Struct(inner_name) = inner.as_ref() else {
+                        unreachable!()
+                    };
+                    c_source_builder_writeln!(self, "if (self->has_{name}) {{");
+                    self.write_block(|this| {
+                        c_source_builder_writeln!(
+                            this,
+                            "{}_free(&self->{name});",
+                            function_prefix(inner_name)
+                        )
+                    });
+                }
+                FieldType::Struct(inner_name) if inner_owns_heap(&field.ty) => {
+                    c_source_builder_writeln!(
+                        self,
+                        "{}_free(&self->{name});",
+                        function_prefix(inner_name)
+                    );
+                }
+                _ => {}
+            }
+        }
+        self.pop_indentation_level();
+        c_source_builder_writeln!(self, "}}\n");
+    }
+}
+
+/// Generates the C99 header and source implementing the spec, in that order. The source includes
+/// the header through `header_name`.
+///
+/// Unlike the C++ backend nothing is allocated except for `Vec` fields: strings point into the
+/// arguments and errors are written to a caller provided buffer instead of being printed. The spec
+/// must have been checked with `semantic::check_c_support` first.
+pub(crate) fn generate_cli(
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+    header_name: &str,
+) -> (String, String) {
+    let owns_heap = spec
+        .structs
+        .iter()
+        .map(|strukt| owns_heap_memory(strukt, spec_metadata, &mut HashSet::new()))
+        .collect::<Vec<_>>();
+
+    let mut header_builder = CSourceBuilder::new(*options);
+    if let Some(provenance) = provenance {
+        header_builder.write_banner(provenance);
+    }
+    c_source_builder_writeln!(header_builder, "#ifndef _CLI_H_");
+    c_source_builder_writeln!(header_builder, "#define _CLI_H_\n");
+    c_source_builder_writeln!(header_builder, "#include <stdbool.h>");
+    c_source_builder_writeln!(header_builder, "#include <stddef.h>");
+    c_source_builder_writeln!(header_builder, "#include <stdint.h>\n");
+    c_source_builder_writeln!(header_builder, "#ifdef __cplusplus");
+    c_source_builder_writeln!(header_builder, "extern \"C\" {{");
+    c_source_builder_writeln!(header_builder, "#endif\n");
+    header_builder.write_ownership_comment(owns_heap.contains(&true));
+    for strukt in &spec.structs {
+        header_builder.write_struct_definition(strukt);
+    }
+    for (strukt, &owns_heap) in spec.structs.iter().zip(&owns_heap) {
+        header_builder.write_struct_prototypes(strukt, owns_heap);
+    }
+    c_source_builder_writeln!(header_builder, "#ifdef __cplusplus");
+    c_source_builder_writeln!(header_builder, "}}");
+    c_source_builder_writeln!(header_builder, "#endif\n");
+    c_source_builder_writeln!(header_builder, "#endif // _CLI_H_");
+
+    // The functions are written first, so that only the helpers they use are written before them.
+    let mut source_builder = CSourceBuilder::new(*options);
+    for (strukt, &owns_heap) in spec.structs.iter().zip(&owns_heap) {
+        if !strukt.is_doc_hidden() {
+            source_builder.write_help_function(strukt, spec_metadata);
+            source_builder.write_parse_function(strukt, spec_metadata);
+        }
+        source_builder.write_print_debug_function(strukt);
+        if owns_heap {
+            source_builder.write_free_function(strukt, spec_metadata);
+        }
+    }
+    let functions = std::mem::take(&mut source_builder.buffer);
+
+    if let Some(provenance) = provenance {
+        source_builder.write_banner(provenance);
+    }
+    c_source_builder_writeln!(source_builder, "#include \"{header_name}\"\n");
+    let helpers = source_builder.helpers;
+    if helpers.equals_ignore_case || helpers.starts_with_ignore_case || helpers.parse_bool_value {
+        c_source_builder_writeln!(source_builder, "#include <ctype.h>");
+    }
+    if helpers.parses_numbers() {
+        c_source_builder_writeln!(source_builder, "#include <errno.h>");
+    }
+    c_source_builder_writeln!(source_builder, "#include <inttypes.h>");
+    if helpers.set_error {
+        c_source_builder_writeln!(source_builder, "#include <stdarg.h>");
+    }
+    c_source_builder_writeln!(source_builder, "#include <stdio.h>");
+    c_source_builder_writeln!(source_builder, "#include <stdlib.h>");
+    c_source_builder_writeln!(source_builder, "#include <string.h>\n");
+    source_builder.write_helper_functions();
+    source_builder.buffer.push_str(&functions);
+    // Every function ends with a blank line, which is not needed after the last one.
+    source_builder.buffer.pop();
+
+    (header_builder.result(), source_builder.result())
+}
+
+/// Writes the header to the output path and the source next to it, with a `.c` extension.
+pub struct CBackend<'a> {
+    pub output: &'a str,
+    pub provenance: Option<&'a Provenance<'a>>,
+}
+
+impl Backend for CBackend<'_> {
+    fn output_paths(&self) -> Vec<String> {
+        let source = std::path::Path::new(self.output).with_extension("c");
+        vec![
+            self.output.to_string(),
+            source.to_string_lossy().into_owned(),
+        ]
+    }
+
+    fn generate<'s>(
+        &self,
+        spec: &'s Spec,
+        spec_metadata: &SpecMetadata<'s>,
+        options: &GenOptions,
+    ) -> Result<Vec<OutputFile>, Vec<Diagnostic>> {
+        check_c_support(spec, spec_metadata, options.no_regex_validation)?;
+
+        // The source includes the header by its name, as they are written next to each other.
+        let header_name = std::path::Path::new(self.output).file_name().map_or_else(
+            || self.output.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let (header, source) =
+            generate_cli(spec, spec_metadata, options, self.provenance, &header_name);
+
+        Ok(self
+            .output_paths()
+            .into_iter()
+            .zip([header, source])
+            .map(|(path, contents)| OutputFile { path, contents })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::{check_c_support, check_semantics};
+
+    fn generate(source: &str) -> (String, String) {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        check_c_support(&spec, &metadata, false).unwrap();
+        generate_cli(&spec, &metadata, &GenOptions::default(), None, "cli.h")
+    }
+
+    #[test]
+    fn function_prefixes_are_snake_case() {
+        assert_eq!(function_prefix("Args"), "args");
+        assert_eq!(function_prefix("ServerConfig"), "server_config");
+        assert_eq!(function_prefix("HTTPServer"), "http_server");
+        assert_eq!(function_prefix("Ipv4Options"), "ipv4_options");
+    }
+
+    #[test]
+    fn free_is_only_generated_for_heap_allocating_structs() {
+        let (header, source) = generate(
+            "struct Common { #[long] values: Vec<u32> }
+             #[main] struct Args { #[long] name: Optional<string>, #[flatten] common: Common }
+             struct Other { #[long] count: i32 }",
+        );
+
+        assert!(header.contains("void args_free(Args* self);"));
+        assert!(header.contains("void common_free(Common* self);"));
+        assert!(!header.contains("other_free"));
+        assert!(header.contains("bool has_name;"));
+        assert!(source.contains("common_free(&self->common);"));
+    }
+
+    #[test]
+    fn unused_helpers_are_left_out() {
+        let (_, source) = generate("#[main] struct Args { #[long] name: string }");
+
+        assert!(source.contains("static void set_error("));
+        assert!(!source.contains("parse_signed"));
+        assert!(!source.contains("grow_array"));
+        assert!(!source.contains("_is_option"));
+    }
+
+    #[test]
+    fn structs_containing_themselves_are_unsupported() {
+        for source in [
+            "#[main] struct Net { #[long] port: u16, #[long] ld: Optional<Net> }",
+            "#[main] struct A { #[long] x: u32, b: Optional<B> }
+             struct B { #[long] y: u32, a: A }",
+        ] {
+            let spec = Parser::new(source).parse().unwrap();
+            let metadata = check_semantics(&spec).unwrap();
+            let errors = check_c_support(&spec, &metadata, false).unwrap_err();
+            assert!(errors.iter().all(
+                |error| error.message == "Structs containing themselves are not supported in C"
+            ));
+        }
+
+        // The values of a Vec are allocated separately.
+        let spec = Parser::new("#[main] struct Net { #[long] port: u16, ld: Vec<Net> }")
+            .parse()
+            .unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        assert!(check_c_support(&spec, &metadata, false).is_ok());
+    }
+}
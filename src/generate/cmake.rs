@@ -0,0 +1,215 @@
+/// The helper defines `generate_cli(INPUT <spec> OUTPUT <header> [TARGET <name>] [OPTIONS ...])`,
+/// which regenerates the header whenever the spec changes. `@VERSION@` and `@EXECUTABLE@` are
+/// substituted when the helper is written.
+const CMAKE_HELPER_TEMPLATE: &str = r#"# Generated by cli-generator @VERSION@. Do not edit by hand.
+#
+# Usage:
+#   include(cli_generator.cmake)
+#   generate_cli(INPUT spec.cli OUTPUT generated/cli.h [TARGET name] [OPTIONS ...])
+#   add_executable(app main.cpp)
+#   add_dependencies(app name)
+#
+# INPUT is relative to the current source directory and OUTPUT to the current binary directory.
+# TARGET defaults to the output file name with `_cli` appended, OPTIONS are passed to the generator.
+# The absolute path of the generated header is stored in `<TARGET>_OUTPUT` in the caller's scope.
+
+set(CLI_GENERATOR_EXECUTABLE "@EXECUTABLE@" CACHE FILEPATH "The cli-generator binary")
+
+function(generate_cli)
+  cmake_parse_arguments(ARG "" "INPUT;OUTPUT;TARGET" "OPTIONS" ${ARGN})
+
+  if(NOT ARG_INPUT OR NOT ARG_OUTPUT)
+    message(FATAL_ERROR "generate_cli requires both INPUT and OUTPUT")
+  endif()
+
+  get_filename_component(input "${ARG_INPUT}" ABSOLUTE BASE_DIR "${CMAKE_CURRENT_SOURCE_DIR}")
+  get_filename_component(output "${ARG_OUTPUT}" ABSOLUTE BASE_DIR "${CMAKE_CURRENT_BINARY_DIR}")
+  get_filename_component(output_dir "${output}" DIRECTORY)
+
+  if(NOT ARG_TARGET)
+    get_filename_component(output_name "${output}" NAME_WE)
+    set(ARG_TARGET "${output_name}_cli")
+  endif()
+
+  add_custom_command(
+    OUTPUT "${output}"
+    COMMAND "${CMAKE_COMMAND}" -E make_directory "${output_dir}"
+    COMMAND "${CLI_GENERATOR_EXECUTABLE}" -i "${input}" -o "${output}" ${ARG_OPTIONS}
+    DEPENDS "${input}" "${CLI_GENERATOR_EXECUTABLE}"
+    COMMENT "Generating ${ARG_OUTPUT} from ${ARG_INPUT}"
+    VERBATIM
+  )
+
+  add_custom_target(${ARG_TARGET} DEPENDS "${output}")
+  set(${ARG_TARGET}_OUTPUT "${output}" PARENT_SCOPE)
+endfunction()
+"#;
+
+/// Generates the CMake include file that runs `executable` to keep generated headers up to date.
+pub fn generate_cmake_helper(executable: &str) -> String {
+    // CMake accepts forward slashes on every platform, and backslashes would start escapes.
+    let executable = executable.replace('\\', "/").replace('"', "\\\"");
+
+    CMAKE_HELPER_TEMPLATE
+        .replace("@VERSION@", env!("CARGO_PKG_VERSION"))
+        .replace("@EXECUTABLE@", &executable)
+}
+
+/// The snippet written by `--emit-cmake`, replaying a single invocation of the generator. The
+/// `@...@` placeholders are substituted when it is written, with every value already quoted.
+const CMAKE_SNIPPET_TEMPLATE: &str = r#"# Generated by cli-generator @VERSION@. Do not edit by hand.
+#
+# Runs the generator again, with the same arguments, whenever the spec changes:
+#   include(<this file>)
+#   add_dependencies(app @TARGET@_cli)
+
+add_custom_command(
+  OUTPUT @OUTPUTS@
+  COMMAND @COMMAND@
+  DEPENDS @INPUT@ @EXECUTABLE@
+  WORKING_DIRECTORY @WORKING_DIRECTORY@
+  COMMENT @COMMENT@
+  VERBATIM
+)
+
+add_custom_target(@TARGET@_cli DEPENDS @OUTPUTS@)
+"#;
+
+/// The options that only check or write auxiliary files, which the build doesn't repeat.
+const NOT_REPLAYED_FLAGS: &[&str] = &["--check", "--diff", "--verify"];
+const NOT_REPLAYED_OPTIONS: &[&str] = &["--emit-cmake", "--cmake-helper"];
+
+/// The arguments of this invocation that the build runs again, leaving out the ones that check
+/// the output instead of writing it or write the CMake files themselves.
+pub fn replayed_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if NOT_REPLAYED_FLAGS.contains(&arg.as_str()) {
+            continue;
+        }
+        if NOT_REPLAYED_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+            continue;
+        }
+        if NOT_REPLAYED_OPTIONS
+            .iter()
+            .any(|option| arg.starts_with(&format!("{option}=")))
+        {
+            continue;
+        }
+        res.push(arg);
+    }
+
+    res
+}
+
+/// Quotes `value` as a single CMake argument, so that spaces and semicolons don't split it and
+/// `${...}` isn't expanded.
+fn quote_cmake(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$");
+
+    format!("\"{escaped}\"")
+}
+
+/// Generates a CMake snippet running `executable` with `args` in `working_directory` whenever
+/// `input` changes, to bring `outputs` up to date. Relative paths are taken from the working
+/// directory, like they were when the generator was run.
+pub fn generate_cmake_snippet(
+    executable: &str,
+    working_directory: &str,
+    args: &[String],
+    input: &str,
+    outputs: &[String],
+) -> String {
+    let absolute = |path: &str| {
+        let path = std::path::Path::new(working_directory).join(path);
+        quote_cmake(&path.to_string_lossy())
+    };
+    // Named after the first output like `generate_cli` does, e.g. `cli_cli` for `cli.h`.
+    let named_after = outputs.first().map_or(input, String::as_str);
+    let target = std::path::Path::new(named_after)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let outputs = outputs
+        .iter()
+        .map(|output| absolute(output))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let command = std::iter::once(quote_cmake(executable))
+        .chain(args.iter().map(|arg| quote_cmake(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    CMAKE_SNIPPET_TEMPLATE
+        .replace("@VERSION@", env!("CARGO_PKG_VERSION"))
+        .replace("@TARGET@", &target)
+        .replace("@OUTPUTS@", &outputs)
+        .replace("@COMMAND@", &command)
+        .replace("@INPUT@", &absolute(input))
+        .replace("@EXECUTABLE@", &quote_cmake(executable))
+        .replace("@WORKING_DIRECTORY@", &quote_cmake(working_directory))
+        .replace(
+            "@COMMENT@",
+            &quote_cmake(&format!("Generating the cli from {input}")),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_replays_the_invocation() {
+        let args = [
+            "-i",
+            "my spec.cli",
+            "-o",
+            "out/cli.h",
+            "--target",
+            "c",
+            "--emit-cmake",
+            "cli_gen.cmake",
+            "--check",
+            "--cmake-helper=helper.cmake",
+        ]
+        .map(String::from);
+        let args = replayed_args(args);
+        assert_eq!(
+            args,
+            ["-i", "my spec.cli", "-o", "out/cli.h", "--target", "c"]
+        );
+
+        let snippet = generate_cmake_snippet(
+            "/usr/bin/cli-generator",
+            "/home/me/project",
+            &args,
+            "my spec.cli",
+            &[String::from("out/cli.h"), String::from("out/cli.c")],
+        );
+        assert!(snippet.contains(
+            r#"  COMMAND "/usr/bin/cli-generator" "-i" "my spec.cli" "-o" "out/cli.h" "--target" "c""#
+        ));
+        assert!(snippet
+            .contains(r#"  OUTPUT "/home/me/project/out/cli.h" "/home/me/project/out/cli.c""#));
+        assert!(snippet.contains(r#"  DEPENDS "/home/me/project/my spec.cli""#));
+        assert!(snippet.contains("add_custom_target(cli_cli DEPENDS"));
+    }
+
+    #[test]
+    fn arguments_are_quoted() {
+        assert_eq!(quote_cmake("a b;c"), r#""a b;c""#);
+        assert_eq!(
+            quote_cmake(r#"C:\dir\"x" ${HOME}"#),
+            r#""C:\\dir\\\"x\" \${HOME}""#
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::generate::completions::{CompletionModel, OptionValue, ScriptBuilder};
+use crate::generate::{GenOptions, Provenance};
+use std::fmt::Write;
+
+/// Once the separator of the trailing arguments was given, everything after it is left alone.
+fn write_separator_check(builder: &mut ScriptBuilder, separator: &str) {
+    script_builder_writeln!(builder, "local i");
+    script_builder_writeln!(builder, "for ((i = 1; i < COMP_CWORD; i++)); do");
+    builder.push_indentation_level();
+    script_builder_writeln!(
+        builder,
+        r#"if [[ "${{COMP_WORDS[i]}}" == "{separator}" ]]; then"#
+    );
+    builder.push_indentation_level();
+    script_builder_writeln!(builder, "return");
+    builder.pop_indentation_level();
+    script_builder_writeln!(builder, "fi");
+    builder.pop_indentation_level();
+    script_builder_writeln!(builder, "done\n");
+}
+
+/// Completes the value of the option before the cursor, when that option takes one.
+fn write_value_cases(builder: &mut ScriptBuilder, model: &CompletionModel) {
+    let cases = model
+        .options
+        .iter()
+        .filter(|option| option.value != OptionValue::None)
+        .collect::<Vec<_>>();
+    if cases.is_empty() {
+        return;
+    }
+
+    script_builder_writeln!(builder, r#"case "$prev" in"#);
+    builder.push_indentation_level();
+    for option in cases {
+        script_builder_writeln!(builder, "{})", option.names().collect::<Vec<_>>().join("|"));
+        builder.push_indentation_level();
+        match &option.value {
+            OptionValue::Choices(choices) => {
+                script_builder_writeln!(
+                    builder,
+                    r#"COMPREPLY=($(compgen -W "{}" -- "$cur"))"#,
+                    choices.join(" ")
+                );
+            }
+            OptionValue::InputFile => {
+                // Quotes the files with spaces, and puts a slash after directories.
+                script_builder_writeln!(builder, "compopt -o filenames 2>/dev/null");
+                script_builder_writeln!(builder, r#"COMPREPLY=($(compgen -f -- "$cur"))"#);
+            }
+            OptionValue::Directory => {
+                script_builder_writeln!(builder, "compopt -o filenames 2>/dev/null");
+                script_builder_writeln!(builder, r#"COMPREPLY=($(compgen -d -- "$cur"))"#);
+            }
+            _ => {}
+        }
+        script_builder_writeln!(builder, "return");
+        script_builder_writeln!(builder, ";;");
+        builder.pop_indentation_level();
+    }
+    builder.pop_indentation_level();
+    script_builder_writeln!(builder, "esac\n");
+}
+
+fn write_completion_function(builder: &mut ScriptBuilder, model: &CompletionModel) {
+    let name = model.function_name();
+
+    script_builder_writeln!(builder, "{name}() {{");
+    builder.push_indentation_level();
+    script_builder_writeln!(builder, "local cur prev");
+    script_builder_writeln!(builder, r#"cur="${{COMP_WORDS[COMP_CWORD]}}""#);
+    script_builder_writeln!(builder, r#"prev="${{COMP_WORDS[COMP_CWORD-1]}}""#);
+    script_builder_writeln!(builder, "COMPREPLY=()\n");
+
+    if let Some(separator) = &model.separator {
+        write_separator_check(builder, separator);
+    }
+    write_value_cases(builder, model);
+
+    let mut names = vec!["-h", "--help"];
+    names.extend(model.options.iter().flat_map(|option| option.names()));
+    script_builder_writeln!(
+        builder,
+        r#"COMPREPLY=($(compgen -W "{}" -- "$cur"))"#,
+        names.join(" ")
+    );
+    builder.pop_indentation_level();
+    script_builder_writeln!(builder, "}}\n");
+
+    script_builder_writeln!(builder, "complete -F {name} {}", model.command);
+}
+
+/// Writes a bash completion script registering a completion function with `complete -F`.
+pub(crate) fn generate_completions(
+    model: &CompletionModel,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+) -> String {
+    let mut builder = ScriptBuilder::new(*options);
+
+    if let Some(provenance) = provenance {
+        builder.write_banner(provenance);
+    }
+    write_completion_function(&mut builder, model);
+
+    builder.result()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate::completions::tests::{generate, GOLDEN_SPEC};
+    use crate::generate::completions::Shell;
+
+    #[test]
+    fn golden_completions() {
+        assert_eq!(
+            generate(Shell::Bash, GOLDEN_SPEC),
+            include_str!("../golden/completions.bash")
+        );
+    }
+
+    #[test]
+    fn flags_have_no_value_case() {
+        let generated = generate(
+            Shell::Bash,
+            "#[main] struct Cli { #[short, long] verbose: bool }",
+        );
+
+        assert!(!generated.contains("case"));
+        assert!(
+            generated.contains(r#"COMPREPLY=($(compgen -W "-h --help -v --verbose" -- "$cur"))"#)
+        );
+    }
+}
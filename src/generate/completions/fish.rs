@@ -0,0 +1,111 @@
+use crate::generate::completions::{CompletedOption, CompletionModel, OptionValue, ScriptBuilder};
+use crate::generate::{GenOptions, Provenance};
+use std::fmt::Write;
+
+/// Single quotes text for fish, where only backslashes and quotes are escaped.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// The `-s`/`-l`/`-o` flags declaring the spellings of the option. fish only knows of options
+/// starting with dashes, so the ones spelled with another prefix are left out.
+fn name_flags(option: &CompletedOption) -> Vec<String> {
+    option
+        .names()
+        .filter_map(|name| {
+            if let Some(long) = name.strip_prefix("--") {
+                Some(format!("-l {long}"))
+            } else if let Some(short) = name.strip_prefix('-') {
+                if short.chars().count() == 1 {
+                    Some(format!("-s {short}"))
+                } else {
+                    Some(format!("-o {short}"))
+                }
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The `complete` command of an option, if fish can spell it.
+fn complete_command(command: &str, option: &CompletedOption) -> Option<String> {
+    let names = name_flags(option);
+    if names.is_empty() {
+        return None;
+    }
+
+    let mut res = format!("complete -c {command} {}", names.join(" "));
+    match &option.value {
+        OptionValue::None => {}
+        OptionValue::Any => res.push_str(" -r"),
+        OptionValue::InputFile => res.push_str(" -r -F"),
+        // Files are completed unless told otherwise, which would suggest overwriting them.
+        OptionValue::OutputFile => res.push_str(" -x"),
+        OptionValue::Directory => res.push_str(" -x -a '(__fish_complete_directories)'"),
+        // `-x` takes a value, completed from the arguments only.
+        OptionValue::Choices(choices) => {
+            write!(res, " -x -a {}", quote(&choices.join(" "))).unwrap()
+        }
+    }
+    if let Some(description) = &option.description {
+        write!(res, " -d {}", quote(description)).unwrap();
+    }
+
+    Some(res)
+}
+
+/// Writes fish completions, one `complete` command per option, to be installed as
+/// `<command>.fish` in a directory of `$fish_complete_path`.
+pub(crate) fn generate_completions(
+    model: &CompletionModel,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+) -> String {
+    let mut builder = ScriptBuilder::new(*options);
+
+    if let Some(provenance) = provenance {
+        builder.write_banner(provenance);
+    }
+
+    let command = &model.command;
+    script_builder_writeln!(
+        builder,
+        "complete -c {command} -s h -l help -d 'Print help'"
+    );
+    for line in model
+        .options
+        .iter()
+        .filter_map(|option| complete_command(command, option))
+    {
+        script_builder_writeln!(builder, "{line}");
+    }
+
+    builder.result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::completions::tests::{generate, GOLDEN_SPEC};
+    use crate::generate::completions::Shell;
+
+    #[test]
+    fn golden_completions() {
+        assert_eq!(
+            generate(Shell::Fish, GOLDEN_SPEC),
+            include_str!("../golden/completions.fish")
+        );
+    }
+
+    #[test]
+    fn options_fish_cannot_spell_are_left_out() {
+        let generated = generate(
+            Shell::Fish,
+            r#"#[main, prefix = "/"] struct Cli { #[short, long] verbose: bool }"#,
+        );
+
+        assert_eq!(generated.lines().count(), 1);
+        assert_eq!(quote(r"it's a\path"), r"'it\'s a\\path'");
+    }
+}
@@ -0,0 +1,372 @@
+/// Defined ahead of the shell modules, so that they can use it.
+macro_rules! script_builder_writeln {
+    ($self:expr) => {{
+        writeln!($self.buffer).unwrap();
+    }};
+    ($self:expr, $($arg:tt)*) => {{
+        crate::generate::left_pad($self.options.indent, $self.indentation, &mut $self.buffer)
+            .unwrap();
+        writeln!($self.buffer, $($arg)*).unwrap();
+    }};
+}
+
+pub mod bash;
+pub mod fish;
+pub mod zsh;
+
+use crate::generate::cpp::banner_lines;
+use crate::generate::{unescape_literal, GenOptions, Provenance};
+use crate::types::{AttributeType, Field, FieldType, Spec, SpecMetadata, StructOption, ValueKind};
+use std::fmt::Write;
+
+/// The shell completion scripts are generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    /// A completion function registered with `complete -F`.
+    Bash,
+    /// A `#compdef` file completing through `_arguments`.
+    Zsh,
+    /// `complete -c` commands, one per option.
+    Fish,
+}
+
+/// What is completed after an option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum OptionValue {
+    /// The option is a flag and takes no value.
+    None,
+    /// Any value, left for the user to type.
+    Any,
+    /// One of the given words.
+    Choices(Vec<String>),
+    /// The path of an existing file, see `Field::is_input_file`.
+    InputFile,
+    /// The path of a file to write, which isn't completed, see `Field::is_output_file`.
+    OutputFile,
+    /// The path of an existing directory, see `Field::is_directory`.
+    Directory,
+}
+
+/// An option as the completion scripts see it, along with its aliases.
+#[derive(Debug)]
+pub(crate) struct CompletedOption {
+    /// The short option, e.g. `-i`.
+    pub short: Option<String>,
+    /// The long option and the aliases, e.g. `--input` and `--in-file`.
+    pub longs: Vec<String>,
+    /// The `doc` of the field, with its escape sequences resolved and on a single line.
+    pub description: Option<String>,
+    pub value: OptionValue,
+    /// Whether the option can be given more than once, which is the case for `Vec` fields.
+    pub repeatable: bool,
+}
+
+impl CompletedOption {
+    /// Every spelling of the option, the short one first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.short.iter().chain(&self.longs).map(String::as_str)
+    }
+}
+
+/// Everything the completion scripts are generated from. The spec is walked once here, so that
+/// the shells share the handling of flattened fields and aliases.
+#[derive(Debug)]
+pub(crate) struct CompletionModel {
+    /// The command the script completes.
+    pub command: String,
+    /// The options of the main struct and the structs it flattens, in declaration order.
+    pub options: Vec<CompletedOption>,
+    /// The separator of the trailing arguments, after which no option is completed, if the main
+    /// struct has trailing arguments.
+    pub separator: Option<String>,
+}
+
+/// The option of `field` out of its `options`, the ones of its struct that give it a value.
+fn completed_option(field: &Field, options: &[&StructOption]) -> Option<CompletedOption> {
+    if field.is_hidden_from_completion() || options.is_empty() {
+        return None;
+    }
+
+    let short = options
+        .iter()
+        .find(|option| option.ty == AttributeType::Short)
+        .map(|option| option.name.clone());
+    let longs = options
+        .iter()
+        .filter(|option| option.ty != AttributeType::Short)
+        .map(|option| option.name.clone())
+        .collect::<Vec<_>>();
+
+    // Bool flags take no value and explicit bools take `true` or `false`.
+    let value = match options[0].value {
+        ValueKind::ExplicitBool => {
+            OptionValue::Choices(vec![String::from("true"), String::from("false")])
+        }
+        ValueKind::Flag => OptionValue::None,
+        _ if field.is_input_file() => OptionValue::InputFile,
+        _ if field.is_output_file() => OptionValue::OutputFile,
+        _ if field.is_directory() => OptionValue::Directory,
+        _ => OptionValue::Any,
+    };
+
+    Some(CompletedOption {
+        short,
+        longs,
+        description: field.doc().map(|doc| {
+            unescape_literal(doc)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        }),
+        value,
+        repeatable: matches!(field.ty, FieldType::Vec(_)),
+    })
+}
+
+impl CompletionModel {
+    /// The model of the main struct, completing `command` or the lowercase name of the main struct.
+    pub fn new(spec: &Spec, spec_metadata: &SpecMetadata, command: Option<&str>) -> Self {
+        let main_struct = spec
+            .structs
+            .iter()
+            .find(|strukt| strukt.has_attribute(AttributeType::Main))
+            .unwrap_or_else(|| &spec.structs[0]);
+
+        Self {
+            command: command.map_or_else(|| main_struct.name.to_lowercase(), String::from),
+            options: main_struct
+                .get_fields(spec_metadata)
+                .filter_map(|field| {
+                    let options = spec_metadata
+                        .struct_metadata(main_struct)
+                        .options
+                        .iter()
+                        .filter(|option| std::ptr::eq(option.field, field))
+                        .collect::<Vec<_>>();
+                    completed_option(field, &options)
+                })
+                .collect(),
+            separator: main_struct
+                .trailing_field()
+                .map(|_| main_struct.separator().to_string()),
+        }
+    }
+
+    /// The name of the completion function, e.g. `_my_tool` for `my-tool`.
+    pub fn function_name(&self) -> String {
+        let name = self
+            .command
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+
+        format!("_{name}")
+    }
+}
+
+/// Builds the completion scripts. Shells don't cope with carriage returns, so lines always end with
+/// LF and `GenOptions::line_ending` is ignored.
+#[derive(Debug, Default)]
+pub(crate) struct ScriptBuilder {
+    buffer: String,
+    indentation: usize,
+    options: GenOptions,
+}
+
+impl ScriptBuilder {
+    pub fn new(options: GenOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn push_indentation_level(&mut self) {
+        self.indentation += 1;
+    }
+
+    #[inline]
+    pub fn pop_indentation_level(&mut self) {
+        self.indentation = self.indentation.saturating_sub(1);
+    }
+
+    /// The banner of the other backends, as shell comments.
+    pub fn write_banner(&mut self, provenance: &Provenance) {
+        for line in banner_lines(provenance) {
+            script_builder_writeln!(self, "#{}", line.trim_start_matches("//"));
+        }
+        script_builder_writeln!(self);
+    }
+
+    #[inline]
+    pub fn result(self) -> String {
+        self.buffer
+    }
+}
+
+/// Writes the completion script of the main struct for `shell`.
+pub fn generate_completions(
+    shell: Shell,
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+    command: Option<&str>,
+) -> String {
+    let model = CompletionModel::new(spec, spec_metadata, command);
+
+    match shell {
+        Shell::Bash => bash::generate_completions(&model, options, provenance),
+        Shell::Zsh => zsh::generate_completions(&model, options, provenance),
+        Shell::Fish => fish::generate_completions(&model, options, provenance),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    /// Covers flattening, aliases, explicit bools, repeated options and trailing arguments, for the
+    /// golden files of every shell.
+    pub(super) const GOLDEN_SPEC: &str = r#"
+        #[doc_hidden]
+        struct Logging {
+            #[short, long, doc = "Print more [debug] output"]
+            verbose: bool,
+            #[long]
+            log_file: Optional<string>,
+        }
+
+        #[main]
+        struct Cli {
+            #[short, long, alias = in_file, doc = "The file to read, 'input.txt' by default"]
+            input: string,
+            #[long]
+            jobs: Vec<u32>,
+            #[long, explicit_bool]
+            color: bool,
+            #[flatten]
+            logging: Logging,
+            #[trailing]
+            rest: Vec<string>,
+        }
+    "#;
+
+    /// Generates the completions of `source` for `my-tool`.
+    pub(super) fn generate(shell: Shell, source: &str) -> String {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        generate_completions(
+            shell,
+            &spec,
+            &metadata,
+            &GenOptions::default(),
+            None,
+            Some("my-tool"),
+        )
+    }
+
+    #[test]
+    fn flattened_fields_and_aliases_are_collected() {
+        let source = "
+            struct Logging { #[short, long, doc = \"Print  more\"] verbose: bool }
+            #[main]
+            struct Cli {
+                #[long, alias = in_file] input: string,
+                #[flatten] logging: Logging,
+                positional: u32,
+            }
+        ";
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let model = CompletionModel::new(&spec, &metadata, None);
+
+        assert_eq!(model.command, "cli");
+        assert_eq!(model.function_name(), "_cli");
+        assert_eq!(model.separator, None);
+        assert_eq!(model.options.len(), 2);
+        assert_eq!(
+            model.options[0].names().collect::<Vec<_>>(),
+            ["--input", "--in-file"]
+        );
+        assert_eq!(model.options[0].value, OptionValue::Any);
+        assert_eq!(
+            model.options[1].names().collect::<Vec<_>>(),
+            ["-v", "--verbose"]
+        );
+        assert_eq!(model.options[1].description.as_deref(), Some("Print more"));
+        assert_eq!(model.options[1].value, OptionValue::None);
+    }
+
+    #[test]
+    fn fields_hidden_from_completion_are_left_out() {
+        let source = "
+            #[main]
+            struct Cli {
+                #[long] input: string,
+                #[long, hidden_from_completion, doc = \"Internal\"] trace: bool,
+            }
+        ";
+
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let generated = generate(shell, source);
+            assert!(generated.contains("input"));
+            assert!(!generated.contains("trace"));
+        }
+        // The field is still parsed and documented.
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let cpp =
+            crate::generate::cpp::generate_cli(&spec, &metadata, &GenOptions::default(), None);
+        assert!(cpp.contains(r#""    --trace  Internal\n""#));
+    }
+
+    #[test]
+    fn input_files_are_completed_and_output_files_are_not() {
+        let source = "
+            #[main]
+            struct Cli {
+                #[short, long, input_file] input: string,
+                #[short, long, output_file] output: Optional<string>,
+            }
+        ";
+
+        let bash = generate(Shell::Bash, source);
+        assert!(bash.contains(
+            "-i|--input)\n            compopt -o filenames 2>/dev/null\n            COMPREPLY=($(compgen -f -- \"$cur\"))\n            return"
+        ));
+        assert!(bash.contains("-o|--output)\n            return"));
+
+        let zsh = generate(Shell::Zsh, source);
+        assert!(zsh.contains(":input:_files'"));
+        assert!(zsh.contains(":output: '"));
+
+        let fish = generate(Shell::Fish, source);
+        assert!(fish.contains("complete -c my-tool -s i -l input -r -F"));
+        assert!(fish.contains("complete -c my-tool -s o -l output -x"));
+    }
+
+    #[test]
+    fn directories_are_completed_as_directories() {
+        let source = "#[main] struct Cli { #[short, long, directory] dir: string }";
+
+        let bash = generate(Shell::Bash, source);
+        assert!(bash.contains(
+            "-d|--dir)\n            compopt -o filenames 2>/dev/null\n            COMPREPLY=($(compgen -d -- \"$cur\"))\n            return"
+        ));
+
+        let zsh = generate(Shell::Zsh, source);
+        assert!(zsh.contains(":dir:_directories'"));
+
+        let fish = generate(Shell::Fish, source);
+        assert!(
+            fish.contains("complete -c my-tool -s d -l dir -x -a '(__fish_complete_directories)'")
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::generate::completions::{CompletedOption, CompletionModel, OptionValue, ScriptBuilder};
+use crate::generate::{GenOptions, Provenance};
+use std::fmt::Write;
+
+/// Escapes text for the brackets of an `_arguments` spec, which is itself single quoted.
+fn escape_description(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace('\'', r"'\''")
+}
+
+/// The `_arguments` spec of an option, e.g. `'(-i --input)'{-i,--input}'[The input]:input: '`.
+/// Options given once exclude their other spellings, and repeatable ones start with `*` instead.
+fn argument_spec(option: &CompletedOption) -> String {
+    let names = option.names().collect::<Vec<_>>();
+
+    let mut spec = match (option.repeatable, names.as_slice()) {
+        (false, [name]) => format!("'{name}"),
+        (true, [name]) => format!("'*{name}"),
+        (false, _) => format!("'({})'{{{}}}'", names.join(" "), names.join(",")),
+        (true, _) => format!("'*'{{{}}}'", names.join(",")),
+    };
+
+    if let Some(description) = &option.description {
+        write!(spec, "[{}]", escape_description(description)).unwrap();
+    }
+
+    // The value is described by the name of the option, and a space completes nothing.
+    let message = option
+        .longs
+        .first()
+        .or(option.short.as_ref())
+        .unwrap()
+        .trim_start_matches(['-', '/', '+']);
+    match &option.value {
+        OptionValue::None => {}
+        OptionValue::Any | OptionValue::OutputFile => write!(spec, ":{message}: ").unwrap(),
+        OptionValue::InputFile => write!(spec, ":{message}:_files").unwrap(),
+        OptionValue::Directory => write!(spec, ":{message}:_directories").unwrap(),
+        OptionValue::Choices(choices) => {
+            write!(spec, ":{message}:({})", choices.join(" ")).unwrap()
+        }
+    }
+    spec.push('\'');
+
+    spec
+}
+
+fn write_completion_function(builder: &mut ScriptBuilder, model: &CompletionModel) {
+    let name = model.function_name();
+
+    let mut specs = vec![String::from("'(- *)'{-h,--help}'[Print help]'")];
+    specs.extend(model.options.iter().map(argument_spec));
+
+    script_builder_writeln!(builder, "{name}() {{");
+    builder.push_indentation_level();
+    // `-S` makes `_arguments` stop completing options after `--`.
+    if model.separator.as_deref() == Some("--") {
+        script_builder_writeln!(builder, "_arguments -S \\");
+    } else {
+        script_builder_writeln!(builder, "_arguments \\");
+    }
+    builder.push_indentation_level();
+    for (index, spec) in specs.iter().enumerate() {
+        if index + 1 == specs.len() {
+            script_builder_writeln!(builder, "{spec}");
+        } else {
+            script_builder_writeln!(builder, "{spec} \\");
+        }
+    }
+    builder.pop_indentation_level();
+    builder.pop_indentation_level();
+    script_builder_writeln!(builder, "}}\n");
+
+    script_builder_writeln!(builder, r#"{name} "$@""#);
+}
+
+/// Writes a zsh completion file, to be installed as `_<command>` in a directory of `$fpath`.
+pub(crate) fn generate_completions(
+    model: &CompletionModel,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+) -> String {
+    let mut builder = ScriptBuilder::new(*options);
+
+    // compinit only picks up files whose first line is the `#compdef` tag.
+    script_builder_writeln!(builder, "#compdef {}", model.command);
+    if let Some(provenance) = provenance {
+        builder.write_banner(provenance);
+    } else {
+        script_builder_writeln!(builder);
+    }
+    write_completion_function(&mut builder, model);
+
+    builder.result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::completions::tests::{generate, GOLDEN_SPEC};
+    use crate::generate::completions::Shell;
+
+    #[test]
+    fn golden_completions() {
+        assert_eq!(
+            generate(Shell::Zsh, GOLDEN_SPEC),
+            include_str!("../golden/completions.zsh")
+        );
+    }
+
+    #[test]
+    fn descriptions_are_escaped() {
+        assert_eq!(
+            escape_description(r"it's [not] a\path"),
+            r"it'\''s \[not\] a\\path"
+        );
+    }
+}
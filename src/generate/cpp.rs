@@ -1,9 +1,324 @@
-use crate::generate::left_pad;
-use crate::types::{AttributeType, Field, FieldType, Spec, SpecMetadata, Struct};
-use std::collections::HashMap;
+use crate::diagnostic::Diagnostic;
+use crate::generate::{
+    format_utc_timestamp, left_pad, unescape_literal, Backend, GenOptions, Indent, OutputFile,
+    Provenance,
+};
+use crate::semantic::check_cpp_support;
+use crate::types::{AttributeType, Field, FieldType, Spec, SpecMetadata, Struct, ValueKind};
+use std::collections::HashSet;
 use std::fmt::Write;
 
-fn field_type_to_cpp_type(field_type: &FieldType) -> String {
+const BANNER_SPEC_HASH_PREFIX: &str = "// Spec hash: ";
+const BANNER_TIMESTAMP_PREFIX: &str = "// Generated at: ";
+
+/// The reader of the config files, written once for the structs with a `config_file`. It reads
+/// flat TOML: `key = value` lines whose value is a string, a number, a bool or a single line
+/// array of them, along with comments and blank lines. Tables are rejected.
+const CONFIG_FILE_READER: &str = r#"struct config_entry {
+    std::string key;
+    bool is_array;
+    // 's', 'n' or 'b' for every value, which is a string, a number or a bool.
+    std::vector<char> kinds;
+    std::vector<std::string> values;
+};
+
+inline void skip_config_spaces(const std::string& line, size_t& pos) {
+    while (pos != line.size() && (line[pos] == ' ' || line[pos] == '\t')) {
+        ++pos;
+    }
+}
+
+inline bool read_config_scalar(const std::string& line, size_t& pos, config_entry& entry) {
+    std::string value;
+    if (line[pos] == '"' || line[pos] == '\'') {
+        char quote = line[pos++];
+        for (; pos != line.size() && line[pos] != quote; ++pos) {
+            if (quote == '"' && line[pos] == '\\' && pos + 1 != line.size()) {
+                char escaped = line[++pos];
+                value += escaped == 'n' ? '\n' : escaped == 't' ? '\t' : escaped;
+            } else {
+                value += line[pos];
+            }
+        }
+        if (pos == line.size()) {
+            return false;
+        }
+        ++pos;
+        entry.kinds.push_back('s');
+        entry.values.push_back(value);
+        return true;
+    }
+
+    size_t end = pos;
+    while (end != line.size() && line[end] != ',' && line[end] != ']' && line[end] != '#' && !isspace((unsigned char)line[end])) {
+        ++end;
+    }
+    value = line.substr(pos, end - pos);
+    pos = end;
+    if (value == "true" || value == "false") {
+        entry.kinds.push_back('b');
+        entry.values.push_back(value);
+        return true;
+    }
+
+    char* number_end = nullptr;
+    strtod(value.c_str(), &number_end);
+    if (value.empty() || *number_end != '\0') {
+        return false;
+    }
+    entry.kinds.push_back('n');
+    entry.values.push_back(value);
+    return true;
+}
+
+inline bool read_config_line(const std::string& line, size_t pos, config_entry& entry) {
+    size_t key_start = pos;
+    while (pos != line.size() && (isalnum((unsigned char)line[pos]) || line[pos] == '-' || line[pos] == '_')) {
+        ++pos;
+    }
+    entry.key = line.substr(key_start, pos - key_start);
+    skip_config_spaces(line, pos);
+    if (entry.key.empty() || pos == line.size() || line[pos] != '=') {
+        return false;
+    }
+    ++pos;
+    skip_config_spaces(line, pos);
+    if (pos == line.size()) {
+        return false;
+    }
+
+    entry.is_array = line[pos] == '[';
+    if (entry.is_array) {
+        ++pos;
+        skip_config_spaces(line, pos);
+        while (pos != line.size() && line[pos] != ']') {
+            if (!read_config_scalar(line, pos, entry)) {
+                return false;
+            }
+            skip_config_spaces(line, pos);
+            if (pos != line.size() && line[pos] == ',') {
+                ++pos;
+                skip_config_spaces(line, pos);
+            } else if (pos != line.size() && line[pos] != ']') {
+                return false;
+            }
+        }
+        if (pos == line.size()) {
+            return false;
+        }
+        ++pos;
+    } else if (!read_config_scalar(line, pos, entry)) {
+        return false;
+    }
+
+    skip_config_spaces(line, pos);
+    return pos == line.size() || line[pos] == '#';
+}
+
+inline bool read_config_file(const char* path, std::vector<config_entry>& entries) {
+    FILE* file = fopen(path, "r");
+    if (file == nullptr) {
+        printf("Could not open the config file '%s'\n", path);
+        return false;
+    }
+
+    std::string line;
+    size_t line_number = 0;
+    for (int c = 0; c != EOF;) {
+        line.clear();
+        while ((c = fgetc(file)) != EOF && c != '\n') {
+            line += (char)c;
+        }
+        if (!line.empty() && line[line.size() - 1] == '\r') {
+            line.erase(line.size() - 1);
+        }
+        ++line_number;
+
+        size_t pos = 0;
+        skip_config_spaces(line, pos);
+        if (pos == line.size() || line[pos] == '#') {
+            continue;
+        }
+
+        config_entry entry = {};
+        if (!read_config_line(line, pos, entry)) {
+            printf("Invalid line %zu of the config file '%s'\n", line_number, path);
+            fclose(file);
+            return false;
+        }
+        entries.push_back(entry);
+    }
+
+    fclose(file);
+    return true;
+}
+
+inline bool check_config_entry(const config_entry& entry, bool is_array, char kind, const char* expected) {
+    bool matches = entry.is_array == is_array && (is_array || entry.values.size() == 1);
+    for (size_t i = 0; i != entry.kinds.size(); ++i) {
+        matches = matches && entry.kinds[i] == kind;
+    }
+    if (!matches) {
+        printf("Value of the config key '%s' should be %s\n", entry.key.c_str(), expected);
+    }
+    return matches;
+}
+"#;
+
+/// The helpers of the `to_command_line()` methods, written once with `--with-to-command-line`.
+/// Arguments are quoted like a POSIX shell would need them, so that the line can be pasted back.
+const COMMAND_LINE_WRITER: &str = r#"inline std::string quote_command_line_argument(const std::string& argument) {
+    bool is_plain = !argument.empty();
+    for (size_t i = 0; i != argument.size(); ++i) {
+        unsigned char c = (unsigned char)argument[i];
+        is_plain = is_plain && (isalnum(c) || strchr("-_./:=,+@%", c) != nullptr);
+    }
+    if (is_plain) {
+        return argument;
+    }
+
+    std::string quoted = "'";
+    for (size_t i = 0; i != argument.size(); ++i) {
+        if (argument[i] == '\'') {
+            quoted += "'\\''";
+        } else {
+            quoted += argument[i];
+        }
+    }
+    return quoted + "'";
+}
+
+inline void append_command_line(std::string& line, const std::string& arguments) {
+    if (arguments.empty()) {
+        return;
+    }
+    if (!line.empty()) {
+        line += ' ';
+    }
+    line += arguments;
+}
+
+"#;
+
+/// The stand-in for `std::optional` before C++17. The value is a member of a union so that it is
+/// only constructed when there is one, since the structs only have a default constructor with
+/// `--accessors`.
+const OPTIONAL_CLASS: &str = r#"template <typename T>
+class cli_optional {
+public:
+    cli_optional() : has_value_(false) {}
+    cli_optional(const T& value) : has_value_(true) { new (&value_) T(value); }
+    cli_optional(const cli_optional& other) : has_value_(other.has_value_) {
+        if (has_value_) {
+            new (&value_) T(other.value_);
+        }
+    }
+    cli_optional& operator=(const cli_optional& other) {
+        if (this != &other) {
+            reset();
+            if (other.has_value_) {
+                new (&value_) T(other.value_);
+                has_value_ = true;
+            }
+        }
+        return *this;
+    }
+    ~cli_optional() { reset(); }
+
+    bool has_value() const { return has_value_; }
+    T& value() { return value_; }
+    const T& value() const { return value_; }
+
+private:
+    void reset() {
+        if (has_value_) {
+            value_.~T();
+            has_value_ = false;
+        }
+    }
+
+    union {
+        T value_;
+    };
+    bool has_value_;
+};
+
+"#;
+
+/// Writes the floats of `to_command_line()` back with the fewest digits parsing to the same value,
+/// e.g. `0.1` rather than `0.100000` or `0.10000000000000001`, whatever the locale.
+const COMMAND_LINE_FLOAT_WRITER: &str = r#"template <typename T>
+inline std::string command_line_float(T value) {
+    for (int precision = std::numeric_limits<T>::digits10;; ++precision) {
+        std::ostringstream written;
+        written.imbue(std::locale::classic());
+        written.precision(precision);
+        written << value;
+
+        std::istringstream read(written.str());
+        read.imbue(std::locale::classic());
+        T read_value = 0;
+        read >> read_value;
+        if (read_value == value || precision == std::numeric_limits<T>::max_digits10) {
+            return written.str();
+        }
+    }
+}
+
+"#;
+
+/// The spec hash recorded in the banner of previously generated code.
+pub fn embedded_spec_hash(generated: &str) -> Option<&str> {
+    generated
+        .lines()
+        .take_while(|line| line.starts_with("//"))
+        .find_map(|line| line.strip_prefix(BANNER_SPEC_HASH_PREFIX))
+}
+
+/// Whether two outputs are the same, apart from the time they were generated at.
+pub fn same_generated_code(lhs: &str, rhs: &str) -> bool {
+    // Line endings are kept, so that switching them counts as a change.
+    let without_timestamp = |generated: &str| {
+        generated
+            .split_inclusive('\n')
+            .filter(|line| !line.starts_with(BANNER_TIMESTAMP_PREFIX))
+            .collect::<String>()
+    };
+
+    without_timestamp(lhs) == without_timestamp(rhs)
+}
+
+/// The comment lines at the top of the generated code, recording where it comes from. Both the C
+/// and C++ backends share them, so that `embedded_spec_hash` works for either.
+pub(crate) fn banner_lines(provenance: &Provenance) -> Vec<String> {
+    let mut lines = vec![format!(
+        "// Generated by {} {}. Do not edit by hand.",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )];
+    lines.extend(provenance_lines(provenance));
+
+    lines
+}
+
+/// The lines of the banner after the first, for backends whose first line is a convention of
+/// their language, like the `// Code generated ... DO NOT EDIT.` of Go.
+pub(crate) fn provenance_lines(provenance: &Provenance) -> Vec<String> {
+    let mut lines = vec![
+        format!("// Spec: {}", provenance.spec_path),
+        format!("{BANNER_SPEC_HASH_PREFIX}{}", provenance.spec_hash),
+    ];
+    if let Some(timestamp) = provenance.timestamp {
+        lines.push(format!(
+            "{BANNER_TIMESTAMP_PREFIX}{}",
+            format_utc_timestamp(timestamp)
+        ));
+    }
+
+    lines
+}
+
+pub(crate) fn field_type_to_cpp_type(field_type: &FieldType) -> String {
     match field_type {
         FieldType::String => "std::string".to_string(),
         FieldType::I16 => "int16_t".to_string(),
@@ -18,13 +333,446 @@ fn field_type_to_cpp_type(field_type: &FieldType) -> String {
         FieldType::Vec(inner) => format!("std::vector<{}>", field_type_to_cpp_type(inner)),
         FieldType::Optional(inner) => format!("std::optional<{}>", field_type_to_cpp_type(inner)),
         FieldType::Struct(strukt) => strukt.to_string(),
+        FieldType::Error => unreachable!(),
+    }
+}
+
+/// The C++ literal of a `default_value_t`. The largest values of a `u64` don't fit in the
+/// `long long` that integer literals are otherwise.
+fn default_value_literal(field_type: &FieldType, value: &str) -> String {
+    match field_type {
+        FieldType::String => format!("\"{value}\""),
+        FieldType::U64 => format!("{value}ULL"),
+        _ => value.to_string(),
+    }
+}
+
+fn collect_field_types<'s>(field_type: &'s FieldType, used_types: &mut HashSet<&'s FieldType>) {
+    used_types.insert(field_type);
+
+    if let FieldType::Vec(inner) | FieldType::Optional(inner) = field_type {
+        collect_field_types(inner, used_types);
+    }
+}
+
+/// All the options accepted by the struct (short, long and alias), in declaration order, along
+/// with whether they are matched ignoring case.
+pub(crate) fn struct_options(strukt: &Struct, spec_metadata: &SpecMetadata) -> Vec<(String, bool)> {
+    spec_metadata
+        .struct_metadata(strukt)
+        .options
+        .iter()
+        .map(|option| (option.name.clone(), option.case_insensitive))
+        .collect()
+}
+
+/// Descriptions are never wrapped narrower than this, even when their option is very long.
+const MIN_WRAPPED_DOC_WIDTH: usize = 20;
+
+/// The column of the descriptions of `next_line_help` fields, below their options.
+const NEXT_LINE_HELP_INDENTATION: usize = 8;
+
+/// The number of characters a string literal's contents print as, counting escape sequences as a
+/// single character.
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        }
+        width += 1;
+    }
+
+    width
+}
+
+/// Escapes the text of a string literal used as a `printf` format.
+pub(crate) fn escape_printf(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+/// The lines of a Doxygen comment taken from the text of a string literal. `*/` would end the
+/// comment early, so it is broken up.
+fn doxygen_lines(text: &str) -> Vec<String> {
+    unescape_literal(text)
+        .replace("*/", "* /")
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+/// Breaks `text` into lines of at most `width` characters at its spaces. Words longer than the
+/// width are put on a line of their own rather than split.
+pub(crate) fn word_wrap(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && display_width(&line) + 1 + display_width(word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+/// The lines printed by the help of a struct, already escaped for a `printf` format: the about
+/// text, the usage and every option along with its description.
+pub(crate) fn help_lines(strukt: &Struct, spec_metadata: &SpecMetadata) -> Vec<String> {
+    let syntax = strukt.option_syntax();
+
+    let mut usage = format!("Usage: {} [OPTIONS]", strukt.name);
+    for field in strukt
+        .fields
+        .iter()
+        .filter(|field| field.min_occurs().is_some_and(|min| min > 0))
+    {
+        usage.push_str(&format!(
+            " {} <{}>...",
+            field_option_name(strukt, field),
+            field.name.to_uppercase()
+        ));
+    }
+    let positional_fields = strukt.positional_fields();
+    for field in &positional_fields {
+        if field.is_required() {
+            usage.push_str(&format!(" <{}>", field.name.to_uppercase()));
+        } else {
+            usage.push_str(&format!(" [<{}>]", field.name.to_uppercase()));
+        }
+    }
+    if let Some(trailing_field) = strukt.trailing_field() {
+        usage.push_str(&format!(
+            " [{} <{}>...]",
+            strukt.separator(),
+            trailing_field.name.to_uppercase()
+        ));
+    }
+    let wrap_width = strukt.word_wrap();
+
+    let mut lines = Vec::new();
+    if let Some(about) = strukt.about() {
+        let about = wrap_width.map_or_else(|| about.to_string(), |width| word_wrap(about, width));
+        lines.extend(about.lines().map(escape_printf));
+        lines.push(String::new());
+    }
+    // The description of an argument or option follows it, or goes below it with
+    // `next_line_help`, and wrapped descriptions continue in the column they started at.
+    let push_entry = |lines: &mut Vec<String>, entry: String, field: &Field| {
+        let Some(doc) = field.doc() else {
+            lines.push(escape_printf(&entry));
+            return;
+        };
+
+        let next_line_help = strukt.is_next_line_help(field, spec_metadata);
+        let doc_column = if next_line_help {
+            lines.push(escape_printf(&entry));
+            NEXT_LINE_HELP_INDENTATION
+        } else {
+            display_width(&entry) + 2
+        };
+        let doc = wrap_width.map_or_else(
+            || doc.to_string(),
+            |width| {
+                word_wrap(
+                    doc,
+                    width.saturating_sub(doc_column).max(MIN_WRAPPED_DOC_WIDTH),
+                )
+            },
+        );
+        for (i, line) in doc.lines().enumerate() {
+            let prefix = if i == 0 && !next_line_help {
+                format!("{}  ", escape_printf(&entry))
+            } else {
+                " ".repeat(doc_column)
+            };
+            lines.push(format!("{prefix}{}", escape_printf(line)));
+        }
+    };
+
+    lines.push(escape_printf(&usage));
+    lines.push(String::new());
+    if !positional_fields.is_empty() {
+        lines.push(String::from("Arguments:"));
+        for field in &positional_fields {
+            push_entry(
+                &mut lines,
+                format!("    <{}>", field.name.to_uppercase()),
+                field,
+            );
+        }
+        lines.push(String::new());
+    }
+    lines.push(String::from("Options:"));
+    lines.push(escape_printf(&format!(
+        "    {}, {}",
+        syntax.short_option("h"),
+        syntax.long_option("help")
+    )));
+
+    // Flattened fields have no option of their own, their struct's options follow instead.
+    let mut option_fields = strukt
+        .get_fields(spec_metadata)
+        .filter(|field| {
+            !field.has_attribute(AttributeType::Trailing)
+                && !field.has_attribute(AttributeType::Flatten)
+                && field.positional_index().is_none()
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, the fields without a display order keep the order they are declared in.
+    option_fields.sort_by_key(|field| field.display_order().unwrap_or(usize::MAX));
+    for field in option_fields {
+        let mut option = String::from("    ");
+        if let Some(short_value) = field.short_value() {
+            option.push_str(&syntax.short_option(&short_value));
+        }
+
+        if let Some(long_value) = field.long_value() {
+            if field.short_value().is_some() {
+                option.push_str(", ");
+            }
+            option.push_str(&syntax.long_option(&long_value));
+        }
+
+        if !matches!(field.ty, FieldType::Bool) {
+            option.push_str(&format!(" <{}>", field.name.to_uppercase()));
+        } else if field.is_explicit_bool() {
+            option.push_str(&format!("[{}true|false]", syntax.value_separator));
+        }
+
+        push_entry(&mut lines, option, field);
+    }
+    // Printed on its own after the options, separated by an empty line.
+    if strukt.after_help().is_some() {
+        lines.push(String::new());
+    }
+
+    lines
+}
+
+/// The `printf` formats of the warning printed when the option of a deprecated field is given,
+/// without their newlines. The first line takes the option as its `%s`, along with the version the
+/// field was deprecated in and the message of its `deprecated` attribute if any. The version it
+/// will be removed in follows on a line of its own.
+pub(crate) fn deprecation_warning(field: &Field) -> (String, Option<String>) {
+    let since = field
+        .deprecated_in()
+        .map(|version| format!(" since v{}", escape_printf(version)))
+        .unwrap_or_default();
+    let message = field
+        .deprecation_message()
+        .map(|message| format!(": {}", escape_printf(message)))
+        .unwrap_or_default();
+    let removal = field
+        .removed_in()
+        .map(|version| format!("  It will be removed in v{}", escape_printf(version)));
+
+    (
+        format!("Warning: option '%s' is deprecated{since}{message}"),
+        removal,
+    )
+}
+
+/// The condition matching `arg` against an option, valid both in C and C++.
+pub(crate) fn option_match_condition(option: &str, case_insensitive: bool) -> String {
+    if case_insensitive {
+        format!(r#"equals_ignore_case(arg, "{option}")"#)
+    } else {
+        format!(r#"strcmp(arg, "{option}") == 0"#)
+    }
+}
+
+/// A branch of the `if`/`else if` chain that matches the arguments against the options.
+pub(crate) struct MatchArm {
+    condition: String,
+    body: String,
+}
+
+/// The mandatory fields of a struct, tracked while parsing through the
+/// `{prefix}mandatory_fields_seen` array. Positional fields are checked against the number of
+/// positional arguments given instead.
+pub(crate) struct MandatoryFields<'s> {
+    pub prefix: String,
+    pub fields: Vec<&'s Field>,
+}
+
+impl<'s> MandatoryFields<'s> {
+    pub fn new(prefix: String, strukt: &'s Struct) -> Self {
+        let fields = strukt
+            .mandatory_fields()
+            .into_iter()
+            .filter(|field| field.positional_index().is_none())
+            .collect();
+
+        Self { prefix, fields }
+    }
+
+    pub fn index_of(&self, field: &Field) -> Option<usize> {
+        self.fields
+            .iter()
+            .position(|&mandatory_field| std::ptr::eq(mandatory_field, field))
+    }
+}
+
+/// A struct flattened through an `Optional` field. The struct only exists once any of its fields
+/// is given, and from then on its mandatory fields are required.
+struct OptionalGroup<'s> {
+    /// The paths of the `std::optional` members, from the outermost enclosing group to this one.
+    paths: Vec<String>,
+    mandatory_fields: MandatoryFields<'s>,
+}
+
+/// A struct flattened through a `Vec` field. Its options fill the last element of the vector, and
+/// giving one of them again starts a new element, so that every complete set of options makes an
+/// element. Such structs don't flatten other structs themselves.
+struct RepeatedGroup<'s> {
+    /// The vector, e.g. `res.servers`.
+    path: String,
+    /// The prefix of the `fields_given` array tracking the options given to the last element.
+    prefix: String,
+    strukt: &'s Struct,
+}
+
+/// The prefix of the arrays tracking the mandatory fields of an optional group, e.g. `net_` for
+/// `res.net` and `net_proxy_` for `res.net.value().proxy`.
+fn optional_group_prefix(path: &str) -> String {
+    let path = path.trim_start_matches("res.").replace(".value()", "");
+    format!("{}_", path.replace('.', "_"))
+}
+
+/// The option a field is referred to by in messages, preferring the long one.
+pub(crate) fn field_option_name(strukt: &Struct, field: &Field) -> String {
+    let syntax = strukt.option_syntax();
+
+    match (field.long_value(), field.short_value()) {
+        (Some(long_value), _) => syntax.long_option(&long_value),
+        (None, Some(short_value)) => syntax.short_option(&short_value),
+        (None, None) => syntax.long_option(&field.name),
+    }
+}
+
+/// The long options (long and alias) accepted by the struct, in declaration order.
+fn struct_long_options(strukt: &Struct, spec_metadata: &SpecMetadata) -> Vec<String> {
+    spec_metadata
+        .struct_metadata(strukt)
+        .options
+        .iter()
+        .filter(|option| option.ty != AttributeType::Short)
+        .map(|option| option.name.clone())
+        .collect()
+}
+
+/// The C++ standard the generated code is written for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum CppStandard {
+    #[value(name = "c++11")]
+    Cpp11,
+    #[value(name = "c++14")]
+    Cpp14,
+    #[default]
+    #[value(name = "c++17")]
+    Cpp17,
+    #[value(name = "c++20")]
+    Cpp20,
+}
+
+impl CppStandard {
+    #[inline]
+    pub fn has_from_chars(&self) -> bool {
+        *self >= CppStandard::Cpp17
+    }
+
+    #[inline]
+    pub fn has_inline_variables(&self) -> bool {
+        *self >= CppStandard::Cpp17
+    }
+
+    #[inline]
+    pub fn has_filesystem(&self) -> bool {
+        *self >= CppStandard::Cpp17
+    }
+
+    #[inline]
+    pub fn has_optional(&self) -> bool {
+        *self >= CppStandard::Cpp17
+    }
+}
+
+/// The operating system the generated code runs on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TargetOs {
+    #[default]
+    Unix,
+    Windows,
+}
+
+/// How the lines of the generated code end.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    /// The line ending of the platform the generator runs on.
+    Native,
+}
+
+impl LineEnding {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Native if cfg!(windows) => "\r\n",
+            LineEnding::Native => "\n",
+        }
+    }
+}
+
+/// The codes the generated `parse` exits with, by kind of error.
+#[derive(Debug, Clone, Copy)]
+struct ExitCodes {
+    parse_error: u8,
+    missing_arg: u8,
+    unknown_flag: u8,
+}
+
+impl ExitCodes {
+    fn of(strukt: &Struct) -> Self {
+        Self {
+            parse_error: strukt.parse_error_exit_code(),
+            missing_arg: strukt.missing_arg_exit_code(),
+            unknown_flag: strukt.unknown_flag_exit_code(),
+        }
+    }
+}
+
+impl Default for ExitCodes {
+    fn default() -> Self {
+        Self {
+            parse_error: 1,
+            missing_arg: 1,
+            unknown_flag: 1,
+        }
     }
 }
 
 #[derive(Debug, Default)]
 struct CppSourceBuilder {
     buffer: String,
+    /// The number of indentation levels, see `GenOptions::indent` for their width.
     indentation: usize,
+    options: GenOptions,
+    /// The exit codes of the struct being written. Flattened structs are parsed by the struct
+    /// flattening them, so they exit with its codes.
+    exit_codes: ExitCodes,
 }
 
 macro_rules! cpp_source_builder_writeln {
@@ -32,9 +780,7 @@ macro_rules! cpp_source_builder_writeln {
         writeln!($self.buffer).unwrap();
     }};
     ($self:expr, $($arg:tt)*) => {{
-        if $self.indentation != 0 {
-            left_pad($self.indentation, &mut $self.buffer).unwrap();
-        }
+        left_pad($self.options.indent, $self.indentation, &mut $self.buffer).unwrap();
         writeln!($self.buffer, $($arg)*).unwrap();
     }};
 }
@@ -44,41 +790,60 @@ macro_rules! cpp_source_builder_write {
         write!($self.buffer).unwrap()
     }};
     ($self:expr, $($arg:tt)*) => {{
-        if $self.indentation != 0 {
-            left_pad($self.indentation, &mut $self.buffer).unwrap();
-        }
+        left_pad($self.options.indent, $self.indentation, &mut $self.buffer).unwrap();
         write!($self.buffer, $($arg)*).unwrap()
     }};
 }
 
-impl CppSourceBuilder {
-    #[inline]
-    pub fn push_indentation_level(&mut self) {
-        self.indentation += 4;
-    }
+/// Continues the current line, without indenting.
+macro_rules! cpp_source_builder_append {
+    ($self:expr, $($arg:tt)*) => {{
+        write!($self.buffer, $($arg)*).unwrap()
+    }};
+}
 
-    #[inline]
-    pub fn pop_indentation_level(&mut self) {
-        if self.indentation >= 4 {
-            self.indentation -= 4;
+/// Continues and ends the current line, without indenting.
+macro_rules! cpp_source_builder_appendln {
+    ($self:expr, $($arg:tt)*) => {{
+        writeln!($self.buffer, $($arg)*).unwrap()
+    }};
+}
+
+impl CppSourceBuilder {
+    pub fn new(options: GenOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
         }
     }
 
     #[inline]
-    pub fn get_indentation_level(&self) -> usize {
-        self.indentation
+    pub fn push_indentation_level(&mut self) {
+        self.indentation += 1;
     }
 
     #[inline]
-    pub fn set_indentation_level(&mut self, indentation: usize) {
-        self.indentation = indentation;
+    pub fn pop_indentation_level(&mut self) {
+        self.indentation = self.indentation.saturating_sub(1);
     }
 }
 
 impl CppSourceBuilder {
     #[inline]
     pub fn result(self) -> String {
-        self.buffer
+        // Everything is written with `\n`, so converting at the end also covers multi-line writes.
+        match self.options.line_ending.as_str() {
+            "\n" => self.buffer,
+            line_ending => self.buffer.replace('\n', line_ending),
+        }
+    }
+
+    #[inline]
+    pub fn write_banner(&mut self, provenance: &Provenance) {
+        for line in banner_lines(provenance) {
+            cpp_source_builder_writeln!(self, "{line}");
+        }
+        cpp_source_builder_writeln!(self);
     }
 
     #[inline]
@@ -93,606 +858,4123 @@ impl CppSourceBuilder {
         cpp_source_builder_writeln!(self, "#endif // _CLI_H_");
     }
 
+    /// Only the headers required by the field types used in the spec are included.
     #[inline]
-    pub fn write_include_headers(&mut self) {
+    pub fn write_include_headers(
+        &mut self,
+        used_types: &HashSet<&FieldType>,
+        uses_regex: bool,
+        uses_filesystem: bool,
+        uses_config_file: bool,
+    ) {
+        let uses_vec = used_types.iter().any(|ty| matches!(ty, FieldType::Vec(_)));
+        let uses_optional = used_types
+            .iter()
+            .any(|ty| matches!(ty, FieldType::Optional(_)));
+        let uses_float =
+            used_types.contains(&FieldType::F32) || used_types.contains(&FieldType::F64);
+
+        cpp_source_builder_writeln!(self, "#include <cctype>");
         cpp_source_builder_writeln!(self, "#include <cstdint>");
         cpp_source_builder_writeln!(self, "#include <cstdlib>");
         cpp_source_builder_writeln!(self, "#include <cstring>");
         cpp_source_builder_writeln!(self, "#include <cstdio>");
         cpp_source_builder_writeln!(self, "#include <cerrno>");
         cpp_source_builder_writeln!(self, "#include <string>");
-        cpp_source_builder_writeln!(self, "#include <vector>");
+        let has_optional = self.options.cpp_std.has_optional();
+        if uses_vec || uses_config_file || self.options.target_os == TargetOs::Windows {
+            cpp_source_builder_writeln!(self, "#include <vector>");
+        }
+        if uses_optional && has_optional {
+            cpp_source_builder_writeln!(self, "#include <optional>");
+        } else if uses_optional {
+            cpp_source_builder_writeln!(self, "#include <new>");
+        }
+        if uses_regex {
+            cpp_source_builder_writeln!(self, "#include <regex>");
+        }
+        if uses_filesystem {
+            cpp_source_builder_writeln!(self, "#include <filesystem>");
+            cpp_source_builder_writeln!(self, "#include <system_error>");
+        }
+        if self.options.target_os == TargetOs::Windows {
+            cpp_source_builder_writeln!(self, "#ifndef WIN32_LEAN_AND_MEAN");
+            cpp_source_builder_writeln!(self, "#define WIN32_LEAN_AND_MEAN");
+            cpp_source_builder_writeln!(self, "#endif");
+            cpp_source_builder_writeln!(self, "#ifndef NOMINMAX");
+            cpp_source_builder_writeln!(self, "#define NOMINMAX");
+            cpp_source_builder_writeln!(self, "#endif");
+            cpp_source_builder_writeln!(self, "#include <windows.h>");
+        }
+        if uses_float {
+            let has_from_chars = self.options.cpp_std.has_from_chars();
+            if has_from_chars {
+                cpp_source_builder_writeln!(self, "#include <charconv>");
+            }
+            // `to_command_line()` writes floats back with streams, see `COMMAND_LINE_FLOAT_WRITER`.
+            if !has_from_chars || self.options.to_command_line {
+                cpp_source_builder_writeln!(self, "#include <limits>");
+            }
+            if !has_from_chars || self.options.to_command_line {
+                cpp_source_builder_writeln!(self, "#include <locale>");
+                cpp_source_builder_writeln!(self, "#include <sstream>");
+            }
+        }
         cpp_source_builder_writeln!(self);
     }
 
-    #[inline]
-    pub fn write_struct_start(&mut self, struct_name: &str) {
-        cpp_source_builder_writeln!(self, "struct {struct_name} {{");
-    }
-
-    #[inline]
-    pub fn write_struct_end(&mut self) {
-        cpp_source_builder_writeln!(self, "}};\n");
-    }
-
-    #[inline]
-    pub fn write_struct_field(&mut self, field: &Field) {
-        let field_type = field_type_to_cpp_type(&field.ty);
-        let field_name = &field.name;
+    fn write_starts_with_ignore_case_function(&mut self) {
+        cpp_source_builder_writeln!(
+            self,
+            "inline bool starts_with_ignore_case(const char* str, const char* prefix) {{"
+        );
         self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "{field_type} {field_name};");
+        if self.options.target_os == TargetOs::Windows {
+            cpp_source_builder_writeln!(
+                self,
+                "return _strnicmp(str, prefix, strlen(prefix)) == 0;"
+            );
+        } else {
+            cpp_source_builder_writeln!(self, "for (; *prefix != '\\0'; ++str, ++prefix) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                "if (tolower((unsigned char)*str) != tolower((unsigned char)*prefix)) {{"
+            );
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "return false;");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            cpp_source_builder_writeln!(self, "return true;");
+        }
         self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
     }
 
-    pub fn write_parse_numeric_field(&mut self, field_type: &FieldType) {
-        let cpp_type = field_type_to_cpp_type(field_type);
-        let conversion_function = match field_type {
-            FieldType::I16
-            | FieldType::U16
-            | FieldType::I32
-            | FieldType::U32
-            | FieldType::I64
-            | FieldType::U64 => "std::strtoll(arg_value, nullptr, 10)",
-            FieldType::F32 => "std::strtof(arg_value, nullptr)",
-            FieldType::F64 => "std::strtod(arg_value, nullptr)",
-            _ => unreachable!(),
-        };
+    /// Optional fields are `std::optional` from C++17. Before, they are a `cli_optional` with the
+    /// part of its interface the generated code uses, see `OPTIONAL_CLASS`.
+    pub fn write_optional_class(&mut self) {
+        self.write_code(OPTIONAL_CLASS);
+    }
 
-        cpp_source_builder_writeln!(self, "char* arg_value = args[0];");
+    pub fn write_helper_functions(
+        &mut self,
+        uses_case_insensitive: bool,
+        uses_config_file: bool,
+        uses_float: bool,
+    ) {
         cpp_source_builder_writeln!(
             self,
-            "{cpp_type} arg_res = static_cast<{cpp_type}>({conversion_function});"
+            "inline bool equals_ignore_case(const char* lhs, const char* rhs) {{"
         );
-        cpp_source_builder_writeln!(self);
+        self.push_indentation_level();
+        if self.options.target_os == TargetOs::Windows {
+            cpp_source_builder_writeln!(self, "return _stricmp(lhs, rhs) == 0;");
+        } else {
+            cpp_source_builder_writeln!(
+                self,
+                "for (; *lhs != '\\0' && *rhs != '\\0'; ++lhs, ++rhs) {{"
+            );
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                "if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {{"
+            );
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "return false;");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            cpp_source_builder_writeln!(self, "return *lhs == *rhs;");
+        }
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
+
+        if uses_case_insensitive {
+            self.write_starts_with_ignore_case_function();
+        }
 
-        cpp_source_builder_writeln!(self, "if (errno == ERANGE) {{");
+        cpp_source_builder_writeln!(
+            self,
+            "inline bool parse_bool_value(const char* value, bool* res) {{"
+        );
         self.push_indentation_level();
         cpp_source_builder_writeln!(
             self,
-            r#"printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);"#
+            r#"if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {{"#
         );
-        cpp_source_builder_writeln!(self, "exit(1);");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "*res = true;");
+        cpp_source_builder_writeln!(self, "return true;");
         self.pop_indentation_level();
         cpp_source_builder_writeln!(self, "}}");
-
-        cpp_source_builder_writeln!(self, r#"if (arg_res == 0 && strcmp(arg, "0") != 0) {{"#);
-        self.push_indentation_level();
         cpp_source_builder_writeln!(
             self,
-            r#"printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);"#
+            r#"if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {{"#
         );
-        cpp_source_builder_writeln!(self, "exit(1);");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "*res = false;");
+        cpp_source_builder_writeln!(self, "return true;");
         self.pop_indentation_level();
         cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, "return false;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
+
+        if uses_config_file {
+            self.write_code(CONFIG_FILE_READER);
+        }
+        if self.options.to_command_line {
+            self.write_code(COMMAND_LINE_WRITER);
+            if uses_float {
+                self.write_code(COMMAND_LINE_FLOAT_WRITER);
+            }
+        }
     }
 
-    pub fn write_parse_field_type(&mut self, struct_name: &str, field_type: &FieldType) {
-        fn write_parse_value_option_preamble(
-            self_: &mut CppSourceBuilder,
-            struct_name: &str,
-            is_string: bool,
-        ) {
-            cpp_source_builder_writeln!(self_, "++args;");
-            cpp_source_builder_writeln!(self_, "++i;");
+    /// Writes a block of code indented with 4 spaces per level at the current indentation level,
+    /// converting its indentation to the configured one.
+    fn write_code(&mut self, code: &str) {
+        for line in code.lines() {
+            let content = line.trim_start_matches(' ');
+            let levels = (line.len() - content.len()) / 4;
 
-            cpp_source_builder_write!(self_, "if (i == argc");
-            let indentation_level = self_.get_indentation_level();
-            self_.set_indentation_level(0);
-            if is_string {
-                cpp_source_builder_writeln!(self_, ") {{");
+            if content.is_empty() {
+                cpp_source_builder_writeln!(self);
+                continue;
+            }
+            for _ in 0..levels {
+                self.push_indentation_level();
+            }
+            cpp_source_builder_writeln!(self, "{content}");
+            for _ in 0..levels {
+                self.pop_indentation_level();
+            }
+        }
+    }
+
+    /// Writes a Doxygen comment, on a single line when `lines` is made of one.
+    fn write_doxygen_comment(&mut self, lines: &[String]) {
+        if let [line] = lines {
+            cpp_source_builder_writeln!(self, "/** {line} */");
+            return;
+        }
+
+        cpp_source_builder_writeln!(self, "/**");
+        for line in lines {
+            if line.is_empty() {
+                cpp_source_builder_writeln!(self, " *");
             } else {
-                cpp_source_builder_writeln!(self_, " || {struct_name}::is_option(args[0])) {{");
+                cpp_source_builder_writeln!(self, " * {line}");
             }
-            self_.set_indentation_level(indentation_level);
-            self_.push_indentation_level();
+        }
+        cpp_source_builder_writeln!(self, " */");
+    }
 
-            cpp_source_builder_writeln!(
-                self_,
-                r#"printf("Expected value for option '%s' but no value was provided", arg);"#
-            );
-            cpp_source_builder_writeln!(self_, "exit(1);");
+    /// Documents a member, or its getter with accessors, with the `doc` of the field.
+    fn write_field_doxygen_comment(&mut self, field: &Field) {
+        if let Some(doc) = field.doc().filter(|_| self.options.doxygen) {
+            self.write_doxygen_comment(&doxygen_lines(doc));
+        }
+    }
 
-            self_.pop_indentation_level();
-            cpp_source_builder_writeln!(self_, "}}");
+    pub fn write_struct_start(&mut self, strukt: &Struct) {
+        if let Some(about) = strukt.about().filter(|_| self.options.doxygen) {
+            self.write_doxygen_comment(&doxygen_lines(about));
         }
+        cpp_source_builder_writeln!(self, "struct {} {{", strukt.name);
+    }
 
-        match field_type {
-            FieldType::Vec(_) | FieldType::Bool => {}
-            _ => write_parse_value_option_preamble(
-                self,
-                struct_name,
-                matches!(field_type, FieldType::String),
-            ),
+    #[inline]
+    pub fn write_struct_end(&mut self) {
+        cpp_source_builder_writeln!(self, "}};\n");
+    }
+
+    /// Runs `write` against an empty buffer one indentation level deeper and returns what it
+    /// wrote, so that blocks can be assembled before being written out.
+    fn capture(&mut self, write: impl FnOnce(&mut Self)) -> String {
+        let previous_buffer = std::mem::take(&mut self.buffer);
+        self.push_indentation_level();
+        write(self);
+        self.pop_indentation_level();
+        std::mem::replace(&mut self.buffer, previous_buffer)
+    }
+
+    /// The name of the C++ member holding the field. With accessors the members are private and
+    /// get a trailing underscore, leaving the field name to the getter.
+    fn member_name(&self, field: &Field) -> String {
+        if self.options.accessors {
+            format!("{}_", field.name)
+        } else {
+            field.name.clone()
         }
+    }
 
-        match field_type {
-            FieldType::String => {
-                cpp_source_builder_writeln!(self, "std::string arg_res = args[0];");
-            }
-            FieldType::I16
-            | FieldType::U16
-            | FieldType::I32
-            | FieldType::U32
-            | FieldType::I64
-            | FieldType::U64
-            | FieldType::F32
-            | FieldType::F64 => {
-                self.write_parse_numeric_field(field_type);
-            }
-            FieldType::Bool => {
-                cpp_source_builder_writeln!(self, "bool arg_res = true;");
-            }
-            FieldType::Struct(struct_name) => {
-                cpp_source_builder_writeln!(
-                    self,
-                    "{struct_name} arg_res = {struct_name}::parse(argc - i, args);"
-                );
-            }
-            FieldType::Vec(inner) => {
-                self.write_parse_field_type(struct_name, inner);
-            }
-            FieldType::Optional(inner) => {
-                self.write_parse_field_type(struct_name, inner);
+    /// The C++ type of the member of `field`, see `write_optional_class` for the optional fields
+    /// before C++17.
+    fn member_type(&self, field: &Field) -> String {
+        match &field.ty {
+            FieldType::Optional(inner) if !self.options.cpp_std.has_optional() => {
+                format!("cli_optional<{}>", field_type_to_cpp_type(inner))
             }
+            field_type => field_type_to_cpp_type(field_type),
+        }
+    }
+
+    #[inline]
+    pub fn write_struct_field(&mut self, field: &Field) {
+        let field_type = self.member_type(field);
+        let member_name = self.member_name(field);
+        self.push_indentation_level();
+        // Private members are documented through their getter instead.
+        if !self.options.accessors {
+            self.write_field_doxygen_comment(field);
         }
+        cpp_source_builder_writeln!(self, "{field_type} {member_name};");
+        self.pop_indentation_level();
+    }
+
+    /// Structs containing this one need access to its private members and constructor to fill
+    /// them in while parsing.
+    pub fn write_struct_friends(&mut self, friend_names: &[&str]) {
+        if friend_names.is_empty() {
+            return;
+        }
+
+        self.push_indentation_level();
+        for friend_name in friend_names {
+            cpp_source_builder_writeln!(self, "friend struct {friend_name};");
+        }
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self);
+    }
+
+    #[inline]
+    pub fn write_struct_accessor(&mut self, field: &Field) {
+        let field_type = self.member_type(field);
+        let field_name = &field.name;
+        let member_name = self.member_name(field);
+        self.push_indentation_level();
+        self.write_field_doxygen_comment(field);
+        cpp_source_builder_writeln!(
+            self,
+            "const {field_type}& {field_name}() const {{ return {member_name}; }}"
+        );
+        self.pop_indentation_level();
     }
 
-    fn write_parse_fields_r(
+    /// The constants holding the `default_value_t` of the fields, which `parse` starts them from,
+    /// e.g. `static constexpr uint16_t PORT_DEFAULT = 8080;`. Strings can't be `constexpr`, so
+    /// they are `inline` from C++17 and a `const char*` before it, unless the implementation is
+    /// split from the header, where they are defined.
+    pub fn write_default_constants(
         &mut self,
-        struct_name: &str,
-        fields: &[Field],
-        spec_metadata: &SpecMetadata,
-        parents: &mut Vec<String>,
-        mandatory_field_to_index: &HashMap<&str, usize>,
+        strukt: &Struct,
+        mut implementation: Option<&mut CppSourceBuilder>,
     ) {
-        let mut match_fields_buffer = Vec::new();
+        let fields = strukt
+            .fields
+            .iter()
+            .filter_map(|field| Some((field, field.default_value()?)))
+            .collect::<Vec<_>>();
+        if fields.is_empty() {
+            return;
+        }
 
-        for field in fields {
-            for attr in &field.attributes {
-                match attr.ty {
-                    AttributeType::Short => {
-                        let arg_match = format!("-{}", field.short_value().unwrap());
-                        match_fields_buffer.push(arg_match);
-                    }
-                    AttributeType::Long => {
-                        let arg_match = format!("--{}", field.long_value().unwrap());
-                        match_fields_buffer.push(arg_match);
+        cpp_source_builder_writeln!(self);
+        if let Some(implementation) = implementation.as_deref_mut() {
+            if fields
+                .iter()
+                .any(|(field, _)| field.ty == FieldType::String)
+            {
+                cpp_source_builder_writeln!(implementation);
+            }
+        }
+        self.push_indentation_level();
+        for (field, value) in fields {
+            if self.options.doxygen {
+                self.write_doxygen_comment(&[format!(
+                    "The value of `{}` when it isn't given.",
+                    field.name
+                )]);
+            }
+
+            let name = field.default_constant_name();
+            let value = default_value_literal(&field.ty, value);
+            match &field.ty {
+                FieldType::String => match implementation.as_deref_mut() {
+                    Some(implementation) => {
+                        cpp_source_builder_writeln!(self, "static const std::string {name};");
+                        cpp_source_builder_writeln!(
+                            implementation,
+                            "const std::string {}::{name} = {value};",
+                            strukt.name
+                        );
                     }
-                    AttributeType::Alias => {
-                        let value = attr.value.as_ref().unwrap();
-                        let arg_match = format!("--{}", value.replace('_', "-"));
-                        match_fields_buffer.push(arg_match);
+                    None if self.options.cpp_std.has_inline_variables() => {
+                        cpp_source_builder_writeln!(
+                            self,
+                            "static inline const std::string {name} = {value};"
+                        );
                     }
-                    AttributeType::Flatten => {
-                        let flatten_type = match &field.ty {
-                            FieldType::Vec(inner) => match inner.as_ref() {
-                                FieldType::Struct(name) => {
-                                    spec_metadata.identifier_to_struct[name.as_str()]
-                                }
-                                _ => unreachable!(),
-                            },
-                            FieldType::Struct(name) => {
-                                spec_metadata.identifier_to_struct[name.as_str()]
-                            }
-                            _ => unreachable!(),
-                        };
-                        parents.push(field.name.clone());
-                        self.write_parse_fields_r(
-                            struct_name,
-                            &flatten_type.fields,
-                            spec_metadata,
-                            parents,
-                            mandatory_field_to_index,
+                    None => {
+                        cpp_source_builder_writeln!(
+                            self,
+                            "static constexpr const char* {name} = {value};"
                         );
                     }
-                    _ => unreachable!(),
+                },
+                field_type => {
+                    cpp_source_builder_writeln!(
+                        self,
+                        "static constexpr {} {name} = {value};",
+                        field_type_to_cpp_type(field_type)
+                    );
                 }
             }
+        }
+        self.pop_indentation_level();
+    }
 
-            if !match_fields_buffer.is_empty() {
-                let field_matcher = match_fields_buffer
-                    .drain(..)
-                    .map(|arg_match| format!(r#"strcmp(arg, "{arg_match}") == 0"#))
-                    .collect::<Vec<_>>()
-                    .join(" || ");
+    /// Starts the fields of the struct at `path` from their `default_value_t`, along with the ones
+    /// of the structs it flattens. Optional and repeated flattened structs start theirs when they
+    /// are created.
+    fn write_default_values(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata, path: &str) {
+        for field in &strukt.fields {
+            let member_name = self.member_name(field);
+            if field.default_value().is_some() {
+                cpp_source_builder_writeln!(
+                    self,
+                    "{path}.{member_name} = {}::{};",
+                    strukt.name,
+                    field.default_constant_name()
+                );
+            } else if let (true, FieldType::Struct(name)) =
+                (field.has_attribute(AttributeType::Flatten), &field.ty)
+            {
+                let flatten_type = spec_metadata.identifier_to_struct[name.as_str()];
+                self.write_default_values(
+                    flatten_type,
+                    spec_metadata,
+                    &format!("{path}.{member_name}"),
+                );
+            }
+        }
+    }
+
+    /// With accessors the members can only be set through `parse`, so both them and the default
+    /// constructor are private.
+    pub fn write_struct_private_section(&mut self, strukt: &Struct) {
+        cpp_source_builder_writeln!(self);
+        cpp_source_builder_writeln!(self, "private:");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "{}() = default;\n", strukt.name);
+        self.pop_indentation_level();
+
+        for field in &strukt.fields {
+            self.write_struct_field(field);
+        }
+    }
+
+    /// Reports an invalid `arg_value` for the option `arg`. The value of secret fields is never
+    /// echoed back, so that it doesn't end up in terminals or logs.
+    fn write_invalid_value_message(&mut self, reason: &str, secret: bool) {
+        if secret {
+            cpp_source_builder_writeln!(self, r#"printf("Value of option '%s' {reason}", arg);"#);
+        } else {
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("Value '%s' of option '%s' {reason}", arg_value, arg);"#
+            );
+        }
+    }
+
+    /// The pattern string fields are validated against, unless regex validation is turned off.
+    fn regex_pattern<'f>(&self, field: &'f Field) -> Option<&'f str> {
+        if self.options.no_regex_validation {
+            return None;
+        }
+
+        field.attribute_value(AttributeType::Regex)
+    }
+
+    pub fn write_regex_check(&mut self, field: &Field, pattern: &str) {
+        let regex_name = format!("{}_regex", field.name);
+        let secret = field.has_attribute(AttributeType::Secret);
+
+        cpp_source_builder_writeln!(
+            self,
+            r#"static const std::regex {regex_name}("{pattern}");"#
+        );
+        cpp_source_builder_writeln!(self, "if (!std::regex_match(arg_res, {regex_name})) {{");
+        self.push_indentation_level();
+        // Secret values aren't printed, so they would be an unused variable.
+        if !secret {
+            cpp_source_builder_writeln!(self, "const char* arg_value = arg_res.c_str();");
+        }
+        self.write_invalid_value_message(
+            &format!("does not match the pattern {}", pattern.replace('%', "%%")),
+            secret,
+        );
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Whether the values of `field` are checked to be existing directories.
+    fn validates_directory(&self, field: &Field) -> bool {
+        self.options.validate_directory && field.is_directory()
+    }
+
+    /// The error code overload of `is_directory` is used so that a path that can't be looked at
+    /// is reported like a missing one rather than thrown.
+    pub fn write_directory_check(&mut self, field: &Field) {
+        let secret = field.has_attribute(AttributeType::Secret);
+
+        cpp_source_builder_writeln!(self, "std::error_code arg_error;");
+        cpp_source_builder_writeln!(
+            self,
+            "if (!std::filesystem::is_directory(arg_res, arg_error)) {{"
+        );
+        self.push_indentation_level();
+        if !secret {
+            cpp_source_builder_writeln!(self, "const char* arg_value = arg_res.c_str();");
+        }
+        self.write_invalid_value_message("is not an existing directory", secret);
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Floating point values are parsed independently of the process locale, so that `3.14` is
+    /// accepted (and `3,14` rejected) no matter the machine the generated code runs on. Before
+    /// C++17, streams stand in for `std::from_chars`, made to reject what it rejects: leading
+    /// whitespace, and values out of range, which they report by setting the largest one.
+    pub fn write_parse_float_field(&mut self, field_type: &FieldType, secret: bool, value: &str) {
+        let cpp_type = field_type_to_cpp_type(field_type);
+
+        cpp_source_builder_writeln!(self, "char* arg_value = {value};");
+        cpp_source_builder_writeln!(self, "{cpp_type} arg_res = 0;");
+
+        if self.options.cpp_std.has_from_chars() {
+            cpp_source_builder_writeln!(
+                self,
+                "std::from_chars_result conversion = std::from_chars(arg_value, arg_value + strlen(arg_value), arg_res);"
+            );
+            cpp_source_builder_writeln!(self);
+
+            cpp_source_builder_writeln!(
+                self,
+                "if (conversion.ec == std::errc::result_out_of_range) {{"
+            );
+            self.push_indentation_level();
+            self.write_invalid_value_message("out of range for floating point type", secret);
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+
+            cpp_source_builder_writeln!(
+                self,
+                r#"if (conversion.ec != std::errc() || *conversion.ptr != '\0') {{"#
+            );
+        } else {
+            cpp_source_builder_writeln!(self, "std::istringstream arg_stream(arg_value);");
+            cpp_source_builder_writeln!(self, "arg_stream.imbue(std::locale::classic());");
+            cpp_source_builder_writeln!(self, "arg_stream >> std::noskipws >> arg_res;");
+            cpp_source_builder_writeln!(self);
+
+            cpp_source_builder_writeln!(
+                self,
+                "if (arg_stream.fail() && (arg_res == std::numeric_limits<{cpp_type}>::max() || arg_res == std::numeric_limits<{cpp_type}>::lowest())) {{"
+            );
+            self.push_indentation_level();
+            self.write_invalid_value_message("out of range for floating point type", secret);
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+
+            cpp_source_builder_writeln!(
+                self,
+                "if (arg_stream.fail() || arg_stream.peek() != EOF) {{"
+            );
+        }
+
+        self.push_indentation_level();
+        self.write_invalid_value_message("is not a valid floating point number", secret);
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Converts `value`, the argument following the option or the value attached to it.
+    pub fn write_parse_numeric_field(
+        &mut self,
+        field: &Field,
+        field_type: &FieldType,
+        value: &str,
+    ) {
+        let secret = field.has_attribute(AttributeType::Secret);
+        let cpp_type = field_type_to_cpp_type(field_type);
+        // Values are parsed as `long long` and checked against the bounds of the type before the
+        // cast, which would silently wrap them around otherwise.
+        let type_bounds_check = match field_type {
+            FieldType::I16 => " || arg_number < INT16_MIN || arg_number > INT16_MAX",
+            FieldType::U16 => " || arg_number < 0 || arg_number > UINT16_MAX",
+            FieldType::I32 => " || arg_number < INT32_MIN || arg_number > INT32_MAX",
+            FieldType::U32 => " || arg_number < 0 || arg_number > UINT32_MAX",
+            FieldType::I64 => "",
+            FieldType::U64 => " || arg_number < 0",
+            FieldType::F32 | FieldType::F64 => {
+                self.write_parse_float_field(field_type, secret, value);
+                self.write_range_check(field, field_type, secret);
+                return;
+            }
+            _ => unreachable!(),
+        };
+
+        cpp_source_builder_writeln!(self, "char* arg_value = {value};");
+        cpp_source_builder_writeln!(self, "errno = 0;");
+        cpp_source_builder_writeln!(
+            self,
+            "long long arg_number = std::strtoll(arg_value, nullptr, 10);"
+        );
+        cpp_source_builder_writeln!(
+            self,
+            "{cpp_type} arg_res = static_cast<{cpp_type}>(arg_number);"
+        );
+        cpp_source_builder_writeln!(self);
+
+        cpp_source_builder_writeln!(self, "if (errno == ERANGE{type_bounds_check}) {{");
+        self.push_indentation_level();
+        self.write_invalid_value_message("out of range for integer type", secret);
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+
+        cpp_source_builder_writeln!(
+            self,
+            r#"if (arg_res == 0 && strcmp(arg_value, "0") != 0) {{"#
+        );
+        self.push_indentation_level();
+        self.write_invalid_value_message("is not a valid integer", secret);
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+
+        self.write_range_check(field, field_type, secret);
+    }
+
+    /// Checks the parsed value against the `min`/`max` attributes of the field, if any.
+    fn write_range_check(&mut self, field: &Field, field_type: &FieldType, secret: bool) {
+        let literal = |value: &str| match field_type {
+            FieldType::I64 => format!("{value}LL"),
+            FieldType::U64 => format!("{value}ULL"),
+            _ => value.to_string(),
+        };
+        let min = field.attribute_value(AttributeType::Min);
+        let max = field.attribute_value(AttributeType::Max);
+
+        let (condition, reason) = match (min, max) {
+            (Some(min), Some(max)) => (
+                format!("arg_res < {} || arg_res > {}", literal(min), literal(max)),
+                format!("is not between {min} and {max}"),
+            ),
+            (Some(min), None) => (
+                format!("arg_res < {}", literal(min)),
+                format!("is less than {min}"),
+            ),
+            (None, Some(max)) => (
+                format!("arg_res > {}", literal(max)),
+                format!("is greater than {max}"),
+            ),
+            (None, None) => return,
+        };
+
+        cpp_source_builder_writeln!(self, "if ({condition}) {{");
+        self.push_indentation_level();
+        self.write_invalid_value_message(&reason, secret);
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Bool options are set by their mere presence, but an explicit value can also be attached
+    /// (`--flag=false`). Explicit bool fields additionally consume the next argument as value.
+    pub fn write_parse_bool_field(&mut self, explicit: bool, value_separator: &str) {
+        cpp_source_builder_writeln!(self, "bool arg_res = true;");
+        cpp_source_builder_writeln!(
+            self,
+            "const char* bool_value = strchr(arg, '{value_separator}');"
+        );
+        cpp_source_builder_writeln!(self, "if (bool_value != nullptr) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "++bool_value;");
+        self.pop_indentation_level();
+
+        if explicit {
+            cpp_source_builder_writeln!(self, "}} else {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "++args;");
+            cpp_source_builder_writeln!(self, "++i;");
+            cpp_source_builder_writeln!(self, "if (i == argc) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("Expected value for option '%s' but no value was provided", arg);"#
+            );
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.missing_arg);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            cpp_source_builder_writeln!(self, "bool_value = args[0];");
+            self.pop_indentation_level();
+        }
+        cpp_source_builder_writeln!(self, "}}");
+
+        cpp_source_builder_writeln!(
+            self,
+            "if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {{"
+        );
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(
+            self,
+            r#"printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);"#
+        );
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    pub fn write_parse_field_type(
+        &mut self,
+        strukt: &Struct,
+        field: &Field,
+        field_type: &FieldType,
+    ) {
+        /// Moves to the argument following the option. Prefix flags first look for a value
+        /// attached to the option after `value_separator`, and only move on without one.
+        fn write_parse_value_option_preamble(
+            self_: &mut CppSourceBuilder,
+            struct_name: &str,
+            is_string: bool,
+            value_separator: Option<&str>,
+        ) {
+            if let Some(value_separator) = value_separator {
+                cpp_source_builder_writeln!(
+                    self_,
+                    "char* option_value = strchr(args[0], '{value_separator}');"
+                );
+                cpp_source_builder_writeln!(self_, "if (option_value != nullptr) {{");
+                self_.push_indentation_level();
+                cpp_source_builder_writeln!(self_, "++option_value;");
+                self_.pop_indentation_level();
+                cpp_source_builder_writeln!(self_, "}} else {{");
+                self_.push_indentation_level();
+            }
+
+            cpp_source_builder_writeln!(self_, "++args;");
+            cpp_source_builder_writeln!(self_, "++i;");
+
+            cpp_source_builder_write!(self_, "if (i == argc");
+            if is_string {
+                cpp_source_builder_appendln!(self_, ") {{");
+            } else {
+                cpp_source_builder_appendln!(self_, " || {struct_name}::is_option(args[0])) {{");
+            }
+            self_.push_indentation_level();
+
+            cpp_source_builder_writeln!(
+                self_,
+                r#"printf("Expected value for option '%s' but no value was provided", arg);"#
+            );
+            cpp_source_builder_writeln!(self_, "exit({});", self_.exit_codes.missing_arg);
+
+            self_.pop_indentation_level();
+            cpp_source_builder_writeln!(self_, "}}");
+
+            if value_separator.is_some() {
+                cpp_source_builder_writeln!(self_, "option_value = args[0];");
+                self_.pop_indentation_level();
+                cpp_source_builder_writeln!(self_, "}}");
+            }
+        }
+
+        let syntax = strukt.option_syntax();
+        let value_separator = field.is_prefix_flag().then_some(syntax.value_separator);
+        let value = if field.is_prefix_flag() {
+            "option_value"
+        } else {
+            "args[0]"
+        };
+
+        match field_type {
+            FieldType::Vec(_) | FieldType::Optional(_) | FieldType::Bool => {}
+            _ => write_parse_value_option_preamble(
+                self,
+                &strukt.name,
+                matches!(field_type, FieldType::String),
+                value_separator,
+            ),
+        }
+
+        match field_type {
+            FieldType::String
+            | FieldType::I16
+            | FieldType::U16
+            | FieldType::I32
+            | FieldType::U32
+            | FieldType::I64
+            | FieldType::U64
+            | FieldType::F32
+            | FieldType::F64 => {
+                self.write_convert_value(field, field_type, value);
+            }
+            FieldType::Bool => {
+                self.write_parse_bool_field(field.is_explicit_bool(), syntax.value_separator);
+            }
+            FieldType::Struct(struct_name) if field.is_from_str() => {
+                cpp_source_builder_writeln!(
+                    self,
+                    "{struct_name} arg_res = {struct_name}::from_string({value});"
+                );
+            }
+            FieldType::Struct(struct_name) => {
+                cpp_source_builder_writeln!(
+                    self,
+                    "{struct_name} arg_res = {struct_name}::parse(argc - i, args);"
+                );
+            }
+            FieldType::Vec(inner) => {
+                self.write_parse_field_type(strukt, field, inner);
+            }
+            FieldType::Optional(inner) => {
+                self.write_parse_field_type(strukt, field, inner);
+            }
+            FieldType::Error => unreachable!(),
+        }
+    }
+
+    /// Converts `value`, a `char*` holding the text of a string or numeric value, to the type of
+    /// the field into `arg_res` and validates it.
+    fn write_convert_value(&mut self, field: &Field, field_type: &FieldType, value: &str) {
+        match field_type {
+            // The custom function replaces the conversion, and the value is still validated.
+            FieldType::String
+            | FieldType::I16
+            | FieldType::U16
+            | FieldType::I32
+            | FieldType::U32
+            | FieldType::I64
+            | FieldType::U64
+            | FieldType::F32
+            | FieldType::F64
+                if field.parse_fn().is_some() =>
+            {
+                let parse_fn = field.parse_fn().unwrap();
+                let cpp_type = field_type_to_cpp_type(field_type);
+                if matches!(field_type, FieldType::String) {
+                    cpp_source_builder_writeln!(self, "{cpp_type} arg_res = {parse_fn}({value});");
+                    if let Some(pattern) = self.regex_pattern(field) {
+                        self.write_regex_check(field, pattern);
+                    }
+                    if self.validates_directory(field) {
+                        self.write_directory_check(field);
+                    }
+                } else {
+                    cpp_source_builder_writeln!(self, "char* arg_value = {value};");
+                    cpp_source_builder_writeln!(
+                        self,
+                        "{cpp_type} arg_res = {parse_fn}(arg_value);"
+                    );
+                    let secret = field.has_attribute(AttributeType::Secret);
+                    self.write_range_check(field, field_type, secret);
+                }
+            }
+            FieldType::String => {
+                cpp_source_builder_writeln!(self, "std::string arg_res = {value};");
+                if let Some(pattern) = self.regex_pattern(field) {
+                    self.write_regex_check(field, pattern);
+                }
+                if self.validates_directory(field) {
+                    self.write_directory_check(field);
+                }
+            }
+            FieldType::I16
+            | FieldType::U16
+            | FieldType::I32
+            | FieldType::U32
+            | FieldType::I64
+            | FieldType::U64
+            | FieldType::F32
+            | FieldType::F64 => {
+                self.write_parse_numeric_field(field, field_type, value);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn collect_parse_arms_r(
+        &mut self,
+        strukt: &Struct,
+        fields: &[Field],
+        spec_metadata: &SpecMetadata,
+        parents: &mut Vec<String>,
+        optional_paths: &mut Vec<(String, String)>,
+        mandatory_fields: &MandatoryFields,
+        repeated_group: Option<&RepeatedGroup>,
+    ) -> Vec<MatchArm> {
+        let syntax = strukt.option_syntax();
+        let mut arms = Vec::new();
+        let mut match_fields_buffer = Vec::new();
+
+        // Fields with a parse order are matched first, the rest in the order they are declared.
+        let mut ordered_fields = fields.iter().enumerate().collect::<Vec<_>>();
+        ordered_fields.sort_by_key(|(_, field)| field.parse_order().unwrap_or(usize::MAX));
+
+        for (index, field) in ordered_fields {
+            let case_insensitive = strukt.is_case_insensitive(field, spec_metadata);
+            for attr in &field.attributes {
+                match attr.ty {
+                    AttributeType::Short => {
+                        let arg_match = syntax.short_option(&field.short_value().unwrap());
+                        match_fields_buffer.push((arg_match, false));
+                    }
+                    AttributeType::Long => {
+                        let arg_match = syntax.long_option(&field.long_value().unwrap());
+                        match_fields_buffer.push((arg_match, case_insensitive));
+                    }
+                    AttributeType::Alias => {
+                        let value = attr.value.as_ref().unwrap();
+                        let arg_match = syntax.long_option(&value.replace('_', "-"));
+                        match_fields_buffer.push((arg_match, case_insensitive));
+                    }
+                    AttributeType::Flatten => {
+                        let member_name = self.member_name(field);
+                        match &field.ty {
+                            FieldType::Optional(inner) => {
+                                let FieldType::Struct(name) = inner.as_ref() else {
+                                    unreachable!()
+                                };
+                                let flatten_type =
+                                    spec_metadata.identifier_to_struct[name.as_str()];
+                                let path = format!("{}.{member_name}", parents.join("."));
+                                let group_mandatory_fields = MandatoryFields::new(
+                                    optional_group_prefix(&path),
+                                    flatten_type,
+                                );
+
+                                parents.push(format!("{member_name}.value()"));
+                                optional_paths.push((path, name.clone()));
+                                arms.extend(self.collect_parse_arms_r(
+                                    strukt,
+                                    &flatten_type.fields,
+                                    spec_metadata,
+                                    parents,
+                                    optional_paths,
+                                    &group_mandatory_fields,
+                                    repeated_group,
+                                ));
+                                optional_paths.pop();
+                            }
+                            FieldType::Vec(inner) => {
+                                let FieldType::Struct(name) = inner.as_ref() else {
+                                    unreachable!()
+                                };
+                                let flatten_type =
+                                    spec_metadata.identifier_to_struct[name.as_str()];
+                                let path = format!("{}.{member_name}", parents.join("."));
+                                let group = RepeatedGroup {
+                                    prefix: optional_group_prefix(&path),
+                                    path,
+                                    strukt: flatten_type,
+                                };
+                                // The mandatory fields of the elements are checked on their own.
+                                let group_mandatory_fields = MandatoryFields {
+                                    prefix: group.prefix.clone(),
+                                    fields: Vec::new(),
+                                };
+
+                                parents.push(format!("{member_name}.back()"));
+                                arms.extend(self.collect_parse_arms_r(
+                                    strukt,
+                                    &flatten_type.fields,
+                                    spec_metadata,
+                                    parents,
+                                    optional_paths,
+                                    &group_mandatory_fields,
+                                    Some(&group),
+                                ));
+                            }
+                            _ => {
+                                let FieldType::Struct(name) = &field.ty else {
+                                    unreachable!()
+                                };
+                                let flatten_type =
+                                    spec_metadata.identifier_to_struct[name.as_str()];
+                                parents.push(member_name);
+                                arms.extend(self.collect_parse_arms_r(
+                                    strukt,
+                                    &flatten_type.fields,
+                                    spec_metadata,
+                                    parents,
+                                    optional_paths,
+                                    mandatory_fields,
+                                    repeated_group,
+                                ));
+                            }
+                        }
+                    }
+                    AttributeType::ExplicitBool
+                    | AttributeType::TakesValue
+                    | AttributeType::Trailing
+                    | AttributeType::Secret
+                    | AttributeType::MinOccurs
+                    | AttributeType::MaxOccurs
+                    | AttributeType::Min
+                    | AttributeType::Max
+                    | AttributeType::Regex
+                    | AttributeType::CaseInsensitive
+                    | AttributeType::Default
+                    | AttributeType::Doc
+                    | AttributeType::Deprecated
+                    | AttributeType::DeprecatedIn
+                    | AttributeType::RemovedIn
+                    | AttributeType::HiddenFromCompletion
+                    | AttributeType::ParseFn
+                    | AttributeType::AppendEnv
+                    | AttributeType::EnvList
+                    | AttributeType::ExclusiveGroup
+                    | AttributeType::RequiresGroup
+                    | AttributeType::Prefix
+                    | AttributeType::NextLineHelp
+                    | AttributeType::FromStr
+                    | AttributeType::DefaultValueT
+                    | AttributeType::PrefixFlag
+                    | AttributeType::Env
+                    | AttributeType::EnvNoOverride
+                    | AttributeType::Index
+                    | AttributeType::ParseOrder
+                    | AttributeType::DisplayOrder
+                    | AttributeType::InputFile
+                    | AttributeType::OutputFile
+                    | AttributeType::Directory
+                    | AttributeType::GlobalFlag => {}
+                    _ => unreachable!(),
+                }
+            }
+
+            // An option given to a later field of the struct as well is left to that field.
+            match_fields_buffer
+                .retain(|(option, _)| spec_metadata.struct_metadata(strukt).accepts(field, option));
+            if !match_fields_buffer.is_empty() {
+                // Bools and prefix flags also match with a value attached, e.g. `--port=8080`.
+                let takes_attached_value =
+                    field.is_prefix_flag() || matches!(field.ty.value_type(), FieldType::Bool);
+                let field_matcher = match_fields_buffer
+                    .drain(..)
+                    .map(|(arg_match, case_insensitive)| {
+                        let condition = option_match_condition(&arg_match, case_insensitive);
+                        if !takes_attached_value {
+                            condition
+                        } else if case_insensitive {
+                            format!(
+                                r#"{condition} || starts_with_ignore_case(arg, "{arg_match}{}")"#,
+                                syntax.value_separator
+                            )
+                        } else {
+                            format!(
+                                r#"{condition} || strncmp(arg, "{arg_match}{}", {}) == 0"#,
+                                syntax.value_separator,
+                                arg_match.len() + syntax.value_separator.len()
+                            )
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" || ");
+
+                let destination = parents.join(".");
+                let member_name = self.member_name(field);
+
+                let body = self.capture(|this| {
+                    if field.is_deprecated() {
+                        this.write_deprecation_warning(field);
+                    }
+                    this.write_parse_field_type(strukt, field, &field.ty);
+
+                    // Assigning a temporary instead of `emplace` keeps the construction in this
+                    // struct, which is a friend of the flattened one in accessors mode.
+                    for (path, struct_name) in optional_paths.iter() {
+                        cpp_source_builder_writeln!(this, "if (!{path}.has_value()) {{");
+                        this.push_indentation_level();
+                        cpp_source_builder_writeln!(this, "{path} = {struct_name}();");
+                        this.write_default_values(
+                            spec_metadata.identifier_to_struct[struct_name.as_str()],
+                            spec_metadata,
+                            &format!("{path}.value()"),
+                        );
+                        this.pop_indentation_level();
+                        cpp_source_builder_writeln!(this, "}}");
+                    }
+
+                    if let Some(group) = repeated_group {
+                        this.write_repeated_group_element(
+                            group,
+                            spec_metadata,
+                            index,
+                            matches!(field.ty, FieldType::Vec(_)),
+                            syntax.long_prefix,
+                        );
+                    }
+
+                    match &field.ty {
+                        FieldType::Vec(_) => {
+                            cpp_source_builder_writeln!(
+                                this,
+                                "{destination}.{member_name}.push_back(arg_res);"
+                            );
+                        }
+                        _ => {
+                            cpp_source_builder_writeln!(
+                                this,
+                                "{destination}.{member_name} = arg_res;"
+                            );
+                        }
+                    }
+
+                    if let Some(index) = mandatory_fields.index_of(field) {
+                        cpp_source_builder_writeln!(
+                            this,
+                            "{}mandatory_fields_seen[{index}] = true;",
+                            mandatory_fields.prefix
+                        )
+                    }
+                    if tracks_given_fields(strukt) && parents.len() == 1 && repeated_group.is_none()
+                    {
+                        cpp_source_builder_writeln!(this, "fields_given[{index}] = true;");
+                    }
+                });
+                arms.push(MatchArm {
+                    condition: field_matcher,
+                    body,
+                });
+            }
+        }
+        parents.pop();
+
+        arms
+    }
+
+    pub fn collect_parse_arms(
+        &mut self,
+        strukt: &Struct,
+        spec_metadata: &SpecMetadata,
+        mandatory_fields: &MandatoryFields,
+        arms: &mut Vec<MatchArm>,
+    ) {
+        let mut parents = vec!["res".to_string()];
+        arms.extend(self.collect_parse_arms_r(
+            strukt,
+            &strukt.fields,
+            spec_metadata,
+            &mut parents,
+            &mut Vec::new(),
+            mandatory_fields,
+            None,
+        ));
+    }
+
+    /// Writes an `if`/`else if`/`else` chain out of the arms, where each body was written one
+    /// level deeper than the chain itself.
+    fn write_if_else_chain(&mut self, arms: &[MatchArm], else_body: &str) {
+        for (i, arm) in arms.iter().enumerate() {
+            if i == 0 {
+                cpp_source_builder_writeln!(self, "if ({}) {{", arm.condition);
+            } else {
+                cpp_source_builder_writeln!(self, "}} else if ({}) {{", arm.condition);
+            }
+            self.buffer.push_str(&arm.body);
+        }
+
+        if arms.is_empty() {
+            self.buffer.push_str(else_body);
+        } else {
+            cpp_source_builder_writeln!(self, "}} else {{");
+            self.buffer.push_str(else_body);
+            cpp_source_builder_writeln!(self, "}}");
+        }
+    }
+
+    /// Finds the optional groups in the same order and with the same paths that
+    /// `collect_parse_arms_r` walks the flattened structs.
+    fn collect_optional_groups<'s>(
+        &self,
+        fields: &'s [Field],
+        spec_metadata: &SpecMetadata<'s>,
+        parents: &mut Vec<String>,
+        enclosing_paths: &mut Vec<String>,
+        groups: &mut Vec<OptionalGroup<'s>>,
+    ) {
+        for field in fields
+            .iter()
+            .filter(|field| field.has_attribute(AttributeType::Flatten))
+        {
+            let FieldType::Struct(name) = field.ty.value_type() else {
+                continue;
+            };
+            let flatten_type = spec_metadata.identifier_to_struct[name.as_str()];
+            let member_name = self.member_name(field);
+
+            if matches!(field.ty, FieldType::Optional(_)) {
+                let path = format!("{}.{member_name}", parents.join("."));
+                enclosing_paths.push(path.clone());
+                groups.push(OptionalGroup {
+                    paths: enclosing_paths.clone(),
+                    mandatory_fields: MandatoryFields::new(
+                        optional_group_prefix(&path),
+                        flatten_type,
+                    ),
+                });
+
+                parents.push(format!("{member_name}.value()"));
+                self.collect_optional_groups(
+                    &flatten_type.fields,
+                    spec_metadata,
+                    parents,
+                    enclosing_paths,
+                    groups,
+                );
+                parents.pop();
+                enclosing_paths.pop();
+            } else {
+                parents.push(member_name);
+                self.collect_optional_groups(
+                    &flatten_type.fields,
+                    spec_metadata,
+                    parents,
+                    enclosing_paths,
+                    groups,
+                );
+                parents.pop();
+            }
+        }
+    }
+
+    /// Warns on stderr that the option of a deprecated field was given, see
+    /// `deprecation_warning`.
+    fn write_deprecation_warning(&mut self, field: &Field) {
+        let (warning, removal) = deprecation_warning(field);
+        cpp_source_builder_writeln!(self, r#"fprintf(stderr, "{warning}\n", arg);"#);
+        if let Some(removal) = removal {
+            cpp_source_builder_writeln!(self, r#"fprintf(stderr, "{removal}\n");"#);
+        }
+    }
+
+    /// Finds the repeated groups with the same paths that `collect_parse_arms_r` gives them.
+    fn collect_repeated_groups<'s>(
+        &self,
+        fields: &'s [Field],
+        spec_metadata: &SpecMetadata<'s>,
+        parents: &mut Vec<String>,
+        groups: &mut Vec<RepeatedGroup<'s>>,
+    ) {
+        for field in fields
+            .iter()
+            .filter(|field| field.has_attribute(AttributeType::Flatten))
+        {
+            let FieldType::Struct(name) = field.ty.value_type() else {
+                continue;
+            };
+            let flatten_type = spec_metadata.identifier_to_struct[name.as_str()];
+            let member_name = self.member_name(field);
+
+            match &field.ty {
+                FieldType::Vec(_) => {
+                    let path = format!("{}.{member_name}", parents.join("."));
+                    groups.push(RepeatedGroup {
+                        prefix: optional_group_prefix(&path),
+                        path,
+                        strukt: flatten_type,
+                    });
+                    continue;
+                }
+                FieldType::Optional(_) => parents.push(format!("{member_name}.value()")),
+                _ => parents.push(member_name),
+            }
+            self.collect_repeated_groups(&flatten_type.fields, spec_metadata, parents, groups);
+            parents.pop();
+        }
+    }
+
+    /// Starts a new element of the group when it is empty or when the option at `index` was
+    /// already given to its last element, which is checked for its mandatory fields first. `Vec`
+    /// fields accumulate in the last element instead.
+    fn write_repeated_group_element(
+        &mut self,
+        group: &RepeatedGroup,
+        spec_metadata: &SpecMetadata,
+        index: usize,
+        accumulates: bool,
+        long_prefix: &str,
+    ) {
+        let RepeatedGroup {
+            path,
+            prefix,
+            strukt,
+        } = group;
+
+        if accumulates {
+            cpp_source_builder_writeln!(self, "if ({path}.empty()) {{");
+        } else {
+            cpp_source_builder_writeln!(
+                self,
+                "if ({path}.empty() || {prefix}fields_given[{index}]) {{"
+            );
+        }
+        self.push_indentation_level();
+        if !accumulates && !strukt.mandatory_fields().is_empty() {
+            cpp_source_builder_writeln!(self, "if (!{path}.empty()) {{");
+            self.push_indentation_level();
+            self.write_repeated_group_check(group, long_prefix);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+        cpp_source_builder_writeln!(self, "{path}.push_back({}());", strukt.name);
+        self.write_default_values(strukt, spec_metadata, &format!("{path}.back()"));
+        cpp_source_builder_writeln!(self, "for (bool& given : {prefix}fields_given) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "given = false;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, "{prefix}fields_given[{index}] = true;");
+    }
+
+    /// Exits when the last element of the group misses one of its mandatory fields.
+    fn write_repeated_group_check(&mut self, group: &RepeatedGroup, long_prefix: &str) {
+        for (index, field) in group.strukt.fields.iter().enumerate() {
+            if !field.is_required() {
+                continue;
+            }
+
+            cpp_source_builder_writeln!(self, "if (!{}fields_given[{index}]) {{", group.prefix);
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("{long_prefix}{} was required but it was not provided\n");"#,
+                field.name
+            );
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.missing_arg);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+    }
+
+    /// The arm taking the arguments that aren't options as the positional fields of the struct, in
+    /// the order of their indices. A lone prefix, e.g. `-` for the standard input, is a value.
+    fn positional_parse_arm(&mut self, strukt: &Struct) -> Option<MatchArm> {
+        let positional_fields = strukt.positional_fields();
+        if positional_fields.is_empty() {
+            return None;
+        }
+
+        let syntax = strukt.option_syntax();
+        let mut prefixes = vec![syntax.short_prefix];
+        if syntax.long_prefix != syntax.short_prefix {
+            prefixes.push(syntax.long_prefix);
+        }
+        let not_an_option = prefixes
+            .iter()
+            .map(|prefix| {
+                format!(
+                    r#"(strncmp(arg, "{prefix}", {}) != 0 || strcmp(arg, "{prefix}") == 0)"#,
+                    prefix.len()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" && ");
+
+        let body = self.capture(|this| {
+            for (i, field) in positional_fields.iter().enumerate() {
+                if i == 0 {
+                    cpp_source_builder_writeln!(this, "if (positional_count == {i}) {{");
+                } else {
+                    cpp_source_builder_writeln!(this, "}} else if (positional_count == {i}) {{");
+                }
+                this.push_indentation_level();
+                // Invalid values are reported for the name of the argument.
+                if !matches!(field.ty.value_type(), FieldType::String)
+                    || this.regex_pattern(field).is_some()
+                    || this.validates_directory(field)
+                {
+                    cpp_source_builder_writeln!(
+                        this,
+                        r#"const char* arg = "<{}>";"#,
+                        field.name.to_uppercase()
+                    );
+                }
+                this.write_convert_value(field, field.ty.value_type(), "args[0]");
+                cpp_source_builder_writeln!(this, "res.{} = arg_res;", this.member_name(field));
+                if tracks_given_fields(strukt) {
+                    let index = strukt
+                        .fields
+                        .iter()
+                        .position(|other| std::ptr::eq(other, *field))
+                        .unwrap();
+                    cpp_source_builder_writeln!(this, "fields_given[{index}] = true;");
+                }
+                this.pop_indentation_level();
+            }
+            cpp_source_builder_writeln!(this, "}}");
+            cpp_source_builder_writeln!(this, "++positional_count;");
+        });
+
+        Some(MatchArm {
+            condition: format!(
+                "positional_count != {} && {not_an_option}",
+                positional_fields.len()
+            ),
+            body,
+        })
+    }
+
+    /// Reports the mandatory positional fields that weren't given, which are the first ones since
+    /// the optional ones come after them.
+    fn write_positional_fields_check(&mut self, strukt: &Struct) {
+        for (i, field) in strukt.positional_fields().iter().enumerate() {
+            if !field.is_required() {
+                break;
+            }
+
+            cpp_source_builder_writeln!(self, "if (positional_count <= {i}) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("<{}> was required but it was not provided\n");"#,
+                field.name.to_uppercase()
+            );
+            cpp_source_builder_writeln!(self, "not_seen_any = true;");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+    }
+
+    fn write_mandatory_fields_declaration(&mut self, mandatory_fields: &MandatoryFields) {
+        let prefix = &mandatory_fields.prefix;
+
+        // Zero-length arrays are not valid C++.
+        if mandatory_fields.fields.is_empty() {
+            return;
+        }
+
+        cpp_source_builder_write!(self, "const char* {prefix}mandatory_field_names[] = {{");
+        for field in &mandatory_fields.fields {
+            cpp_source_builder_append!(self, r#" "{}","#, field.name);
+        }
+        cpp_source_builder_appendln!(self, " }};");
+
+        cpp_source_builder_writeln!(
+            self,
+            "bool {prefix}mandatory_fields_seen[sizeof({prefix}mandatory_field_names)/sizeof({prefix}mandatory_field_names[0])] = {{ false }};\n"
+        );
+    }
+
+    fn write_mandatory_fields_check(
+        &mut self,
+        mandatory_fields: &MandatoryFields,
+        long_prefix: &str,
+    ) {
+        let prefix = &mandatory_fields.prefix;
+
+        if mandatory_fields.fields.is_empty() {
+            return;
+        }
+
+        cpp_source_builder_writeln!(
+            self,
+            "for (size_t i = 0; i != sizeof({prefix}mandatory_field_names)/sizeof({prefix}mandatory_field_names[0]); ++i) {{"
+        );
+        self.push_indentation_level();
+
+        cpp_source_builder_writeln!(self, "if (!{prefix}mandatory_fields_seen[i]) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(
+            self,
+            r#"printf("{long_prefix}%s was required but it was not provided\n", {prefix}mandatory_field_names[i]);"#
+        );
+        cpp_source_builder_writeln!(self, "not_seen_any = true;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Checks that at most one field of every `exclusive_group` is given, and that a field with a
+    /// `requires_group` is given along with one of the fields of its group. Groups are checked in
+    /// the order their first field is declared.
+    fn write_option_group_checks(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let group_options = |group: &str, conjunction: &str| {
+            let options = spec_metadata.option_groups[group]
+                .iter()
+                .map(|&index| field_option_name(strukt, &strukt.fields[index]))
+                .collect::<Vec<_>>();
+            match options.split_last() {
+                Some((last, [])) => last.clone(),
+                Some((last, rest)) => format!("{} {conjunction} {last}", rest.join(", ")),
+                None => unreachable!(),
+            }
+        };
+        let fields_given = |group: &str, operator: &str| {
+            spec_metadata.option_groups[group]
+                .iter()
+                .map(|index| format!("fields_given[{index}]"))
+                .collect::<Vec<_>>()
+                .join(operator)
+        };
+
+        // A group of a single field can't be given more than once.
+        let mut groups = Vec::new();
+        for group in strukt.fields.iter().filter_map(Field::exclusive_group) {
+            if !groups.contains(&group) && spec_metadata.option_groups[group].len() > 1 {
+                groups.push(group);
+            }
+        }
+        for group in groups {
+            cpp_source_builder_writeln!(self, "if ({} > 1) {{", fields_given(group, " + "));
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("Only one of {} can be given\n");"#,
+                group_options(group, "and")
+            );
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+
+        for (index, field) in strukt.fields.iter().enumerate() {
+            let Some(group) = field.requires_group() else {
+                continue;
+            };
+            cpp_source_builder_writeln!(
+                self,
+                "if (fields_given[{index}] && !({})) {{",
+                fields_given(group, " || ")
+            );
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("{} requires {}{}\n");"#,
+                field_option_name(strukt, field),
+                if spec_metadata.option_groups[group].len() == 1 {
+                    ""
+                } else {
+                    "one of "
+                },
+                group_options(group, "or")
+            );
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+    }
+
+    /// Global flags are set by a first pass over the arguments, up to the separator, so that they
+    /// are in effect before any other argument is parsed. The main pass still takes them where
+    /// they are given, e.g. for `--verbose=false`. The argument following an option taking a value
+    /// is skipped, since in `--name -v` it is the value of `--name` rather than a flag.
+    fn write_global_flags_scan(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let options = &spec_metadata.struct_metadata(strukt).options;
+        let global_flags = strukt
+            .fields
+            .iter()
+            .filter(|field| field.is_global_flag())
+            .collect::<Vec<_>>();
+        if global_flags.is_empty() {
+            return;
+        }
+
+        cpp_source_builder_writeln!(self, "for (int i = 0; i != argc; ++i) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "const char *arg = args[i];");
+        if strukt.trailing_field().is_some() {
+            let separator = strukt.separator();
+            cpp_source_builder_writeln!(self, r#"if (strcmp(arg, "{separator}") == 0) {{"#);
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "break;");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+        let value_options = options
+            .iter()
+            .filter(|option| option.value != ValueKind::Flag)
+            .map(|option| option_match_condition(&option.name, option.case_insensitive))
+            .collect::<Vec<_>>();
+        if !value_options.is_empty() {
+            cpp_source_builder_writeln!(self, "if ({}) {{", value_options.join(" || "));
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "++i;");
+            cpp_source_builder_writeln!(self, "continue;");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+        for field in global_flags {
+            let condition = options
+                .iter()
+                .filter(|option| std::ptr::eq(option.field, field))
+                .map(|option| option_match_condition(&option.name, option.case_insensitive))
+                .collect::<Vec<_>>()
+                .join(" || ");
+            cpp_source_builder_writeln!(self, "if ({condition}) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "res.{} = true;", self.member_name(field));
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    pub fn write_struct_parse_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        cpp_source_builder_writeln!(self);
+
+        let struct_name = &strukt.name;
+        let syntax = strukt.option_syntax();
+
+        // Errors exit instead of throwing, but the parsing of other structs and user `parse_fn`s
+        // are left out since they might.
+        let noexcept = self.options.cpp_noexcept
+            && strukt.fields.iter().all(|field| {
+                !matches!(field.ty.value_type(), FieldType::Struct(_))
+                    && !field.has_attribute(AttributeType::ParseFn)
+            });
+
+        self.push_indentation_level();
+        if self.options.doxygen {
+            let args = if strukt.has_attribute(AttributeType::Main) {
+                "The arguments, starting with the name of the program."
+            } else {
+                "The arguments."
+            };
+            self.write_doxygen_comment(&[
+                format!(
+                    "Parses the arguments into a `{struct_name}`, printing an error and exiting when they are invalid."
+                ),
+                String::new(),
+                String::from("@param argc The number of arguments."),
+                format!("@param args {args}"),
+                format!("@return The parsed `{struct_name}`."),
+            ]);
+        }
+        cpp_source_builder_writeln!(
+            self,
+            "static {struct_name} parse (int argc, char *args[]){} {{",
+            if noexcept { " noexcept" } else { "" }
+        );
+
+        self.push_indentation_level();
+
+        if strukt.has_attribute(AttributeType::Main) {
+            cpp_source_builder_writeln!(self, "--argc;");
+            cpp_source_builder_writeln!(self, "++args;\n");
+        }
+
+        let mandatory_fields = MandatoryFields::new(String::new(), strukt);
+        self.write_mandatory_fields_declaration(&mandatory_fields);
+
+        let mut optional_groups = Vec::new();
+        self.collect_optional_groups(
+            &strukt.fields,
+            spec_metadata,
+            &mut vec!["res".to_string()],
+            &mut Vec::new(),
+            &mut optional_groups,
+        );
+        for group in &optional_groups {
+            self.write_mandatory_fields_declaration(&group.mandatory_fields);
+        }
+
+        let mut repeated_groups = Vec::new();
+        self.collect_repeated_groups(
+            &strukt.fields,
+            spec_metadata,
+            &mut vec!["res".to_string()],
+            &mut repeated_groups,
+        );
+        for group in &repeated_groups {
+            cpp_source_builder_writeln!(
+                self,
+                "bool {}fields_given[{}] = {{ false }};\n",
+                group.prefix,
+                group.strukt.fields.len()
+            );
+        }
+
+        // Values from the config file only fill the fields that weren't given as arguments, and
+        // option groups constrain the ones given.
+        if tracks_given_fields(strukt) {
+            cpp_source_builder_writeln!(
+                self,
+                "bool fields_given[{}] = {{ false }};\n",
+                strukt.fields.len()
+            );
+        }
+
+        let allow_abbrev = strukt.has_attribute(AttributeType::AllowAbbrev);
+
+        if !strukt.positional_fields().is_empty() {
+            cpp_source_builder_writeln!(self, "size_t positional_count = 0;");
+        }
+        cpp_source_builder_writeln!(self, "{struct_name} res = {{}};");
+        self.write_default_values(strukt, spec_metadata, "res");
+        self.write_env_values(
+            strukt,
+            spec_metadata,
+            &mandatory_fields,
+            tracks_given_fields(strukt),
+            &mut vec!["res".to_string()],
+        );
+        self.write_global_flags_scan(strukt, spec_metadata);
+        if allow_abbrev {
+            cpp_source_builder_writeln!(self, "std::string expanded_arg;");
+        }
+        cpp_source_builder_writeln!(self, "for (int i = 0; i != argc; ++i, ++args) {{");
+
+        self.push_indentation_level();
+        // The separator is checked before abbreviations are expanded, as it is a prefix of every
+        // long option when it is `--`.
+        if let Some(trailing_field) = strukt.trailing_field() {
+            let separator = strukt.separator();
+            cpp_source_builder_writeln!(self, r#"if (strcmp(args[0], "{separator}") == 0) {{"#);
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(self, "for (++i, ++args; i != argc; ++i, ++args) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                "res.{}.push_back(args[0]);",
+                self.member_name(trailing_field)
+            );
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            cpp_source_builder_writeln!(self, "break;");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+        if allow_abbrev {
+            cpp_source_builder_writeln!(
+                self,
+                "const char *arg = {struct_name}::expand_abbreviation(args[0], expanded_arg);"
+            );
+        } else {
+            cpp_source_builder_writeln!(self, "const char *arg = args[0];");
+        }
+        let mut arms = vec![MatchArm {
+            condition: format!(
+                r#"strcmp("{}", arg) == 0 || strcmp("{}", arg) == 0"#,
+                syntax.short_option("h"),
+                syntax.long_option("help")
+            ),
+            body: self.capture(|this| {
+                cpp_source_builder_writeln!(this, "{struct_name}::help();");
+            }),
+        }];
+        self.collect_parse_arms(strukt, spec_metadata, &mandatory_fields, &mut arms);
+        arms.extend(self.positional_parse_arm(strukt));
+
+        let unknown_option_body = self.capture(|this| {
+            cpp_source_builder_writeln!(this, r#"printf("Unknown option '%s'\n", arg);"#);
+            cpp_source_builder_writeln!(this, "exit({});", this.exit_codes.unknown_flag);
+        });
+        self.write_if_else_chain(&arms, &unknown_option_body);
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
+
+        self.write_config_file_loading(strukt, &mandatory_fields);
+        self.write_option_group_checks(strukt, spec_metadata);
+
+        // The last element of every group is only checked once the arguments run out.
+        for group in repeated_groups
+            .iter()
+            .filter(|group| !group.strukt.mandatory_fields().is_empty())
+        {
+            let has_element = (0..group.strukt.fields.len())
+                .map(|index| format!("{}fields_given[{index}]", group.prefix))
+                .collect::<Vec<_>>()
+                .join(" || ");
+            cpp_source_builder_writeln!(self, "if ({has_element}) {{");
+            self.push_indentation_level();
+            self.write_repeated_group_check(group, syntax.long_prefix);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+
+        let checks_missing_fields = !mandatory_fields.fields.is_empty()
+            || strukt
+                .positional_fields()
+                .first()
+                .is_some_and(|field| field.is_required())
+            || optional_groups
+                .iter()
+                .any(|group| !group.mandatory_fields.fields.is_empty());
+        if checks_missing_fields {
+            self.write_missing_fields_check(strukt, &mandatory_fields, &optional_groups);
+        }
+
+        for field in &strukt.fields {
+            self.write_occurrence_checks(strukt, field);
+        }
+
+        cpp_source_builder_writeln!(self, "return res;");
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+    }
+
+    /// Reports every mandatory field that wasn't given, those of the optional groups that are
+    /// set included, and exits if there is any.
+    fn write_missing_fields_check(
+        &mut self,
+        strukt: &Struct,
+        mandatory_fields: &MandatoryFields,
+        optional_groups: &[OptionalGroup],
+    ) {
+        let syntax = strukt.option_syntax();
+
+        cpp_source_builder_writeln!(self, "bool not_seen_any = false;");
+        self.write_mandatory_fields_check(mandatory_fields, syntax.long_prefix);
+        self.write_positional_fields_check(strukt);
+        for group in optional_groups {
+            if group.mandatory_fields.fields.is_empty() {
+                continue;
+            }
+            let group_is_set = group
+                .paths
+                .iter()
+                .map(|path| format!("{path}.has_value()"))
+                .collect::<Vec<_>>()
+                .join(" && ");
+
+            cpp_source_builder_writeln!(self, "if ({group_is_set}) {{");
+            self.push_indentation_level();
+            self.write_mandatory_fields_check(&group.mandatory_fields, syntax.long_prefix);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+
+        cpp_source_builder_writeln!(self, "if (not_seen_any) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.missing_arg);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Fills the `env`, `append_env` and `env_list` fields, including the flattened ones, with the
+    /// values of their environment variable before the arguments are parsed, so that the arguments
+    /// come last: `env` values are replaced by the option and the lists come before its values,
+    /// of which empty ones are skipped. A mandatory field given values this way counts as seen,
+    /// and a field given a value counts as given for the config file. The `env` variables include
+    /// the ones named after the fields with `env_prefix`.
+    fn write_env_values<'s>(
+        &mut self,
+        strukt: &'s Struct,
+        spec_metadata: &SpecMetadata<'s>,
+        mandatory_fields: &MandatoryFields,
+        tracks_given_fields: bool,
+        parents: &mut Vec<String>,
+    ) {
+        let path_separator = if self.options.target_os == TargetOs::Windows {
+            ";"
+        } else {
+            ":"
+        };
+
+        for (index, field) in strukt.fields.iter().enumerate() {
+            let member_name = self.member_name(field);
+
+            if let Some(variable) = strukt.field_env(field) {
+                let path = format!("{}.{member_name}", parents.join("."));
+                cpp_source_builder_writeln!(
+                    self,
+                    r#"if (char *env_value = getenv("{variable}")) {{"#
+                );
+                self.push_indentation_level();
+                // Errors name the variable in place of an option. Strings are only invalid when
+                // they don't match their regex or aren't an existing directory.
+                if !matches!(field.ty.value_type(), FieldType::String)
+                    || self.regex_pattern(field).is_some()
+                    || self.validates_directory(field)
+                {
+                    cpp_source_builder_writeln!(self, r#"const char* arg = "{variable}";"#);
+                }
+                match field.ty.value_type() {
+                    FieldType::Bool => {
+                        cpp_source_builder_writeln!(self, "bool arg_res = false;");
+                        cpp_source_builder_writeln!(
+                            self,
+                            "if (!parse_bool_value(env_value, &arg_res)) {{"
+                        );
+                        self.push_indentation_level();
+                        cpp_source_builder_writeln!(
+                            self,
+                            r#"printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", env_value, arg);"#
+                        );
+                        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+                        self.pop_indentation_level();
+                        cpp_source_builder_writeln!(self, "}}");
+                    }
+                    value_type => self.write_convert_value(field, value_type, "env_value"),
+                }
+                cpp_source_builder_writeln!(self, "{path} = arg_res;");
+                if parents.len() == 1 {
+                    if let Some(index) = mandatory_fields.index_of(field) {
+                        cpp_source_builder_writeln!(
+                            self,
+                            "{}mandatory_fields_seen[{index}] = true;",
+                            mandatory_fields.prefix
+                        );
+                    }
+                    if tracks_given_fields {
+                        cpp_source_builder_writeln!(self, "fields_given[{index}] = true;");
+                    }
+                }
+                self.pop_indentation_level();
+                cpp_source_builder_writeln!(self, "}}");
+            }
+
+            // `append_env` values are separated like paths, and `env_list` ones by newlines.
+            let env_lists = [
+                field
+                    .append_env()
+                    .map(|variable| (variable, path_separator)),
+                field.env_list().map(|variable| (variable, "\\n")),
+            ];
+            for (variable, separator) in env_lists.into_iter().flatten() {
+                let path = format!("{}.{member_name}", parents.join("."));
+                cpp_source_builder_writeln!(
+                    self,
+                    r#"if (const char *env_value = getenv("{variable}")) {{"#
+                );
+                self.push_indentation_level();
+                cpp_source_builder_writeln!(self, "std::string env_values = env_value;");
+                cpp_source_builder_writeln!(self, "size_t start = 0;");
+                cpp_source_builder_writeln!(
+                    self,
+                    "for (size_t end; (end = env_values.find('{separator}', start)) != std::string::npos; start = end + 1) {{"
+                );
+                self.push_indentation_level();
+                cpp_source_builder_writeln!(self, "if (end != start) {{");
+                self.push_indentation_level();
+                cpp_source_builder_writeln!(
+                    self,
+                    "{path}.push_back(env_values.substr(start, end - start));"
+                );
+                self.pop_indentation_level();
+                cpp_source_builder_writeln!(self, "}}");
+                self.pop_indentation_level();
+                cpp_source_builder_writeln!(self, "}}");
+                cpp_source_builder_writeln!(self, "if (start != env_values.size()) {{");
+                self.push_indentation_level();
+                cpp_source_builder_writeln!(self, "{path}.push_back(env_values.substr(start));");
+                self.pop_indentation_level();
+                cpp_source_builder_writeln!(self, "}}");
+                if let Some(index) = mandatory_fields
+                    .index_of(field)
+                    .filter(|_| parents.len() == 1)
+                {
+                    cpp_source_builder_writeln!(
+                        self,
+                        "{}mandatory_fields_seen[{index}] = !{path}.empty();",
+                        mandatory_fields.prefix
+                    );
+                }
+                self.pop_indentation_level();
+                cpp_source_builder_writeln!(self, "}}");
+            }
+
+            // Flattened `Vec` and `Optional` structs only exist once their options are given.
+            if let FieldType::Struct(name) = &field.ty {
+                if field.has_attribute(AttributeType::Flatten) {
+                    parents.push(member_name);
+                    self.write_env_values(
+                        spec_metadata.identifier_to_struct[name.as_str()],
+                        spec_metadata,
+                        mandatory_fields,
+                        tracks_given_fields,
+                        parents,
+                    );
+                    parents.pop();
+                }
+            }
+        }
+    }
+
+    /// Reads the config file once the arguments are parsed, so that the fields given as arguments
+    /// keep their value. Unknown keys are warned about, and values of the wrong type are errors.
+    fn write_config_file_loading(&mut self, strukt: &Struct, mandatory_fields: &MandatoryFields) {
+        let Some(config_file_field) = strukt.config_file_field() else {
+            return;
+        };
+        let index_of = |field: &Field| {
+            strukt
+                .fields
+                .iter()
+                .position(|other| other.name == field.name)
+                .unwrap()
+        };
+
+        let path = match &config_file_field.ty {
+            FieldType::Optional(_) => {
+                format!("res.{}.value()", self.member_name(config_file_field))
+            }
+            _ => format!("res.{}", self.member_name(config_file_field)),
+        };
+        cpp_source_builder_writeln!(
+            self,
+            "if (fields_given[{}]) {{",
+            index_of(config_file_field)
+        );
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "std::vector<config_entry> config_entries;");
+        cpp_source_builder_writeln!(
+            self,
+            "if (!read_config_file({path}.c_str(), config_entries)) {{"
+        );
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
+
+        cpp_source_builder_writeln!(
+            self,
+            "for (size_t entry_index = 0; entry_index != config_entries.size(); ++entry_index) {{"
+        );
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "config_entry& entry = config_entries[entry_index];");
+        cpp_source_builder_writeln!(self, "const char* arg = entry.key.c_str();");
+
+        let arms = strukt
+            .config_fields()
+            .into_iter()
+            .map(|(key, field)| MatchArm {
+                condition: format!(r#"entry.key == "{key}""#),
+                body: self.capture(|this| {
+                    let index = index_of(field);
+                    cpp_source_builder_writeln!(this, "if (!fields_given[{index}]) {{");
+                    this.push_indentation_level();
+                    this.write_config_value(field);
+                    if let Some(index) = mandatory_fields.index_of(field) {
+                        let seen = match &field.ty {
+                            FieldType::Vec(_) => {
+                                format!("!res.{}.empty()", this.member_name(field))
+                            }
+                            _ => String::from("true"),
+                        };
+                        cpp_source_builder_writeln!(
+                            this,
+                            "{}mandatory_fields_seen[{index}] = {seen};",
+                            mandatory_fields.prefix
+                        );
+                    }
+                    this.pop_indentation_level();
+                    cpp_source_builder_writeln!(this, "}}");
+                }),
+            })
+            .collect::<Vec<_>>();
+        let unknown_key_body = self.capture(|this| {
+            cpp_source_builder_writeln!(
+                this,
+                r#"fprintf(stderr, "Warning: unknown key '%s' in the config file\n", arg);"#
+            );
+        });
+        self.write_if_else_chain(&arms, &unknown_key_body);
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
+    }
+
+    /// Converts the value of the config `entry` to the type of the field, after checking that it
+    /// is of the right kind.
+    fn write_config_value(&mut self, field: &Field) {
+        let value_type = field.ty.value_type();
+        let is_array = matches!(field.ty, FieldType::Vec(_));
+        let (kind, expected) = match value_type {
+            FieldType::String => ('s', "string"),
+            FieldType::Bool => ('b', "bool"),
+            _ => ('n', "number"),
+        };
+        let expected = if is_array {
+            format!("an array of {expected}s")
+        } else if kind == 's' {
+            String::from("a string")
+        } else {
+            format!("a {expected}")
+        };
+
+        cpp_source_builder_writeln!(
+            self,
+            r#"if (!check_config_entry(entry, {is_array}, '{kind}', "{expected}")) {{"#
+        );
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+
+        cpp_source_builder_writeln!(
+            self,
+            "for (size_t value_index = 0; value_index != entry.values.size(); ++value_index) {{"
+        );
+        self.push_indentation_level();
+        if kind == 'b' {
+            cpp_source_builder_writeln!(
+                self,
+                r#"bool arg_res = entry.values[value_index] == "true";"#
+            );
+        } else {
+            cpp_source_builder_writeln!(
+                self,
+                "char* config_value = &entry.values[value_index][0];"
+            );
+            self.write_convert_value(field, value_type, "config_value");
+        }
+        let member_name = self.member_name(field);
+        if is_array {
+            cpp_source_builder_writeln!(self, "res.{member_name}.push_back(arg_res);");
+        } else {
+            cpp_source_builder_writeln!(self, "res.{member_name} = arg_res;");
+        }
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+    }
+
+    /// Vec fields constrained with `min_occurs`/`max_occurs` have their number of values checked
+    /// once all the arguments are parsed.
+    fn write_occurrence_checks(&mut self, strukt: &Struct, field: &Field) {
+        let option_name = field_option_name(strukt, field);
+        let member_name = self.member_name(field);
+
+        if let Some(min) = field.min_occurs().filter(|&min| min > 0) {
+            cpp_source_builder_writeln!(self, "if (res.{member_name}.size() < {min}) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("Expected at least {min} occurrences of {option_name} but %zu were provided\n", res.{member_name}.size());"#
+            );
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+
+        if let Some(max) = field.max_occurs() {
+            cpp_source_builder_writeln!(self, "if (res.{member_name}.size() > {max}) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"printf("Expected at most {max} occurrences of {option_name} but %zu were provided\n", res.{member_name}.size());"#
+            );
+            cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.parse_error);
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+    }
+
+    /// Windows entry points (`wmain`) receive UTF-16 arguments, so they are converted to UTF-8
+    /// before delegating to the regular `parse`.
+    pub fn write_struct_parse_wide_method(&mut self, strukt: &Struct) {
+        cpp_source_builder_writeln!(self);
+
+        let struct_name = &strukt.name;
+
+        self.push_indentation_level();
+        if self.options.doxygen {
+            self.write_doxygen_comment(&[
+                String::from("Converts the UTF-16 arguments of `wmain` to UTF-8 and parses them."),
+                String::new(),
+                String::from("@param argc The number of arguments."),
+                String::from("@param wargs The arguments, starting with the name of the program."),
+                format!("@return The parsed `{struct_name}`."),
+            ]);
+        }
+        cpp_source_builder_writeln!(
+            self,
+            "static {struct_name} parse_wide(int argc, wchar_t *wargs[]) {{"
+        );
+        self.push_indentation_level();
+
+        cpp_source_builder_writeln!(self, "std::vector<std::string> utf8_args;");
+        cpp_source_builder_writeln!(self, "utf8_args.reserve(argc);");
+        cpp_source_builder_writeln!(self, "for (int i = 0; i != argc; ++i) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(
+            self,
+            "int size = WideCharToMultiByte(CP_UTF8, 0, wargs[i], -1, nullptr, 0, nullptr, nullptr);"
+        );
+        cpp_source_builder_writeln!(self, "std::string arg(size > 0 ? size - 1 : 0, '\\0');");
+        cpp_source_builder_writeln!(self, "if (size > 1) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(
+            self,
+            "WideCharToMultiByte(CP_UTF8, 0, wargs[i], -1, &arg[0], size, nullptr, nullptr);"
+        );
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, "utf8_args.push_back(arg);");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}\n");
+
+        cpp_source_builder_writeln!(self, "std::vector<char*> args;");
+        cpp_source_builder_writeln!(self, "args.reserve(argc + 1);");
+        cpp_source_builder_writeln!(self, "for (size_t i = 0; i != utf8_args.size(); ++i) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "args.push_back(&utf8_args[i][0]);");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, "args.push_back(nullptr);\n");
+
+        cpp_source_builder_writeln!(self, "return {struct_name}::parse(argc, args.data());");
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+    }
+
+    pub fn write_struct_help_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        cpp_source_builder_writeln!(self);
+
+        self.push_indentation_level();
+
+        if self.options.doxygen {
+            self.write_doxygen_comment(&[String::from(
+                "Prints the usage and the options to stdout, then exits successfully.",
+            )]);
+        }
+        cpp_source_builder_writeln!(self, "static void help() {{");
+        self.push_indentation_level();
+        let lines = help_lines(strukt, spec_metadata);
+        cpp_source_builder_writeln!(self, r#"printf("{}\n""#, lines[0]);
+        self.push_indentation_level();
+        for line in &lines[1..] {
+            cpp_source_builder_writeln!(self, r#""{line}\n""#);
+        }
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, ");");
+        // The text is an argument rather than the format, so it needs no escaping.
+        if let Some(after_help) = strukt.after_help() {
+            cpp_source_builder_writeln!(self, r#"printf("%s", "{after_help}");"#);
+        }
+
+        cpp_source_builder_writeln!(self, "exit(0);");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+    }
+
+    /// Declares the function `from_str` fields of the struct are parsed with, which is left to
+    /// the user to define.
+    pub fn write_from_string_declaration(&mut self, strukt: &Struct) {
+        cpp_source_builder_writeln!(self);
+
+        self.push_indentation_level();
+        if self.options.doxygen {
+            self.write_doxygen_comment(&[
+                format!("Parses a `{}` out of a single value.", strukt.name),
+                String::new(),
+                String::from("@param value The value given to the option."),
+                format!("@return The parsed `{}`.", strukt.name),
+            ]);
+        }
+        cpp_source_builder_writeln!(
+            self,
+            "static {} from_string(const char* value);",
+            strukt.name
+        );
+        self.pop_indentation_level();
+    }
+
+    pub fn write_is_option_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        cpp_source_builder_writeln!(self);
+
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "static bool is_option(char* arg) {{");
+
+        self.push_indentation_level();
+
+        let (case_insensitive_options, options): (Vec<_>, Vec<_>) =
+            struct_options(strukt, spec_metadata)
+                .into_iter()
+                .partition(|(_, case_insensitive)| *case_insensitive);
+
+        let has_options = !options.is_empty() || !case_insensitive_options.is_empty();
+        let groups = [
+            (
+                "valid_options",
+                options,
+                "strcmp(arg, valid_options[i]) == 0",
+            ),
+            (
+                "case_insensitive_options",
+                case_insensitive_options,
+                "equals_ignore_case(arg, case_insensitive_options[i])",
+            ),
+        ];
+
+        for (array_name, options, condition) in groups {
+            // Structs without any options keep the (empty) case sensitive group.
+            if options.is_empty() && (has_options || array_name != "valid_options") {
+                continue;
+            }
+
+            cpp_source_builder_writeln!(self, "static const char* {array_name}[] = {{");
+            self.push_indentation_level();
+
+            let num_options = options.len();
+            for (option, _) in options {
+                cpp_source_builder_writeln!(self, r#""{option}","#);
+            }
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}};");
+
+            cpp_source_builder_writeln!(self);
+
+            cpp_source_builder_writeln!(self, "for (size_t i = 0; i != {num_options}; ++i) {{");
+            self.push_indentation_level();
+
+            cpp_source_builder_writeln!(self, "if ({condition}) {{");
+            self.push_indentation_level();
+
+            cpp_source_builder_writeln!(self, "return true;");
+
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+
+            cpp_source_builder_writeln!(self);
+        }
+
+        cpp_source_builder_writeln!(self, "return false;");
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+    }
+
+    /// Expands a unique prefix of a long option (e.g. `--verb` for `--verbose`) to the full option.
+    /// Exact matches always win, ambiguous prefixes are an error and arguments that don't prefix any
+    /// long option are returned as is, so that they end up in the unknown option path.
+    pub fn write_expand_abbreviation_method(
+        &mut self,
+        strukt: &Struct,
+        spec_metadata: &SpecMetadata,
+    ) {
+        cpp_source_builder_writeln!(self);
+
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(
+            self,
+            "static const char* expand_abbreviation(const char* arg, std::string& expanded_arg) {{"
+        );
+
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "static const char* long_options[] = {{");
+        let syntax = strukt.option_syntax();
+
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, r#""{}","#, syntax.long_option("help"));
+        let long_options = struct_long_options(strukt, spec_metadata);
+        let num_long_options = long_options.len() + 1;
+        for option in long_options {
+            cpp_source_builder_writeln!(self, r#""{option}","#);
+        }
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}};");
+        cpp_source_builder_writeln!(self);
+
+        cpp_source_builder_writeln!(
+            self,
+            r#"if (strncmp(arg, "{}", {}) != 0) {{"#,
+            syntax.long_prefix,
+            syntax.long_prefix.len()
+        );
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "return arg;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self);
+
+        cpp_source_builder_writeln!(
+            self,
+            "const char* value = strchr(arg, '{}');",
+            syntax.value_separator
+        );
+        cpp_source_builder_writeln!(
+            self,
+            "size_t arg_len = value != nullptr ? (size_t)(value - arg) : strlen(arg);"
+        );
+        cpp_source_builder_writeln!(self, "const char* match = nullptr;");
+        cpp_source_builder_writeln!(self, "size_t num_matches = 0;");
+        cpp_source_builder_writeln!(self, "for (size_t i = 0; i != {num_long_options}; ++i) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "if (strncmp(arg, long_options[i], arg_len) == 0) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "if (long_options[i][arg_len] == '\\0') {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "return arg;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, "match = long_options[i];");
+        cpp_source_builder_writeln!(self, "++num_matches;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self);
+
+        cpp_source_builder_writeln!(self, "if (num_matches == 0) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "return arg;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self);
+
+        cpp_source_builder_writeln!(self, "if (num_matches > 1) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(
+            self,
+            r#"printf("Ambiguous option '%.*s', could be any of:", (int)arg_len, arg);"#
+        );
+        cpp_source_builder_writeln!(self, "for (size_t i = 0; i != {num_long_options}; ++i) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "if (strncmp(arg, long_options[i], arg_len) == 0) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, r#"printf(" %s", long_options[i]);"#);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, r#"printf("\n");"#);
+        cpp_source_builder_writeln!(self, "exit({});", self.exit_codes.unknown_flag);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self);
+
+        cpp_source_builder_writeln!(self, "expanded_arg = match;");
+        cpp_source_builder_writeln!(self, "if (value != nullptr) {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "expanded_arg += value;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        cpp_source_builder_writeln!(self, "return expanded_arg.c_str();");
+
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+        self.pop_indentation_level();
+    }
+
+    pub fn write_debug_print_method(&mut self, strukt: &Struct) {
+        fn field_to_print_statement(name: &str, field_type: &FieldType, member: &str) -> String {
+            match field_type {
+                FieldType::String => {
+                    format!(r#"printf("\t{name}: %s\n", this->{member}.c_str());"#)
+                }
+                FieldType::I16 => format!(r#"printf("\t{name}: %d\n", this->{member});"#),
+                FieldType::U16 => format!(r#"printf("\t{name}: %d\n", this->{member});"#),
+                FieldType::I32 => format!(r#"printf("\t{name}: %d\n", this->{member});"#),
+                FieldType::U32 => format!(r#"printf("\t{name}: %u\n", this->{member});"#),
+                FieldType::I64 => {
+                    format!(r#"printf("\t{name}: %lld\n", (long long)this->{member});"#)
+                }
+                FieldType::U64 => {
+                    format!(r#"printf("\t{name}: %llu\n", (unsigned long long)this->{member});"#)
+                }
+                FieldType::F32 => format!(r#"printf("\t{name}: %f\n", this->{member});"#),
+                FieldType::F64 => format!(r#"printf("\t{name}: %f\n", this->{member});"#),
+                FieldType::Bool => {
+                    format!(r#"printf("\t{name}: %s\n", this->{member} ? "true" : "false");"#)
+                }
+                FieldType::Vec(inner) => match inner.as_ref() {
+                    FieldType::String => {
+                        format!(r#"printf("\t%s,\n", this->{member}[i].c_str());"#)
+                    }
+                    FieldType::I16 => format!(r#"printf("\t%d,\n", this->{member}[i]);"#),
+                    FieldType::U16 => format!(r#"printf("\t%d,\n", this->{member}[i]);"#),
+                    FieldType::I32 => format!(r#"printf("\t%d,\n", this->{member}[i]);"#),
+                    FieldType::U32 => format!(r#"printf("\t%u,\n", this->{member}[i]);"#),
+                    FieldType::I64 => {
+                        format!(r#"printf("\t%lld,\n", (long long)this->{member}[i]);"#)
+                    }
+                    FieldType::U64 => {
+                        format!(r#"printf("\t%llu,\n", (unsigned long long)this->{member}[i]);"#)
+                    }
+                    FieldType::F32 => format!(r#"printf("\t%f,\n", this->{member}[i]);"#),
+                    FieldType::F64 => format!(r#"printf("\t%f,\n", this->{member}[i]);"#),
+                    FieldType::Bool => {
+                        format!(r#"printf("\t%s,\n", this->{member}[i] ? "true" : "false");"#)
+                    }
+                    FieldType::Vec(_) => unreachable!(),
+                    FieldType::Optional(_) => unreachable!(),
+                    FieldType::Error => unreachable!(),
+                    FieldType::Struct(_) => format!("this->{member}[i].print_debug();"),
+                },
+                FieldType::Struct(_) => format!("this->{member}.print_debug();"),
+                // Optional fields print their value only when present, see below.
+                FieldType::Optional(_) => unreachable!(),
+                FieldType::Error => unreachable!(),
+            }
+        }
+        cpp_source_builder_writeln!(self);
+        self.push_indentation_level();
+
+        cpp_source_builder_writeln!(self, "void print_debug() {{");
+        self.push_indentation_level();
+
+        cpp_source_builder_writeln!(self, r#"printf("{} {{\n");"#, strukt.name);
+        for field in &strukt.fields {
+            let member_name = self.member_name(field);
+            match &field.ty {
+                _ if field.has_attribute(AttributeType::Secret) => {
+                    cpp_source_builder_writeln!(self, r#"printf("\t{}: ********\n");"#, field.name);
+                }
+                FieldType::Vec(_) => {
+                    let print_statement =
+                        field_to_print_statement(&field.name, &field.ty, &member_name);
+                    cpp_source_builder_writeln!(self, r#"printf("\t{}: [\n");"#, field.name);
+                    cpp_source_builder_writeln!(
+                        self,
+                        "for (size_t i = 0; i != this->{member_name}.size(); ++i) {{"
+                    );
+                    self.push_indentation_level();
+                    cpp_source_builder_writeln!(self, "{print_statement}");
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}}");
+                    cpp_source_builder_writeln!(self, r#"printf("\t]\n");"#);
+                }
+                FieldType::Optional(inner) => {
+                    let print_statement = field_to_print_statement(
+                        &field.name,
+                        inner,
+                        &format!("{member_name}.value()"),
+                    );
+                    cpp_source_builder_writeln!(self, "if (this->{member_name}.has_value()) {{");
+                    self.push_indentation_level();
+                    cpp_source_builder_writeln!(self, "{print_statement}");
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}} else {{");
+                    self.push_indentation_level();
+                    cpp_source_builder_writeln!(self, r#"printf("\t{}: none\n");"#, field.name);
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}}");
+                }
+                _ => {
+                    let print_statement =
+                        field_to_print_statement(&field.name, &field.ty, &member_name);
+                    cpp_source_builder_writeln!(self, "{print_statement}")
+                }
+            }
+        }
+
+        cpp_source_builder_writeln!(self, r#"printf("}}\n");"#);
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+
+        self.pop_indentation_level();
+    }
+
+    /// Appends the options giving `field` its value, `member` being the expression of the value.
+    fn write_command_line_value(
+        &mut self,
+        field: &Field,
+        field_type: &FieldType,
+        option: &str,
+        value_separator: &str,
+        member: &str,
+    ) {
+        let value = match field_type {
+            FieldType::Bool => {
+                // Explicit bools would take the next argument as their value without a separator.
+                let given = if field.is_explicit_bool() {
+                    format!("{option}{value_separator}true")
+                } else {
+                    option.to_string()
+                };
+                cpp_source_builder_writeln!(
+                    self,
+                    r#"append_command_line(line, {member} ? "{given}" : "{option}{value_separator}false");"#
+                );
+                return;
+            }
+            FieldType::Struct(_) => format!("{member}.to_command_line()"),
+            _ if field.has_attribute(AttributeType::Secret) => String::from(r#""********""#),
+            FieldType::String => format!("quote_command_line_argument({member})"),
+            FieldType::F32 | FieldType::F64 => format!("command_line_float({member})"),
+            FieldType::Vec(_) | FieldType::Optional(_) => unreachable!(),
+            _ => format!("std::to_string({member})"),
+        };
+
+        cpp_source_builder_writeln!(self, r#"append_command_line(line, "{option}");"#);
+        cpp_source_builder_writeln!(self, "append_command_line(line, {value});");
+    }
+
+    /// Appends the options of `fields` to `line`, spelled with the syntax of the struct parsing
+    /// them. `owner` is the expression the members are accessed through, e.g. `this->` or
+    /// `this->net.value().` for the fields of a flattened struct.
+    fn write_command_line_fields(
+        &mut self,
+        strukt: &Struct,
+        fields: &[Field],
+        spec_metadata: &SpecMetadata,
+        owner: &str,
+    ) {
+        let syntax = strukt.option_syntax();
+
+        for field in fields {
+            let member = format!("{owner}{}", self.member_name(field));
+
+            if field.has_attribute(AttributeType::Flatten) {
+                let FieldType::Struct(name) = field.ty.value_type() else {
+                    unreachable!()
+                };
+                let flatten_type = spec_metadata.identifier_to_struct[name.as_str()];
+                match &field.ty {
+                    FieldType::Optional(_) => {
+                        cpp_source_builder_writeln!(self, "if ({member}.has_value()) {{");
+                        self.push_indentation_level();
+                        self.write_command_line_fields(
+                            strukt,
+                            &flatten_type.fields,
+                            spec_metadata,
+                            &format!("{member}.value()."),
+                        );
+                        self.pop_indentation_level();
+                        cpp_source_builder_writeln!(self, "}}");
+                    }
+                    FieldType::Vec(_) => {
+                        let index = format!("{}_index", field.name);
+                        cpp_source_builder_writeln!(
+                            self,
+                            "for (size_t {index} = 0; {index} != {member}.size(); ++{index}) {{"
+                        );
+                        self.push_indentation_level();
+                        self.write_command_line_fields(
+                            strukt,
+                            &flatten_type.fields,
+                            spec_metadata,
+                            &format!("{member}[{index}]."),
+                        );
+                        self.pop_indentation_level();
+                        cpp_source_builder_writeln!(self, "}}");
+                    }
+                    _ => self.write_command_line_fields(
+                        strukt,
+                        &flatten_type.fields,
+                        spec_metadata,
+                        &format!("{member}."),
+                    ),
+                }
+                continue;
+            }
+
+            // Positional and trailing fields come last, and fields without an option can't be
+            // given at all.
+            let option = match (field.long_value(), field.short_value()) {
+                _ if field.has_attribute(AttributeType::Trailing) => continue,
+                _ if field.positional_index().is_some() => continue,
+                (Some(long_value), _) => syntax.long_option(&long_value),
+                (None, Some(short_value)) => syntax.short_option(&short_value),
+                (None, None) => continue,
+            };
+
+            match &field.ty {
+                FieldType::Optional(inner) => {
+                    cpp_source_builder_writeln!(self, "if ({member}.has_value()) {{");
+                    self.push_indentation_level();
+                    self.write_command_line_value(
+                        field,
+                        inner,
+                        &option,
+                        syntax.value_separator,
+                        &format!("{member}.value()"),
+                    );
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}}");
+                }
+                FieldType::Vec(inner) => {
+                    cpp_source_builder_writeln!(
+                        self,
+                        "for (size_t i = 0; i != {member}.size(); ++i) {{"
+                    );
+                    self.push_indentation_level();
+                    self.write_command_line_value(
+                        field,
+                        inner,
+                        &option,
+                        syntax.value_separator,
+                        &format!("{member}[i]"),
+                    );
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}}");
+                }
+                // Fields with a `default_value_t` are only written when they have another value.
+                field_type if field.default_value().is_some() => {
+                    let value = default_value_literal(field_type, field.default_value().unwrap());
+                    cpp_source_builder_writeln!(self, "if ({member} != {value}) {{");
+                    self.push_indentation_level();
+                    self.write_command_line_value(
+                        field,
+                        field_type,
+                        &option,
+                        syntax.value_separator,
+                        &member,
+                    );
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}}");
+                }
+                // Fields with a `default` are left zero when not given, so only other values are
+                // written. The mandatory ones are always written, even when zero or false.
+                field_type if field.has_attribute(AttributeType::Default) => {
+                    let condition = match field_type {
+                        FieldType::Bool => member.clone(),
+                        FieldType::String => format!("!{member}.empty()"),
+                        _ => format!("{member} != 0"),
+                    };
+                    cpp_source_builder_writeln!(self, "if ({condition}) {{");
+                    self.push_indentation_level();
+                    self.write_command_line_value(
+                        field,
+                        field_type,
+                        &option,
+                        syntax.value_separator,
+                        &member,
+                    );
+                    self.pop_indentation_level();
+                    cpp_source_builder_writeln!(self, "}}");
+                }
+                field_type => self.write_command_line_value(
+                    field,
+                    field_type,
+                    &option,
+                    syntax.value_separator,
+                    &member,
+                ),
+            }
+        }
+    }
+
+    /// Writes `to_command_line()`, rebuilding the options the struct would be parsed from, e.g.
+    /// `--port 8080 --host localhost --verbose`. Secret values are masked like in `print_debug()`.
+    pub fn write_to_command_line_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        cpp_source_builder_writeln!(self);
+        self.push_indentation_level();
+
+        if self.options.doxygen {
+            self.write_doxygen_comment(&[String::from(
+                "Rebuilds the command line the struct would be parsed from.",
+            )]);
+        }
+        cpp_source_builder_writeln!(self, "std::string to_command_line() const {{");
+        self.push_indentation_level();
+        cpp_source_builder_writeln!(self, "std::string line;");
+        self.write_command_line_fields(strukt, &strukt.fields, spec_metadata, "this->");
+
+        // Positional fields with a default are always written, since the ones after them are
+        // given by their position.
+        for field in strukt.positional_fields() {
+            let member = format!("this->{}", self.member_name(field));
+            let (member, is_optional) = match &field.ty {
+                FieldType::Optional(_) => (format!("{member}.value()"), true),
+                _ => (member, false),
+            };
+            let value = match field.ty.value_type() {
+                _ if field.has_attribute(AttributeType::Secret) => String::from(r#""********""#),
+                FieldType::String => format!("quote_command_line_argument({member})"),
+                FieldType::F32 | FieldType::F64 => format!("command_line_float({member})"),
+                _ => format!("std::to_string({member})"),
+            };
+
+            if is_optional {
+                cpp_source_builder_writeln!(
+                    self,
+                    "if (this->{}.has_value()) {{",
+                    self.member_name(field)
+                );
+                self.push_indentation_level();
+            }
+            cpp_source_builder_writeln!(self, "append_command_line(line, {value});");
+            if is_optional {
+                self.pop_indentation_level();
+                cpp_source_builder_writeln!(self, "}}");
+            }
+        }
+
+        if let Some(trailing_field) = strukt.trailing_field() {
+            let member = self.member_name(trailing_field);
+            cpp_source_builder_writeln!(self, "if (!this->{member}.empty()) {{");
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                r#"append_command_line(line, "{}");"#,
+                strukt.separator()
+            );
+            cpp_source_builder_writeln!(
+                self,
+                "for (size_t i = 0; i != this->{member}.size(); ++i) {{"
+            );
+            self.push_indentation_level();
+            cpp_source_builder_writeln!(
+                self,
+                "append_command_line(line, quote_command_line_argument(this->{member}[i]));"
+            );
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+            self.pop_indentation_level();
+            cpp_source_builder_writeln!(self, "}}");
+        }
+
+        cpp_source_builder_writeln!(self, "return line;");
+        self.pop_indentation_level();
+        cpp_source_builder_writeln!(self, "}}");
+
+        self.pop_indentation_level();
+    }
+}
+
+/// Whether the parser of the struct tracks the fields given as arguments in a `fields_given` array,
+/// for the config file or the option groups.
+fn tracks_given_fields(strukt: &Struct) -> bool {
+    strukt.config_file_field().is_some()
+        || strukt
+            .fields
+            .iter()
+            .any(|field| field.exclusive_group().is_some() || field.requires_group().is_some())
+}
+
+/// Whether `container` holds a `target` struct, directly or through the structs of its fields.
+/// Flattened fields are written by the outermost struct, so it needs access to all of them.
+fn contains_struct<'s>(
+    container: &'s Struct,
+    target: &str,
+    spec_metadata: &SpecMetadata<'s>,
+    visited: &mut HashSet<&'s str>,
+) -> bool {
+    if !visited.insert(container.name.as_str()) {
+        return false;
+    }
+
+    container
+        .fields
+        .iter()
+        .any(|field| match field.ty.value_type() {
+            FieldType::Struct(name) if name == target => true,
+            FieldType::Struct(name) => spec_metadata
+                .identifier_to_struct
+                .get(name.as_str())
+                .is_some_and(|inner| contains_struct(inner, target, spec_metadata, visited)),
+            _ => false,
+        })
+}
+
+/// Splits the methods of `struct_name`, written inside of it, into their declarations, left in
+/// the struct along with their documentation, and their definitions out of it.
+fn split_methods(methods: &str, struct_name: &str, indent: Indent) -> (String, String) {
+    let mut one_level = String::new();
+    left_pad(indent, 1, &mut one_level).unwrap();
+
+    let mut declarations = String::new();
+    let mut definitions = String::new();
+    let mut in_body = false;
+    for line in methods.lines() {
+        if in_body {
+            let line = line.strip_prefix(&one_level).unwrap_or(line);
+            writeln!(definitions, "{line}").unwrap();
+            // The closing brace of the method is the only one that is not nested in its body.
+            in_body = line != "}";
+            continue;
+        }
+
+        let signature = line.trim();
+        if !signature.ends_with(" {") || signature.starts_with('*') || signature.starts_with('/') {
+            writeln!(declarations, "{line}").unwrap();
+            continue;
+        }
+
+        let signature = &signature[..signature.len() - " {".len()];
+        writeln!(declarations, "{one_level}{signature};").unwrap();
+
+        // `static` is only spelled in the declaration, and the name is qualified by the struct.
+        let signature = signature.strip_prefix("static ").unwrap_or(signature);
+        let name_end = signature[..signature.find('(').unwrap()].trim_end().len();
+        let name_start = signature[..name_end]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .map_or(0, |index| index + 1);
+        if !definitions.is_empty() {
+            writeln!(definitions).unwrap();
+        }
+        writeln!(
+            definitions,
+            "{}{struct_name}::{} {{",
+            &signature[..name_start],
+            &signature[name_start..]
+        )
+        .unwrap();
+        in_body = true;
+    }
+
+    (declarations, definitions)
+}
+
+/// The output is a pure function of the spec and the options: structs and fields are written in
+/// declaration order and hashed collections are only used for lookups, never iterated into the
+/// output.
+pub(crate) fn generate_cli(
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+) -> String {
+    generate_files(spec, spec_metadata, options, provenance, None).0
+}
+
+/// Generates a header declaring the structs and their methods, and a source file defining the
+/// methods, which includes the header by `header_name`. Large specs compile once instead of in
+/// every file including the header.
+pub(crate) fn generate_header_and_implementation(
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+    header_name: &str,
+) -> (String, String) {
+    let (header, implementation) =
+        generate_files(spec, spec_metadata, options, provenance, Some(header_name));
+    (header, implementation.unwrap())
+}
+
+/// Generates the header, along with the source file defining its methods when `header_name` is
+/// given.
+fn generate_files(
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+    header_name: Option<&str>,
+) -> (String, Option<String>) {
+    let mut source_builder = CppSourceBuilder::new(*options);
+    let mut implementation = header_name.map(|header_name| {
+        let mut implementation = CppSourceBuilder::new(*options);
+        if let Some(provenance) = provenance {
+            implementation.write_banner(provenance);
+        }
+        cpp_source_builder_writeln!(implementation, "#include \"{header_name}\"");
+        implementation
+    });
+
+    if let Some(provenance) = provenance {
+        source_builder.write_banner(provenance);
+    }
+
+    let mut used_types = HashSet::new();
+    let mut uses_regex = false;
+    let mut uses_filesystem = false;
+    let mut uses_case_insensitive = spec
+        .structs
+        .iter()
+        .any(|strukt| strukt.has_attribute(AttributeType::CaseInsensitive));
+    for field in spec.structs.iter().flat_map(|strukt| &strukt.fields) {
+        collect_field_types(&field.ty, &mut used_types);
+        uses_regex |= source_builder.regex_pattern(field).is_some();
+        uses_filesystem |= source_builder.validates_directory(field);
+        uses_case_insensitive |= field.has_attribute(AttributeType::CaseInsensitive);
+    }
+
+    source_builder.write_header_guard_start();
+    let uses_config_file = spec
+        .structs
+        .iter()
+        .any(|strukt| strukt.config_file_field().is_some());
+    source_builder.write_include_headers(
+        &used_types,
+        uses_regex,
+        uses_filesystem,
+        uses_config_file,
+    );
+    if !options.cpp_std.has_optional()
+        && used_types
+            .iter()
+            .any(|ty| matches!(ty, FieldType::Optional(_)))
+    {
+        source_builder.write_optional_class();
+    }
+    let uses_float = used_types.contains(&FieldType::F32) || used_types.contains(&FieldType::F64);
+    source_builder.write_helper_functions(uses_case_insensitive, uses_config_file, uses_float);
+
+    let from_string_types = spec
+        .structs
+        .iter()
+        .flat_map(|strukt| &strukt.fields)
+        .filter(|field| field.is_from_str())
+        .filter_map(|field| match field.ty.value_type() {
+            FieldType::Struct(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    for strukt in &spec.structs {
+        source_builder.exit_codes = ExitCodes::of(strukt);
+        source_builder.write_struct_start(strukt);
+
+        if options.accessors {
+            let friend_names = spec
+                .structs
+                .iter()
+                .filter(|other| {
+                    contains_struct(other, &strukt.name, spec_metadata, &mut HashSet::new())
+                })
+                .map(|other| other.name.as_str())
+                .collect::<Vec<_>>();
+
+            source_builder.write_struct_friends(&friend_names);
+            for field in &strukt.fields {
+                source_builder.write_struct_accessor(field);
+            }
+        } else {
+            for field in &strukt.fields {
+                source_builder.write_struct_field(field);
+            }
+        }
+        source_builder.write_default_constants(strukt, implementation.as_mut());
+
+        let methods_start = source_builder.buffer.len();
+        source_builder.write_debug_print_method(strukt);
+
+        if from_string_types.contains(strukt.name.as_str()) {
+            source_builder.write_from_string_declaration(strukt);
+        }
+
+        // Doc hidden structs are only parsed as part of the structs that flatten them.
+        if !strukt.is_doc_hidden() {
+            source_builder.write_struct_help_method(strukt, spec_metadata);
+            source_builder.write_is_option_method(strukt, spec_metadata);
+            if strukt.has_attribute(AttributeType::AllowAbbrev) {
+                source_builder.write_expand_abbreviation_method(strukt, spec_metadata);
+            }
+            source_builder.write_struct_parse_method(strukt, spec_metadata);
+            if options.to_command_line {
+                source_builder.write_to_command_line_method(strukt, spec_metadata);
+            }
+            if options.target_os == TargetOs::Windows && strukt.has_attribute(AttributeType::Main) {
+                source_builder.write_struct_parse_wide_method(strukt);
+            }
+        }
+
+        if let Some(implementation) = &mut implementation {
+            let methods = source_builder.buffer.split_off(methods_start);
+            let (declarations, definitions) = split_methods(&methods, &strukt.name, options.indent);
+            source_builder.buffer.push_str(&declarations);
+            cpp_source_builder_writeln!(implementation);
+            implementation.buffer.push_str(&definitions);
+        }
+
+        if options.accessors {
+            source_builder.write_struct_private_section(strukt);
+        }
+
+        source_builder.write_struct_end();
+    }
+
+    source_builder.write_header_guard_end();
+
+    (
+        source_builder.result(),
+        implementation.map(CppSourceBuilder::result),
+    )
+}
+
+/// Writes every struct to a single header, or with `split_implementation` the definitions of
+/// their methods to a `.cpp` file next to it.
+pub struct CppBackend<'a> {
+    pub output: &'a str,
+    pub provenance: Option<&'a Provenance<'a>>,
+    pub split_implementation: bool,
+}
+
+impl Backend for CppBackend<'_> {
+    fn output_paths(&self) -> Vec<String> {
+        let mut paths = vec![self.output.to_string()];
+        if self.split_implementation {
+            let implementation = std::path::Path::new(self.output).with_extension("cpp");
+            paths.push(implementation.to_string_lossy().into_owned());
+        }
+        paths
+    }
+
+    fn generate<'s>(
+        &self,
+        spec: &'s Spec,
+        spec_metadata: &SpecMetadata<'s>,
+        options: &GenOptions,
+    ) -> Result<Vec<OutputFile>, Vec<Diagnostic>> {
+        check_cpp_support(spec, options.cpp_std, options.validate_directory)?;
+
+        if !self.split_implementation {
+            return Ok(vec![OutputFile {
+                path: self.output.to_string(),
+                contents: generate_cli(spec, spec_metadata, options, self.provenance),
+            }]);
+        }
+
+        // The implementation includes the header by its name, as they are written next to each
+        // other.
+        let header_name = std::path::Path::new(self.output).file_name().map_or_else(
+            || self.output.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let (header, implementation) = generate_header_and_implementation(
+            spec,
+            spec_metadata,
+            options,
+            self.provenance,
+            &header_name,
+        );
+
+        Ok(self
+            .output_paths()
+            .into_iter()
+            .zip([header, implementation])
+            .map(|(path, contents)| OutputFile { path, contents })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Indent;
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    fn generate(source: &str) -> String {
+        generate_with_options(source, &GenOptions::default())
+    }
+
+    fn generate_with_options(source: &str, options: &GenOptions) -> String {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        generate_cli(&spec, &metadata, options, None)
+    }
+
+    /// The lines of the `if`/`else if`/`else` chain in the parse loop of the main struct.
+    fn parse_chain(generated: &str) -> Vec<&str> {
+        let mut lines = generated
+            .lines()
+            .skip_while(|line| !line.contains(r#"if (strcmp("-h", arg) == 0"#))
+            .take_while(|line| !line.contains("Unknown option"))
+            .peekable();
+        let indentation = lines
+            .peek()
+            .map_or(0, |line| line.len() - line.trim_start().len());
+
+        lines
+            .filter(|line| line.len() - line.trim_start().len() == indentation)
+            .map(str::trim)
+            .collect()
+    }
+
+    fn assert_well_formed(generated: &str) {
+        assert_eq!(
+            generated.matches('{').count(),
+            generated.matches('}').count()
+        );
+        assert!(
+            generated
+                .lines()
+                .all(|line| !line.trim_start().starts_with("else")),
+            "dangling else in:\n{generated}"
+        );
+    }
+
+    #[test]
+    fn struct_without_attributed_fields() {
+        let generated = generate("#[main] struct Cli { x: i32, y: string }");
+        assert_well_formed(&generated);
+
+        let chain = parse_chain(&generated);
+        assert_eq!(
+            chain,
+            [
+                r#"if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {"#,
+                "} else {"
+            ]
+        );
+    }
+
+    #[test]
+    fn struct_with_only_flattened_fields() {
+        let generated = generate(
+            "struct Common { #[short, long] verbose: bool, #[long] level: u32 }
+             #[main] struct Cli { #[flatten] common: Common }",
+        );
+        assert_well_formed(&generated);
+
+        let chain = parse_chain(&generated);
+        assert_eq!(chain.len(), 4);
+        assert!(chain[1].starts_with(r#"} else if (strcmp(arg, "-v") == 0"#));
+        assert_eq!(chain[2], r#"} else if (strcmp(arg, "--level") == 0) {"#);
+        assert_eq!(chain[3], "} else {");
+    }
+
+    #[test]
+    fn struct_ending_with_plain_fields() {
+        let generated = generate("#[main] struct Cli { #[long] port: u16, x: i32, y: string }");
+        assert_well_formed(&generated);
+
+        let chain = parse_chain(&generated);
+        assert_eq!(
+            chain,
+            [
+                r#"if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {"#,
+                r#"} else if (strcmp(arg, "--port") == 0) {"#,
+                "} else {"
+            ]
+        );
+    }
+
+    #[test]
+    fn vec_flattened_struct_starts_new_elements() {
+        let generated = generate(
+            "struct Server { #[long] host: string, #[long] tags: Vec<string> }
+             #[main] struct Cli { #[flatten] servers: Vec<Server> }",
+        );
+        assert_well_formed(&generated);
+
+        assert!(generated.contains("bool servers_fields_given[2] = { false };"));
+        assert!(generated.contains("if (res.servers.empty() || servers_fields_given[0]) {"));
+        assert!(generated.contains("res.servers.back().host = arg_res;"));
+        // Repeating a `Vec` option adds to the last element instead of starting a new one.
+        assert!(generated.contains("res.servers.back().tags.push_back(arg_res);"));
+        assert!(!generated.contains("res.servers.empty() || servers_fields_given[1]"));
+    }
+
+    #[test]
+    fn after_help_is_printed_after_the_options() {
+        let generated = generate(
+            r#"#[main, after_help = "Examples:\n  myprog --port 8080\n"]
+               struct Cli { #[long] port: u16 }"#,
+        );
+
+        // The escapes of the literal are kept, so the newlines are printed by `printf`.
+        let options_end = generated.find(r#""    --port <PORT>\n""#).unwrap();
+        let after_help = generated
+            .find(r#"printf("%s", "Examples:\n  myprog --port 8080\n");"#)
+            .unwrap();
+        assert!(options_end < after_help);
+        let lines = generated.lines().map(str::trim).collect::<Vec<_>>();
+        let last_option = lines
+            .iter()
+            .position(|line| *line == r#""    --port <PORT>\n""#)
+            .unwrap();
+        assert_eq!(lines[last_option + 1], r#""\n""#);
+    }
+
+    #[test]
+    fn noexcept_is_left_out_for_struct_fields() {
+        let options = GenOptions {
+            cpp_noexcept: true,
+            ..Default::default()
+        };
+        let generated = generate_with_options(
+            "struct Common { #[short, long] verbose: bool }
+             #[main] struct Cli { #[long] port: u16, #[flatten] common: Optional<Common> }",
+            &options,
+        );
+
+        assert!(generated.contains("static Common parse (int argc, char *args[]) noexcept {"));
+        assert!(generated.contains("static Cli parse (int argc, char *args[]) {"));
+        assert!(!generate(GOLDEN_SPEC).contains("noexcept"));
+    }
+
+    #[test]
+    fn global_flags_are_set_before_the_other_arguments() {
+        let generated = generate(
+            r#"#[main]
+            struct Cli {
+                #[short, long, global_flag] verbose: bool,
+                #[long] port: u16,
+                #[trailing] rest: Vec<string>,
+            }"#,
+        );
+
+        let scan = generated.find("for (int i = 0; i != argc; ++i) {").unwrap();
+        let parse = generated
+            .find("for (int i = 0; i != argc; ++i, ++args) {")
+            .unwrap();
+        assert!(scan < parse);
+        assert!(generated[scan..parse].contains(r#"if (strcmp(arg, "--") == 0) {"#));
+        assert!(generated[scan..parse]
+            .contains(r#"if (strcmp(arg, "-v") == 0 || strcmp(arg, "--verbose") == 0) {"#));
+        assert!(generated[scan..parse].contains(
+            "if (strcmp(arg, \"--port\") == 0) {\n                ++i;\n                continue;"
+        ));
+        assert!(!generate(GOLDEN_SPEC).contains("for (int i = 0; i != argc; ++i) {"));
+    }
+
+    #[test]
+    fn noexcept_is_left_out_for_parse_fn_fields() {
+        let options = GenOptions {
+            cpp_noexcept: true,
+            ..Default::default()
+        };
+        let generated = generate_with_options(
+            r#"#[main] struct Cli { #[long, parse_fn = "parse_hex"] mask: u32 }"#,
+            &options,
+        );
+
+        assert!(generated.contains("static Cli parse (int argc, char *args[]) {"));
+    }
+
+    #[test]
+    fn long_prefix_only_changes_long_options() {
+        let generated = generate(
+            r#"#[main, long_prefix = "/"] struct Cli { #[long, alias = listen] port: u16 }"#,
+        );
+
+        assert!(generated.contains(r#"if (strcmp("-h", arg) == 0 || strcmp("/help", arg) == 0) {"#));
+        assert!(generated.contains(r#"strcmp(arg, "/port") == 0 || strcmp(arg, "/listen") == 0"#));
+        assert!(generated.contains(r#""    /port <PORT>\n""#));
+        assert!(!generated.contains("--port"));
+    }
+
+    #[test]
+    fn append_env_values_come_before_the_arguments() {
+        let source = r#"
+            struct Plugins {
+                #[long, append_env = "PLUGIN_PATH"] plugins: Vec<string>,
+            }
+            #[main] struct Cli {
+                #[long, append_env = "MY_PATHS"] paths: Vec<string>,
+                #[flatten] plugins: Plugins,
+            }"#;
+        let generated = generate(source);
+
+        let parse = &generated[generated.find("Cli res = {};").unwrap()..];
+        let env = parse
+            .find(r#"if (const char *env_value = getenv("MY_PATHS")) {"#)
+            .unwrap();
+        assert!(!parse[..env].contains("for (int i = 0; i != argc; ++i, ++args) {"));
+        assert!(parse.contains("mandatory_fields_seen[0] = !res.paths.empty();"));
+        assert!(generated.contains("res.paths.push_back(env_values.substr(start, end - start));"));
+        assert!(generated.contains("res.plugins.plugins.push_back(env_values.substr(start));"));
+        assert!(generated.contains("env_values.find(':', start)"));
+
+        let windows = GenOptions {
+            target_os: TargetOs::Windows,
+            ..GenOptions::default()
+        };
+        assert!(generate_with_options(source, &windows).contains("env_values.find(';', start)"));
+    }
+
+    #[test]
+    fn env_list_values_are_split_by_lines() {
+        let generated =
+            generate(r#"#[main] struct Cli { #[long, env_list = "MY_ITEMS"] items: Vec<string> }"#);
+
+        let parse = &generated[generated.find("Cli res = {};").unwrap()..];
+        let env = parse
+            .find(r#"if (const char *env_value = getenv("MY_ITEMS")) {"#)
+            .unwrap();
+        assert!(!parse[..env].contains("for (int i = 0; i != argc; ++i, ++args) {"));
+        assert!(parse.contains(r"env_values.find('\n', start)"));
+        assert!(parse.contains("res.items.push_back(env_values.substr(start));"));
+    }
+
+    #[test]
+    fn to_command_line_writes_the_options_given() {
+        let source = r#"
+            struct Net { #[long] host: string, #[long, secret] token: Optional<string> }
+            #[main] struct Cli {
+                #[long] port: u16,
+                #[short, default] jobs: u32,
+                #[long, default] verbose: bool,
+                #[long, explicit_bool] color: bool,
+                #[long] tags: Vec<string>,
+                #[flatten] net: Optional<Net>,
+                #[trailing] files: Vec<string>,
+            }
+        "#;
+        let options = GenOptions {
+            to_command_line: true,
+            ..Default::default()
+        };
+        let generated = generate_with_options(source, &options);
+
+        let method = &generated[generated
+            .rfind("std::string to_command_line() const {")
+            .unwrap()..];
+        let lines = method.lines().map(str::trim).collect::<Vec<_>>();
+        let expected = [
+            "std::string line;",
+            r#"append_command_line(line, "--port");"#,
+            "append_command_line(line, std::to_string(this->port));",
+            "if (this->jobs != 0) {",
+            r#"append_command_line(line, "-j");"#,
+            "append_command_line(line, std::to_string(this->jobs));",
+            "}",
+            "if (this->verbose) {",
+            r#"append_command_line(line, this->verbose ? "--verbose" : "--verbose=false");"#,
+            "}",
+            r#"append_command_line(line, this->color ? "--color=true" : "--color=false");"#,
+            "for (size_t i = 0; i != this->tags.size(); ++i) {",
+            r#"append_command_line(line, "--tags");"#,
+            "append_command_line(line, quote_command_line_argument(this->tags[i]));",
+            "}",
+            "if (this->net.has_value()) {",
+            r#"append_command_line(line, "--host");"#,
+            "append_command_line(line, quote_command_line_argument(this->net.value().host));",
+            "if (this->net.value().token.has_value()) {",
+            r#"append_command_line(line, "--token");"#,
+            r#"append_command_line(line, "********");"#,
+            "}",
+            "}",
+            "if (!this->files.empty()) {",
+            r#"append_command_line(line, "--");"#,
+        ];
+        assert_eq!(lines[1..=expected.len()], expected);
+        assert!(generated.contains("inline std::string quote_command_line_argument("));
+        assert!(!generated.contains("command_line_float"));
+        assert!(!generate(source).contains("to_command_line"));
+    }
+
+    #[test]
+    fn to_command_line_writes_floats_so_they_parse_back() {
+        let source = "#[main] struct Cli { #[long] ratio: f64, #[index = 0] scale: f32 }";
+        let options = GenOptions {
+            to_command_line: true,
+            ..Default::default()
+        };
+        let generated = generate_with_options(source, &options);
+
+        assert!(generated.contains("inline std::string command_line_float(T value) {"));
+        assert!(generated.contains("append_command_line(line, command_line_float(this->ratio));"));
+        assert!(generated.contains("append_command_line(line, command_line_float(this->scale));"));
+        assert!(!generated.contains("std::to_string"));
+        assert!(generated.contains("#include <limits>"));
+    }
+
+    #[test]
+    fn option_groups_are_checked_after_the_arguments() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[long] name: string,
+                #[long, exclusive_group = "input"] file: Optional<string>,
+                #[short, exclusive_group = "input"] url: Optional<string>,
+                #[long, exclusive_group = "output"] out: Optional<string>,
+                #[long, default, requires_group = "output"] verbose: bool,
+            }"#,
+        );
 
-                let indentation_level = self.get_indentation_level();
-                self.set_indentation_level(1);
-                cpp_source_builder_writeln!(self, "else if ({field_matcher}) {{");
-                self.set_indentation_level(indentation_level);
+        assert!(generated.contains("bool fields_given[5] = { false };"));
+        assert!(generated.contains("fields_given[2] = true;"));
+        let checks = &generated[generated
+            .find("if (fields_given[1] + fields_given[2] > 1) {")
+            .unwrap()..];
+        assert!(checks.contains(r#"printf("Only one of --file and -u can be given\n");"#));
+        assert!(checks.contains("if (fields_given[4] && !(fields_given[3])) {"));
+        assert!(checks.contains(r#"printf("--verbose requires --out\n");"#));
+        // The group of a single field needs no check of its own.
+        assert!(!checks.contains("fields_given[3] > 1"));
+    }
 
-                self.push_indentation_level();
+    #[test]
+    fn env_values_are_replaced_by_the_options() {
+        let source = r#"
+            #[main, config_file = "config"] struct Cli {
+                #[long] config: Optional<string>,
+                #[long, env = "APP_PORT", env_no_override] port: u16,
+                #[long, env = "APP_VERBOSE"] verbose: Optional<bool>,
+            }"#;
+        let generated = generate(source);
 
-                self.write_parse_field_type(struct_name, &field.ty);
+        let parse = &generated[generated.find("Cli res = {};").unwrap()..];
+        let env = parse
+            .find(r#"if (char *env_value = getenv("APP_PORT")) {"#)
+            .unwrap();
+        let parse_loop = parse
+            .find("for (int i = 0; i != argc; ++i, ++args) {")
+            .unwrap();
+        assert!(env < parse_loop);
+        let env_block = &parse[env..parse_loop];
+        assert!(env_block.contains(r#"const char* arg = "APP_PORT";"#));
+        assert!(env_block.contains("char* arg_value = env_value;"));
+        assert!(env_block.contains("res.port = arg_res;"));
+        assert!(env_block.contains("mandatory_fields_seen[0] = true;"));
+        // The config file doesn't replace the values from the environment either.
+        assert!(env_block.contains("fields_given[1] = true;"));
+        assert!(env_block.contains("if (!parse_bool_value(env_value, &arg_res)) {"));
+        assert!(env_block.contains("res.verbose = arg_res;"));
+    }
 
-                let destination = parents.join(".");
+    #[test]
+    fn env_prefix_names_the_variables_after_the_fields() {
+        let source = |env_case: &str| {
+            format!(
+                r#"
+                #[main, env_prefix = "APP_"{env_case}] struct Cli {{
+                    #[long] log_level: Optional<string>,
+                    #[long, env = "PORT"] port: Optional<u16>,
+                    #[long] paths: Vec<string>,
+                    #[flatten] net: Net,
+                }}
+                struct Net {{ #[long] host: Optional<string> }}"#
+            )
+        };
 
-                match &field.ty {
-                    FieldType::Vec(_) => {
-                        cpp_source_builder_writeln!(
-                            self,
-                            "{destination}.{}.push_back(arg_res);",
-                            field.name
-                        );
-                    }
-                    _ => {
-                        cpp_source_builder_writeln!(
-                            self,
-                            "{destination}.{} = arg_res;",
-                            field.name
-                        );
-                    }
-                }
+        let generated = generate(&source(""));
+        assert!(generated.contains(r#"if (char *env_value = getenv("APP_LOG_LEVEL")) {"#));
+        // An explicit `env` is kept, and the flattened struct has no prefix of its own.
+        assert!(generated.contains(r#"getenv("PORT")"#));
+        assert!(!generated.contains(r#"getenv("APP_PATHS")"#));
+        assert!(!generated.contains(r#"getenv("APP_HOST")"#));
 
-                if let Some(index) = mandatory_field_to_index.get(field.name.as_str()) {
-                    cpp_source_builder_writeln!(self, "mandatory_fields_seen[{index}] = true;")
-                }
+        let generated = generate(&source(r#", env_case = "lower""#));
+        assert!(generated.contains(r#"getenv("APP_log_level")"#));
+        let generated = generate(&source(r#", env_case = "original""#));
+        assert!(generated.contains(r#"getenv("APP_log_level")"#));
+    }
 
-                self.pop_indentation_level();
-                cpp_source_builder_write!(self, "}}");
-            }
-        }
-        parents.pop();
+    #[test]
+    fn the_last_field_given_an_option_takes_it() {
+        let generated = generate(
+            r#"#[main, long_short_conflict = "warn"] struct Cli {
+                #[short, long] port: Optional<u16>,
+                #[short, long] path: Optional<string>,
+            }"#,
+        );
+
+        assert!(generated.contains(r#"if (strcmp(arg, "--port") == 0) {"#));
+        assert!(generated
+            .contains(r#"} else if (strcmp(arg, "-p") == 0 || strcmp(arg, "--path") == 0) {"#));
     }
 
-    pub fn write_parse_fields(
-        &mut self,
-        struct_name: &str,
-        fields: &[Field],
-        spec_metadata: &SpecMetadata,
-        mandatory_field_to_index: &HashMap<&str, usize>,
-    ) {
-        let mut parents = vec!["res".to_string()];
-        self.write_parse_fields_r(
-            struct_name,
-            fields,
-            spec_metadata,
-            &mut parents,
-            mandatory_field_to_index,
-        )
+    #[test]
+    fn fields_with_a_parse_order_are_matched_first() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[long] host: Optional<string>,
+                #[long, parse_order = 2] port: Optional<u16>,
+                #[long] user: Optional<string>,
+                #[long, parse_order = 1] verbose: bool,
+            }"#,
+        );
+
+        let position = |option: &str| {
+            generated
+                .find(&format!(r#"strcmp(arg, "--{option}") == 0"#))
+                .unwrap()
+        };
+        assert!(position("verbose") < position("port"));
+        assert!(position("port") < position("host"));
+        assert!(position("host") < position("user"));
     }
 
-    pub fn write_struct_parse_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
-        cpp_source_builder_writeln!(self);
+    #[test]
+    fn fields_with_a_display_order_are_listed_first_in_the_help() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[long] host: Optional<string>,
+                #[long, display_order = 2] port: Optional<u16>,
+                #[long] user: Optional<string>,
+                #[long, display_order = 1] verbose: bool,
+            }"#,
+        );
 
-        let struct_name = &strukt.name;
+        let position = |option: &str| generated.find(&format!(r#""    --{option}"#)).unwrap();
+        assert!(position("verbose") < position("port"));
+        assert!(position("port") < position("host"));
+        assert!(position("host") < position("user"));
+    }
 
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(
-            self,
-            "static {struct_name} parse (int argc, char *args[]) {{"
+    #[test]
+    fn prefix_flags_also_take_an_attached_value() {
+        let generated = generate(
+            r#"#[main, value_separator = ":"] struct Cli {
+                #[short, long, prefix_flag] port: u16,
+                #[long] host: string,
+            }"#,
         );
 
-        self.push_indentation_level();
+        assert!(generated.contains(
+            r#"} else if (strcmp(arg, "-p") == 0 || strncmp(arg, "-p:", 3) == 0 || strcmp(arg, "--port") == 0 || strncmp(arg, "--port:", 7) == 0) {"#
+        ));
+        assert!(generated.contains("char* option_value = strchr(args[0], ':');"));
+        assert!(generated.contains("char* arg_value = option_value;"));
+        assert!(generated.contains(r#"} else if (strcmp(arg, "--host") == 0) {"#));
+        assert!(generated.contains("std::string arg_res = args[0];"));
+    }
 
-        if strukt
-            .attributes
-            .iter()
-            .any(|attr| matches!(attr.ty, AttributeType::Main))
-        {
-            cpp_source_builder_writeln!(self, "--argc;");
-            cpp_source_builder_writeln!(self, "++args;\n");
-        }
+    #[test]
+    fn field_prefixes_come_before_the_long_name() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[short, long, prefix = "my_"] file: string,
+                #[long] output: string,
+            }"#,
+        );
 
-        cpp_source_builder_write!(self, "const char* mandatory_field_names[] = {{");
+        assert!(generated
+            .contains(r#"} else if (strcmp(arg, "-f") == 0 || strcmp(arg, "--my-file") == 0) {"#));
+        assert!(generated.contains(r#"} else if (strcmp(arg, "--output") == 0) {"#));
+        assert!(generated.contains(r#""    -f, --my-file <FILE>\n""#));
+    }
 
-        let indentation_level = self.get_indentation_level();
-        self.set_indentation_level(1);
+    #[test]
+    fn config_file_fills_the_fields_not_given() {
+        let generated = generate(
+            r#"#[main, config_file = "config"] struct Cli {
+                #[short, long] config: Optional<string>,
+                #[long = dry_run] dry_run: bool,
+                #[long] jobs: Vec<u32>,
+            }"#,
+        );
 
-        let mut mandatory_field_name_to_index = HashMap::new();
+        assert!(generated.contains(
+            "inline bool read_config_file(const char* path, std::vector<config_entry>& entries) {"
+        ));
+        assert!(generated.contains("bool fields_given[3] = { false };"));
+        assert!(generated.contains("fields_given[1] = true;"));
 
-        for (i, field) in strukt
-            .fields
-            .iter()
-            .filter(|f| !matches!(f.ty, FieldType::Optional(_)))
-            .enumerate()
-        {
-            cpp_source_builder_write!(self, r#""{}","#, field.name);
-            mandatory_field_name_to_index.insert(field.name.as_str(), i);
-        }
+        // The file is read after the arguments, before the mandatory fields are checked.
+        let loading = generated.find("if (fields_given[0]) {").unwrap();
+        assert!(generated[loading..]
+            .contains("if (!read_config_file(res.config.value().c_str(), config_entries)) {"));
+        assert!(generated[loading..]
+            .find("bool not_seen_any = false;")
+            .is_some());
+        assert!(generated[..loading].contains("Unknown option"));
 
-        cpp_source_builder_writeln!(self, "}};");
-        self.set_indentation_level(indentation_level);
+        assert!(generated.contains(r#"if (entry.key == "dry-run") {"#));
+        assert!(generated.contains(r#"if (!check_config_entry(entry, false, 'b', "a bool")) {"#));
+        assert!(generated
+            .contains(r#"if (!check_config_entry(entry, true, 'n', "an array of numbers")) {"#));
+        assert!(generated.contains("char* arg_value = config_value;"));
+        assert!(generated.contains("mandatory_fields_seen[1] = !res.jobs.empty();"));
+        assert!(generated.contains("Warning: unknown key '%s' in the config file"));
+        assert!(!generate("#[main] struct Cli { #[long] port: u16 }").contains("config_entry"));
+    }
 
-        cpp_source_builder_writeln!(
-            self,
-            "bool mandatory_fields_seen[sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0])] = {{ false }};\n"
+    #[test]
+    fn parse_fn_replaces_the_conversion() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[long, parse_fn = "net::parse_ipv4"] address: string,
+                #[long, parse_fn = "parse_hex", max = 255] mask: u32,
+            }"#,
         );
 
-        cpp_source_builder_writeln!(self, "{struct_name} res = {{}};");
-        cpp_source_builder_writeln!(self, "for (int i = 0; i != argc; ++i, ++args) {{");
+        assert!(generated.contains("std::string arg_res = net::parse_ipv4(args[0]);"));
+        assert!(generated.contains("uint32_t arg_res = parse_hex(arg_value);"));
+        // Range checks still apply to the converted value.
+        assert!(generated.contains("if (arg_res > 255) {"));
+        assert!(!generated.contains("strtoll"));
+    }
 
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "char *arg = args[0];");
-        cpp_source_builder_writeln!(
-            self,
-            r#"if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {{"#
+    #[test]
+    fn from_str_parses_custom_types_from_a_single_value() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[long, from_str] origin: Point,
+                #[long] area: Area,
+            }
+            struct Point { #[long] x: i32 }
+            struct Area { #[long] width: u32 }"#,
         );
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "{struct_name}::help();");
-        self.pop_indentation_level();
-        cpp_source_builder_write!(self, "}}");
 
-        self.write_parse_fields(
-            strukt.name.as_str(),
-            &strukt.fields,
-            spec_metadata,
-            &mandatory_field_name_to_index,
+        assert!(generated.contains("Point arg_res = Point::from_string(args[0]);"));
+        assert!(generated.contains("static Point from_string(const char* value);"));
+        assert!(!generated.contains("Point::parse(argc - i, args)"));
+        // Other custom types are still parsed from their own options.
+        assert!(generated.contains("Area arg_res = Area::parse(argc - i, args);"));
+        assert!(!generated.contains("static Area from_string"));
+    }
+
+    #[test]
+    fn split_methods_are_declared_in_the_header_and_defined_in_the_implementation() {
+        let source = r#"#[main] struct Cli { #[long] port: u16 }"#;
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let options = GenOptions {
+            cpp_noexcept: true,
+            to_command_line: true,
+            doxygen: true,
+            ..GenOptions::default()
+        };
+        let (header, implementation) =
+            generate_header_and_implementation(&spec, &metadata, &options, None, "cli.h");
+
+        assert!(header.contains("    static Cli parse (int argc, char *args[]) noexcept;\n"));
+        assert!(header.contains("    std::string to_command_line() const;\n"));
+        assert!(header.contains("    void print_debug();\n"));
+        // The documentation stays with the declarations.
+        assert!(header.contains("@return The parsed `Cli`."));
+        assert!(!header.contains("strtoll"));
+
+        assert!(implementation.starts_with("#include \"cli.h\"\n"));
+        assert!(implementation.contains("\nCli Cli::parse (int argc, char *args[]) noexcept {\n"));
+        assert!(implementation.contains("\nstd::string Cli::to_command_line() const {\n"));
+        assert!(implementation.contains("\nbool Cli::is_option(char* arg) {\n"));
+        assert!(!implementation.contains("static Cli"));
+        assert!(!implementation.contains("@return"));
+        // The bodies are moved out of the struct along with their indentation.
+        assert!(implementation.contains("\n    return false;\n}\n"));
+        assert!(implementation.ends_with("}\n"));
+
+        // Without splitting, the methods are still defined in the header.
+        assert!(generate(source).contains("    static Cli parse (int argc, char *args[]) {\n"));
+    }
+
+    #[test]
+    fn default_values_are_constants_of_the_struct() {
+        let source = r#"#[main] struct Cli {
+                #[long, default_value_t = "8080"] port: u16,
+                #[long, default_value_t = "localhost"] host: string,
+                #[flatten] log: Log,
+                #[flatten] net: Optional<Net>,
+            }
+            struct Log { #[long, default_value_t = "2"] level: u32 }
+            struct Net { #[long, default_value_t = "30"] timeout: u32 }"#;
+        let generated = generate_with_options(
+            source,
+            &GenOptions {
+                to_command_line: true,
+                ..GenOptions::default()
+            },
         );
 
-        let indentation_level = self.get_indentation_level();
-        self.set_indentation_level(1);
-        cpp_source_builder_writeln!(self, "else {{");
-        self.set_indentation_level(indentation_level);
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, r#"printf("Unknown option '%s'\n", arg);"#);
-        cpp_source_builder_writeln!(self, "exit(1);");
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+        assert!(generated.contains("    static constexpr uint16_t PORT_DEFAULT = 8080;\n"));
+        assert!(generated
+            .contains("    static inline const std::string HOST_DEFAULT = \"localhost\";\n"));
+        let parse = &generated[generated.find("Cli res = {};").unwrap()..];
+        assert!(parse.contains("res.port = Cli::PORT_DEFAULT;"));
+        assert!(parse.contains("res.host = Cli::HOST_DEFAULT;"));
+        assert!(parse.contains("res.log.level = Log::LEVEL_DEFAULT;"));
+        // Optional flattened structs start from their defaults once one of their options is given.
+        assert!(parse.contains("res.net.value().timeout = Net::TIMEOUT_DEFAULT;"));
+        assert!(generated.contains("if (this->port != 8080) {"));
 
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}\n");
+        let cpp11 = generate_with_options(
+            source,
+            &GenOptions {
+                cpp_std: CppStandard::Cpp11,
+                ..GenOptions::default()
+            },
+        );
+        assert!(cpp11.contains("static constexpr const char* HOST_DEFAULT = \"localhost\";"));
 
-        cpp_source_builder_writeln!(self, "bool not_seen_any = false;");
-        cpp_source_builder_writeln!(
-            self,
-            "for (size_t i = 0; i != sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0]); ++i) {{"
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let (header, implementation) = generate_header_and_implementation(
+            &spec,
+            &metadata,
+            &GenOptions::default(),
+            None,
+            "cli.h",
         );
-        self.push_indentation_level();
+        assert!(header.contains("    static const std::string HOST_DEFAULT;\n"));
+        assert!(implementation.contains("\nconst std::string Cli::HOST_DEFAULT = \"localhost\";\n"));
+    }
 
-        cpp_source_builder_writeln!(self, "if (!mandatory_fields_seen[i]) {{");
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(
-            self,
-            r#"printf("--%s was required but it was not provided\n", mandatory_field_names[i]);"#
+    #[test]
+    fn positional_fields_are_given_in_the_order_of_their_index() {
+        let generated = generate_with_options(
+            r#"#[main] struct Cli {
+                #[long] level: u32,
+                #[index = 1, doc = "Where to copy to"] destination: string,
+                #[index = 0] source: string,
+                #[index = 2] copies: Optional<u32>,
+            }"#,
+            &GenOptions {
+                to_command_line: true,
+                ..GenOptions::default()
+            },
         );
-        cpp_source_builder_writeln!(self, "not_seen_any = true;");
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+        assert_well_formed(&generated);
 
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+        let chain = parse_chain(&generated);
+        assert_eq!(
+            chain[chain.len() - 2],
+            r#"} else if (positional_count != 3 && (strncmp(arg, "-", 1) != 0 || strcmp(arg, "-") == 0) && (strncmp(arg, "--", 2) != 0 || strcmp(arg, "--") == 0)) {"#
+        );
+        let source = generated.find("if (positional_count == 0) {").unwrap();
+        let destination = generated
+            .find("} else if (positional_count == 1) {")
+            .unwrap();
+        assert!(source < destination);
+        assert!(generated[source..destination].contains("res.source = arg_res;"));
+        assert!(generated.contains(r#"const char* arg = "<COPIES>";"#));
 
-        cpp_source_builder_writeln!(self, "if (not_seen_any) {{");
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "exit(1);");
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+        // Positional fields aren't options, so they are checked against the number given.
+        assert!(generated.contains(r#"const char* mandatory_field_names[] = { "level", };"#));
+        assert!(generated
+            .contains(r#"printf("<DESTINATION> was required but it was not provided\n");"#));
+        assert!(!generated.contains("<COPIES> was required"));
 
-        cpp_source_builder_writeln!(self, "return res;");
+        assert!(generated.contains(r#""Usage: Cli [OPTIONS] <SOURCE> <DESTINATION> [<COPIES>]\n""#));
+        assert!(generated.contains(r#""Arguments:\n""#));
+        assert!(generated.contains(r#""    <DESTINATION>  Where to copy to\n""#));
+        assert!(!generated.contains("--source"));
 
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
-        self.pop_indentation_level();
+        let to_command_line = &generated[generated.find("to_command_line() const").unwrap()..];
+        let source = to_command_line
+            .find("quote_command_line_argument(this->source)")
+            .unwrap();
+        let destination = to_command_line
+            .find("quote_command_line_argument(this->destination)")
+            .unwrap();
+        assert!(source < destination);
+        assert!(to_command_line.contains("std::to_string(this->copies.value())"));
     }
 
-    pub fn write_struct_help_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
-        cpp_source_builder_writeln!(self);
+    #[test]
+    fn exit_codes_are_chosen_by_kind_of_error() {
+        let generated = generate(
+            r#"#[main, exit_code = 2, unknown_flag_exit = 64] struct Cli {
+                #[long] port: u16,
+            }"#,
+        );
 
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "static void help() {{");
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, r#"printf("Usage: {} [OPTIONS]\n""#, strukt.name);
-        cpp_source_builder_writeln!(self, r#""\n""#);
-        cpp_source_builder_writeln!(self, r#""Options:\n""#);
-        cpp_source_builder_writeln!(self, r#""    -h, --help\n""#);
+        let lines = generated.lines().map(str::trim).collect::<Vec<_>>();
+        let exit_after = |message: &str| {
+            let index = lines
+                .iter()
+                .position(|line| line.contains(message))
+                .unwrap();
+            lines[index + 1]
+        };
 
-        let identation_level = self.get_indentation_level();
-        for field in strukt.get_fields(spec_metadata) {
-            cpp_source_builder_write!(self, "\"    ");
-            self.set_indentation_level(0);
-            if let Some(short_value) = field.short_value() {
-                cpp_source_builder_write!(self, "-{short_value}");
-            }
+        // Errors without a code of their own exit with `exit_code`.
+        assert!(!generated.contains("exit(1);"));
+        assert_eq!(exit_after("out of range for integer type"), "exit(2);");
+        assert_eq!(exit_after("but no value was provided"), "exit(2);");
+        assert_eq!(exit_after("Unknown option"), "exit(64);");
 
-            if let Some(long_value) = field.long_value() {
-                if field.short_value().is_some() {
-                    cpp_source_builder_write!(self, ", ")
-                }
-                cpp_source_builder_write!(self, "--{long_value}");
-            }
+        let generated = generate("#[main, missing_arg_exit = 3] struct Cli { #[long] port: u16 }");
+        assert!(generated.contains("exit(3);"));
+        assert!(generated.contains("exit(1);"));
+    }
 
-            if !matches!(field.ty, FieldType::Bool) {
-                cpp_source_builder_write!(self, " <{}>", field.name.to_uppercase());
-            }
+    #[test]
+    fn generation_is_deterministic() {
+        let examples = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/examples")).unwrap();
+        let all_options = [
+            GenOptions::default(),
+            GenOptions {
+                cpp_std: CppStandard::Cpp11,
+                target_os: TargetOs::Windows,
+                accessors: true,
+                cpp_noexcept: true,
+                doxygen: true,
+                no_regex_validation: true,
+                // std::filesystem is not in C++11.
+                validate_directory: false,
+                to_command_line: true,
+                indent: Indent::Tabs,
+                line_ending: LineEnding::Crlf,
+            },
+        ];
 
-            cpp_source_builder_writeln!(self, r#"\n""#);
-            self.set_indentation_level(identation_level);
-        }
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, ");");
+        for example in examples {
+            let spec_path = example.unwrap().path().join("cli.in");
+            let source = std::fs::read_to_string(&spec_path).unwrap();
 
-        cpp_source_builder_writeln!(self, "exit(0);");
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
-        self.pop_indentation_level();
+            for options in &all_options {
+                assert_eq!(
+                    generate_with_options(&source, options),
+                    generate_with_options(&source, options),
+                    "{} generated different outputs",
+                    spec_path.display()
+                );
+            }
+        }
     }
 
-    pub fn write_is_option_method(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
-        cpp_source_builder_writeln!(self);
+    #[test]
+    fn banner_records_the_spec_hash() {
+        let source = "#[main] struct Cli { #[long] port: u16 }";
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
 
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "static bool is_option(char* arg) {{");
+        let generate_at = |with_timestamp| {
+            let provenance = Provenance::new("cli.in", source, with_timestamp);
+            generate_cli(&spec, &metadata, &GenOptions::default(), Some(&provenance))
+        };
 
-        self.push_indentation_level();
-        cpp_source_builder_writeln!(self, "static const char* valid_options[] = {{");
-        self.push_indentation_level();
+        let generated = generate_at(true);
+        assert_eq!(
+            embedded_spec_hash(&generated),
+            Some(crate::hash::spec_hash(source).as_str())
+        );
+        assert!(same_generated_code(&generated, &generate_at(false)));
+        assert!(!same_generated_code(&generated, &generate(source)));
+    }
 
-        let mut num_fields = 0;
-        for field in strukt.get_fields(spec_metadata) {
-            if let Some(short_value) = field.short_value() {
-                cpp_source_builder_writeln!(self, r#""-{short_value}","#);
-                num_fields += 1;
-            }
+    #[test]
+    fn case_insensitive_struct_keeps_short_options_case_sensitive() {
+        let generated =
+            generate("#[main, case_insensitive] struct Cli { #[short, long] mode: u16 }");
+        assert_well_formed(&generated);
 
-            if let Some(long_value) = field.long_value() {
-                cpp_source_builder_writeln!(self, r#""--{long_value}","#);
-                num_fields += 1;
-            }
+        let chain = parse_chain(&generated);
+        assert_eq!(
+            chain[1],
+            r#"} else if (strcmp(arg, "-m") == 0 || equals_ignore_case(arg, "--mode")) {"#
+        );
+    }
+
+    const GOLDEN_SPEC: &str = "
+        #[doc_hidden]
+        struct Logging { #[short, long] verbose: bool, #[long] log_file: Optional<string> }
+
+        #[main]
+        struct Cli {
+            #[short, long]
+            input: string,
+            #[long]
+            jobs: Vec<u32>,
+            #[flatten]
+            logging: Logging,
         }
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}};");
+    ";
 
-        cpp_source_builder_writeln!(self);
+    #[test]
+    fn golden_two_space_indentation() {
+        let options = GenOptions {
+            indent: Indent::Spaces(2),
+            ..Default::default()
+        };
 
-        cpp_source_builder_writeln!(self, "for (size_t i = 0; i != {num_fields}; ++i) {{");
-        self.push_indentation_level();
+        assert_eq!(
+            generate_with_options(GOLDEN_SPEC, &options),
+            include_str!("golden/indent_2.h")
+        );
+    }
 
-        cpp_source_builder_writeln!(self, "if (strcmp(arg, valid_options[i]) == 0) {{");
-        self.push_indentation_level();
+    #[test]
+    fn golden_tab_indentation() {
+        let options = GenOptions {
+            indent: Indent::Tabs,
+            ..Default::default()
+        };
 
-        cpp_source_builder_writeln!(self, "return true;");
+        assert_eq!(
+            generate_with_options(GOLDEN_SPEC, &options),
+            include_str!("golden/indent_tabs.h")
+        );
+    }
 
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+    #[test]
+    fn golden_doxygen() {
+        let source = r#"
+            #[main, about = "Copies files.\nGlobs like src/*/ are expanded."]
+            struct Cli {
+                #[short, long, doc = "The file to copy"]
+                input: string,
+                #[long]
+                jobs: Vec<u32>,
+            }
+        "#;
+        let options = GenOptions {
+            doxygen: true,
+            ..Default::default()
+        };
 
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+        assert_eq!(
+            generate_with_options(source, &options),
+            include_str!("golden/doxygen.h")
+        );
+    }
 
-        cpp_source_builder_writeln!(self);
-        cpp_source_builder_writeln!(self, "return false;");
+    #[test]
+    fn doxygen_documents_the_getters_with_accessors() {
+        let options = GenOptions {
+            doxygen: true,
+            accessors: true,
+            ..Default::default()
+        };
+        let generated = generate_with_options(
+            r#"#[main] struct Cli { #[long, doc = "The port"] port: u16 }"#,
+            &options,
+        );
 
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
-        self.pop_indentation_level();
+        assert!(generated
+            .contains("    /** The port */\n    const uint16_t& port() const { return port_; }"));
+        assert_eq!(generated.matches("/** The port */").count(), 1);
+        assert!(!generate(GOLDEN_SPEC).contains("/**"));
     }
 
-    pub fn write_debug_print_method(&mut self, strukt: &Struct) {
-        fn field_to_print_statement(field: &Field) -> String {
-            match &field.ty {
-                FieldType::String => {
-                    format!(r#"printf("\t{0}: %s\n", this->{0}.c_str());"#, field.name)
-                }
-                FieldType::I16 => format!(r#"printf("\t{0}: %d\n", this->{0});"#, field.name),
-                FieldType::U16 => format!(r#"printf("\t{0}: %d\n", this->{0});"#, field.name),
-                FieldType::I32 => format!(r#"printf("\t{0}: %d\n", this->{0});"#, field.name),
-                FieldType::U32 => format!(r#"printf("\t{0}: %d\n", this->{0});"#, field.name),
-                FieldType::I64 => format!(r#"printf("\t{0}: %d\n", this->{0});"#, field.name),
-                FieldType::U64 => format!(r#"printf("\t{0}: %d\n", this->{0});"#, field.name),
-                FieldType::F32 => format!(r#"printf("\t{0}: %f\n", this->{0});"#, field.name),
-                FieldType::F64 => format!(r#"printf("\t{0}: %f\n", this->{0});"#, field.name),
-                FieldType::Bool => format!(
-                    r#"printf("\t{0}: %s\n", this->{0} ? "true" : "false");"#,
-                    field.name
-                ),
-                FieldType::Vec(inner) => match inner.as_ref() {
-                    FieldType::String => {
-                        format!(r#"printf("\t%s,\n", this->{}[i].c_str());"#, field.name)
-                    }
-                    FieldType::I16 => format!(r#"printf("\t%d,\n", this->{}[i]);"#, field.name),
-                    FieldType::U16 => format!(r#"printf("\t%d,\n", this->{}[i]);"#, field.name),
-                    FieldType::I32 => format!(r#"printf("\t%d,\n", this->{}[i]);"#, field.name),
-                    FieldType::U32 => format!(r#"printf("\t%d,\n", this->{}[i]);"#, field.name),
-                    FieldType::I64 => format!(r#"printf("\t%d,\n", this->{}[i]);"#, field.name),
-                    FieldType::U64 => format!(r#"printf("\t%d,\n", this->{}[i]);"#, field.name),
-                    FieldType::F32 => format!(r#"printf("\t%f,\n", this->{}[i]);"#, field.name),
-                    FieldType::F64 => format!(r#"printf("\t%f,\n", this->{}[i]);"#, field.name),
-                    FieldType::Bool => format!(
-                        r#"printf("\t%s,\n", this->{}[i] ? "true" : "false");"#,
-                        field.name
-                    ),
-                    FieldType::Vec(_) => unreachable!(),
-                    FieldType::Optional(_) => unreachable!(),
-                    FieldType::Struct(_) => format!("this->{}[i].print_debug();", field.name),
-                },
-                FieldType::Struct(_) => format!("this->{}.print_debug();", field.name),
-                FieldType::Optional(inner) => match inner.as_ref() {
-                    FieldType::String => {
-                        format!(r#"printf("\t%s,\n", this->{}[i].c_str());"#, field.name)
-                    }
-                    FieldType::I16 => {
-                        format!(r#"printf("\t%d,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::U16 => {
-                        format!(r#"printf("\t%d,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::I32 => {
-                        format!(r#"printf("\t%d,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::U32 => {
-                        format!(r#"printf("\t%d,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::I64 => {
-                        format!(r#"printf("\t%d,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::U64 => {
-                        format!(r#"printf("\t%d,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::F32 => {
-                        format!(r#"printf("\t%f,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::F64 => {
-                        format!(r#"printf("\t%f,\n", this->{}[i].value());"#, field.name)
-                    }
-                    FieldType::Bool => format!(
-                        r#"printf("\t%s,\n", this->{}[i].value() ? "true" : "false");"#,
-                        field.name
-                    ),
-                    FieldType::Vec(_) => unreachable!(),
-                    FieldType::Optional(_) => unreachable!(),
-                    FieldType::Struct(_) => {
-                        format!("this->{}[i].value().print_debug();", field.name)
-                    }
-                },
-            }
-        }
-        cpp_source_builder_writeln!(self);
-        self.push_indentation_level();
+    #[test]
+    fn indentation_width_only_changes_leading_whitespace() {
+        let default = generate(GOLDEN_SPEC);
+        let options = GenOptions {
+            indent: Indent::Spaces(2),
+            ..Default::default()
+        };
 
-        cpp_source_builder_writeln!(self, "void print_debug() {{");
-        self.push_indentation_level();
+        let reindented = default
+            .lines()
+            .map(|line| {
+                let content = line.trim_start_matches(' ');
+                let levels = (line.len() - content.len()) / 4;
+                format!("{}{content}\n", "  ".repeat(levels))
+            })
+            .collect::<String>();
 
-        cpp_source_builder_writeln!(self, r#"printf("{} {{\n");"#, strukt.name);
-        for field in &strukt.fields {
-            let print_statement = field_to_print_statement(field);
-            match field.ty {
-                FieldType::Vec(_) => {
-                    cpp_source_builder_writeln!(self, r#"printf("\t{}: [\n");"#, field.name);
-                    cpp_source_builder_writeln!(
-                        self,
-                        "for (size_t i = 0; i != this->{}.size(); ++i) {{",
-                        field.name
-                    );
-                    self.push_indentation_level();
-                    cpp_source_builder_writeln!(self, "{print_statement}");
-                    self.pop_indentation_level();
-                    cpp_source_builder_writeln!(self, "}}");
-                    cpp_source_builder_writeln!(self, r#"printf("\t]\n");"#);
-                }
-                _ => cpp_source_builder_writeln!(self, "{print_statement}"),
-            }
-        }
+        assert_eq!(generate_with_options(GOLDEN_SPEC, &options), reindented);
+    }
 
-        cpp_source_builder_writeln!(self, r#"printf("}}\n");"#);
-        self.pop_indentation_level();
-        cpp_source_builder_writeln!(self, "}}");
+    #[test]
+    fn line_endings_are_applied_to_every_line() {
+        let source = "#[main] struct Cli { #[short, long] verbose: bool }";
+        let generate_with_line_ending = |line_ending| {
+            let options = GenOptions {
+                line_ending,
+                ..Default::default()
+            };
+            generate_with_options(source, &options)
+        };
 
-        self.pop_indentation_level();
+        let lf = generate_with_line_ending(LineEnding::Lf);
+        assert!(lf.starts_with("#ifndef _CLI_H_\n#define _CLI_H_\n\n#include <cctype>\n"));
+        assert!(!lf.contains('\r'));
+
+        let crlf = generate_with_line_ending(LineEnding::Crlf);
+        assert!(crlf.starts_with("#ifndef _CLI_H_\r\n#define _CLI_H_\r\n\r\n#include <cctype>\r\n"));
+        assert!(crlf.ends_with("#endif // _CLI_H_\r\n"));
+        assert_eq!(crlf.matches("\r\n").count(), crlf.matches('\n').count());
+        assert_eq!(crlf.replace("\r\n", "\n"), lf);
     }
-}
 
-pub(crate) fn generate_cli(spec: &Spec, spec_metadata: &SpecMetadata) -> String {
-    let mut source_builder = CppSourceBuilder::default();
+    #[test]
+    fn word_wrap_breaks_at_spaces() {
+        assert_eq!(word_wrap("a bb ccc dddd", 6), "a bb\nccc\ndddd");
+        assert_eq!(word_wrap("unbreakable words", 5), "unbreakable\nwords");
+        assert_eq!(word_wrap(r#"say \"hi\" now"#, 8), "say \\\"hi\\\"\nnow");
+        assert_eq!(word_wrap("", 10), "");
+    }
 
-    source_builder.write_header_guard_start();
-    source_builder.write_include_headers();
+    #[test]
+    fn next_line_help_prints_descriptions_below_the_options() {
+        let generated = generate(
+            r#"#[main] struct Cli {
+                #[long, doc = "Where to write", next_line_help] output_directory: string,
+                #[long, doc = "How many jobs"] jobs: u32,
+            }"#,
+        );
+        let lines = generated.lines().map(str::trim).collect::<Vec<_>>();
+        let output_directory = lines
+            .iter()
+            .position(|line| *line == r#""    --output-directory <OUTPUT_DIRECTORY>\n""#)
+            .unwrap();
+        assert_eq!(lines[output_directory + 1], r#""        Where to write\n""#);
+        assert!(generated.contains(r#""    --jobs <JOBS>  How many jobs\n""#));
 
-    for strukt in &spec.structs {
-        source_builder.write_struct_start(&strukt.name);
+        let generated = generate(
+            r#"#[main, next_line_help, word_wrap = 30] struct Cli {
+                #[long, doc = "How many jobs to run at once"] jobs: u32,
+            }"#,
+        );
+        let lines = generated.lines().map(str::trim).collect::<Vec<_>>();
+        let jobs = lines
+            .iter()
+            .position(|line| *line == r#""    --jobs <JOBS>\n""#)
+            .unwrap();
+        assert_eq!(
+            lines[jobs + 1..jobs + 3],
+            [
+                r#""        How many jobs to run\n""#,
+                r#""        at once\n""#
+            ]
+        );
+    }
 
-        for field in &strukt.fields {
-            source_builder.write_struct_field(field);
-        }
+    #[test]
+    fn structs_without_mandatory_fields_track_none() {
+        let generated =
+            generate(r#"#[main] struct Cli { #[long, default_value_t = "8080"] port: u16 }"#);
+        assert!(!generated.contains("mandatory_field"));
+        assert!(!generated.contains("not_seen_any"));
 
-        source_builder.write_debug_print_method(strukt);
-        source_builder.write_struct_help_method(strukt, spec_metadata);
-        source_builder.write_is_option_method(strukt, spec_metadata);
-        source_builder.write_struct_parse_method(strukt, spec_metadata);
+        let generated = generate("#[main] struct Cli { #[long] port: u16 }");
+        assert!(generated.contains(r#"const char* mandatory_field_names[] = { "port", };"#));
+    }
 
-        source_builder.write_struct_end();
+    #[test]
+    fn optional_fields_have_a_fallback_before_cpp17() {
+        let source = "#[main] struct Cli { #[long] name: Optional<string> }";
+        let generated = generate(source);
+        assert!(generated.contains("#include <optional>"));
+        assert!(generated.contains("std::optional<std::string> name;"));
+        assert!(!generated.contains("cli_optional"));
+
+        let mut options = GenOptions::default();
+        options.cpp_std = CppStandard::Cpp14;
+        let generated = generate_with_options(source, &options);
+        assert!(!generated.contains("#include <optional>"));
+        assert!(generated.contains("#include <new>"));
+        assert!(!generated.contains("std::vector"));
+        assert!(generated.contains("class cli_optional {"));
+        assert!(generated.contains("cli_optional<std::string> name;"));
+        assert!(!generated.contains("std::optional"));
     }
 
-    source_builder.write_header_guard_end();
+    #[test]
+    fn secret_values_failing_their_pattern_are_not_bound() {
+        let generated =
+            generate(r#"#[main] struct Cli { #[long, regex = "[a-z]+"] name: string }"#);
+        assert!(generated.contains("const char* arg_value = arg_res.c_str();"));
+
+        let generated =
+            generate(r#"#[main] struct Cli { #[long, secret, regex = "[a-z]+"] token: string }"#);
+        assert!(generated
+            .contains(r#"printf("Value of option '%s' does not match the pattern [a-z]+", arg);"#));
+        assert!(!generated.contains("const char* arg_value = arg_res.c_str();"));
+    }
+
+    #[test]
+    fn directories_are_only_validated_when_asked() {
+        let source = "#[main] struct Cli { #[long, directory] dir: string }";
+        assert!(!generate(source).contains("is_directory"));
 
-    source_builder.result()
+        let mut options = GenOptions::default();
+        options.validate_directory = true;
+        let generated = generate_with_options(source, &options);
+        assert!(generated.contains("#include <filesystem>"));
+        assert!(generated.contains("if (!std::filesystem::is_directory(arg_res, arg_error)) {"));
+        assert!(generated.contains(
+            r#"printf("Value '%s' of option '%s' is not an existing directory", arg_value, arg);"#
+        ));
+
+        options.cpp_std = CppStandard::Cpp14;
+        let spec = Parser::new(source).parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let backend = CppBackend {
+            output: "cli.h",
+            provenance: None,
+            split_implementation: false,
+        };
+        let errors = backend.generate(&spec, &metadata, &options).unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "Directories are only validated from C++17, which has std::filesystem"
+        );
+    }
 }
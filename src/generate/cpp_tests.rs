@@ -0,0 +1,630 @@
+use crate::generate::cpp::{
+    banner_lines, field_option_name, field_type_to_cpp_type, struct_options,
+};
+use crate::generate::{left_pad, GenOptions, Provenance};
+use crate::types::{AttributeType, Field, FieldType, NumericBound, Spec, SpecMetadata, Struct};
+use std::fmt::Write;
+
+/// The values a scenario gives a field, through the fields flattening it into the parsed struct.
+#[derive(Debug)]
+pub(crate) struct Given<'s> {
+    pub path: Vec<&'s Field>,
+    pub values: Vec<String>,
+}
+
+/// What parsing the arguments of a scenario is expected to do.
+#[derive(Debug)]
+pub(crate) enum Outcome<'s> {
+    /// Parsing succeeds, with the fields holding the values they were given.
+    Parses(Vec<Given<'s>>),
+    /// Parsing reports an error and exits.
+    Fails,
+    /// Parsing prints the help and exits successfully, without returning.
+    ShowsHelp,
+}
+
+/// A command line to parse and what parsing it is expected to do.
+#[derive(Debug)]
+pub(crate) struct Scenario<'s> {
+    /// Names the test, e.g. `missing_input_is_rejected`.
+    pub name: String,
+    /// The arguments, without the name of the program.
+    pub args: Vec<String>,
+    pub outcome: Outcome<'s>,
+}
+
+/// The fields of `fields` given by an option, with the fields flattening them. Only plain
+/// flattened structs are looked into, as optional and repeated groups have requirements of their
+/// own.
+fn collect_option_fields<'s>(
+    fields: &'s [Field],
+    spec_metadata: &SpecMetadata<'s>,
+    parents: &mut Vec<&'s Field>,
+    option_fields: &mut Vec<Vec<&'s Field>>,
+) {
+    for field in fields {
+        match &field.ty {
+            FieldType::Struct(name) if field.has_attribute(AttributeType::Flatten) => {
+                parents.push(field);
+                collect_option_fields(
+                    &spec_metadata.identifier_to_struct[name.as_str()].fields,
+                    spec_metadata,
+                    parents,
+                    option_fields,
+                );
+                parents.pop();
+            }
+            _ if matches!(field.ty.value_type(), FieldType::Struct(_)) => {}
+            _ if field.short_value().is_some() || field.long_value().is_some() => {
+                option_fields.push([parents.as_slice(), &[field]].concat());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A value the field accepts, within its `min`/`max`. There is none when the value is converted
+/// or validated by code the generator doesn't know about.
+fn sample_value(field: &Field) -> Option<String> {
+    if field.parse_fn().is_some() || field.has_attribute(AttributeType::Regex) {
+        return None;
+    }
+
+    let field_type = field.ty.value_type();
+    let sample = match field_type {
+        FieldType::String => return Some(String::from("value")),
+        FieldType::Bool => return Some(String::from("true")),
+        FieldType::F32 | FieldType::F64 => "1.5",
+        FieldType::Struct(_) => return None,
+        _ => "7",
+    };
+
+    let sample = match (
+        field.attribute_value(AttributeType::Min),
+        field.attribute_value(AttributeType::Max),
+    ) {
+        (Some(min), _) => min,
+        (None, Some(max)) if field_type.parse_bound(max) < field_type.parse_bound(sample) => max,
+        _ => sample,
+    };
+
+    Some(sample.to_string())
+}
+
+/// A value that doesn't fit the numeric type of the field.
+fn out_of_range_value(field: &Field) -> Option<String> {
+    if field.parse_fn().is_some() {
+        return None;
+    }
+
+    match field.ty.value_type().bounds()? {
+        (_, NumericBound::Integer(max)) => Some((max + 1).to_string()),
+        (_, NumericBound::Float(_)) => Some(String::from("1e999")),
+    }
+}
+
+/// The arguments giving `value` to the option of `field`. Bools are set by the option alone,
+/// unless their value is explicit.
+fn option_args(strukt: &Struct, field: &Field, value: &str) -> Vec<String> {
+    let option = field_option_name(strukt, field);
+    if matches!(field.ty.value_type(), FieldType::Bool) && !field.is_explicit_bool() {
+        vec![option]
+    } else {
+        vec![option, value.to_string()]
+    }
+}
+
+fn given_args(strukt: &Struct, given: &[Given]) -> Vec<String> {
+    given
+        .iter()
+        .flat_map(|given| {
+            let field = given.path.last().unwrap();
+            given
+                .values
+                .iter()
+                .flat_map(|value| option_args(strukt, field, value))
+        })
+        .collect()
+}
+
+fn same_path(lhs: &[&Field], rhs: &[&Field]) -> bool {
+    lhs.len() == rhs.len()
+        && lhs
+            .iter()
+            .zip(rhs)
+            .all(|(lhs, rhs)| std::ptr::eq(*lhs, *rhs))
+}
+
+fn scenario_name(path: &[&Field]) -> String {
+    path.iter()
+        .map(|field| field.name.as_str())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// An option none of the options of the struct starts with, so that it isn't taken for an
+/// abbreviation either.
+fn unknown_option(strukt: &Struct, spec_metadata: &SpecMetadata) -> String {
+    let options = struct_options(strukt, spec_metadata);
+    let mut unknown = strukt.option_syntax().long_option("unknown");
+    while options
+        .iter()
+        .any(|(option, _)| option.to_lowercase().starts_with(&unknown))
+    {
+        unknown.push_str("-option");
+    }
+
+    unknown
+}
+
+/// Plans the command lines the generated tests parse with `strukt`. The mandatory options are
+/// given along with every other scenario, which all derive from them:
+/// - every mandatory option given, and every one of them left out
+/// - the help asked for with all but the first mandatory option
+/// - every numeric option given a value out of the range of its type
+/// - every `Vec` option repeated
+/// - an unknown option
+///
+/// Only the help and the unknown option are planned when a mandatory field can't be given a value,
+/// e.g. because it is validated by a `regex` or converted by a `parse_fn`.
+pub(crate) fn plan_scenarios<'s>(
+    strukt: &'s Struct,
+    spec_metadata: &SpecMetadata<'s>,
+) -> Vec<Scenario<'s>> {
+    let mut option_fields = Vec::new();
+    collect_option_fields(
+        &strukt.fields,
+        spec_metadata,
+        &mut Vec::new(),
+        &mut option_fields,
+    );
+    // The config file can't be given a path that exists.
+    option_fields.retain(|path| {
+        !strukt
+            .config_file_field()
+            .is_some_and(|config_file_field| std::ptr::eq(*path.last().unwrap(), config_file_field))
+    });
+    // Nor can a field whose option is taken by a later field, see `long_short_conflict`.
+    option_fields.retain(|path| {
+        let field = path.last().unwrap();
+        spec_metadata
+            .struct_metadata(strukt)
+            .accepts(field, &field_option_name(strukt, field))
+    });
+
+    // Fields with `min_occurs` aren't mandatory, but have to be given that many times.
+    let required_fields = strukt
+        .fields
+        .iter()
+        .filter(|field| field.has_attribute(AttributeType::MinOccurs) || field.is_required())
+        .collect::<Vec<_>>();
+    let required = required_fields
+        .iter()
+        .map(|field| {
+            // A field of its group would have to be given along with it.
+            if field.requires_group().is_some() {
+                return None;
+            }
+            let path = option_fields
+                .iter()
+                .find(|path| path.len() == 1 && std::ptr::eq(path[0], *field))?;
+            let count = match field.ty {
+                FieldType::Vec(_) => field.min_occurs().unwrap_or(1).max(1),
+                _ => 1,
+            };
+            Some(Given {
+                path: path.clone(),
+                values: vec![sample_value(field)?; count],
+            })
+        })
+        .collect::<Option<Vec<_>>>();
+
+    let mut scenarios = Vec::new();
+    let unknown = Scenario {
+        name: String::from("unknown_option_is_rejected"),
+        args: [
+            given_args(strukt, required.as_deref().unwrap_or_default()),
+            vec![unknown_option(strukt, spec_metadata)],
+        ]
+        .concat(),
+        outcome: Outcome::Fails,
+    };
+    // The help is shown before the mandatory options are checked, so it doesn't need them.
+    let help = Scenario {
+        name: String::from("help_is_shown_without_the_mandatory_options"),
+        args: [
+            given_args(
+                strukt,
+                required
+                    .as_deref()
+                    .and_then(|required| required.get(1..))
+                    .unwrap_or_default(),
+            ),
+            vec![strukt.option_syntax().long_option("help")],
+        ]
+        .concat(),
+        outcome: Outcome::ShowsHelp,
+    };
+    let Some(required) = required else {
+        scenarios.push(help);
+        scenarios.push(unknown);
+        return scenarios;
+    };
+
+    // The required values without the ones of `path`, which the scenario gives itself.
+    let required_without = |path: &[&Field]| {
+        required
+            .iter()
+            .filter(|given| !same_path(&given.path, path))
+            .map(|given| Given {
+                path: given.path.clone(),
+                values: given.values.clone(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    scenarios.push(Scenario {
+        name: String::from("mandatory_options_are_accepted"),
+        args: given_args(strukt, &required),
+        outcome: Outcome::Parses(required_without(&[])),
+    });
+
+    for given in &required {
+        // The environment could give the values of the field instead.
+        if strukt.field_env(given.path[0]).is_some()
+            || given.path[0].append_env().is_some()
+            || given.path[0].env_list().is_some()
+        {
+            continue;
+        }
+        scenarios.push(Scenario {
+            name: format!("missing_{}_is_rejected", scenario_name(&given.path)),
+            args: given_args(strukt, &required_without(&given.path)),
+            outcome: Outcome::Fails,
+        });
+    }
+    scenarios.push(help);
+
+    for path in &option_fields {
+        let field = path.last().unwrap();
+        let Some(value) = out_of_range_value(field) else {
+            continue;
+        };
+        scenarios.push(Scenario {
+            name: format!("out_of_range_{}_is_rejected", scenario_name(path)),
+            args: [
+                given_args(strukt, &required_without(path)),
+                option_args(strukt, field, &value),
+            ]
+            .concat(),
+            outcome: Outcome::Fails,
+        });
+    }
+
+    for path in &option_fields {
+        let field = path.last().unwrap();
+        // One more than needed, so that the values aren't only the mandatory ones.
+        let count = field.min_occurs().unwrap_or(1) + 1;
+        if !matches!(field.ty, FieldType::Vec(_))
+            || field
+                .max_occurs()
+                .is_some_and(|max_occurs| max_occurs < count)
+        {
+            continue;
+        }
+        let Some(value) = sample_value(field) else {
+            continue;
+        };
+
+        let mut given = required_without(path);
+        given.push(Given {
+            path: path.clone(),
+            values: vec![value; count],
+        });
+        scenarios.push(Scenario {
+            name: format!("repeated_{}_are_collected", scenario_name(path)),
+            args: given_args(strukt, &given),
+            outcome: Outcome::Parses(given),
+        });
+    }
+
+    scenarios.push(unknown);
+    scenarios
+}
+
+/// The C++ literal of a value given to a field of type `field_type`.
+fn value_literal(field_type: &FieldType, value: &str) -> String {
+    match field_type {
+        FieldType::String => format!("\"{value}\""),
+        FieldType::Bool => value.to_string(),
+        _ => format!(
+            "static_cast<{}>({value})",
+            field_type_to_cpp_type(field_type)
+        ),
+    }
+}
+
+/// The asserts checking the values a field was given, e.g. `assert(res.port == 7);`.
+fn given_checks(given: &Given, accessors: bool) -> Vec<String> {
+    let path = given
+        .path
+        .iter()
+        .map(|field| {
+            if accessors {
+                format!("{}()", field.name)
+            } else {
+                field.name.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    let field = given.path.last().unwrap();
+    let literal = |value: &str| value_literal(field.ty.value_type(), value);
+
+    match &field.ty {
+        FieldType::Vec(_) => {
+            let mut checks = vec![format!(
+                "assert(res.{path}.size() == {});",
+                given.values.len()
+            )];
+            checks.extend(given.values.iter().enumerate().map(|(index, value)| {
+                format!("assert(res.{path}[{index}] == {});", literal(value))
+            }));
+            checks
+        }
+        FieldType::Optional(_) => vec![format!(
+            "assert(res.{path}.has_value() && res.{path}.value() == {});",
+            literal(&given.values[0])
+        )],
+        _ => vec![format!(
+            "assert(res.{path} == {});",
+            literal(&given.values[0])
+        )],
+    }
+}
+
+/// Parses the arguments in a child process, whose exit status tells whether they are valid. The
+/// generated parser exits on errors, so they couldn't be tested in the test process itself.
+/// `@STRUCT@` is substituted with the parsed struct.
+const PARSES_FUNCTION: &str = r#"static bool parses(int argc, const char* args[]) {
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        if (freopen("/dev/null", "w", stdout) == nullptr) {
+            _exit(2);
+        }
+        @STRUCT@::parse(argc, const_cast<char**>(args));
+        _exit(0);
+    }
+
+    int status = 0;
+    waitpid(pid, &status, 0);
+    return WIFEXITED(status) && WEXITSTATUS(status) == 0;
+}
+"#;
+
+/// Generates a C++ program testing the parser of the main struct with the scenarios of
+/// `plan_scenarios`, using nothing but `assert`. It includes `header`, the generated code, and
+/// needs a POSIX system to run the scenarios in child processes.
+pub fn generate_cpp_tests<'s>(
+    spec: &'s Spec,
+    spec_metadata: &SpecMetadata<'s>,
+    options: &GenOptions,
+    header: &str,
+    provenance: Option<&Provenance>,
+) -> String {
+    let strukt = spec
+        .structs
+        .iter()
+        .find(|strukt| strukt.has_attribute(AttributeType::Main))
+        .unwrap_or_else(|| &spec.structs[0]);
+    let scenarios = plan_scenarios(strukt, spec_metadata);
+    let mut buffer = String::new();
+    let mut line = |levels: usize, text: &str| {
+        if !text.is_empty() {
+            left_pad(options.indent, levels, &mut buffer).unwrap();
+        }
+        writeln!(buffer, "{text}").unwrap();
+    };
+
+    if let Some(provenance) = provenance {
+        for banner_line in banner_lines(provenance) {
+            line(0, &banner_line);
+        }
+        line(0, "");
+    }
+
+    line(
+        0,
+        "// The scenarios are checked with asserts, which must not be compiled out.",
+    );
+    line(0, "#undef NDEBUG");
+    for include in [
+        "<cassert>",
+        "<cstdio>",
+        "<cstdlib>",
+        "<sys/wait.h>",
+        "<unistd.h>",
+    ] {
+        line(0, &format!("#include {include}"));
+    }
+    line(0, "");
+    line(0, &format!("#include \"{header}\""));
+    line(0, "");
+
+    for parses_line in PARSES_FUNCTION.replace("@STRUCT@", &strukt.name).lines() {
+        let code = parses_line.trim_start();
+        line((parses_line.len() - code.len()) / 4, code);
+    }
+
+    // The main struct skips the name of the program.
+    let program = strukt
+        .has_attribute(AttributeType::Main)
+        .then(|| format!("\"{}\"", strukt.name.to_lowercase()));
+    for scenario in &scenarios {
+        line(0, "");
+        line(0, &format!("static void test_{}() {{", scenario.name));
+        let args = program
+            .iter()
+            .cloned()
+            .chain(scenario.args.iter().map(|arg| format!("\"{arg}\"")))
+            .collect::<Vec<_>>();
+        line(
+            1,
+            &format!("const char* args[] = {{ {} }};", args.join(", ")),
+        );
+        line(1, "int argc = sizeof(args) / sizeof(args[0]);");
+
+        match &scenario.outcome {
+            Outcome::Fails => line(1, "assert(!parses(argc, args));"),
+            Outcome::ShowsHelp => line(1, "assert(parses(argc, args));"),
+            Outcome::Parses(given) => {
+                line(1, "assert(parses(argc, args));");
+                line(0, "");
+                line(
+                    1,
+                    &format!(
+                        "{0} res = {0}::parse(argc, const_cast<char**>(args));",
+                        strukt.name
+                    ),
+                );
+                for given in given {
+                    for check in given_checks(given, options.accessors) {
+                        line(1, &check);
+                    }
+                }
+            }
+        }
+        line(0, "}");
+    }
+
+    line(0, "");
+    line(0, "int main() {");
+    for scenario in &scenarios {
+        line(1, &format!("test_{}();", scenario.name));
+    }
+    line(
+        1,
+        &format!("printf(\"All {} scenarios passed\\n\");", scenarios.len()),
+    );
+    line(1, "return 0;");
+    line(0, "}");
+
+    match options.line_ending.as_str() {
+        "\n" => buffer,
+        line_ending => buffer.replace('\n', line_ending),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    fn scenarios(source: &str) -> Vec<(String, Vec<String>, bool)> {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let strukt = spec.structs.last().unwrap();
+
+        plan_scenarios(strukt, &metadata)
+            .into_iter()
+            .map(|scenario| {
+                let parses = !matches!(scenario.outcome, Outcome::Fails);
+                (scenario.name, scenario.args, parses)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scenarios_derive_from_the_mandatory_options() {
+        let source = r#"
+            struct Logging {
+                #[short, long]
+                verbose: bool,
+            }
+
+            #[main]
+            struct Cli {
+                #[long, min = 1024]
+                port: u16,
+                #[long, min_occurs = 1]
+                tags: Vec<string>,
+                #[flatten]
+                logging: Logging,
+            }
+        "#;
+        let strings = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            scenarios(source),
+            [
+                (
+                    String::from("mandatory_options_are_accepted"),
+                    strings(&["--port", "1024", "--tags", "value"]),
+                    true
+                ),
+                (
+                    String::from("missing_port_is_rejected"),
+                    strings(&["--tags", "value"]),
+                    false
+                ),
+                (
+                    String::from("missing_tags_is_rejected"),
+                    strings(&["--port", "1024"]),
+                    false
+                ),
+                (
+                    String::from("help_is_shown_without_the_mandatory_options"),
+                    strings(&["--tags", "value", "--help"]),
+                    true
+                ),
+                (
+                    String::from("out_of_range_port_is_rejected"),
+                    strings(&["--tags", "value", "--port", "65536"]),
+                    false
+                ),
+                (
+                    String::from("repeated_tags_are_collected"),
+                    strings(&["--port", "1024", "--tags", "value", "--tags", "value"]),
+                    true
+                ),
+                (
+                    String::from("unknown_option_is_rejected"),
+                    strings(&["--port", "1024", "--tags", "value", "--unknown"]),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_unknown_options_are_tested_without_mandatory_values() {
+        let source = r#"
+            #[main]
+            struct Cli {
+                #[long, regex = "^[a-z]+$"]
+                name: string,
+                #[long]
+                unknown_flag: bool,
+            }
+        "#;
+
+        assert_eq!(
+            scenarios(source),
+            [
+                (
+                    String::from("help_is_shown_without_the_mandatory_options"),
+                    vec![String::from("--help")],
+                    true
+                ),
+                (
+                    String::from("unknown_option_is_rejected"),
+                    vec![String::from("--unknown-option")],
+                    false
+                )
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::generate::cpp::banner_lines;
+use crate::generate::{left_pad, GenOptions, Provenance};
+use crate::types::{AttributeType, Field, FieldType, Spec, SpecMetadata, Struct};
+use std::fmt::Write;
+
+/// The wrappers around the struct a field refers to, outermost first, e.g. `["Vec"]`.
+fn type_wrappers(field_type: &FieldType) -> Vec<&'static str> {
+    match field_type {
+        FieldType::Vec(inner) => [vec!["Vec"], type_wrappers(inner)].concat(),
+        FieldType::Optional(inner) => [vec!["Optional"], type_wrappers(inner)].concat(),
+        _ => Vec::new(),
+    }
+}
+
+fn node_attributes(strukt: &Struct) -> &'static str {
+    if strukt.has_attribute(AttributeType::Main) {
+        " [style=bold]"
+    } else if strukt.has_attribute(AttributeType::SubCommand) {
+        " [style=rounded]"
+    } else {
+        ""
+    }
+}
+
+/// The edge of a struct typed field, labeled with the field and the wrappers of its type.
+/// Flattened fields are drawn dashed.
+fn edge(strukt: &Struct, field: &Field, target: &Struct) -> String {
+    let mut label = field.name.clone();
+    let wrappers = type_wrappers(&field.ty);
+    if !wrappers.is_empty() {
+        write!(label, " ({})", wrappers.join(", ")).unwrap();
+    }
+
+    let mut edge = format!(r#""{}" -> "{}" [label="{label}""#, strukt.name, target.name);
+    if field.has_attribute(AttributeType::Flatten) {
+        edge.push_str(", style=dashed");
+    }
+    edge.push_str("];");
+
+    edge
+}
+
+/// Draws the structs of the spec as a Graphviz digraph, with an edge for every struct typed field.
+/// Nodes and edges follow the order of the spec, so that the output diffs well.
+pub fn generate_dot<'s>(
+    spec: &'s Spec,
+    spec_metadata: &SpecMetadata<'s>,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+) -> String {
+    let mut buffer = String::new();
+
+    if let Some(provenance) = provenance {
+        for line in banner_lines(provenance) {
+            writeln!(buffer, "{line}").unwrap();
+        }
+        buffer.push('\n');
+    }
+
+    let mut lines = vec![String::from("node [shape=box];"), String::new()];
+    lines.extend(
+        spec.structs
+            .iter()
+            .map(|strukt| format!(r#""{}"{};"#, strukt.name, node_attributes(strukt))),
+    );
+
+    let edges = spec
+        .structs
+        .iter()
+        .flat_map(|strukt| {
+            strukt.fields.iter().filter_map(move |field| {
+                let FieldType::Struct(name) = field.ty.value_type() else {
+                    return None;
+                };
+                let target = spec_metadata.identifier_to_struct.get(name.as_str())?;
+                Some(edge(strukt, field, target))
+            })
+        })
+        .collect::<Vec<_>>();
+    if !edges.is_empty() {
+        lines.push(String::new());
+        lines.extend(edges);
+    }
+
+    buffer.push_str("digraph spec {\n");
+    for line in lines {
+        if !line.is_empty() {
+            left_pad(options.indent, 1, &mut buffer).unwrap();
+        }
+        writeln!(buffer, "{line}").unwrap();
+    }
+    buffer.push_str("}\n");
+
+    match options.line_ending.as_str() {
+        "\n" => buffer,
+        line_ending => buffer.replace('\n', line_ending),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    fn generate(source: &str) -> String {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        generate_dot(&spec, &metadata, &GenOptions::default(), None)
+    }
+
+    #[test]
+    fn golden_dot() {
+        let source = r#"
+            struct Logging {
+                #[short, long]
+                verbose: bool,
+            }
+
+            struct Server {
+                #[long]
+                host: string,
+            }
+
+            #[subcommand]
+            struct Status {
+                #[flatten]
+                logging: Logging,
+            }
+
+            #[main]
+            struct Cli {
+                #[flatten]
+                logging: Logging,
+                #[flatten]
+                servers: Vec<Server>,
+                #[flatten]
+                status: Optional<Status>,
+            }
+        "#;
+
+        assert_eq!(generate(source), include_str!("golden/structs.dot"));
+    }
+
+    #[test]
+    fn structs_without_struct_fields_have_no_edges() {
+        assert_eq!(
+            generate("#[main] struct Cli { #[long] port: u16 }"),
+            "digraph spec {\n    node [shape=box];\n\n    \"Cli\" [style=bold];\n}\n"
+        );
+    }
+}
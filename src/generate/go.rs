@@ -0,0 +1,1153 @@
+use crate::diagnostic::Diagnostic;
+use crate::generate::cpp::{
+    deprecation_warning, field_option_name, help_lines, provenance_lines, struct_options,
+    MandatoryFields,
+};
+use crate::generate::{left_pad, Backend, GenOptions, Indent, OutputFile, Provenance};
+use crate::semantic::check_go_support;
+use crate::types::{AttributeType, Field, FieldType, NumericBound, Spec, SpecMetadata, Struct};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// The identifier Go exports a name of the spec under, e.g. `InputFile` for `input_file`.
+fn exported_name(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first| {
+                format!("{}{}", first.to_ascii_uppercase(), chars.as_str())
+            })
+        })
+        .collect()
+}
+
+fn field_type_to_go_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::String => "string".to_string(),
+        FieldType::I16 => "int16".to_string(),
+        FieldType::U16 => "uint16".to_string(),
+        FieldType::I32 => "int32".to_string(),
+        FieldType::U32 => "uint32".to_string(),
+        FieldType::I64 => "int64".to_string(),
+        FieldType::U64 => "uint64".to_string(),
+        FieldType::F32 => "float32".to_string(),
+        FieldType::F64 => "float64".to_string(),
+        FieldType::Bool => "bool".to_string(),
+        FieldType::Vec(inner) => format!("[]{}", field_type_to_go_type(inner)),
+        FieldType::Optional(inner) => format!("*{}", field_type_to_go_type(inner)),
+        FieldType::Struct(strukt) => exported_name(strukt),
+        FieldType::Error => unreachable!(),
+    }
+}
+
+/// The function parsing a struct: `Parse` for the main struct and `Parse<Struct>` for the others.
+fn parse_function_name(strukt: &Struct) -> String {
+    if strukt.has_attribute(AttributeType::Main) {
+        String::from("Parse")
+    } else {
+        format!("Parse{}", exported_name(&strukt.name))
+    }
+}
+
+/// The function printing the help of a struct, named after the same scheme as the parse function.
+fn help_function_name(strukt: &Struct) -> String {
+    if strukt.has_attribute(AttributeType::Main) {
+        String::from("PrintHelp")
+    } else {
+        format!("Print{}Help", exported_name(&strukt.name))
+    }
+}
+
+fn is_option_function_name(strukt: &Struct) -> String {
+    format!("is{}Option", exported_name(&strukt.name))
+}
+
+/// A `min`/`max` value as a Go constant. Integers are written back from their value and floats
+/// get the lowercase exponent gofmt normalizes them to.
+fn go_bound_literal(field_type: &FieldType, value: &str) -> String {
+    match field_type.parse_bound(value) {
+        Some(NumericBound::Integer(value)) => value.to_string(),
+        _ => value.replace('E', "e"),
+    }
+}
+
+/// The imports and helpers of the file, which are only written when the parsing code uses them
+/// since Go rejects unused imports.
+#[derive(Debug, Default, Clone, Copy)]
+struct UsedHelpers {
+    regexp: bool,
+    strconv: bool,
+    strings: bool,
+    fmt: bool,
+    os: bool,
+    parse_bool: bool,
+    has_prefix_fold: bool,
+}
+
+/// A struct flattened through an `Optional` field, see `cpp::OptionalGroup`. In Go the flattened
+/// struct is a pointer, allocated when the first of its fields is given.
+struct OptionalGroup<'s> {
+    /// The pointers of the enclosing groups, from the outermost to this one.
+    paths: Vec<String>,
+    mandatory_fields: MandatoryFields<'s>,
+}
+
+/// The prefix of the variables tracking the mandatory fields of an optional group, e.g. `seenNet`
+/// for `res.Net` and `seenNetProxy` for `res.Net.Proxy`.
+fn optional_group_prefix(path: &str) -> String {
+    format!("seen{}", path.trim_start_matches("res.").replace('.', ""))
+}
+
+/// A case of the `switch` that matches the arguments against the options.
+struct MatchArm {
+    condition: String,
+    body: String,
+}
+
+/// Builds the Go source. The output is formatted the way gofmt would, so indentation is always
+/// made of tabs and `GenOptions::indent` and `GenOptions::line_ending` are ignored.
+#[derive(Debug, Default)]
+struct GoSourceBuilder {
+    buffer: String,
+    indentation: usize,
+    options: GenOptions,
+    helpers: UsedHelpers,
+    /// The names and expressions of the compiled regex patterns, declared at package level.
+    patterns: Vec<(String, String)>,
+}
+
+macro_rules! go_source_builder_writeln {
+    ($self:expr) => {{
+        writeln!($self.buffer).unwrap();
+    }};
+    ($self:expr, $($arg:tt)*) => {{
+        left_pad(Indent::Tabs, $self.indentation, &mut $self.buffer).unwrap();
+        writeln!($self.buffer, $($arg)*).unwrap();
+    }};
+}
+
+impl GoSourceBuilder {
+    pub fn new(options: GenOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn push_indentation_level(&mut self) {
+        self.indentation += 1;
+    }
+
+    #[inline]
+    pub fn pop_indentation_level(&mut self) {
+        self.indentation = self.indentation.saturating_sub(1);
+    }
+
+    /// Runs `write` against an empty buffer one indentation level deeper and returns what it
+    /// wrote, so that blocks can be assembled before being written out.
+    fn capture(&mut self, write: impl FnOnce(&mut Self)) -> String {
+        let previous_buffer = std::mem::take(&mut self.buffer);
+        self.push_indentation_level();
+        write(self);
+        self.pop_indentation_level();
+        std::mem::replace(&mut self.buffer, previous_buffer)
+    }
+
+    /// Writes `body` as a block closing with `}`, the opening line having been written already.
+    fn write_block(&mut self, body: impl FnOnce(&mut Self)) {
+        self.push_indentation_level();
+        body(self);
+        self.pop_indentation_level();
+        go_source_builder_writeln!(self, "}}");
+    }
+
+    /// Goes through `fmt.Errorf` when there are arguments to format, and `errors.New` otherwise.
+    fn write_error(&mut self, format: &str, args: &[&str]) {
+        if args.is_empty() {
+            go_source_builder_writeln!(
+                self,
+                r#"return nil, errors.New("{}")"#,
+                format.replace("%%", "%")
+            );
+        } else {
+            self.helpers.fmt = true;
+            go_source_builder_writeln!(
+                self,
+                r#"return nil, fmt.Errorf("{format}", {})"#,
+                args.join(", ")
+            );
+        }
+    }
+
+    /// Reports an invalid `value` for the option `arg`. The value of secret fields is never echoed
+    /// back, so that it doesn't end up in terminals or logs.
+    fn write_invalid_value_error(&mut self, reason: &str, secret: bool) {
+        if secret {
+            self.write_error(&format!("Value of option '%s' {reason}"), &["arg"]);
+        } else {
+            self.write_error(
+                &format!("Value '%s' of option '%s' {reason}"),
+                &["value", "arg"],
+            );
+        }
+    }
+
+    /// Warns on stderr that the option of a deprecated field was given, like the C++ backend.
+    fn write_deprecation_warning(&mut self, field: &Field) {
+        self.helpers.fmt = true;
+        self.helpers.os = true;
+
+        let (warning, removal) = deprecation_warning(field);
+        go_source_builder_writeln!(self, r#"fmt.Fprintf(os.Stderr, "{warning}\n", arg)"#);
+        if let Some(removal) = removal {
+            go_source_builder_writeln!(self, r#"fmt.Fprintf(os.Stderr, "{removal}\n")"#);
+        }
+    }
+
+    /// gofmt aligns the types of consecutive fields, and a comment line starts a new run of them.
+    fn write_struct_definition(&mut self, strukt: &Struct) {
+        let struct_name = exported_name(&strukt.name);
+        if strukt.is_doc_hidden() {
+            go_source_builder_writeln!(
+                self,
+                "// {struct_name} holds options flattened into other structs."
+            );
+        } else {
+            go_source_builder_writeln!(
+                self,
+                "// {struct_name} holds the options parsed by {}.",
+                parse_function_name(strukt)
+            );
+        }
+        go_source_builder_writeln!(self, "type {struct_name} struct {{");
+        self.push_indentation_level();
+
+        let mut runs: Vec<Vec<&Field>> = Vec::new();
+        for field in &strukt.fields {
+            match runs.last_mut() {
+                Some(run) if field.doc().is_none() => run.push(field),
+                _ => runs.push(vec![field]),
+            }
+        }
+
+        for run in runs {
+            let width = run
+                .iter()
+                .map(|field| exported_name(&field.name).len())
+                .max()
+                .unwrap_or_default();
+            for field in run {
+                if let Some(doc) = field.doc() {
+                    // The doc is a string literal, whose escaped quotes read better as quotes.
+                    let doc = doc.trim().replace("\\\"", "\"");
+                    if doc.is_empty() {
+                        go_source_builder_writeln!(self, "//");
+                    } else {
+                        go_source_builder_writeln!(self, "// {doc}");
+                    }
+                }
+                go_source_builder_writeln!(
+                    self,
+                    "{:width$} {}",
+                    exported_name(&field.name),
+                    field_type_to_go_type(&field.ty)
+                );
+            }
+        }
+
+        self.pop_indentation_level();
+        go_source_builder_writeln!(self, "}}\n");
+    }
+
+    fn write_helper_functions(&mut self) {
+        let helpers = self.helpers;
+
+        if helpers.parse_bool {
+            go_source_builder_writeln!(self, "func parseBool(value string) (bool, bool) {{");
+            self.push_indentation_level();
+            go_source_builder_writeln!(self, "switch strings.ToLower(value) {{");
+            go_source_builder_writeln!(self, r#"case "true", "1", "yes":"#);
+            self.push_indentation_level();
+            go_source_builder_writeln!(self, "return true, true");
+            self.pop_indentation_level();
+            go_source_builder_writeln!(self, r#"case "false", "0", "no":"#);
+            self.write_block(|this| go_source_builder_writeln!(this, "return false, true"));
+            go_source_builder_writeln!(self, "return false, false");
+            self.pop_indentation_level();
+            go_source_builder_writeln!(self, "}}\n");
+        }
+
+        if helpers.has_prefix_fold {
+            go_source_builder_writeln!(self, "func hasPrefixFold(s, prefix string) bool {{");
+            self.write_block(|this| {
+                go_source_builder_writeln!(
+                    this,
+                    "return len(s) >= len(prefix) && strings.EqualFold(s[:len(prefix)], prefix)"
+                )
+            });
+            go_source_builder_writeln!(self);
+        }
+    }
+
+    /// Checks the parsed value against the `min`/`max` attributes of the field, if any.
+    fn write_range_check(&mut self, field: &Field, field_type: &FieldType, secret: bool) {
+        let min = field.attribute_value(AttributeType::Min);
+        let max = field.attribute_value(AttributeType::Max);
+        let literal = |value: &str| go_bound_literal(field_type, value);
+
+        let (condition, reason) = match (min, max) {
+            (Some(min), Some(max)) => (
+                format!("parsed < {} || parsed > {}", literal(min), literal(max)),
+                format!("is not between {min} and {max}"),
+            ),
+            (Some(min), None) => (
+                format!("parsed < {}", literal(min)),
+                format!("is less than {min}"),
+            ),
+            (None, Some(max)) => (
+                format!("parsed > {}", literal(max)),
+                format!("is greater than {max}"),
+            ),
+            (None, None) => return,
+        };
+
+        go_source_builder_writeln!(self, "if {condition} {{");
+        self.write_block(|this| this.write_invalid_value_error(&reason, secret));
+    }
+
+    /// Numbers go through `strconv`, which is told the size of the field type so that it reports
+    /// values out of its range. The result is widened to 64 bits and narrowed back afterwards.
+    fn write_parse_numeric_field(&mut self, field: &Field, field_type: &FieldType) -> String {
+        self.helpers.strconv = true;
+        let secret = field.has_attribute(AttributeType::Secret);
+
+        let (conversion, bits, is_integer) = match field_type {
+            FieldType::I16 => ("ParseInt(value, 10, ", 16, true),
+            FieldType::I32 => ("ParseInt(value, 10, ", 32, true),
+            FieldType::I64 => ("ParseInt(value, 10, ", 64, true),
+            FieldType::U16 => ("ParseUint(value, 10, ", 16, true),
+            FieldType::U32 => ("ParseUint(value, 10, ", 32, true),
+            FieldType::U64 => ("ParseUint(value, 10, ", 64, true),
+            FieldType::F32 => ("ParseFloat(value, ", 32, false),
+            FieldType::F64 => ("ParseFloat(value, ", 64, false),
+            _ => unreachable!(),
+        };
+        let (out_of_range, invalid) = if is_integer {
+            ("out of range for integer type", "is not a valid integer")
+        } else {
+            (
+                "out of range for floating point type",
+                "is not a valid floating point number",
+            )
+        };
+
+        go_source_builder_writeln!(self, "parsed, err := strconv.{conversion}{bits})");
+        go_source_builder_writeln!(self, "if errors.Is(err, strconv.ErrRange) {{");
+        self.write_block(|this| this.write_invalid_value_error(out_of_range, secret));
+        go_source_builder_writeln!(self, "if err != nil {{");
+        self.write_block(|this| this.write_invalid_value_error(invalid, secret));
+
+        self.write_range_check(field, field_type, secret);
+
+        if bits == 64 {
+            String::from("parsed")
+        } else {
+            format!("{}(parsed)", field_type_to_go_type(field_type))
+        }
+    }
+
+    /// Bool options are set by their mere presence, but an explicit value can also be attached
+    /// (`--flag=false`). Explicit bool fields additionally consume the next argument as value.
+    fn write_parse_bool_field(&mut self, explicit: bool, value_separator: &str) {
+        self.helpers.strings = true;
+        self.helpers.parse_bool = true;
+
+        if explicit {
+            go_source_builder_writeln!(self, "var value string");
+        } else {
+            go_source_builder_writeln!(self, r#"value := "true""#);
+        }
+        go_source_builder_writeln!(
+            self,
+            r#"if _, after, found := strings.Cut(arg, "{value_separator}"); found {{"#
+        );
+        self.push_indentation_level();
+        go_source_builder_writeln!(self, "value = after");
+        self.pop_indentation_level();
+        if explicit {
+            go_source_builder_writeln!(self, "}} else {{");
+            self.push_indentation_level();
+            go_source_builder_writeln!(self, "if len(args) == 0 {{");
+            self.write_block(|this| {
+                this.write_error(
+                    "Expected value for option '%s' but no value was provided",
+                    &["arg"],
+                )
+            });
+            go_source_builder_writeln!(self, "value = args[0]");
+            go_source_builder_writeln!(self, "args = args[1:]");
+            self.pop_indentation_level();
+        }
+        go_source_builder_writeln!(self, "}}");
+
+        go_source_builder_writeln!(self, "parsed, ok := parseBool(value)");
+        go_source_builder_writeln!(self, "if !ok {{");
+        self.write_block(|this| {
+            this.write_error(
+                "Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)",
+                &["value", "arg"],
+            )
+        });
+    }
+
+    /// Validates string values against the `regex` attribute of the field, if any. The pattern is
+    /// compiled once, at package level, and anchored since it has to match the whole value.
+    fn write_regex_check(&mut self, declaring_struct: &str, field: &Field) {
+        if self.options.no_regex_validation {
+            return;
+        }
+        let Some(pattern) = field.attribute_value(AttributeType::Regex) else {
+            return;
+        };
+        self.helpers.regexp = true;
+
+        let pattern_name = format!(
+            "pattern{}{}",
+            exported_name(declaring_struct),
+            exported_name(&field.name)
+        );
+        if !self.patterns.iter().any(|(name, _)| *name == pattern_name) {
+            self.patterns.push((
+                pattern_name.clone(),
+                format!(r#"regexp.MustCompile("^(?:{pattern})$")"#),
+            ));
+        }
+
+        go_source_builder_writeln!(self, "if !{pattern_name}.MatchString(value) {{");
+        self.write_block(|this| {
+            this.write_invalid_value_error(
+                &format!("does not match the pattern {}", pattern.replace('%', "%%")),
+                field.has_attribute(AttributeType::Secret),
+            )
+        });
+    }
+
+    /// Writes the parsing of a single value of `field`, declared by `declaring_struct`, and
+    /// returns the expression of the parsed value.
+    fn write_parse_value(
+        &mut self,
+        strukt: &Struct,
+        declaring_struct: &str,
+        field: &Field,
+        spec_metadata: &SpecMetadata,
+        uses_is_option: &mut bool,
+    ) -> String {
+        let field_type = field.ty.value_type();
+
+        if !matches!(field_type, FieldType::Bool | FieldType::Struct(_)) {
+            if matches!(field_type, FieldType::String) {
+                go_source_builder_writeln!(self, "if len(args) == 0 {{");
+            } else {
+                *uses_is_option = true;
+                go_source_builder_writeln!(
+                    self,
+                    "if len(args) == 0 || {}(args[0]) {{",
+                    is_option_function_name(strukt)
+                );
+            }
+            self.write_block(|this| {
+                this.write_error(
+                    "Expected value for option '%s' but no value was provided",
+                    &["arg"],
+                )
+            });
+            go_source_builder_writeln!(self, "value := args[0]");
+            go_source_builder_writeln!(self, "args = args[1:]");
+        }
+
+        match field_type {
+            FieldType::String => {
+                self.write_regex_check(declaring_struct, field);
+                String::from("value")
+            }
+            FieldType::I16
+            | FieldType::U16
+            | FieldType::I32
+            | FieldType::U32
+            | FieldType::I64
+            | FieldType::U64
+            | FieldType::F32
+            | FieldType::F64 => self.write_parse_numeric_field(field, field_type),
+            FieldType::Bool => {
+                let value_separator = strukt.option_syntax().value_separator;
+                self.write_parse_bool_field(field.is_explicit_bool(), value_separator);
+                String::from("parsed")
+            }
+            // The struct is parsed from all the remaining arguments.
+            FieldType::Struct(struct_name) => {
+                let inner = spec_metadata.identifier_to_struct[struct_name.as_str()];
+                go_source_builder_writeln!(
+                    self,
+                    "parsed, err := {}(args)",
+                    parse_function_name(inner)
+                );
+                go_source_builder_writeln!(self, "if err != nil {{");
+                self.write_block(|this| go_source_builder_writeln!(this, "return nil, err"));
+                go_source_builder_writeln!(self, "args = nil");
+                String::from("*parsed")
+            }
+            FieldType::Vec(_) | FieldType::Optional(_) | FieldType::Error => unreachable!(),
+        }
+    }
+
+    /// Stores the parsed `value` in the field at `destination`. Optional fields point to the
+    /// value, which is copied first when it is not a variable.
+    fn write_assignment(&mut self, field_type: &FieldType, destination: &str, value: &str) {
+        match field_type {
+            FieldType::Vec(_) => {
+                go_source_builder_writeln!(self, "{destination} = append({destination}, {value})");
+            }
+            FieldType::Optional(_) => match value {
+                "*parsed" => go_source_builder_writeln!(self, "{destination} = parsed"),
+                "value" | "parsed" => go_source_builder_writeln!(self, "{destination} = &{value}"),
+                _ => {
+                    go_source_builder_writeln!(self, "converted := {value}");
+                    go_source_builder_writeln!(self, "{destination} = &converted");
+                }
+            },
+            _ => go_source_builder_writeln!(self, "{destination} = {value}"),
+        }
+    }
+
+    /// Collects the cases of the fields, descending into flattened structs. `path` is where the
+    /// fields of `declaring_struct` live (e.g. `res.` or `res.Network.`) and `optional_groups` are
+    /// the pointers and types of the optional groups enclosing them.
+    #[allow(clippy::too_many_arguments)]
+    fn collect_parse_arms(
+        &mut self,
+        strukt: &Struct,
+        declaring_struct: &Struct,
+        spec_metadata: &SpecMetadata,
+        path: &str,
+        optional_groups: &mut Vec<(String, String)>,
+        mandatory_fields: &MandatoryFields,
+        uses_is_option: &mut bool,
+    ) -> Vec<MatchArm> {
+        let syntax = strukt.option_syntax();
+        let mut arms = Vec::new();
+
+        for field in &declaring_struct.fields {
+            let name = exported_name(&field.name);
+
+            if field.has_attribute(AttributeType::Flatten) {
+                let FieldType::Struct(struct_name) = field.ty.value_type() else {
+                    unreachable!()
+                };
+                let flatten_type = spec_metadata.identifier_to_struct[struct_name.as_str()];
+                let inner_path = format!("{path}{name}.");
+
+                if matches!(field.ty, FieldType::Optional(_)) {
+                    let group_mandatory_fields = MandatoryFields::new(
+                        optional_group_prefix(&format!("{path}{name}")),
+                        flatten_type,
+                    );
+
+                    optional_groups.push((format!("{path}{name}"), exported_name(struct_name)));
+                    arms.extend(self.collect_parse_arms(
+                        strukt,
+                        flatten_type,
+                        spec_metadata,
+                        &inner_path,
+                        optional_groups,
+                        &group_mandatory_fields,
+                        uses_is_option,
+                    ));
+                    optional_groups.pop();
+                } else {
+                    arms.extend(self.collect_parse_arms(
+                        strukt,
+                        flatten_type,
+                        spec_metadata,
+                        &inner_path,
+                        optional_groups,
+                        mandatory_fields,
+                        uses_is_option,
+                    ));
+                }
+                continue;
+            }
+
+            let options = spec_metadata
+                .struct_metadata(strukt)
+                .options
+                .iter()
+                .filter(|option| std::ptr::eq(option.field, field))
+                .map(|option| (option.name.clone(), option.case_insensitive))
+                .collect::<Vec<_>>();
+
+            if options.is_empty() {
+                continue;
+            }
+
+            let is_bool = matches!(field.ty.value_type(), FieldType::Bool);
+            let condition = options
+                .into_iter()
+                .map(|(option, case_insensitive)| {
+                    self.helpers.strings |= case_insensitive;
+                    self.helpers.has_prefix_fold |= case_insensitive && is_bool;
+                    let value_prefix = format!("{option}{}", syntax.value_separator);
+                    match (case_insensitive, is_bool) {
+                        (false, false) => format!(r#"arg == "{option}""#),
+                        (true, false) => format!(r#"strings.EqualFold(arg, "{option}")"#),
+                        (false, true) => format!(
+                            r#"arg == "{option}" || strings.HasPrefix(arg, "{value_prefix}")"#
+                        ),
+                        (true, true) => format!(
+                            r#"strings.EqualFold(arg, "{option}") || hasPrefixFold(arg, "{value_prefix}")"#
+                        ),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" || ");
+
+            let body = self.capture(|this| {
+                if field.is_deprecated() {
+                    this.write_deprecation_warning(field);
+                }
+                let value = this.write_parse_value(
+                    strukt,
+                    &declaring_struct.name,
+                    field,
+                    spec_metadata,
+                    uses_is_option,
+                );
+
+                for (group_path, group_type) in optional_groups.iter() {
+                    go_source_builder_writeln!(this, "if {group_path} == nil {{");
+                    this.write_block(|this| {
+                        go_source_builder_writeln!(this, "{group_path} = &{group_type}{{}}")
+                    });
+                }
+
+                this.write_assignment(&field.ty, &format!("{path}{name}"), &value);
+
+                if mandatory_fields.index_of(field).is_some() {
+                    go_source_builder_writeln!(this, "{}{name} = true", mandatory_fields.prefix);
+                }
+            });
+            arms.push(MatchArm { condition, body });
+        }
+
+        arms
+    }
+
+    /// Finds the optional groups in the same order and with the same paths that
+    /// `collect_parse_arms` walks the flattened structs.
+    fn collect_optional_groups<'s>(
+        fields: &'s [Field],
+        spec_metadata: &SpecMetadata<'s>,
+        path: &str,
+        paths: &mut Vec<String>,
+        groups: &mut Vec<OptionalGroup<'s>>,
+    ) {
+        for field in fields
+            .iter()
+            .filter(|field| field.has_attribute(AttributeType::Flatten))
+        {
+            let FieldType::Struct(struct_name) = field.ty.value_type() else {
+                continue;
+            };
+            let flatten_type = spec_metadata.identifier_to_struct[struct_name.as_str()];
+            let member = format!("{path}{}", exported_name(&field.name));
+            let inner_path = format!("{member}.");
+
+            if matches!(field.ty, FieldType::Optional(_)) {
+                paths.push(member.clone());
+                groups.push(OptionalGroup {
+                    paths: paths.clone(),
+                    mandatory_fields: MandatoryFields::new(
+                        optional_group_prefix(&member),
+                        flatten_type,
+                    ),
+                });
+                Self::collect_optional_groups(
+                    &flatten_type.fields,
+                    spec_metadata,
+                    &inner_path,
+                    paths,
+                    groups,
+                );
+                paths.pop();
+            } else {
+                Self::collect_optional_groups(
+                    &flatten_type.fields,
+                    spec_metadata,
+                    &inner_path,
+                    paths,
+                    groups,
+                );
+            }
+        }
+    }
+
+    fn write_mandatory_fields_declaration(&mut self, mandatory_fields: &MandatoryFields) {
+        for field in &mandatory_fields.fields {
+            go_source_builder_writeln!(
+                self,
+                "{}{} := false",
+                mandatory_fields.prefix,
+                exported_name(&field.name)
+            );
+        }
+    }
+
+    fn write_mandatory_fields_check(
+        &mut self,
+        mandatory_fields: &MandatoryFields,
+        long_prefix: &str,
+    ) {
+        for field in &mandatory_fields.fields {
+            go_source_builder_writeln!(
+                self,
+                "if !{}{} {{",
+                mandatory_fields.prefix,
+                exported_name(&field.name)
+            );
+            self.write_block(|this| {
+                this.write_error(
+                    &format!(
+                        "{long_prefix}{} was required but it was not provided",
+                        field.name
+                    ),
+                    &[],
+                )
+            });
+        }
+    }
+
+    /// Vec fields constrained with `min_occurs`/`max_occurs` have their number of values checked
+    /// once all the arguments are parsed.
+    fn write_occurrence_checks(&mut self, strukt: &Struct, field: &Field) {
+        let option_name = field_option_name(strukt, field);
+        let count = format!("len(res.{})", exported_name(&field.name));
+
+        if let Some(min) = field.min_occurs().filter(|&min| min > 0) {
+            go_source_builder_writeln!(self, "if {count} < {min} {{");
+            self.write_block(|this| {
+                this.write_error(
+                    &format!(
+                        "Expected at least {min} occurrences of {option_name} but %d were provided"
+                    ),
+                    &[&count],
+                )
+            });
+        }
+
+        if let Some(max) = field.max_occurs() {
+            go_source_builder_writeln!(self, "if {count} > {max} {{");
+            self.write_block(|this| {
+                this.write_error(
+                    &format!(
+                        "Expected at most {max} occurrences of {option_name} but %d were provided"
+                    ),
+                    &[&count],
+                )
+            });
+        }
+    }
+
+    fn write_switch(&mut self, arms: &[MatchArm], default_body: &str) {
+        go_source_builder_writeln!(self, "switch {{");
+        for arm in arms {
+            go_source_builder_writeln!(self, "case {}:", arm.condition);
+            self.buffer.push_str(&arm.body);
+        }
+        go_source_builder_writeln!(self, "default:");
+        self.buffer.push_str(default_body);
+        go_source_builder_writeln!(self, "}}");
+    }
+
+    fn write_is_option_function(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let mut seen = HashSet::new();
+        let (case_insensitive_options, options): (Vec<_>, Vec<_>) =
+            struct_options(strukt, spec_metadata)
+                .into_iter()
+                .filter(|option| seen.insert(option.clone()))
+                .map(|(option, case_insensitive)| (format!(r#""{option}""#), case_insensitive))
+                .partition(|(_, case_insensitive)| *case_insensitive);
+        let join = |options: Vec<(String, bool)>| {
+            options
+                .into_iter()
+                .map(|(option, _)| option)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        go_source_builder_writeln!(
+            self,
+            "func {}(arg string) bool {{",
+            is_option_function_name(strukt)
+        );
+        self.push_indentation_level();
+
+        if !options.is_empty() {
+            go_source_builder_writeln!(self, "switch arg {{");
+            go_source_builder_writeln!(self, "case {}:", join(options));
+            go_source_builder_writeln!(self, "\treturn true");
+            go_source_builder_writeln!(self, "}}");
+        }
+
+        if !case_insensitive_options.is_empty() {
+            self.helpers.strings = true;
+            go_source_builder_writeln!(
+                self,
+                "for _, option := range []string{{{}}} {{",
+                join(case_insensitive_options)
+            );
+            self.write_block(|this| {
+                go_source_builder_writeln!(this, "if strings.EqualFold(arg, option) {{");
+                this.write_block(|this| go_source_builder_writeln!(this, "return true"));
+            });
+        }
+
+        go_source_builder_writeln!(self, "return false");
+        self.pop_indentation_level();
+        go_source_builder_writeln!(self, "}}\n");
+    }
+
+    /// The help lines are escaped for `printf`, so each of them is printed through `fmt.Printf`.
+    fn write_help_function(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let function_name = help_function_name(strukt);
+        self.helpers.fmt = true;
+
+        go_source_builder_writeln!(
+            self,
+            "// {function_name} prints the help of {} to the standard output.",
+            exported_name(&strukt.name)
+        );
+        go_source_builder_writeln!(self, "func {function_name}() {{");
+        self.push_indentation_level();
+        for line in help_lines(strukt, spec_metadata) {
+            go_source_builder_writeln!(self, r#"fmt.Printf("{line}\n")"#);
+        }
+        if let Some(after_help) = strukt.after_help() {
+            go_source_builder_writeln!(self, r#"fmt.Print("{after_help}")"#);
+        }
+        self.pop_indentation_level();
+        go_source_builder_writeln!(self, "}}\n");
+    }
+
+    fn write_parse_function(&mut self, strukt: &Struct, spec_metadata: &SpecMetadata) {
+        let struct_name = exported_name(&strukt.name);
+        let function_name = parse_function_name(strukt);
+        let syntax = strukt.option_syntax();
+
+        let mandatory_fields = MandatoryFields::new(String::from("seen"), strukt);
+        let mut optional_groups = Vec::new();
+        Self::collect_optional_groups(
+            &strukt.fields,
+            spec_metadata,
+            "res.",
+            &mut Vec::new(),
+            &mut optional_groups,
+        );
+
+        // The function is written aside, since whether it needs `is<Struct>Option` is only known
+        // once the cases are collected.
+        let previous_buffer = std::mem::take(&mut self.buffer);
+        let mut uses_is_option = false;
+        if strukt.has_attribute(AttributeType::Main) {
+            go_source_builder_writeln!(
+                self,
+                "// {function_name} parses the arguments of the program, without its name (os.Args[1:]), into a {struct_name}."
+            );
+        } else {
+            go_source_builder_writeln!(
+                self,
+                "// {function_name} parses args into a {struct_name}."
+            );
+        }
+        go_source_builder_writeln!(
+            self,
+            "// It returns ErrHelp after printing the help when -h or --help is given."
+        );
+        go_source_builder_writeln!(
+            self,
+            "func {function_name}(args []string) (*{struct_name}, error) {{"
+        );
+        self.push_indentation_level();
+
+        go_source_builder_writeln!(self, "res := &{struct_name}{{}}");
+        self.write_mandatory_fields_declaration(&mandatory_fields);
+        for group in &optional_groups {
+            self.write_mandatory_fields_declaration(&group.mandatory_fields);
+        }
+        go_source_builder_writeln!(self);
+
+        go_source_builder_writeln!(self, "for len(args) > 0 {{");
+        self.push_indentation_level();
+        go_source_builder_writeln!(self, "arg := args[0]");
+        go_source_builder_writeln!(self, "args = args[1:]");
+
+        let mut arms = Vec::new();
+        if let Some(trailing_field) = strukt.trailing_field() {
+            let name = exported_name(&trailing_field.name);
+            arms.push(MatchArm {
+                condition: format!(r#"arg == "{}""#, strukt.separator()),
+                body: self.capture(|this| {
+                    go_source_builder_writeln!(this, "res.{name} = append(res.{name}, args...)");
+                    go_source_builder_writeln!(this, "args = nil");
+                }),
+            });
+        }
+        arms.push(MatchArm {
+            condition: format!(
+                r#"arg == "{}" || arg == "{}""#,
+                syntax.short_option("h"),
+                syntax.long_option("help")
+            ),
+            body: self.capture(|this| {
+                go_source_builder_writeln!(this, "{}()", help_function_name(strukt));
+                go_source_builder_writeln!(this, "return nil, ErrHelp");
+            }),
+        });
+        arms.extend(self.collect_parse_arms(
+            strukt,
+            strukt,
+            spec_metadata,
+            "res.",
+            &mut Vec::new(),
+            &mandatory_fields,
+            &mut uses_is_option,
+        ));
+
+        let unknown_option_body =
+            self.capture(|this| this.write_error("Unknown option '%s'", &["arg"]));
+        self.write_switch(&arms, &unknown_option_body);
+
+        self.pop_indentation_level();
+        go_source_builder_writeln!(self, "}}\n");
+
+        self.write_mandatory_fields_check(&mandatory_fields, syntax.long_prefix);
+        for group in &optional_groups {
+            if group.mandatory_fields.fields.is_empty() {
+                continue;
+            }
+            let condition = group
+                .paths
+                .iter()
+                .map(|path| format!("{path} != nil"))
+                .collect::<Vec<_>>()
+                .join(" && ");
+            go_source_builder_writeln!(self, "if {condition} {{");
+            self.write_block(|this| {
+                this.write_mandatory_fields_check(&group.mandatory_fields, syntax.long_prefix)
+            });
+        }
+
+        for field in &strukt.fields {
+            self.write_occurrence_checks(strukt, field);
+        }
+
+        go_source_builder_writeln!(self, "return res, nil");
+        self.pop_indentation_level();
+        go_source_builder_writeln!(self, "}}\n");
+        let function = std::mem::replace(&mut self.buffer, previous_buffer);
+
+        self.buffer.push_str(&function);
+        if uses_is_option {
+            self.write_is_option_function(strukt, spec_metadata);
+        }
+    }
+}
+
+/// Generates a single Go file of package `package` implementing the spec, formatted exactly like
+/// gofmt would.
+///
+/// Every struct becomes an exported struct, with `Vec` fields as slices and `Optional` fields as
+/// pointers. The parse functions return errors instead of exiting, `ErrHelp` once the help was
+/// printed. The spec must have been checked with `semantic::check_go_support` first.
+pub(crate) fn generate_cli(
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    options: &GenOptions,
+    provenance: Option<&Provenance>,
+    package: &str,
+) -> String {
+    let mut builder = GoSourceBuilder::new(*options);
+
+    // The declarations are written first, so that only the imports and helpers they use are
+    // written around them.
+    for strukt in &spec.structs {
+        builder.write_struct_definition(strukt);
+    }
+    for strukt in spec.structs.iter().filter(|strukt| !strukt.is_doc_hidden()) {
+        builder.write_help_function(strukt, spec_metadata);
+        builder.write_parse_function(strukt, spec_metadata);
+    }
+    builder.write_helper_functions();
+    let declarations = std::mem::take(&mut builder.buffer);
+
+    go_source_builder_writeln!(
+        builder,
+        "// Code generated by {}. DO NOT EDIT.",
+        env!("CARGO_PKG_NAME")
+    );
+    if let Some(provenance) = provenance {
+        for line in provenance_lines(provenance) {
+            go_source_builder_writeln!(builder, "{line}");
+        }
+    }
+    go_source_builder_writeln!(builder);
+    go_source_builder_writeln!(builder, "package {package}\n");
+
+    let helpers = builder.helpers;
+    go_source_builder_writeln!(builder, "import (");
+    for (used, import) in [
+        (true, "errors"),
+        (helpers.fmt, "fmt"),
+        (helpers.os, "os"),
+        (helpers.regexp, "regexp"),
+        (helpers.strconv, "strconv"),
+        (helpers.strings, "strings"),
+    ] {
+        if used {
+            go_source_builder_writeln!(builder, "\t\"{import}\"");
+        }
+    }
+    go_source_builder_writeln!(builder, ")\n");
+
+    go_source_builder_writeln!(
+        builder,
+        "// ErrHelp is returned by the parse functions when the help was asked for and printed."
+    );
+    go_source_builder_writeln!(builder, r#"var ErrHelp = errors.New("help requested")"#);
+    go_source_builder_writeln!(builder);
+
+    let patterns = std::mem::take(&mut builder.patterns);
+    for (name, expression) in &patterns {
+        go_source_builder_writeln!(builder, "var {name} = {expression}");
+    }
+    if !patterns.is_empty() {
+        go_source_builder_writeln!(builder);
+    }
+
+    builder.buffer.push_str(&declarations);
+    // Every declaration ends with a blank line, which is not needed after the last one.
+    builder.buffer.pop();
+
+    builder.buffer
+}
+
+/// Writes every struct to a single Go file of the given package.
+pub struct GoBackend<'a> {
+    pub output: &'a str,
+    pub provenance: Option<&'a Provenance<'a>>,
+    pub package: &'a str,
+}
+
+impl Backend for GoBackend<'_> {
+    fn output_paths(&self) -> Vec<String> {
+        vec![self.output.to_string()]
+    }
+
+    fn generate<'s>(
+        &self,
+        spec: &'s Spec,
+        spec_metadata: &SpecMetadata<'s>,
+        options: &GenOptions,
+    ) -> Result<Vec<OutputFile>, Vec<Diagnostic>> {
+        check_go_support(spec)?;
+
+        Ok(vec![OutputFile {
+            path: self.output.to_string(),
+            contents: generate_cli(spec, spec_metadata, options, self.provenance, self.package),
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::{check_go_support, check_semantics};
+
+    fn generate(source: &str) -> String {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        check_go_support(&spec).unwrap();
+        generate_cli(&spec, &metadata, &GenOptions::default(), None, "main")
+    }
+
+    #[test]
+    fn names_are_exported() {
+        assert_eq!(exported_name("input"), "Input");
+        assert_eq!(exported_name("log_file"), "LogFile");
+        assert_eq!(exported_name("ServerConfig"), "ServerConfig");
+        assert_eq!(exported_name("ipv4_address"), "Ipv4Address");
+    }
+
+    #[test]
+    fn struct_fields_are_aligned_like_gofmt() {
+        let source = generate(
+            r#"#[main] struct Args {
+                #[long] name: string,
+                #[long] verbose: bool,
+                #[long, doc = "Where to write"] output_file: Optional<string>,
+                #[long] values: Vec<u32>,
+            }"#,
+        );
+
+        assert!(source.contains(
+            "type Args struct {\n\tName    string\n\tVerbose bool\n\t// Where to write\n\tOutputFile *string\n\tValues     []uint32\n}\n"
+        ));
+    }
+
+    #[test]
+    fn unused_imports_are_left_out() {
+        let source = generate("#[main] struct Args { #[long] name: string }");
+
+        assert!(source.contains("import (\n\t\"errors\"\n\t\"fmt\"\n)\n"));
+        assert!(!source.contains("isArgsOption"));
+        assert!(!source.contains("func parseBool"));
+    }
+
+    #[test]
+    fn the_banner_has_a_single_do_not_edit_line() {
+        let source = "#[main] struct Args { #[long] name: string }";
+        let spec = Parser::new(source).parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let provenance = Provenance {
+            spec_path: "args.cli",
+            spec_hash: String::from("fnv1a-64:0"),
+            timestamp: None,
+        };
+        let generated = generate_cli(
+            &spec,
+            &metadata,
+            &GenOptions::default(),
+            Some(&provenance),
+            "main",
+        );
+
+        assert!(generated.starts_with(
+            "// Code generated by cli-generator. DO NOT EDIT.\n// Spec: args.cli\n// Spec hash: fnv1a-64:0\n\npackage main\n"
+        ));
+        assert!(!generated.contains("Do not edit by hand"));
+    }
+
+    #[test]
+    fn the_help_lists_the_options_of_flattened_structs() {
+        let source = generate(
+            "struct Net { #[long] host: string }
+             #[main] struct Args { #[long] port: u16, #[flatten] net: Net }",
+        );
+
+        assert!(source.contains(
+            "\tfmt.Printf(\"    --port <PORT>\\n\")\n\tfmt.Printf(\"    --host <HOST>\\n\")\n}"
+        ));
+        assert!(!source.contains("<NET>"));
+    }
+}
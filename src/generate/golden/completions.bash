@@ -0,0 +1,33 @@
+_my_tool() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    COMPREPLY=()
+
+    local i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        if [[ "${COMP_WORDS[i]}" == "--" ]]; then
+            return
+        fi
+    done
+
+    case "$prev" in
+        -i|--input|--in-file)
+            return
+            ;;
+        --jobs)
+            return
+            ;;
+        --color)
+            COMPREPLY=($(compgen -W "true false" -- "$cur"))
+            return
+            ;;
+        --log-file)
+            return
+            ;;
+    esac
+
+    COMPREPLY=($(compgen -W "-h --help -i --input --in-file --jobs --color -v --verbose --log-file" -- "$cur"))
+}
+
+complete -F _my_tool my-tool
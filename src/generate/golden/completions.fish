@@ -0,0 +1,6 @@
+complete -c my-tool -s h -l help -d 'Print help'
+complete -c my-tool -s i -l input -l in-file -r -d 'The file to read, \'input.txt\' by default'
+complete -c my-tool -l jobs -r
+complete -c my-tool -l color -x -a 'true false'
+complete -c my-tool -s v -l verbose -d 'Print more [debug] output'
+complete -c my-tool -l log-file -r
@@ -0,0 +1,184 @@
+#ifndef _CLI_H_
+#define _CLI_H_
+
+#include <cctype>
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <cstdio>
+#include <cerrno>
+#include <string>
+#include <vector>
+#include <optional>
+
+inline bool equals_ignore_case(const char* lhs, const char* rhs) {
+	for (; *lhs != '\0' && *rhs != '\0'; ++lhs, ++rhs) {
+		if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {
+			return false;
+		}
+	}
+	return *lhs == *rhs;
+}
+
+inline bool parse_bool_value(const char* value, bool* res) {
+	if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {
+		*res = true;
+		return true;
+	}
+	if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {
+		*res = false;
+		return true;
+	}
+	return false;
+}
+
+struct Logging {
+	bool verbose;
+	std::optional<std::string> log_file;
+
+	void print_debug() {
+		printf("Logging {\n");
+		printf("\tverbose: %s\n", this->verbose ? "true" : "false");
+		if (this->log_file.has_value()) {
+			printf("\tlog_file: %s\n", this->log_file.value().c_str());
+		} else {
+			printf("\tlog_file: none\n");
+		}
+		printf("}\n");
+	}
+};
+
+struct Cli {
+	std::string input;
+	std::vector<uint32_t> jobs;
+	Logging logging;
+
+	void print_debug() {
+		printf("Cli {\n");
+		printf("\tinput: %s\n", this->input.c_str());
+		printf("\tjobs: [\n");
+		for (size_t i = 0; i != this->jobs.size(); ++i) {
+			printf("\t%u,\n", this->jobs[i]);
+		}
+		printf("\t]\n");
+		this->logging.print_debug();
+		printf("}\n");
+	}
+
+	static void help() {
+		printf("Usage: Cli [OPTIONS]\n"
+			"\n"
+			"Options:\n"
+			"    -h, --help\n"
+			"    -i, --input <INPUT>\n"
+			"    --jobs <JOBS>\n"
+			"    -v, --verbose\n"
+			"    --log-file <LOG_FILE>\n"
+		);
+		exit(0);
+	}
+
+	static bool is_option(char* arg) {
+		static const char* valid_options[] = {
+			"-i",
+			"--input",
+			"--jobs",
+			"-v",
+			"--verbose",
+			"--log-file",
+		};
+
+		for (size_t i = 0; i != 6; ++i) {
+			if (strcmp(arg, valid_options[i]) == 0) {
+				return true;
+			}
+		}
+
+		return false;
+	}
+
+	static Cli parse (int argc, char *args[]) {
+		--argc;
+		++args;
+
+		const char* mandatory_field_names[] = { "input", "jobs", };
+		bool mandatory_fields_seen[sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0])] = { false };
+
+		Cli res = {};
+		for (int i = 0; i != argc; ++i, ++args) {
+			const char *arg = args[0];
+			if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {
+				Cli::help();
+			} else if (strcmp(arg, "-i") == 0 || strcmp(arg, "--input") == 0) {
+				++args;
+				++i;
+				if (i == argc) {
+					printf("Expected value for option '%s' but no value was provided", arg);
+					exit(1);
+				}
+				std::string arg_res = args[0];
+				res.input = arg_res;
+				mandatory_fields_seen[0] = true;
+			} else if (strcmp(arg, "--jobs") == 0) {
+				++args;
+				++i;
+				if (i == argc || Cli::is_option(args[0])) {
+					printf("Expected value for option '%s' but no value was provided", arg);
+					exit(1);
+				}
+				char* arg_value = args[0];
+				errno = 0;
+				long long arg_number = std::strtoll(arg_value, nullptr, 10);
+				uint32_t arg_res = static_cast<uint32_t>(arg_number);
+
+				if (errno == ERANGE || arg_number < 0 || arg_number > UINT32_MAX) {
+					printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
+					exit(1);
+				}
+				if (arg_res == 0 && strcmp(arg_value, "0") != 0) {
+					printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
+					exit(1);
+				}
+				res.jobs.push_back(arg_res);
+				mandatory_fields_seen[1] = true;
+			} else if (strcmp(arg, "-v") == 0 || strncmp(arg, "-v=", 3) == 0 || strcmp(arg, "--verbose") == 0 || strncmp(arg, "--verbose=", 10) == 0) {
+				bool arg_res = true;
+				const char* bool_value = strchr(arg, '=');
+				if (bool_value != nullptr) {
+					++bool_value;
+				}
+				if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+					printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+					exit(1);
+				}
+				res.logging.verbose = arg_res;
+			} else if (strcmp(arg, "--log-file") == 0) {
+				++args;
+				++i;
+				if (i == argc) {
+					printf("Expected value for option '%s' but no value was provided", arg);
+					exit(1);
+				}
+				std::string arg_res = args[0];
+				res.logging.log_file = arg_res;
+			} else {
+				printf("Unknown option '%s'\n", arg);
+				exit(1);
+			}
+		}
+
+		bool not_seen_any = false;
+		for (size_t i = 0; i != sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0]); ++i) {
+			if (!mandatory_fields_seen[i]) {
+				printf("--%s was required but it was not provided\n", mandatory_field_names[i]);
+				not_seen_any = true;
+			}
+		}
+		if (not_seen_any) {
+			exit(1);
+		}
+		return res;
+	}
+};
+
+#endif // _CLI_H_
@@ -0,0 +1,370 @@
+use crate::diagnostic::Diagnostic;
+use crate::generate::{left_pad, unescape_literal, Backend, GenOptions, OutputFile};
+use crate::types::{AttributeType, Field, FieldType, NumericBound, Spec, SpecMetadata, Struct};
+use std::fmt::Write;
+
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Just enough of JSON to write the schema. Objects keep their keys in insertion order, so that the
+/// output follows the order of the spec.
+#[derive(Debug, Clone, PartialEq)]
+enum JsonValue {
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    fn string(value: &str) -> Self {
+        JsonValue::String(value.to_string())
+    }
+
+    fn object() -> Self {
+        JsonValue::Object(Vec::new())
+    }
+
+    fn insert(&mut self, key: &str, value: JsonValue) {
+        if let JsonValue::Object(entries) = self {
+            entries.push((key.to_string(), value));
+        }
+    }
+}
+
+fn write_json_string(value: &str, buffer: &mut String) {
+    buffer.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\t' => buffer.push_str("\\t"),
+            '\r' => buffer.push_str("\\r"),
+            c if c.is_control() => write!(buffer, "\\u{:04x}", c as u32).unwrap(),
+            c => buffer.push(c),
+        }
+    }
+    buffer.push('"');
+}
+
+/// Writes the value the way `JSON.stringify(value, null, indent)` does, one member per line.
+fn write_json_value(value: &JsonValue, options: &GenOptions, levels: usize, buffer: &mut String) {
+    match value {
+        JsonValue::Bool(value) => write!(buffer, "{value}").unwrap(),
+        JsonValue::Number(value) => buffer.push_str(value),
+        JsonValue::String(value) => write_json_string(value, buffer),
+        JsonValue::Array(values) if values.is_empty() => buffer.push_str("[]"),
+        JsonValue::Object(entries) if entries.is_empty() => buffer.push_str("{}"),
+        JsonValue::Array(values) => {
+            buffer.push_str("[\n");
+            for (index, value) in values.iter().enumerate() {
+                left_pad(options.indent, levels + 1, &mut *buffer).unwrap();
+                write_json_value(value, options, levels + 1, buffer);
+                buffer.push_str(if index + 1 == values.len() {
+                    "\n"
+                } else {
+                    ",\n"
+                });
+            }
+            left_pad(options.indent, levels, &mut *buffer).unwrap();
+            buffer.push(']');
+        }
+        JsonValue::Object(entries) => {
+            buffer.push_str("{\n");
+            for (index, (key, value)) in entries.iter().enumerate() {
+                left_pad(options.indent, levels + 1, &mut *buffer).unwrap();
+                write_json_string(key, buffer);
+                buffer.push_str(": ");
+                write_json_value(value, options, levels + 1, buffer);
+                buffer.push_str(if index + 1 == entries.len() {
+                    "\n"
+                } else {
+                    ",\n"
+                });
+            }
+            left_pad(options.indent, levels, &mut *buffer).unwrap();
+            buffer.push('}');
+        }
+    }
+}
+
+/// A numeric attribute value of the field as a JSON number, if it is a valid one.
+fn json_number(field_type: &FieldType, value: &str) -> Option<JsonValue> {
+    let number = match field_type.parse_bound(value)? {
+        NumericBound::Integer(value) => value.to_string(),
+        NumericBound::Float(value) => value.to_string(),
+    };
+
+    Some(JsonValue::Number(number))
+}
+
+/// The schema of a single value of the given type. Structs refer to their definition in `$defs`.
+fn field_type_schema(field: &Field, field_type: &FieldType) -> JsonValue {
+    let mut schema = JsonValue::object();
+
+    match field_type {
+        FieldType::String => {
+            schema.insert("type", JsonValue::string("string"));
+            if let Some(pattern) = field.attribute_value(AttributeType::Regex) {
+                // The generated parsers match the whole value, while JSON Schema patterns are not
+                // anchored.
+                let pattern = format!("^(?:{})$", unescape_literal(pattern));
+                schema.insert("pattern", JsonValue::String(pattern));
+            }
+        }
+        FieldType::I16
+        | FieldType::U16
+        | FieldType::I32
+        | FieldType::U32
+        | FieldType::I64
+        | FieldType::U64
+        | FieldType::F32
+        | FieldType::F64 => {
+            schema.insert("type", JsonValue::string("number"));
+            for (attribute, keyword) in [
+                (AttributeType::Min, "minimum"),
+                (AttributeType::Max, "maximum"),
+            ] {
+                if let Some(bound) = field
+                    .attribute_value(attribute)
+                    .and_then(|value| json_number(field_type, value))
+                {
+                    schema.insert(keyword, bound);
+                }
+            }
+        }
+        FieldType::Bool => schema.insert("type", JsonValue::string("boolean")),
+        FieldType::Vec(inner) => {
+            schema.insert("type", JsonValue::string("array"));
+            schema.insert("items", field_type_schema(field, inner));
+            if let Some(min_occurs) = field.min_occurs() {
+                schema.insert("minItems", JsonValue::Number(min_occurs.to_string()));
+            }
+            if let Some(max_occurs) = field.max_occurs() {
+                schema.insert("maxItems", JsonValue::Number(max_occurs.to_string()));
+            }
+        }
+        // Optional fields are described by their value and left out of `required`.
+        FieldType::Optional(inner) => return field_type_schema(field, inner),
+        FieldType::Struct(name) => {
+            schema.insert("$ref", JsonValue::String(format!("#/$defs/{name}")));
+        }
+        FieldType::Error => unreachable!(),
+    }
+
+    schema
+}
+
+fn field_schema(field: &Field) -> JsonValue {
+    let mut schema = field_type_schema(field, &field.ty);
+
+    if let Some(value) = field.default_value() {
+        let default = match &field.ty {
+            FieldType::String => Some(JsonValue::String(unescape_literal(value))),
+            field_type => json_number(field_type, value),
+        };
+        if let Some(default) = default {
+            schema.insert("default", default);
+        }
+    }
+
+    if let Some(doc) = field.doc() {
+        schema.insert("description", JsonValue::String(unescape_literal(doc)));
+    }
+
+    schema
+}
+
+/// The schema of the data a struct is parsed into. Its required fields are the mandatory ones,
+/// the ones that always have to be given on the command line.
+fn struct_schema(strukt: &Struct) -> JsonValue {
+    let mut schema = JsonValue::object();
+
+    if let Some(about) = strukt.about() {
+        schema.insert("description", JsonValue::String(unescape_literal(about)));
+    }
+    schema.insert("type", JsonValue::string("object"));
+    schema.insert(
+        "properties",
+        JsonValue::Object(
+            strukt
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), field_schema(field)))
+                .collect(),
+        ),
+    );
+    schema.insert(
+        "required",
+        JsonValue::Array(
+            strukt
+                .mandatory_fields()
+                .into_iter()
+                .map(|field| JsonValue::string(&field.name))
+                .collect(),
+        ),
+    );
+    schema.insert("additionalProperties", JsonValue::Bool(false));
+
+    schema
+}
+
+/// The structs the fields of `strukt` refer to, directly or through other structs, in the order
+/// they are first referred to.
+fn collect_referenced_structs<'s>(
+    strukt: &'s Struct,
+    spec_metadata: &SpecMetadata<'s>,
+    structs: &mut Vec<&'s Struct>,
+) {
+    for field in &strukt.fields {
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+
+        let inner_struct = spec_metadata.identifier_to_struct[name.as_str()];
+        if !structs
+            .iter()
+            .any(|other| std::ptr::eq(*other, inner_struct))
+        {
+            structs.push(inner_struct);
+            collect_referenced_structs(inner_struct, spec_metadata, structs);
+        }
+    }
+}
+
+/// Describes the data model of the spec as a JSON Schema: the main struct is the root of the
+/// schema and every other struct is a definition it refers to.
+pub(crate) fn generate_json_schema<'s>(
+    spec: &'s Spec,
+    spec_metadata: &SpecMetadata<'s>,
+    options: &GenOptions,
+) -> String {
+    let main_struct = spec
+        .structs
+        .iter()
+        .find(|strukt| strukt.has_attribute(AttributeType::Main))
+        .unwrap_or_else(|| &spec.structs[0]);
+
+    let mut schema = JsonValue::object();
+    schema.insert("$schema", JsonValue::string(SCHEMA_DIALECT));
+    schema.insert("title", JsonValue::string(&main_struct.name));
+
+    let JsonValue::Object(entries) = struct_schema(main_struct) else {
+        unreachable!()
+    };
+    for (key, value) in entries {
+        schema.insert(&key, value);
+    }
+
+    let mut referenced_structs = Vec::new();
+    collect_referenced_structs(main_struct, spec_metadata, &mut referenced_structs);
+    let definitions = referenced_structs
+        .into_iter()
+        .filter(|strukt| !std::ptr::eq(*strukt, main_struct))
+        .map(|strukt| (strukt.name.clone(), struct_schema(strukt)))
+        .collect::<Vec<_>>();
+    if !definitions.is_empty() {
+        schema.insert("$defs", JsonValue::Object(definitions));
+    }
+
+    let mut buffer = String::new();
+    write_json_value(&schema, options, 0, &mut buffer);
+    buffer.push('\n');
+
+    match options.line_ending.as_str() {
+        "\n" => buffer,
+        line_ending => buffer.replace('\n', line_ending),
+    }
+}
+
+/// Writes the JSON Schema to the output path, whatever the target.
+pub struct JsonSchemaBackend<'a> {
+    pub output: &'a str,
+}
+
+impl Backend for JsonSchemaBackend<'_> {
+    fn output_paths(&self) -> Vec<String> {
+        vec![self.output.to_string()]
+    }
+
+    fn generate<'s>(
+        &self,
+        spec: &'s Spec,
+        spec_metadata: &SpecMetadata<'s>,
+        options: &GenOptions,
+    ) -> Result<Vec<OutputFile>, Vec<Diagnostic>> {
+        Ok(vec![OutputFile {
+            path: self.output.to_string(),
+            contents: generate_json_schema(spec, spec_metadata, options),
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    fn generate(source: &str) -> String {
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        generate_json_schema(&spec, &metadata, &GenOptions::default())
+    }
+
+    #[test]
+    fn field_types_map_to_json_schema_types() {
+        let generated = generate(
+            "struct Server { #[long] host: string }
+             #[main] struct Cli {
+                 #[long] name: string,
+                 #[long, min = 1, max = 10] jobs: u16,
+                 #[long] verbose: bool,
+                 #[long] files: Vec<string>,
+                 #[flatten] server: Server,
+             }",
+        );
+
+        assert!(generated.starts_with(
+            "{\n    \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n    \"title\": \"Cli\",\n"
+        ));
+        assert!(generated.contains("\"name\": {\n            \"type\": \"string\"\n        }"));
+        assert!(generated.contains(
+            "\"jobs\": {\n            \"type\": \"number\",\n            \"minimum\": 1,\n            \"maximum\": 10\n        }"
+        ));
+        assert!(generated.contains("\"verbose\": {\n            \"type\": \"boolean\"\n        }"));
+        assert!(generated.contains(
+            "\"files\": {\n            \"type\": \"array\",\n            \"items\": {\n                \"type\": \"string\"\n            }\n        }"
+        ));
+        assert!(generated.contains("\"$ref\": \"#/$defs/Server\""));
+        assert!(generated.contains("\"$defs\": {\n        \"Server\": {\n"));
+    }
+
+    #[test]
+    fn only_mandatory_fields_are_required() {
+        let generated = generate(
+            "#[main] struct Cli {
+                 #[long] input: string,
+                 #[long] output: Optional<string>,
+                 #[long, default] jobs: u32,
+                 #[long, default_value_t = \"4\"] threads: u32,
+             }",
+        );
+
+        assert!(generated.contains("\"required\": [\n        \"input\"\n    ],"));
+        assert!(generated.contains("\"default\": 4"));
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let mut buffer = String::new();
+        write_json_string(&unescape_literal(r#"say \"hi\"\n\\ now"#), &mut buffer);
+        assert_eq!(buffer, r#""say \"hi\"\n\\ now""#);
+
+        buffer.clear();
+        write_json_string("\u{1}", &mut buffer);
+        assert_eq!(buffer, r#""\u0001""#);
+    }
+}
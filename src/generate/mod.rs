@@ -1,5 +1,265 @@
+pub mod c;
+pub mod cmake;
+pub mod completions;
 pub mod cpp;
+pub mod cpp_tests;
+pub mod dot;
+pub mod go;
+pub mod json_schema;
+pub mod spec_json;
 
-pub(crate) fn left_pad<W: std::fmt::Write>(padding: usize, mut buffer: W) -> std::fmt::Result {
-    write!(buffer, "{:padding$}", "")
+use crate::diagnostic::Diagnostic;
+use crate::generate::cpp::{CppStandard, LineEnding, TargetOs};
+use crate::types::{Spec, SpecMetadata};
+
+/// How the code is generated, whatever the target. The defaults are the ones of the command line.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GenOptions {
+    /// The C++ standard the generated C++ is written for.
+    pub cpp_std: CppStandard,
+    /// The operating system the generated code runs on.
+    pub target_os: TargetOs,
+    /// Whether the fields of the C++ structs are private and exposed through const getters.
+    pub accessors: bool,
+    /// Whether the `parse()` methods of the C++ structs without struct fields are `noexcept`.
+    pub cpp_noexcept: bool,
+    /// Whether the generated C++ is documented with Doxygen comments.
+    pub doxygen: bool,
+    /// Whether the values of fields with a regex are left unchecked.
+    pub no_regex_validation: bool,
+    /// Whether the C++ checks that the values of `directory` fields are existing directories.
+    pub validate_directory: bool,
+    /// Whether every C++ struct has a `to_command_line()` method rebuilding its options.
+    pub to_command_line: bool,
+    /// A single level of indentation of the generated code.
+    pub indent: Indent,
+    /// How the lines of the generated code end.
+    pub line_ending: LineEnding,
+}
+
+/// The language the code is generated for.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    /// A single header with the structs and their parsing code.
+    #[default]
+    Cpp,
+    /// A C99 header declaring the structs and functions, and a source file implementing them.
+    C,
+    /// A single Go file, formatted like gofmt would.
+    Go,
+}
+
+impl Target {
+    /// The extension of the output path, which is the header for C++ and C.
+    pub fn output_extension(self) -> &'static str {
+        match self {
+            Target::Cpp | Target::C => "h",
+            Target::Go => "go",
+        }
+    }
+}
+
+/// The backend generating the code of `target` at `output`. The C++ implementation is only split
+/// from its header with `split_implementation`, and the Go code is part of `go_package`.
+pub fn code_backend<'a>(
+    target: Target,
+    output: &'a str,
+    provenance: Option<&'a Provenance<'a>>,
+    split_implementation: bool,
+    go_package: &'a str,
+) -> Box<dyn Backend + 'a> {
+    match target {
+        Target::Cpp => Box::new(cpp::CppBackend {
+            output,
+            provenance,
+            split_implementation,
+        }),
+        Target::C => Box::new(c::CBackend { output, provenance }),
+        Target::Go => Box::new(go::GoBackend {
+            output,
+            provenance,
+            package: go_package,
+        }),
+    }
+}
+
+/// What is generated from the spec.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// The parsing code for the target language.
+    #[default]
+    Code,
+    /// A JSON Schema describing the data the main struct is parsed into.
+    JsonSchema,
+}
+
+/// A file written by a backend.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    /// Where the file is written, derived from the output path given on the command line.
+    pub path: String,
+    /// The generated code.
+    pub contents: String,
+}
+
+/// A generator of the files for a target or output format, configured with the output path and
+/// whatever else it needs besides the `GenOptions`.
+pub trait Backend {
+    /// The paths of the files `generate` writes, in the same order. `--verify` checks them without
+    /// generating anything.
+    fn output_paths(&self) -> Vec<String>;
+
+    /// Generates the files of the spec, or the reasons the backend can't express it.
+    fn generate<'s>(
+        &self,
+        spec: &'s Spec,
+        spec_metadata: &SpecMetadata<'s>,
+        options: &GenOptions,
+    ) -> Result<Vec<OutputFile>, Vec<Diagnostic>>;
+}
+
+/// How a single level of indentation is written in the generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// The given number of spaces.
+    Spaces(usize),
+    /// A single tab.
+    Tabs,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(4)
+    }
+}
+
+/// Where the generated code comes from, recorded in the banner at the top of the output.
+#[derive(Debug, Clone)]
+pub struct Provenance<'a> {
+    /// The path of the spec, as given on the command line.
+    pub spec_path: &'a str,
+    /// The hash of the spec, checked by `--verify`.
+    pub spec_hash: String,
+    /// Seconds since the Unix epoch, omitted for reproducible builds.
+    pub timestamp: Option<u64>,
+}
+
+impl<'a> Provenance<'a> {
+    /// The provenance of `spec`, read from `spec_path`, timestamped with the current time when
+    /// `with_timestamp` is set.
+    pub fn new(spec_path: &'a str, spec: &str, with_timestamp: bool) -> Self {
+        let timestamp = with_timestamp.then(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+        });
+
+        Self {
+            spec_path,
+            spec_hash: crate::hash::spec_hash(spec),
+            timestamp,
+        }
+    }
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC date and time.
+pub(crate) fn format_utc_timestamp(timestamp: u64) -> String {
+    let days = timestamp / 86400;
+    let seconds = timestamp % 86400;
+
+    // Howard Hinnant's civil_from_days, with eras of 400 years starting at 0000-03-01.
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// The value of a string literal of the spec, with its escape sequences resolved.
+pub(crate) fn unescape_literal(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('r') => res.push('\r'),
+            Some(escaped) => res.push(escaped),
+            None => res.push('\\'),
+        }
+    }
+
+    res
+}
+
+pub(crate) fn left_pad<W: std::fmt::Write>(
+    indent: Indent,
+    levels: usize,
+    mut buffer: W,
+) -> std::fmt::Result {
+    match indent {
+        Indent::Spaces(width) => write!(buffer, "{:padding$}", "", padding = width * levels),
+        Indent::Tabs => write!(buffer, "{:\t<levels$}", ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_timestamps_are_formatted_as_iso_8601() {
+        assert_eq!(format_utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc_timestamp(1_791_982_245), "2026-10-14T12:50:45Z");
+    }
+
+    #[test]
+    fn backends_write_their_output_paths() {
+        let mut parser = crate::parse::Parser::new("#[main] struct Cli { #[long] port: u16 }");
+        let spec = parser.parse().unwrap();
+        let metadata = crate::semantic::check_semantics(&spec).unwrap();
+        let backend = c::CBackend {
+            output: "include/cli.h",
+            provenance: None,
+        };
+
+        let files = backend
+            .generate(&spec, &metadata, &GenOptions::default())
+            .unwrap();
+        assert_eq!(backend.output_paths(), ["include/cli.h", "include/cli.c"]);
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect::<Vec<_>>(),
+            backend.output_paths()
+        );
+        assert!(files[1].contents.contains(r#"#include "cli.h""#));
+    }
 }
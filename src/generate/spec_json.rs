@@ -0,0 +1,233 @@
+use crate::diagnostic::SourceSpan;
+use crate::source_map::SourceMap;
+use crate::types::{Attribute, AttributeType, Field, FieldType, Spec, Struct};
+use serde::{Deserialize, Serialize};
+
+/// The version of the JSON the spec is dumped as. It is bumped whenever a key is renamed or
+/// removed, or the meaning of a value changes, so that tools can reject dumps they don't know.
+pub(crate) const SPEC_JSON_VERSION: u32 = 1;
+
+/// An attribute, e.g. `{"type": "doc", "value": "The input", "span": ...}`. The value is written
+/// as in the spec, escape sequences included, and is `null` for attributes without one.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AttributeJson {
+    #[serde(rename = "type")]
+    pub ty: AttributeType,
+    pub value: Option<String>,
+    pub span: SourceSpan,
+}
+
+/// A field. Its type is `"u32"` for the primitive types, `{"vec": ...}` and `{"optional": ...}`
+/// around their inner type, and `{"struct": "Name"}` for the other structs of the spec.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FieldJson {
+    pub name: String,
+    pub name_span: SourceSpan,
+    #[serde(rename = "type")]
+    pub ty: FieldType,
+    pub type_span: SourceSpan,
+    pub attributes: Vec<AttributeJson>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StructJson {
+    pub name: String,
+    pub name_span: SourceSpan,
+    pub attributes: Vec<AttributeJson>,
+    pub fields: Vec<FieldJson>,
+}
+
+/// The whole spec, with its structs in declaration order.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SpecJson {
+    pub version: u32,
+    /// The version of the spec format, see `Spec::format_version`.
+    pub format_version: u32,
+    pub structs: Vec<StructJson>,
+}
+
+fn attributes_json(source_map: &SourceMap, attributes: &[Attribute]) -> Vec<AttributeJson> {
+    attributes
+        .iter()
+        .map(|attribute| AttributeJson {
+            ty: attribute.ty,
+            value: attribute.value.clone(),
+            span: source_map.source_span(&attribute.span),
+        })
+        .collect()
+}
+
+fn field_json(source_map: &SourceMap, field: &Field) -> FieldJson {
+    FieldJson {
+        name: field.name.clone(),
+        name_span: source_map.source_span(&field.name_span),
+        ty: field.ty.clone(),
+        type_span: source_map.source_span(&field.type_span),
+        attributes: attributes_json(source_map, &field.attributes),
+    }
+}
+
+fn struct_json(source_map: &SourceMap, strukt: &Struct) -> StructJson {
+    StructJson {
+        name: strukt.name.clone(),
+        name_span: source_map.source_span(&strukt.name_span),
+        attributes: attributes_json(source_map, &strukt.attributes),
+        fields: strukt
+            .fields
+            .iter()
+            .map(|field| field_json(source_map, field))
+            .collect(),
+    }
+}
+
+impl SpecJson {
+    pub fn new(spec: &Spec) -> Self {
+        let source_map = SourceMap::new(&spec.source);
+
+        Self {
+            version: SPEC_JSON_VERSION,
+            format_version: spec.format_version(),
+            structs: spec
+                .structs
+                .iter()
+                .map(|strukt| struct_json(&source_map, strukt))
+                .collect(),
+        }
+    }
+}
+
+/// Dumps the parsed spec as JSON, for tools that consume the spec without parsing it themselves.
+pub fn generate_spec_json(spec: &Spec) -> String {
+    let mut json = serde_json::to_string_pretty(&SpecJson::new(spec)).unwrap();
+    json.push('\n');
+
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Position;
+    use crate::parse::Parser;
+    use logos::Span;
+
+    const SOURCE: &str = r#"struct Logging {
+    #[short, long, doc = "Print \"more\" output"]
+    verbose: bool,
+}
+
+#[main, about = "A tool"]
+struct Cli {
+    #[long, doc = "Ünïcode"] jobs: Vec<u32>,
+    #[flatten]
+    logging: Optional<Logging>,
+}
+"#;
+
+    /// The byte offset `position` refers to in `source`.
+    fn offset(source: &str, position: Position) -> usize {
+        let line_start = source
+            .split_inclusive('\n')
+            .take(position.line - 1)
+            .map(str::len)
+            .sum::<usize>();
+
+        line_start
+            + source[line_start..]
+                .chars()
+                .take(position.column - 1)
+                .map(char::len_utf8)
+                .sum::<usize>()
+    }
+
+    fn assert_span(source: &str, json: &SourceSpan, span: &Span) {
+        assert_eq!(offset(source, json.start)..offset(source, json.end), *span);
+    }
+
+    fn assert_attributes(source: &str, json: &[AttributeJson], attributes: &[Attribute]) {
+        assert_eq!(json.len(), attributes.len());
+        for (json, attribute) in json.iter().zip(attributes) {
+            assert_eq!(json.ty, attribute.ty);
+            assert_eq!(json.value, attribute.value);
+            assert_span(source, &json.span, &attribute.span);
+        }
+    }
+
+    #[test]
+    fn round_trips_to_the_parsed_spec() {
+        let mut parser = Parser::new(SOURCE);
+        let spec = parser.parse().unwrap();
+
+        let dump = generate_spec_json(&spec);
+        let json = serde_json::from_str::<SpecJson>(&dump).unwrap();
+        assert_eq!(json, SpecJson::new(&spec));
+
+        assert_eq!(json.version, SPEC_JSON_VERSION);
+        assert_eq!(json.structs.len(), spec.structs.len());
+        for (struct_json, strukt) in json.structs.iter().zip(&spec.structs) {
+            assert_eq!(struct_json.name, strukt.name);
+            assert_span(SOURCE, &struct_json.name_span, &strukt.name_span);
+            assert_attributes(SOURCE, &struct_json.attributes, &strukt.attributes);
+
+            assert_eq!(struct_json.fields.len(), strukt.fields.len());
+            for (field_json, field) in struct_json.fields.iter().zip(&strukt.fields) {
+                assert_eq!(field_json.name, field.name);
+                assert_eq!(field_json.ty, field.ty);
+                assert_span(SOURCE, &field_json.name_span, &field.name_span);
+                assert_span(SOURCE, &field_json.type_span, &field.type_span);
+                assert_attributes(SOURCE, &field_json.attributes, &field.attributes);
+            }
+        }
+    }
+
+    #[test]
+    fn spans_are_lines_and_columns() {
+        let mut parser = Parser::new(SOURCE);
+        let spec = parser.parse().unwrap();
+        let json = SpecJson::new(&spec);
+
+        let verbose = &json.structs[0].fields[0];
+        assert_eq!(verbose.name_span.start, Position { line: 3, column: 5 });
+        assert_eq!(
+            verbose.name_span.end,
+            Position {
+                line: 3,
+                column: 12
+            }
+        );
+        // Columns count characters, so the multi-byte ones before `jobs` count once.
+        let jobs = &json.structs[1].fields[0];
+        assert_eq!(
+            jobs.name_span.start,
+            Position {
+                line: 8,
+                column: 30
+            }
+        );
+    }
+
+    #[test]
+    fn types_and_attributes_are_spelled_like_the_spec() {
+        let mut parser = Parser::new(SOURCE);
+        let spec = parser.parse().unwrap();
+        let value = serde_json::to_value(SpecJson::new(&spec)).unwrap();
+
+        let jobs = &value["structs"][1]["fields"][0];
+        assert_eq!(jobs["type"], serde_json::json!({"vec": "u32"}));
+        assert_eq!(jobs["attributes"][1]["type"], "doc");
+        assert_eq!(jobs["attributes"][1]["value"], "Ünïcode");
+        assert_eq!(
+            value["structs"][1]["fields"][1]["type"],
+            serde_json::json!({"optional": {"struct": "Logging"}})
+        );
+        assert_eq!(
+            value["structs"][0]["fields"][0]["attributes"][2]["value"],
+            r#"Print \"more\" output"#
+        );
+        assert_eq!(value["structs"][0]["attributes"], serde_json::json!([]));
+        assert_eq!(
+            value["structs"][1]["fields"][1]["attributes"][0]["value"],
+            serde_json::Value::Null
+        );
+    }
+}
@@ -0,0 +1,581 @@
+//! Specs read from JSON rather than parsed, for the tools generating them. The JSON is the one a
+//! [`Spec`] is serialized to, which `--emit-spec-json` writes as well along with the spans:
+//!
+//! ```json
+//! {"structs": [{"name": "Cli", "attributes": [{"type": "main"}], "fields": [
+//!     {"name": "port", "type": "u16", "attributes": [{"type": "long"}]}
+//! ]}]}
+//! ```
+//!
+//! The spans of the spec point into the JSON, at the values they are about, e.g. the `"name"` of a
+//! field for its name, so that the diagnostics show the JSON around them. The elements that can't
+//! be deserialized are reported along with their path, e.g. `$.structs[0].fields[1].type`, and so
+//! are the keys a spec doesn't have, e.g. `$.structs[0].fields[1].sohrt`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use logos::Span;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::builder::{invalid_name, is_field_name, is_struct_name, parse_attribute};
+use crate::diagnostic::Diagnostic;
+use crate::parse::resolve_field_attributes;
+use crate::source_map::SourceMap;
+use crate::types::{Attribute, AttributeType, Field, FieldType, Spec, Struct};
+
+/// How the input spec is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InputFormat {
+    /// The language of the specs, described in the README.
+    #[default]
+    Dsl,
+    /// The JSON a spec is serialized to, e.g. by `--emit-spec-json`.
+    Json,
+}
+
+/// The path of an element of the JSON that can't be deserialized, along with why.
+type PathError = (String, serde_json::Error);
+
+/// The keys the objects of the JSON may have. Their values are checked along with the spec, this
+/// only rejects the other keys, which would be ignored otherwise. The spans `--emit-spec-json`
+/// writes are accepted and left out, the spans of the spec pointing into the JSON instead.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecKeys {
+    #[serde(default, rename = "structs")]
+    _structs: IgnoredAny,
+    #[serde(default, rename = "format_version")]
+    _format_version: IgnoredAny,
+    #[serde(default, rename = "version")]
+    _version: IgnoredAny,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructKeys {
+    #[serde(default, rename = "name")]
+    _name: IgnoredAny,
+    #[serde(default, rename = "attributes")]
+    _attributes: IgnoredAny,
+    #[serde(default, rename = "fields")]
+    _fields: IgnoredAny,
+    #[serde(default, rename = "name_span")]
+    _name_span: IgnoredAny,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldKeys {
+    #[serde(default, rename = "name")]
+    _name: IgnoredAny,
+    #[serde(default, rename = "type")]
+    _ty: IgnoredAny,
+    #[serde(default, rename = "attributes")]
+    _attributes: IgnoredAny,
+    #[serde(default, rename = "name_span")]
+    _name_span: IgnoredAny,
+    #[serde(default, rename = "type_span")]
+    _type_span: IgnoredAny,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttributeKeys {
+    #[serde(default, rename = "type")]
+    _ty: IgnoredAny,
+    #[serde(default, rename = "value")]
+    _value: IgnoredAny,
+    #[serde(default, rename = "span")]
+    _span: IgnoredAny,
+}
+
+/// Reads the spec in the JSON `source`, the diagnostics pointing into it.
+pub(crate) fn parse_json(source: &str) -> Result<Spec<'_>, Vec<Diagnostic>> {
+    let value =
+        serde_json::from_str::<Value>(source).map_err(|err| vec![syntax_error(source, &err)])?;
+    let spans = JsonSpans::new(source);
+
+    let spec = deserialize(&value).map_err(|(path, err)| {
+        vec![Diagnostic::new(
+            "JSON error",
+            &spans.get(&path),
+            &format!("{path}: {err}"),
+        )]
+    })?;
+
+    locate(spec, source, &spans)
+}
+
+/// Reports where the JSON stops being JSON. The position is shown by the span, so it is left out
+/// of the message, which serde_json spells `<message> at line L column C`.
+fn syntax_error(source: &str, err: &serde_json::Error) -> Diagnostic {
+    let message = err.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+
+    // Like the parser, running out of input is reported on the last character.
+    let source_map = SourceMap::new(source);
+    let line = err.line().clamp(1, source_map.line_count());
+    let mut start = (source_map.line_span(line).start + err.column().saturating_sub(1))
+        .min(source.trim_end().len().saturating_sub(1));
+    while !source.is_char_boundary(start) {
+        start -= 1;
+    }
+    let end = source[start..]
+        .chars()
+        .next()
+        .map_or(start, |c| start + c.len_utf8());
+
+    Diagnostic::new("JSON error", &(start..end), message)
+}
+
+/// The spec in `value`, or the innermost element that can't be deserialized. The elements are
+/// tried from the inside out, so that the error is about the one that is wrong rather than about
+/// the spec as a whole.
+fn deserialize(value: &Value) -> Result<Spec<'static>, PathError> {
+    check_keys::<SpecKeys>(value, "$")?;
+    check_key::<Option<u32>>(value, "$", "format_version")?;
+    for (i, strukt) in elements(value, "$", "structs")? {
+        let path = format!("$.structs[{i}]");
+        check_keys::<StructKeys>(strukt, &path)?;
+        check_key::<String>(strukt, &path, "name")?;
+        check_attributes(strukt, &path)?;
+        for (j, field) in elements(strukt, &path, "fields")? {
+            let path = format!("{path}.fields[{j}]");
+            check_keys::<FieldKeys>(field, &path)?;
+            check_key::<String>(field, &path, "name")?;
+            check_attributes(field, &path)?;
+            if let Some(ty) = field.get("type") {
+                check_type(ty, &format!("{path}.type"))?;
+            }
+            element::<Field>(field, &path)?;
+        }
+        element::<Struct>(strukt, &path)?;
+    }
+
+    element::<Spec>(value, "$")
+}
+
+fn check_attributes(value: &Value, path: &str) -> Result<(), PathError> {
+    for (k, attribute) in elements(value, path, "attributes")? {
+        let path = format!("{path}.attributes[{k}]");
+        check_keys::<AttributeKeys>(attribute, &path)?;
+        check_key::<AttributeType>(attribute, &path, "type")?;
+        check_key::<Option<String>>(attribute, &path, "value")?;
+        element::<Attribute>(attribute, &path)?;
+    }
+
+    Ok(())
+}
+
+fn element<T: DeserializeOwned>(value: &Value, path: &str) -> Result<T, PathError> {
+    T::deserialize(value).map_err(|err| (path.to_string(), err))
+}
+
+/// Checks that the object `value` has none but the keys of `K`. The first other key is reported at
+/// its own path, e.g. `$.structs[0].sohrt`, which serde names in `unknown field `sohrt`, ...`.
+fn check_keys<K: DeserializeOwned>(value: &Value, path: &str) -> Result<(), PathError> {
+    // What isn't an object is reported when the element is deserialized.
+    if !value.is_object() {
+        return Ok(());
+    }
+
+    element::<K>(value, path)
+        .map(|_| ())
+        .map_err(|(path, err)| {
+            let message = err.to_string();
+            let key = message
+                .strip_prefix("unknown field `")
+                .and_then(|rest| rest.split_once('`'))
+                .map(|(key, _)| key);
+            match key {
+                Some(key) => (format!("{path}.{key}"), err),
+                None => (path, err),
+            }
+        })
+}
+
+/// Checks the field type `value`, the inner type of a `{"vec": ...}` or an `{"optional": ...}`
+/// first, so that an unknown one is reported rather than the type as a whole.
+fn check_type(value: &Value, path: &str) -> Result<(), PathError> {
+    for key in ["vec", "optional"] {
+        if let Some(inner) = value.get(key) {
+            check_type(inner, &format!("{path}.{key}"))?;
+        }
+    }
+
+    element::<FieldType>(value, path).map(|_| ())
+}
+
+/// Checks that the value of `key` in the object `value` is a `T`, if it has one.
+fn check_key<T: DeserializeOwned>(value: &Value, path: &str, key: &str) -> Result<(), PathError> {
+    match value.get(key) {
+        Some(key_value) => element::<T>(key_value, &format!("{path}.{key}")).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// The elements of the array `key` of the object `value`, if it has one.
+fn elements<'v>(
+    value: &'v Value,
+    path: &str,
+    key: &str,
+) -> Result<Vec<(usize, &'v Value)>, PathError> {
+    let Some(array) = value.get(key) else {
+        return Ok(Vec::new());
+    };
+    element::<Vec<Value>>(array, &format!("{path}.{key}"))?;
+
+    Ok(array.as_array().into_iter().flatten().enumerate().collect())
+}
+
+/// Gives the spec deserialized from `source` the spans of its elements in `source`. The names and
+/// the attribute values, which are strings as far as the JSON goes, are checked like the parser
+/// would, and the field attributes are resolved like it does.
+fn locate<'s>(
+    spec: Spec<'static>,
+    source: &'s str,
+    spans: &JsonSpans,
+) -> Result<Spec<'s>, Vec<Diagnostic>> {
+    let mut spec: Spec<'s> = spec;
+    spec.source = Cow::Borrowed(source);
+    let mut errors = Vec::new();
+
+    let version_span = spans.get("$.format_version");
+    match spec.format_directive {
+        Some(0) => errors.push(Diagnostic::new(
+            "JSON error",
+            &version_span,
+            &format!(
+                "$.format_version: Expected a format version from 1 to {}",
+                Spec::FORMAT_VERSION
+            ),
+        )),
+        Some(version) if version > Spec::FORMAT_VERSION => errors.push(
+            Diagnostic::new(
+                "Unsupported format version",
+                &version_span,
+                &format!(
+                    "Spec requires format {version}, this tool supports up to {}",
+                    Spec::FORMAT_VERSION
+                ),
+            )
+            .help("Generate the code with a newer cli-generator"),
+        ),
+        _ => {}
+    }
+
+    for (i, strukt) in spec.structs.iter_mut().enumerate() {
+        let path = format!("$.structs[{i}]");
+        strukt.name_span = spans.get(&format!("{path}.name"));
+        strukt.attributes_span = spans.find(&format!("{path}.attributes"));
+        strukt.body_span = spans
+            .find(&format!("{path}.fields"))
+            .unwrap_or_else(|| spans.get(&path));
+        if !is_struct_name(&strukt.name) {
+            errors.push(invalid_name(&strukt.name_span, "struct", &strukt.name));
+        }
+        let attributes = std::mem::take(&mut strukt.attributes);
+        strukt.attributes = locate_attributes(attributes, &path, spans, &mut errors);
+
+        for (j, field) in strukt.fields.iter_mut().enumerate() {
+            let path = format!("{path}.fields[{j}]");
+            field.span = spans.get(&path);
+            field.name_span = spans.get(&format!("{path}.name"));
+            // The innermost type, like for a parsed field.
+            field.type_span = match field.ty {
+                FieldType::Vec(_) => spans.get(&format!("{path}.type.vec")),
+                FieldType::Optional(_) => spans.get(&format!("{path}.type.optional")),
+                FieldType::Struct(_) => spans.get(&format!("{path}.type.struct")),
+                _ => spans.get(&format!("{path}.type")),
+            };
+            if !is_field_name(&field.name) {
+                errors.push(invalid_name(&field.name_span, "field", &field.name));
+            }
+            let attributes = std::mem::take(&mut field.attributes);
+            let attributes = locate_attributes(attributes, &path, spans, &mut errors);
+            field.attributes = resolve_field_attributes(&field.name, attributes);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(spec)
+}
+
+/// The attributes of the element at `path`, spanning their `"type"` and their `"value"`. They are
+/// written back as in a spec and parsed, the ones that can't be are reported in `errors` instead.
+fn locate_attributes(
+    attributes: Vec<Attribute>,
+    path: &str,
+    spans: &JsonSpans,
+    errors: &mut Vec<Diagnostic>,
+) -> Vec<Attribute> {
+    let mut located = Vec::new();
+    for (k, attribute) in attributes.into_iter().enumerate() {
+        let path = format!("{path}.attributes[{k}]");
+        let span = spans.get(&format!("{path}.type"));
+        let value_span = spans
+            .find(&format!("{path}.value"))
+            .unwrap_or_else(|| span.clone());
+
+        match parse_attribute(&attribute.to_string(), &span, &value_span) {
+            Ok(attribute) => located.push(attribute),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    located
+}
+
+/// The spans of the values of a JSON document by their path, e.g. `$.structs[0].name`.
+struct JsonSpans(HashMap<String, Span>);
+
+impl JsonSpans {
+    /// Indexes `source`, which is valid JSON.
+    fn new(source: &str) -> Self {
+        let mut scanner = Scanner {
+            source,
+            position: 0,
+            spans: HashMap::new(),
+        };
+        scanner.value(String::from("$"));
+
+        Self(scanner.spans)
+    }
+
+    fn find(&self, path: &str) -> Option<Span> {
+        self.0.get(path).cloned()
+    }
+
+    /// The span of the value at `path`, which the spec was deserialized from.
+    fn get(&self, path: &str) -> Span {
+        self.find(path).unwrap_or_default()
+    }
+}
+
+struct Scanner<'a> {
+    source: &'a str,
+    position: usize,
+    spans: HashMap<String, Span>,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.position).copied()
+    }
+
+    fn advance(&mut self) {
+        self.position = (self.position + 1).min(self.source.len());
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.advance();
+        }
+    }
+
+    /// Scans the value at the position, recording its span and the ones of the values in it.
+    fn value(&mut self, path: String) {
+        self.skip_whitespace();
+        let start = self.position;
+
+        match self.peek() {
+            Some(b'{') => {
+                self.advance();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b'}') | None => break,
+                        Some(b',') => self.advance(),
+                        _ => {
+                            let key = self.string();
+                            self.skip_whitespace();
+                            // The `:` between the key and its value.
+                            self.advance();
+                            self.value(format!("{path}.{key}"));
+                        }
+                    }
+                }
+                self.advance();
+            }
+            Some(b'[') => {
+                self.advance();
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b']') | None => break,
+                        Some(b',') => self.advance(),
+                        _ => {
+                            self.value(format!("{path}[{index}]"));
+                            index += 1;
+                        }
+                    }
+                }
+                self.advance();
+            }
+            Some(b'"') => {
+                self.string();
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, b',' | b'}' | b']') && !c.is_ascii_whitespace())
+                {
+                    self.advance();
+                }
+            }
+        }
+
+        self.spans.insert(path, start..self.position);
+    }
+
+    /// Scans the string at the position, returning it unescaped.
+    fn string(&mut self) -> String {
+        let start = self.position;
+        self.advance();
+        while let Some(c) = self.peek() {
+            self.advance();
+            match c {
+                b'"' => break,
+                b'\\' => self.advance(),
+                _ => {}
+            }
+        }
+
+        serde_json::from_str(&self.source[start..self.position]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::spec_json::generate_spec_json;
+    use crate::parse::Parser;
+
+    #[test]
+    fn spans_point_at_the_values_they_are_about() {
+        let source = r#"{"structs": [{"name": "Cli", "attributes": [{"type": "main"}], "fields": [
+    {"name": "port", "type": {"optional": "u16"}, "attributes": [{"type": "long", "value": "p"}]}
+]}]}"#;
+        let spec = parse_json(source).unwrap();
+
+        let strukt = &spec.structs[0];
+        let field = &strukt.fields[0];
+        assert_eq!(&source[strukt.name_span.clone()], r#""Cli""#);
+        assert_eq!(&source[strukt.attributes[0].span.clone()], r#""main""#);
+        assert_eq!(&source[field.name_span()], r#""port""#);
+        assert_eq!(&source[field.type_span()], r#""u16""#);
+        assert!(source[field.span()].starts_with(r#"{"name": "port""#));
+        assert_eq!(&source[field.attributes[0].span.clone()], r#""long""#);
+        assert_eq!(
+            &source[field.attributes[0].value_span.clone().unwrap()],
+            r#""p""#
+        );
+        assert_eq!(field.long_value().as_deref(), Some("p"));
+    }
+
+    #[test]
+    fn what_is_not_json_is_reported_where_it_stops_being_json() {
+        let source = "{\"structs\": [\n  {\"name\": \"Cli\",, \"fields\": []}\n]}";
+        let errors = parse_json(source).unwrap_err();
+        assert_eq!(errors[0].message, "key must be a string");
+        assert_eq!(&source[errors[0].span.clone()], ",");
+
+        let errors = parse_json("{\"structs\": [").unwrap_err();
+        assert_eq!(errors[0].message, "EOF while parsing a list");
+        assert_eq!(errors[0].span, 12..13);
+    }
+
+    #[test]
+    fn elements_that_are_not_part_of_a_spec_are_reported_with_their_path() {
+        let source = r#"{"structs": [{"name": "Cli", "fields": [
+    {"name": "port", "type": "u16", "attributes": [{"type": "longest"}]}
+]}]}"#;
+        let errors = parse_json(source).unwrap_err();
+        assert!(errors[0]
+            .message
+            .starts_with("$.structs[0].fields[0].attributes[0].type: unknown variant `longest`"));
+        assert_eq!(&source[errors[0].span.clone()], r#""longest""#);
+
+        let errors = parse_json(r#"{"structs": [{"fields": []}]}"#).unwrap_err();
+        assert_eq!(errors[0].message, "$.structs[0]: missing field `name`");
+    }
+
+    #[test]
+    fn keys_that_are_not_part_of_a_spec_are_reported_with_their_path() {
+        let source = r#"{"structs": [{"name": "Cli", "fields": [
+    {"name": "port", "type": "u16", "attributes": [{"type": "long"}], "sohrt": 1}
+]}]}"#;
+        let errors = parse_json(source).unwrap_err();
+        assert!(errors[0]
+            .message
+            .starts_with("$.structs[0].fields[0].sohrt: unknown field `sohrt`"));
+        assert_eq!(&source[errors[0].span.clone()], "1");
+
+        let source = r#"{"structs": [{"name": "Cli", "bogus": true}]}"#;
+        let errors = parse_json(source).unwrap_err();
+        assert!(errors[0]
+            .message
+            .starts_with("$.structs[0].bogus: unknown field `bogus`"));
+        assert_eq!(&source[errors[0].span.clone()], "true");
+    }
+
+    #[test]
+    fn errors_point_at_the_innermost_value() {
+        let source = r#"{"structs": [], "format_version": "x"}"#;
+        let errors = parse_json(source).unwrap_err();
+        assert!(errors[0]
+            .message
+            .starts_with("$.format_version: invalid type"));
+        assert_eq!(&source[errors[0].span.clone()], r#""x""#);
+
+        let source = r#"{"structs": [{"name": "Cli", "fields": [
+    {"name": "ports", "type": {"vec": {"optional": "u17"}}}
+]}]}"#;
+        let errors = parse_json(source).unwrap_err();
+        assert!(errors[0]
+            .message
+            .starts_with("$.structs[0].fields[0].type.vec.optional: unknown variant `u17`"));
+        assert_eq!(&source[errors[0].span.clone()], r#""u17""#);
+    }
+
+    #[test]
+    fn names_and_values_are_checked_like_the_parsed_ones() {
+        let source = r#"{"structs": [{"name": "my cli", "fields": [
+    {"name": "port", "type": "u16", "attributes": [{"type": "min", "value": "x"}]}
+]}]}"#;
+        let errors = parse_json(source).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(&source[errors[0].span.clone()], r#""my cli""#);
+        assert_eq!(&source[errors[1].span.clone()], r#""x""#);
+
+        let errors = parse_json(r#"{"format_version": 99, "structs": []}"#).unwrap_err();
+        assert_eq!(errors[0].label, "Unsupported format version");
+    }
+
+    #[test]
+    fn the_json_of_emit_spec_json_is_read_back() {
+        let source = "#!cli-spec 1\n#[main]\nstruct Cli {\n    #[short, long]\n    port: u16,\n}\n";
+        let parsed = Parser::new(source).parse().unwrap();
+        let json = generate_spec_json(&parsed);
+
+        let spec = parse_json(&json).unwrap();
+        assert_eq!(spec.format_version(), 1);
+        assert_eq!(
+            spec.to_string().lines().count(),
+            parsed.to_string().lines().count()
+        );
+        assert_eq!(
+            spec.structs[0].fields[0].long_value().as_deref(),
+            Some("port")
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::source_map::SourceMap;
 use crate::types::{AttributeType, FieldType};
 use logos::Logos;
+use std::fmt::Write;
 
 #[derive(Debug, Logos, Copy, Clone, PartialEq)]
 #[logos(skip r"[ \t\n\f]+")]
@@ -41,6 +43,112 @@ pub(crate) enum Tokens {
     Main,
     #[token("subcommand")]
     SubCommand,
+    #[token("explicit_bool")]
+    ExplicitBool,
+    #[token("takes_value")]
+    TakesValue,
+    #[token("allow_abbrev")]
+    AllowAbbrev,
+    #[token("trailing")]
+    Trailing,
+    #[token("separator")]
+    Separator,
+    #[token("prefix")]
+    Prefix,
+    #[token("value_separator")]
+    ValueSeparator,
+    #[token("secret")]
+    Secret,
+    #[token("doc_hidden")]
+    DocHidden,
+    #[token("min_occurs")]
+    MinOccurs,
+    #[token("max_occurs")]
+    MaxOccurs,
+    #[token("min")]
+    Min,
+    #[token("max")]
+    Max,
+    #[token("regex")]
+    Regex,
+    #[token("case_insensitive")]
+    CaseInsensitive,
+    #[token("default")]
+    Default,
+    #[token("doc")]
+    Doc,
+    #[token("about")]
+    About,
+    #[token("after_help")]
+    AfterHelp,
+    #[token("long_prefix")]
+    LongPrefix,
+    #[token("word_wrap")]
+    WordWrap,
+    #[token("deprecated")]
+    Deprecated,
+    #[token("hidden_from_completion")]
+    HiddenFromCompletion,
+    #[token("parse_fn")]
+    ParseFn,
+    #[token("append_env")]
+    AppendEnv,
+    #[token("prefix_flag")]
+    PrefixFlag,
+    #[token("config_file")]
+    ConfigFile,
+    #[token("env")]
+    Env,
+    #[token("env_no_override")]
+    EnvNoOverride,
+    #[token("clap_compat")]
+    ClapCompat,
+    #[token("deprecated_in")]
+    DeprecatedIn,
+    #[token("removed_in")]
+    RemovedIn,
+    #[token("env_list")]
+    EnvList,
+    #[token("exclusive_group")]
+    ExclusiveGroup,
+    #[token("requires_group")]
+    RequiresGroup,
+    #[token("next_line_help")]
+    NextLineHelp,
+    #[token("short_and_long")]
+    ShortAndLong,
+    #[token("from_str")]
+    FromStr,
+    #[token("default_value_t")]
+    DefaultValueT,
+    #[token("exit_code")]
+    ExitCode,
+    #[token("parse_error_exit")]
+    ParseErrorExit,
+    #[token("missing_arg_exit")]
+    MissingArgExit,
+    #[token("unknown_flag_exit")]
+    UnknownFlagExit,
+    #[token("index")]
+    Index,
+    #[token("env_prefix")]
+    EnvPrefix,
+    #[token("env_case")]
+    EnvCase,
+    #[token("parse_order")]
+    ParseOrder,
+    #[token("display_order")]
+    DisplayOrder,
+    #[token("input_file")]
+    InputFile,
+    #[token("output_file")]
+    OutputFile,
+    #[token("directory")]
+    Directory,
+    #[token("global_flag")]
+    GlobalFlag,
+    #[token("long_short_conflict")]
+    LongShortConflict,
 
     // Types
     #[token("string")]
@@ -68,6 +176,18 @@ pub(crate) enum Tokens {
     #[token("bool")]
     Bool,
 
+    // Literals
+    #[token("true")]
+    True,
+    #[token("false")]
+    False,
+    #[regex(r#""([^"\\]|\\.)*""#)]
+    StringLiteral,
+    #[regex("-?[0-9]+")]
+    IntegerLiteral,
+    #[regex(r"-?[0-9]+\.[0-9]+")]
+    FloatLiteral,
+
     // Generic
     #[regex("[a-zA-Z_]+")]
     Identifier,
@@ -79,14 +199,71 @@ impl Tokens {
             Tokens::Short,
             Tokens::Long,
             Tokens::Alias,
-            Tokens::Equals,
-            Tokens::Comma,
             Tokens::Flatten,
             Tokens::Main,
             Tokens::SubCommand,
+            Tokens::ExplicitBool,
+            Tokens::TakesValue,
+            Tokens::AllowAbbrev,
+            Tokens::Trailing,
+            Tokens::Separator,
+            Tokens::Prefix,
+            Tokens::ValueSeparator,
+            Tokens::Secret,
+            Tokens::DocHidden,
+            Tokens::MinOccurs,
+            Tokens::MaxOccurs,
+            Tokens::Min,
+            Tokens::Max,
+            Tokens::Regex,
+            Tokens::CaseInsensitive,
+            Tokens::Default,
+            Tokens::Doc,
+            Tokens::About,
+            Tokens::AfterHelp,
+            Tokens::LongPrefix,
+            Tokens::WordWrap,
+            Tokens::Deprecated,
+            Tokens::HiddenFromCompletion,
+            Tokens::ParseFn,
+            Tokens::AppendEnv,
+            Tokens::PrefixFlag,
+            Tokens::ConfigFile,
+            Tokens::Env,
+            Tokens::EnvNoOverride,
+            Tokens::ClapCompat,
+            Tokens::DeprecatedIn,
+            Tokens::RemovedIn,
+            Tokens::EnvList,
+            Tokens::ExclusiveGroup,
+            Tokens::RequiresGroup,
+            Tokens::NextLineHelp,
+            Tokens::ShortAndLong,
+            Tokens::FromStr,
+            Tokens::DefaultValueT,
+            Tokens::ExitCode,
+            Tokens::ParseErrorExit,
+            Tokens::MissingArgExit,
+            Tokens::UnknownFlagExit,
+            Tokens::Index,
+            Tokens::EnvPrefix,
+            Tokens::EnvCase,
+            Tokens::ParseOrder,
+            Tokens::DisplayOrder,
+            Tokens::InputFile,
+            Tokens::OutputFile,
+            Tokens::Directory,
+            Tokens::GlobalFlag,
+            Tokens::LongShortConflict,
         ]
     }
 
+    /// Whether the token can name a field or an option. Attribute names only mean something within
+    /// `#[...]`, so they are names anywhere else, e.g. a field named `output_file`.
+    pub fn is_name(&self) -> bool {
+        *self == Tokens::Identifier || Self::attribute_tokens().contains(self)
+    }
+
     pub const fn type_tokens() -> &'static [Self] {
         &[
             Tokens::String,
@@ -105,6 +282,37 @@ impl Tokens {
         ]
     }
 
+    /// The types `Vec` and `Optional` can wrap, which are all but themselves.
+    pub const fn value_type_tokens() -> &'static [Self] {
+        &[
+            Tokens::String,
+            Tokens::I16,
+            Tokens::U16,
+            Tokens::I32,
+            Tokens::U32,
+            Tokens::I64,
+            Tokens::U64,
+            Tokens::F32,
+            Tokens::F64,
+            Tokens::Bool,
+            Tokens::Identifier,
+        ]
+    }
+
+    /// A readable name for well known groups of tokens, so that error messages can say "expected
+    /// a type" instead of listing every single type token.
+    pub fn as_friendly_group_name(tokens: &[Tokens]) -> Option<&'static str> {
+        if tokens == Self::type_tokens() {
+            Some("a type")
+        } else if tokens == Self::value_type_tokens() {
+            Some("a type other than Vec and Optional")
+        } else if tokens == Self::attribute_tokens() {
+            Some("an attribute")
+        } else {
+            None
+        }
+    }
+
     pub fn as_attribute_type(&self) -> AttributeType {
         match self {
             Tokens::Short => AttributeType::Short,
@@ -113,6 +321,59 @@ impl Tokens {
             Tokens::Flatten => AttributeType::Flatten,
             Tokens::Main => AttributeType::Main,
             Tokens::SubCommand => AttributeType::SubCommand,
+            Tokens::ExplicitBool => AttributeType::ExplicitBool,
+            Tokens::TakesValue => AttributeType::TakesValue,
+            Tokens::AllowAbbrev => AttributeType::AllowAbbrev,
+            Tokens::Trailing => AttributeType::Trailing,
+            Tokens::Separator => AttributeType::Separator,
+            Tokens::Prefix => AttributeType::Prefix,
+            Tokens::ValueSeparator => AttributeType::ValueSeparator,
+            Tokens::Secret => AttributeType::Secret,
+            Tokens::DocHidden => AttributeType::DocHidden,
+            Tokens::MinOccurs => AttributeType::MinOccurs,
+            Tokens::MaxOccurs => AttributeType::MaxOccurs,
+            Tokens::Min => AttributeType::Min,
+            Tokens::Max => AttributeType::Max,
+            Tokens::Regex => AttributeType::Regex,
+            Tokens::CaseInsensitive => AttributeType::CaseInsensitive,
+            Tokens::Default => AttributeType::Default,
+            Tokens::Doc => AttributeType::Doc,
+            Tokens::About => AttributeType::About,
+            Tokens::AfterHelp => AttributeType::AfterHelp,
+            Tokens::LongPrefix => AttributeType::LongPrefix,
+            Tokens::WordWrap => AttributeType::WordWrap,
+            Tokens::Deprecated => AttributeType::Deprecated,
+            Tokens::HiddenFromCompletion => AttributeType::HiddenFromCompletion,
+            Tokens::ParseFn => AttributeType::ParseFn,
+            Tokens::AppendEnv => AttributeType::AppendEnv,
+            Tokens::PrefixFlag => AttributeType::PrefixFlag,
+            Tokens::ConfigFile => AttributeType::ConfigFile,
+            Tokens::Env => AttributeType::Env,
+            Tokens::EnvNoOverride => AttributeType::EnvNoOverride,
+            Tokens::ClapCompat => AttributeType::ClapCompat,
+            Tokens::DeprecatedIn => AttributeType::DeprecatedIn,
+            Tokens::RemovedIn => AttributeType::RemovedIn,
+            Tokens::EnvList => AttributeType::EnvList,
+            Tokens::ExclusiveGroup => AttributeType::ExclusiveGroup,
+            Tokens::RequiresGroup => AttributeType::RequiresGroup,
+            Tokens::NextLineHelp => AttributeType::NextLineHelp,
+            Tokens::ShortAndLong => AttributeType::ShortAndLong,
+            Tokens::FromStr => AttributeType::FromStr,
+            Tokens::DefaultValueT => AttributeType::DefaultValueT,
+            Tokens::ExitCode => AttributeType::ExitCode,
+            Tokens::ParseErrorExit => AttributeType::ParseErrorExit,
+            Tokens::MissingArgExit => AttributeType::MissingArgExit,
+            Tokens::UnknownFlagExit => AttributeType::UnknownFlagExit,
+            Tokens::Index => AttributeType::Index,
+            Tokens::EnvPrefix => AttributeType::EnvPrefix,
+            Tokens::EnvCase => AttributeType::EnvCase,
+            Tokens::ParseOrder => AttributeType::ParseOrder,
+            Tokens::DisplayOrder => AttributeType::DisplayOrder,
+            Tokens::InputFile => AttributeType::InputFile,
+            Tokens::OutputFile => AttributeType::OutputFile,
+            Tokens::Directory => AttributeType::Directory,
+            Tokens::GlobalFlag => AttributeType::GlobalFlag,
+            Tokens::LongShortConflict => AttributeType::LongShortConflict,
             _ => unreachable!(),
         }
     }
@@ -155,6 +416,59 @@ impl Tokens {
             Tokens::Flatten => "flatten",
             Tokens::Main => "main",
             Tokens::SubCommand => "subcommand",
+            Tokens::ExplicitBool => "explicit_bool",
+            Tokens::TakesValue => "takes_value",
+            Tokens::AllowAbbrev => "allow_abbrev",
+            Tokens::Trailing => "trailing",
+            Tokens::Separator => "separator",
+            Tokens::Prefix => "prefix",
+            Tokens::ValueSeparator => "value_separator",
+            Tokens::Secret => "secret",
+            Tokens::DocHidden => "doc_hidden",
+            Tokens::MinOccurs => "min_occurs",
+            Tokens::MaxOccurs => "max_occurs",
+            Tokens::Min => "min",
+            Tokens::Max => "max",
+            Tokens::Regex => "regex",
+            Tokens::CaseInsensitive => "case_insensitive",
+            Tokens::Default => "default",
+            Tokens::Doc => "doc",
+            Tokens::About => "about",
+            Tokens::AfterHelp => "after_help",
+            Tokens::LongPrefix => "long_prefix",
+            Tokens::WordWrap => "word_wrap",
+            Tokens::Deprecated => "deprecated",
+            Tokens::HiddenFromCompletion => "hidden_from_completion",
+            Tokens::ParseFn => "parse_fn",
+            Tokens::AppendEnv => "append_env",
+            Tokens::PrefixFlag => "prefix_flag",
+            Tokens::ConfigFile => "config_file",
+            Tokens::Env => "env",
+            Tokens::EnvNoOverride => "env_no_override",
+            Tokens::ClapCompat => "clap_compat",
+            Tokens::DeprecatedIn => "deprecated_in",
+            Tokens::RemovedIn => "removed_in",
+            Tokens::EnvList => "env_list",
+            Tokens::ExclusiveGroup => "exclusive_group",
+            Tokens::RequiresGroup => "requires_group",
+            Tokens::NextLineHelp => "next_line_help",
+            Tokens::ShortAndLong => "short_and_long",
+            Tokens::FromStr => "from_str",
+            Tokens::DefaultValueT => "default_value_t",
+            Tokens::ExitCode => "exit_code",
+            Tokens::ParseErrorExit => "parse_error_exit",
+            Tokens::MissingArgExit => "missing_arg_exit",
+            Tokens::UnknownFlagExit => "unknown_flag_exit",
+            Tokens::Index => "index",
+            Tokens::EnvPrefix => "env_prefix",
+            Tokens::EnvCase => "env_case",
+            Tokens::ParseOrder => "parse_order",
+            Tokens::DisplayOrder => "display_order",
+            Tokens::InputFile => "input_file",
+            Tokens::OutputFile => "output_file",
+            Tokens::Directory => "directory",
+            Tokens::GlobalFlag => "global_flag",
+            Tokens::LongShortConflict => "long_short_conflict",
             Tokens::String => "string",
             Tokens::I16 => "i16",
             Tokens::U16 => "u16",
@@ -167,7 +481,52 @@ impl Tokens {
             Tokens::Vec => "Vec",
             Tokens::Optional => "Optional",
             Tokens::Bool => "bool",
+            Tokens::True => "true",
+            Tokens::False => "false",
+            Tokens::StringLiteral => "string literal",
+            Tokens::IntegerLiteral => "integer literal",
+            Tokens::FloatLiteral => "float literal",
             Tokens::Identifier => "regex: [a-z,A-Z_]+",
         }
     }
 }
+
+/// The tokens of `source` as printed by `--dump-tokens`, one per line with its span, its kind and
+/// the text it was lexed from, e.g. `3:7-3:11   Long             "long"`. Text that is not a
+/// token is listed as an `Error`.
+pub fn dump_tokens(source: &str) -> String {
+    let mut res = String::new();
+    let source_map = SourceMap::new(source);
+
+    for (token, span) in Tokens::lexer(source).spanned() {
+        let kind = match token {
+            Ok(token) => format!("{token:?}"),
+            Err(()) => String::from("Error"),
+        };
+        let span_text = source_map.source_span(&span).to_string();
+        writeln!(res, "{span_text:<12} {kind:<16} {:?}", &source[span]).unwrap();
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_dumped_with_their_spans() {
+        assert_eq!(
+            dump_tokens("#[long]\nport: u16 $"),
+            r##"1:1-1:2      Pound            "#"
+1:2-1:3      LSquareBracket   "["
+1:3-1:7      Long             "long"
+1:7-1:8      RSquareBracket   "]"
+2:1-2:5      Identifier       "port"
+2:5-2:6      Colon            ":"
+2:7-2:10     U16              "u16"
+2:11-2:12    Error            "$"
+"##
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Generates the code parsing the command line arguments described by a spec, for C++, C and Go.
+//!
+//! A spec is parsed with [`parse_spec`], checked with [`check`], and the code of a [`Target`] is
+//! generated from both with [`generate`]. Nothing touches the filesystem, so the generator can run
+//! from a build script or a test:
+//!
+//! ```
+//! use cli_generator::{check, generate, parse_spec, GenOptions, Target};
+//!
+//! let source = "struct Args {\n    #[short, long]\n    verbose: bool,\n}\n";
+//! let spec = parse_spec(source).map_err(|diagnostics| diagnostics.render(source)).unwrap();
+//! let metadata = check(&spec).map_err(|diagnostics| diagnostics.render(source)).unwrap();
+//! let files = generate(&spec, &metadata, Target::Cpp, &GenOptions::default()).unwrap();
+//! assert!(files[0].contents.contains("struct Args"));
+//! ```
+
+mod builder;
+mod diagnostic;
+mod format;
+mod generate;
+mod hash;
+mod json_input;
+mod lexer;
+mod parse;
+mod semantic;
+mod source_map;
+mod types;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+use std::fmt;
+
+pub use crate::builder::{FieldBuilder, SpecBuilder, StructBuilder};
+pub use crate::diagnostic::{Diagnostic, Diagnostics, Severity};
+pub use crate::generate::cpp::{CppStandard, LineEnding, TargetOs};
+pub use crate::generate::{GenOptions, Indent, OutputFile, Target};
+pub use crate::types::{
+    Attribute, AttributeType, Field, FieldType, NumericBound, OptionSyntax, Spec, SpecMetadata,
+    Struct, StructMetadata, StructOption, ValueKind,
+};
+
+/// The parts of the generator used by the `cli-generator` binary, which are not a stable API.
+#[doc(hidden)]
+pub mod internal {
+    pub use crate::diagnostic::{diagnostics_json, ColorChoice, ErrorFormat};
+    pub use crate::format::format_spec;
+    pub use crate::generate::{
+        c, cmake, code_backend, completions, cpp, cpp_tests, dot, go, json_schema, spec_json,
+        Backend, OutputFormat, Provenance,
+    };
+    pub use crate::hash::spec_hash;
+    pub use crate::json_input::InputFormat;
+    pub use crate::lexer::dump_tokens;
+}
+
+/// Why code can't be generated from a valid spec.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The spec uses something the target language can't express, e.g. positional arguments in Go.
+    Unsupported(Diagnostics),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unsupported(diagnostics) => write!(f, "{diagnostics}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Unsupported(diagnostics) => Some(diagnostics),
+        }
+    }
+}
+
+/// Parses the spec in `source`. The diagnostics point into `source`, which they are rendered with.
+///
+/// Any input gives either a spec or diagnostics, it never panics. Neither does [`check`] with the
+/// spec, so both can be run on untrusted input, e.g. in an editor as the spec is typed.
+pub fn parse_spec(source: &str) -> Result<Spec<'_>, Diagnostics> {
+    parse::Parser::new(source)
+        .parse()
+        .map_err(|err| Diagnostics::new(err.errors))
+}
+
+/// Parses the spec in `source` as far as it can be, for editors and other tools working on a spec
+/// as it is being written. Along with the diagnostics, the spec has every struct and field that
+/// could be parsed, the fields with a broken type being given a [`FieldType::Error`] one. Without
+/// diagnostics, the spec is the one [`parse_spec`] gives.
+pub fn parse_spec_tolerant(source: &str) -> (Spec<'_>, Vec<Diagnostic>) {
+    parse::Parser::new(source).parse_tolerant()
+}
+
+/// Reads the spec in the JSON `source`, written like a [`Spec`] is serialized, e.g. by a tool
+/// generating specs. The diagnostics point into `source`, at the values they are about, and the
+/// values that can't be deserialized are reported along with their path in the JSON, e.g.
+/// `$.structs[0].fields[1].type`. The spec is checked and generated from like a parsed one.
+pub fn parse_spec_json(source: &str) -> Result<Spec<'_>, Diagnostics> {
+    json_input::parse_json(source).map_err(Diagnostics::new)
+}
+
+/// Checks that `spec` is valid, which is required to generate code from it, and gathers what the
+/// backends need to know about it.
+pub fn check<'s>(spec: &'s Spec) -> Result<SpecMetadata<'s>, Diagnostics> {
+    match semantic::check_semantics(spec) {
+        Ok(mut metadata) => {
+            metadata.warnings = locate(spec, metadata.warnings);
+            Ok(metadata)
+        }
+        Err(errors) => Err(Diagnostics::new(locate(spec, errors))),
+    }
+}
+
+/// Generates the code of `target` from a spec checked by [`check`]. The files are named after
+/// `cli`, e.g. `cli.h` and `cli.c` for C, and the Go code is part of the `main` package. Nothing is
+/// written, and the code has no banner telling where it comes from.
+pub fn generate<'s>(
+    spec: &'s Spec,
+    spec_metadata: &SpecMetadata<'s>,
+    target: Target,
+    options: &GenOptions,
+) -> Result<Vec<OutputFile>, Error> {
+    let output = format!("cli.{}", target.output_extension());
+    let backend = generate::code_backend(target, &output, None, false, "main");
+    backend
+        .generate(spec, spec_metadata, options)
+        .map_err(|errors| Error::Unsupported(Diagnostics::new(locate(spec, errors))))
+}
+
+/// The diagnostics of a spec built with [`SpecBuilder`] describe what they are about, as it has no
+/// source to show.
+fn locate(spec: &Spec, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    if !spec.is_built {
+        return diagnostics;
+    }
+
+    diagnostics
+        .into_iter()
+        .map(|diagnostic| diagnostic.locate(&spec.source))
+        .collect()
+}
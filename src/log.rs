@@ -0,0 +1,89 @@
+use std::time::Instant;
+
+/// How much the tool tells about what it does. Errors are printed whatever the verbosity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Verbosity {
+    /// Only the errors, given by `--quiet`.
+    Quiet,
+    /// The errors and the status of every input when several are generated at once.
+    Normal,
+    /// Also how long every phase takes, what the spec is made of and what is written, given by
+    /// `--verbose`.
+    Verbose,
+}
+
+/// Prints the messages of the tool to stderr, prefixed with the input they are about when
+/// several inputs are generated at once.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Logger<'a> {
+    verbosity: Verbosity,
+    color: bool,
+    prefix: Option<&'a str>,
+}
+
+impl<'a> Logger<'a> {
+    pub fn new(verbosity: Verbosity, color: bool) -> Self {
+        Self {
+            verbosity,
+            color,
+            prefix: None,
+        }
+    }
+
+    /// The logger of the messages about `input`, which are prefixed with it when given.
+    pub fn for_input(&self, input: Option<&'a str>) -> Self {
+        Self {
+            prefix: input,
+            ..*self
+        }
+    }
+
+    /// Whether the messages are colored, as chosen by `--color`.
+    pub fn colors(&self) -> bool {
+        self.color
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.verbosity == Verbosity::Quiet
+    }
+
+    pub fn is_verbose(&self) -> bool {
+        self.verbosity == Verbosity::Verbose
+    }
+
+    fn print(&self, message: &str) {
+        match self.prefix {
+            Some(prefix) => eprintln!("{prefix}: {message}"),
+            None => eprintln!("{message}"),
+        }
+    }
+
+    pub fn error(&self, message: &str) {
+        self.print(message);
+    }
+
+    /// Prints `message` as a warning, unless only the errors are printed.
+    pub fn warning(&self, message: &str) {
+        self.info(&format!("warning: {message}"));
+    }
+
+    pub fn info(&self, message: &str) {
+        if self.verbosity >= Verbosity::Normal {
+            self.print(message);
+        }
+    }
+
+    pub fn verbose(&self, message: &str) {
+        if self.is_verbose() {
+            self.print(message);
+        }
+    }
+
+    /// Runs `phase`, logging how long it took as `{what} in 1.23ms` in verbose mode.
+    pub fn timed<T>(&self, what: &str, phase: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = phase();
+        self.verbose(&format!("{what} in {:.2?}", start.elapsed()));
+        result
+    }
+}
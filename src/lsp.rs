@@ -0,0 +1,330 @@
+//! A minimal language server for specs, run by `cli-generator lsp` over stdin and stdout. It
+//! publishes the diagnostics of the parser and the semantic checks whenever a spec is opened or
+//! changed, and lists the structs and fields of a spec for the outline of editors.
+
+use cli_generator::{Diagnostic, Severity, Spec};
+use logos::Span;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// The error code of JSON-RPC for requests the server doesn't handle.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// `SymbolKind.Field` and `SymbolKind.Struct` of the protocol.
+const SYMBOL_KIND_FIELD: u8 = 8;
+const SYMBOL_KIND_STRUCT: u8 = 23;
+
+/// Reads the next message, framed by a `Content-Length` header. Returns `None` once the input is
+/// closed.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
+
+/// A position of the protocol: the line counted from 0, and the character in UTF-16 code units.
+fn position(source: &str, offset: usize) -> Value {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+fn range(source: &str, span: &Span) -> Value {
+    json!({ "start": position(source, span.start), "end": position(source, span.end) })
+}
+
+fn lsp_diagnostic(uri: &str, source: &str, diagnostic: &Diagnostic) -> Value {
+    let message = match &diagnostic.help {
+        Some(help) => format!("{}\n{help}", diagnostic.message),
+        None => diagnostic.message.clone(),
+    };
+    let related_information = diagnostic
+        .info
+        .as_deref()
+        .map(|(span, message)| {
+            vec![json!({
+                "location": { "uri": uri, "range": range(source, span) },
+                "message": message,
+            })]
+        })
+        .unwrap_or_default();
+
+    // The `DiagnosticSeverity` of the protocol.
+    let severity = match diagnostic.severity {
+        Severity::Warning => 2,
+        _ => 1,
+    };
+
+    json!({
+        "range": range(source, &diagnostic.span),
+        "severity": severity,
+        "code": diagnostic.label,
+        "source": "cli-generator",
+        "message": message,
+        "relatedInformation": related_information,
+    })
+}
+
+/// The diagnostics of the parser, or of the semantic checks when the spec parses, warnings
+/// included.
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    match cli_generator::parse_spec(source) {
+        Ok(spec) => match cli_generator::check(&spec) {
+            Ok(metadata) => metadata.warnings,
+            Err(diagnostics) => diagnostics.into_vec(),
+        },
+        Err(diagnostics) => diagnostics.into_vec(),
+    }
+}
+
+/// The structs of the spec with their fields as children, each spanning its whole declaration.
+fn document_symbols(spec: &Spec) -> Vec<Value> {
+    let source = &spec.source;
+    let symbol = |name: &str, kind, span: Span, selection: &Span, children: Vec<Value>| {
+        json!({
+            "name": name,
+            "kind": kind,
+            "range": range(source, &span),
+            "selectionRange": range(source, selection),
+            "children": children,
+        })
+    };
+
+    spec.structs
+        .iter()
+        .map(|strukt| {
+            let fields = strukt
+                .fields
+                .iter()
+                .map(|field| {
+                    symbol(
+                        &field.name,
+                        SYMBOL_KIND_FIELD,
+                        field.span(),
+                        &field.name_span(),
+                        vec![],
+                    )
+                })
+                .collect();
+
+            symbol(
+                &strukt.name,
+                SYMBOL_KIND_STRUCT,
+                strukt.span(),
+                &strukt.name_span,
+                fields,
+            )
+        })
+        .collect()
+}
+
+/// The specs opened in the editor, by their URI.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    fn publish_diagnostics(&self, output: &mut impl Write, uri: &str) -> io::Result<()> {
+        let diagnostics = self.documents.get(uri).map_or_else(Vec::new, |source| {
+            diagnostics(source)
+                .iter()
+                .map(|diagnostic| lsp_diagnostic(uri, source, diagnostic))
+                .collect()
+        });
+
+        write_message(
+            output,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+
+    /// The result of a request, or the code and message of its error.
+    fn handle_request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // Every change sends the whole spec, which is always small.
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "cli-generator", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/documentSymbol" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let Some(source) = self.documents.get(uri) else {
+                    return Ok(Value::Null);
+                };
+                // Specs that are being written are outlined with what could be parsed of them.
+                let (spec, _) = cli_generator::parse_spec_tolerant(source);
+                Ok(Value::Array(document_symbols(&spec)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unhandled method {method}"))),
+        }
+    }
+
+    /// Keeps track of the opened specs, publishing their diagnostics whenever they change.
+    fn handle_notification(
+        &mut self,
+        output: &mut impl Write,
+        method: &str,
+        params: &Value,
+    ) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                else {
+                    return Ok(());
+                };
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            // The diagnostics of closed specs are cleared.
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return Ok(()),
+        }
+
+        self.publish_diagnostics(output, &uri)
+    }
+}
+
+/// Serves the editor until it sends `exit`, returning whether it asked to shut down first as the
+/// protocol requires.
+pub(crate) fn serve(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<bool> {
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        if method == "exit" {
+            return Ok(server.shutdown);
+        }
+        // Responses to requests of the server are never expected, since it doesn't make any.
+        let Some(id) = message.get("id").filter(|_| !method.is_empty()) else {
+            server.handle_notification(output, method, params)?;
+            continue;
+        };
+
+        let response = match server.handle_request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(output, &response)?;
+    }
+
+    Ok(server.shutdown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framed(message: &Value) -> String {
+        let content = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{content}", content.len())
+    }
+
+    #[test]
+    fn positions_count_lines_from_zero_and_characters_in_utf16() {
+        let source = "struct Cli {\n    // ü😀\n    x: u32\n}";
+        let x = source.find('x').unwrap();
+        assert_eq!(position(source, x), json!({ "line": 2, "character": 4 }));
+        let end_of_comment = source.find("\n    x").unwrap();
+        assert_eq!(
+            position(source, end_of_comment),
+            json!({ "line": 1, "character": 10 })
+        );
+    }
+
+    #[test]
+    fn messages_are_answered_until_exit() {
+        let input = [
+            framed(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+            framed(&json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover" })),
+            framed(&json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" })),
+            framed(&json!({ "jsonrpc": "2.0", "method": "exit" })),
+        ]
+        .concat();
+
+        let mut output = Vec::new();
+        assert!(serve(&mut input.as_bytes(), &mut output).unwrap());
+
+        let mut output = output.as_slice();
+        let initialize = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(
+            initialize["result"]["capabilities"]["documentSymbolProvider"],
+            true
+        );
+        let hover = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(hover["error"]["code"], METHOD_NOT_FOUND);
+        let shutdown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(
+            shutdown,
+            json!({ "jsonrpc": "2.0", "id": 3, "result": null })
+        );
+        assert!(read_message(&mut output).unwrap().is_none());
+    }
+}
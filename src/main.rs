@@ -1,38 +1,654 @@
+#[cfg(not(target_arch = "wasm32"))]
 mod cli;
-mod generate;
-mod lexer;
-mod parse;
-mod semantic;
-mod types;
+#[cfg(not(target_arch = "wasm32"))]
+mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod diff;
+#[cfg(not(target_arch = "wasm32"))]
+mod log;
+#[cfg(not(target_arch = "wasm32"))]
+mod lsp;
 
-use crate::cli::Cli;
-use crate::parse::Parser;
-use crate::semantic::check_semantics;
-use clap::Parser as ClapParser;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::cli::{Cli, Command};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::log::Logger;
+#[cfg(not(target_arch = "wasm32"))]
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
+#[cfg(not(target_arch = "wasm32"))]
+use cli_generator::internal::{
+    cmake, completions, cpp, cpp_tests, diagnostics_json, dot, dump_tokens, format_spec,
+    json_schema, spec_hash, spec_json, Backend, ErrorFormat, InputFormat, OutputFormat, Provenance,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use cli_generator::{AttributeType, Diagnostic, GenOptions, Indent, Severity, Target};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Read;
 
-fn main() {
-    let options = Cli::parse();
+/// The backend writing the output, selected by `--output-format` and `--target`.
+#[cfg(not(target_arch = "wasm32"))]
+fn backend<'a>(
+    options: &'a Cli,
+    output: &'a str,
+    provenance: &'a Provenance<'a>,
+) -> Box<dyn Backend + 'a> {
+    if options.output_format == OutputFormat::JsonSchema {
+        return Box::new(json_schema::JsonSchemaBackend { output });
+    }
+
+    cli_generator::internal::code_backend(
+        options.target,
+        output,
+        Some(provenance),
+        options.split_implementation,
+        &options.go_package,
+    )
+}
+
+/// Why the generator failed, which is the only thing its exit code depends on.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// An output is not up to date with `--check` or `--verify`, or a spec is not formatted with
+    /// `fmt --check`.
+    Outdated,
+    /// The spec can't be parsed.
+    Parse,
+    /// The spec is parsed but is not valid, or can't be expressed in the target language.
+    Semantic,
+    /// The spec or the config can't be read, or an output can't be written.
+    Io,
+    /// The arguments are wrong or don't go together.
+    Usage,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Failure {
+    const fn exit_code(self) -> i32 {
+        match self {
+            Failure::Outdated => 1,
+            Failure::Parse => 2,
+            Failure::Semantic => 3,
+            Failure::Io => 4,
+            Failure::Usage => 5,
+        }
+    }
+}
+
+/// Prints a usage error of clap and exits with the exit code of [`Failure::Usage`]. `--help` and
+/// `--version` are not failures, so they still exit with 0.
+#[cfg(not(target_arch = "wasm32"))]
+fn exit_with_usage_error(err: clap::Error) -> ! {
+    if !err.use_stderr() {
+        err.exit();
+    }
+    let _ = err.print();
+    std::process::exit(Failure::Usage.exit_code());
+}
+
+/// The outputs besides the generated code, listed by `--list-emitters`, each written by the
+/// `--emit-` flag of its name.
+#[cfg(not(target_arch = "wasm32"))]
+const EMITTERS: &[&str] = &["completions", "spec-json", "dot", "tests", "cmake"];
+
+/// The path standing for stdin as the input and for stdout as an output.
+#[cfg(not(target_arch = "wasm32"))]
+const STDIO_PATH: &str = "-";
+
+/// Writes `generated` to `output` unless it is already up to date, which is what is returned.
+/// Outputs written to stdout are always up to date.
+#[cfg(not(target_arch = "wasm32"))]
+fn update_output(
+    output: &str,
+    generated: &str,
+    options: &Cli,
+    logger: &Logger,
+) -> Result<bool, String> {
+    if output == STDIO_PATH {
+        if !options.check {
+            print!("{generated}");
+            logger.verbose(&format!("wrote {} bytes to stdout", generated.len()));
+        }
+        return Ok(true);
+    }
+
+    // The output is left untouched when it is up to date, so that its modification time does not
+    // trigger rebuilds of everything that includes it.
+    let existing = std::fs::read_to_string(output).unwrap_or_default();
+    if cpp::same_generated_code(&existing, generated) {
+        logger.verbose(&format!("{output} is up to date"));
+        return Ok(true);
+    }
+
+    if options.diff {
+        print!(
+            "{}",
+            diff::unified_diff(&existing, generated, output, output)
+        );
+    }
+
+    if options.check {
+        logger.error(&format!("{output} is not up to date"));
+    } else {
+        if let Some(parent) = std::path::Path::new(output).parent() {
+            std::fs::create_dir_all(parent).map_err(|err| {
+                format!(
+                    "error: cannot create the directory '{}' of '{output}': {err}",
+                    parent.display()
+                )
+            })?;
+        }
+        std::fs::write(output, generated)
+            .map_err(|err| format!("error: cannot write '{output}': {err}"))?;
+        logger.verbose(&format!("wrote {} bytes to {output}", generated.len()));
+    }
+
+    Ok(false)
+}
+
+/// Rejects the options that need the input or the output to be a file when they are stdin or
+/// stdout.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_stdio_paths(options: &Cli, input: &str, output: Option<&str>) -> Result<(), &'static str> {
+    if output == Some(STDIO_PATH) {
+        if options.target == Target::C && options.output_format == OutputFormat::Code {
+            return Err("The C code is made of two files, so it can't be written to stdout");
+        }
+        if options.split_implementation {
+            return Err(
+                "The split C++ code is made of two files, so it can't be written to stdout",
+            );
+        }
+        if options.verify {
+            return Err("Only outputs written to files can be verified");
+        }
+        if options.emit_tests.is_some() {
+            return Err("Tests include the output by its name, so it can't be written to stdout");
+        }
+    }
+    if input == STDIO_PATH && options.emit_cmake.is_some() {
+        return Err("The generator can't be run again by CMake on a spec read from stdin");
+    }
 
-    let contents = std::fs::read_to_string(options.input).unwrap();
+    Ok(())
+}
+
+/// The extension of the outputs named after their input in `--out-dir`.
+#[cfg(not(target_arch = "wasm32"))]
+fn output_extension(options: &Cli) -> &'static str {
+    if options.output_format == OutputFormat::JsonSchema {
+        return "schema.json";
+    }
+
+    options.target.output_extension()
+}
+
+/// Pairs every input with its output, given by `--output` in the same order or named after the
+/// input in `--out-dir`.
+#[cfg(not(target_arch = "wasm32"))]
+fn inputs_and_outputs(options: &Cli) -> Result<Vec<(&str, Option<String>)>, String> {
+    if options.input.len() > 1 {
+        let single_input_options = [
+            ("--emit-completions", options.emit_completions.is_some()),
+            ("--emit-spec-json", options.emit_spec_json.is_some()),
+            ("--emit-dot", options.emit_dot.is_some()),
+            ("--emit-tests", options.emit_tests.is_some()),
+            ("--emit-cmake", options.emit_cmake.is_some()),
+        ];
+        if let Some((name, _)) = single_input_options.iter().find(|(_, given)| *given) {
+            return Err(format!("{name} can only be used with a single input"));
+        }
+        if options
+            .input
+            .iter()
+            .filter(|input| *input == STDIO_PATH)
+            .count()
+            > 1
+        {
+            return Err(String::from("The spec can only be read from stdin once"));
+        }
+    }
 
-    let mut parser = Parser::new(&contents);
+    if let Some(out_dir) = &options.out_dir {
+        return options
+            .input
+            .iter()
+            .map(|input| {
+                let stem = std::path::Path::new(input)
+                    .file_stem()
+                    .filter(|_| input != STDIO_PATH)
+                    .ok_or_else(|| format!("{input} has no name to name its output after"))?;
+                let output = std::path::Path::new(out_dir).join(format!(
+                    "{}.{}",
+                    stem.to_string_lossy(),
+                    output_extension(options)
+                ));
+                Ok((input.as_str(), Some(output.to_string_lossy().into_owned())))
+            })
+            .collect();
+    }
 
-    let spec = match parser.parse() {
+    if options.output.is_empty() {
+        return Ok(options
+            .input
+            .iter()
+            .map(|input| (input.as_str(), None))
+            .collect());
+    }
+    if options.output.len() != options.input.len() {
+        return Err(format!(
+            "{} outputs were given for {} inputs",
+            options.output.len(),
+            options.input.len()
+        ));
+    }
+
+    Ok(options
+        .input
+        .iter()
+        .map(String::as_str)
+        .zip(options.output.iter().cloned().map(Some))
+        .collect())
+}
+
+/// Generates everything asked for from a single input, returning why it failed if it did.
+/// Everything is logged by `logger`, which tells which input it is about when there are several.
+#[cfg(not(target_arch = "wasm32"))]
+fn run(options: &Cli, input: &str, output: Option<&str>, logger: &Logger) -> Result<(), Failure> {
+    let report = |err: &str| logger.error(err);
+
+    if let Err(err) = check_stdio_paths(options, input, output) {
+        report(err);
+        return Err(Failure::Usage);
+    }
+
+    let (input, contents) = if input == STDIO_PATH {
+        let mut contents = String::new();
+        if let Err(err) = std::io::stdin().read_to_string(&mut contents) {
+            report(&format!("error: cannot read the spec from stdin: {err}"));
+            return Err(Failure::Io);
+        }
+        ("<stdin>", contents)
+    } else {
+        match std::fs::read_to_string(input) {
+            Ok(contents) => (input, contents),
+            Err(err) => {
+                report(&format!("error: cannot read '{input}': {err}"));
+                return Err(Failure::Io);
+            }
+        }
+    };
+    if options.dump_tokens {
+        if options.input_format == InputFormat::Json {
+            report("error: --dump-tokens can't be used with --input-format json");
+            return Err(Failure::Usage);
+        }
+        print!("{}", dump_tokens(&contents));
+        return Ok(());
+    }
+
+    let provenance = Provenance::new(input, &contents, !options.no_timestamp);
+    let report_diagnostics = |diagnostics: &[Diagnostic]| {
+        // Warnings are left out with `--quiet`, like everything but the errors.
+        let diagnostics = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error || !logger.is_quiet())
+            .cloned()
+            .collect::<Vec<_>>();
+        if diagnostics.is_empty() {
+            return;
+        }
+
+        match options.error_format {
+            ErrorFormat::Human => {
+                for diagnostic in &diagnostics {
+                    report(&diagnostic.render_colored(&contents, logger.colors()));
+                }
+            }
+            // The JSON tells which input the diagnostics come from by itself.
+            ErrorFormat::Json => {
+                logger
+                    .for_input(None)
+                    .error(&diagnostics_json(input, &contents, &diagnostics))
+            }
+        }
+    };
+
+    if options.verify {
+        let output = output.unwrap();
+        for output in &backend(options, output, &provenance).output_paths() {
+            let existing = std::fs::read_to_string(output).unwrap_or_default();
+            if cpp::embedded_spec_hash(&existing) != Some(&spec_hash(&contents)) {
+                report(&format!(
+                    "{output} was not generated from the current version of {input}"
+                ));
+                return Err(Failure::Outdated);
+            }
+        }
+        return Ok(());
+    }
+
+    let parsed = logger.timed("parsed", || match options.input_format {
+        InputFormat::Dsl => cli_generator::parse_spec(&contents),
+        InputFormat::Json => cli_generator::parse_spec_json(&contents),
+    });
+    let spec = match parsed {
         Ok(spec) => spec,
-        Err(err) => {
-            eprintln!("{err}");
-            std::process::exit(1);
+        Err(diagnostics) => {
+            report_diagnostics(&diagnostics);
+            return Err(Failure::Parse);
         }
     };
 
-    let metadata = match check_semantics(&spec) {
+    if options.dump_ast {
+        print!("{spec}");
+        return Ok(());
+    }
+
+    let metadata = match logger.timed("checked", || cli_generator::check(&spec)) {
         Ok(metadata) => metadata,
+        Err(diagnostics) => {
+            report_diagnostics(&diagnostics);
+            return Err(Failure::Semantic);
+        }
+    };
+    report_diagnostics(&metadata.warnings);
+    if logger.is_verbose() {
+        let fields = spec.structs.iter().flat_map(|strukt| &strukt.fields);
+        let options = fields
+            .clone()
+            .flat_map(|field| &field.attributes)
+            .filter(|attribute| {
+                matches!(
+                    attribute.ty,
+                    AttributeType::Short | AttributeType::Long | AttributeType::Alias
+                )
+            })
+            .count();
+        logger.verbose(&format!(
+            "{} structs, {} fields, {options} options",
+            spec.structs.len(),
+            fields.count()
+        ));
+    }
+
+    // The options can only be built from the default ones, as more may be added to the library.
+    let mut gen_options = GenOptions::default();
+    gen_options.cpp_std = options.cpp_std;
+    gen_options.target_os = options.target_os;
+    gen_options.accessors = options.accessors;
+    gen_options.cpp_noexcept = options.cpp_noexcept;
+    gen_options.doxygen = options.emit_doxygen;
+    gen_options.no_regex_validation = options.no_regex_validation;
+    gen_options.validate_directory = options.validate_directory;
+    gen_options.to_command_line = options.with_to_command_line;
+    gen_options.indent = if options.indent_tabs {
+        Indent::Tabs
+    } else {
+        Indent::Spaces(options.indent)
+    };
+    gen_options.line_ending = options.line_ending;
+
+    let mut up_to_date = true;
+    let mut write_output =
+        |path: &str, generated: &str| match update_output(path, generated, options, logger) {
+            Ok(updated) => {
+                up_to_date &= updated;
+                Ok(())
+            }
+            Err(err) => {
+                report(&err);
+                Err(Failure::Io)
+            }
+        };
+
+    if let (Some(shell), Some(completions_out)) =
+        (options.emit_completions, &options.completions_out)
+    {
+        let completions = completions::generate_completions(
+            shell,
+            &spec,
+            &metadata,
+            &gen_options,
+            Some(&provenance),
+            options.completions_command.as_deref(),
+        );
+        write_output(completions_out, &completions)?;
+    }
+
+    if let Some(spec_json_out) = &options.emit_spec_json {
+        let spec_json = spec_json::generate_spec_json(&spec);
+        write_output(spec_json_out, &spec_json)?;
+    }
+
+    if let Some(dot_out) = &options.emit_dot {
+        let dot = dot::generate_dot(&spec, &metadata, &gen_options, Some(&provenance));
+        write_output(dot_out, &dot)?;
+    }
+
+    if let (Some(tests_out), Some(output)) = (&options.emit_tests, output) {
+        if options.target != Target::Cpp || options.output_format != OutputFormat::Code {
+            report("Tests can only be generated for the C++ code");
+            return Err(Failure::Usage);
+        }
+
+        // The tests include the header by its name, as they are expected next to each other.
+        let header_name = std::path::Path::new(output).file_name().map_or_else(
+            || output.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let tests = cpp_tests::generate_cpp_tests(
+            &spec,
+            &metadata,
+            &gen_options,
+            &header_name,
+            Some(&provenance),
+        );
+        write_output(tests_out, &tests)?;
+    }
+
+    if let Some(cmake_out) = &options.emit_cmake {
+        let mut outputs = output.map_or_else(Vec::new, |output| {
+            backend(options, output, &provenance).output_paths()
+        });
+        outputs.extend(
+            [
+                &options.completions_out,
+                &options.emit_spec_json,
+                &options.emit_dot,
+                &options.emit_tests,
+            ]
+            .into_iter()
+            .flatten()
+            .cloned(),
+        );
+
+        let snippet = cmake::generate_cmake_snippet(
+            &std::env::current_exe().unwrap().to_string_lossy(),
+            &std::env::current_dir().unwrap().to_string_lossy(),
+            &cmake::replayed_args(std::env::args().skip(1)),
+            input,
+            &outputs,
+        );
+        write_output(cmake_out, &snippet)?;
+    }
+
+    // The output is only optional when the completion script, the spec JSON or the graph is written
+    // instead, or when the spec is only checked.
+    if let Some(output) = output {
+        let generated = logger.timed("generated", || {
+            backend(options, output, &provenance).generate(&spec, &metadata, &gen_options)
+        });
+        let files = match generated {
+            Ok(files) => files,
+            Err(errors) => {
+                report_diagnostics(&errors);
+                return Err(Failure::Semantic);
+            }
+        };
+        for file in &files {
+            write_output(&file.path, &file.contents)?;
+        }
+    }
+
+    if options.check && !up_to_date {
+        return Err(Failure::Outdated);
+    }
+    Ok(())
+}
+
+/// Formats `file` for `fmt`, rewriting it unless it is already formatted. Returns why it failed,
+/// which includes `check` finding it is not formatted.
+#[cfg(not(target_arch = "wasm32"))]
+fn format_file(file: &str, stdout: bool, check: bool, logger: &Logger) -> Result<(), Failure> {
+    let contents = match std::fs::read_to_string(file) {
+        Ok(contents) => contents,
         Err(err) => {
-            eprintln!("{err}");
-            std::process::exit(1);
+            logger.error(&format!("error: cannot read '{file}': {err}"));
+            return Err(Failure::Io);
         }
     };
 
-    let cpp_res = generate::cpp::generate_cli(&spec, &metadata);
-    std::fs::write(options.output, cpp_res).unwrap();
+    let spec = match logger.timed("parsed", || cli_generator::parse_spec(&contents)) {
+        Ok(spec) => spec,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                logger.error(&diagnostic.render_colored(&contents, logger.colors()));
+            }
+            return Err(Failure::Parse);
+        }
+    };
+    let formatted = logger.timed("formatted", || format_spec(&spec));
+
+    if stdout {
+        print!("{formatted}");
+        return Ok(());
+    }
+    // Like the outputs, the file is left untouched when it is already formatted.
+    if formatted == contents {
+        logger.verbose(&format!("{file} is already formatted"));
+        return Ok(());
+    }
+
+    if check {
+        logger.error(&format!("{file} is not formatted"));
+        return Err(Failure::Outdated);
+    }
+    std::fs::write(file, &formatted).map_err(|err| {
+        logger.error(&format!("error: cannot write '{file}': {err}"));
+        Failure::Io
+    })?;
+    logger.verbose(&format!("wrote {} bytes to {file}", formatted.len()));
+
+    Ok(())
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let matches = Cli::command()
+        .try_get_matches()
+        .unwrap_or_else(|err| exit_with_usage_error(err));
+    let mut options =
+        Cli::from_arg_matches(&matches).unwrap_or_else(|err| exit_with_usage_error(err));
+    let logger = Logger::new(options.verbosity(), options.color.colors_stderr());
+
+    if let Some(Command::Fmt {
+        file,
+        stdout,
+        check,
+    }) = &options.command
+    {
+        if let Err(failure) = format_file(file, *stdout, *check, &logger) {
+            std::process::exit(failure.exit_code());
+        }
+        return;
+    }
+
+    if let Some(Command::Lsp) = &options.command {
+        // The protocol asks for 1 when the editor exits without shutting the server down first.
+        match lsp::serve(&mut std::io::stdin().lock(), &mut std::io::stdout().lock()) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                logger.error(&format!("error: {err}"));
+                std::process::exit(Failure::Io.exit_code());
+            }
+        }
+    }
+
+    if options.list_targets {
+        for target in Target::value_variants() {
+            println!("{}", target.to_possible_value().unwrap().get_name());
+        }
+        return;
+    }
+    if options.list_emitters {
+        for emitter in EMITTERS {
+            println!("{emitter}");
+        }
+        return;
+    }
+
+    if let Some(path) = config::config_path(&options) {
+        match config::Config::read(&path) {
+            Ok(config) => {
+                for key in config.unknown_keys() {
+                    logger.warning(&format!("unknown key '{key}' in {}", path.display()));
+                }
+                config.merge_into(&mut options, &matches);
+                logger.verbose(&format!("read the options of {}", path.display()));
+            }
+            Err(err) => {
+                logger.error(&err);
+                std::process::exit(Failure::Io.exit_code());
+            }
+        }
+    }
+
+    if let Some(cmake_helper) = &options.cmake_helper {
+        let executable = std::env::current_exe().unwrap();
+        let helper = cmake::generate_cmake_helper(&executable.to_string_lossy());
+        if let Err(err) = std::fs::write(cmake_helper, helper) {
+            logger.error(&format!("error: cannot write '{cmake_helper}': {err}"));
+            std::process::exit(Failure::Io.exit_code());
+        }
+    }
+
+    let jobs = match inputs_and_outputs(&options) {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            logger.error(&err);
+            std::process::exit(Failure::Usage.exit_code());
+        }
+    };
+
+    // With several inputs every diagnostic tells which input it comes from, and every input is
+    // generated even when the ones before it failed. The exit code is the one of the first failure.
+    let several_inputs = jobs.len() > 1;
+    let mut first_failure = None;
+    for (input, output) in &jobs {
+        let input_logger = logger.for_input(several_inputs.then_some(*input));
+        let result = run(&options, input, output.as_deref(), &input_logger);
+        if several_inputs {
+            logger.info(&format!(
+                "{input}: {}",
+                if result.is_ok() { "ok" } else { "failed" }
+            ));
+        }
+        if let Err(failure) = result {
+            first_failure.get_or_insert(failure);
+        }
+    }
+
+    if let Some(failure) = first_failure {
+        std::process::exit(failure.exit_code());
+    }
+}
+
+// When targeting the browser everything goes through the bindings of the library, which is only
+// linked into the binary when it is used.
+#[cfg(target_arch = "wasm32")]
+use cli_generator as _;
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
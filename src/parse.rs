@@ -1,13 +1,28 @@
+use crate::diagnostic::Diagnostic;
 use crate::lexer::Tokens;
+use crate::source_map::SourceMap;
 use crate::types::{Attribute, AttributeType, Field, FieldType, Spec, Struct};
 use logos::{Logos, Span, SpannedIter};
+use std::borrow::Cow;
 use std::iter::Peekable;
 
 type LexerType<'s> = Peekable<SpannedIter<'s, Tokens>>;
 
+/// Starts the first line of a spec giving the version of its format, e.g. `#!cli-spec 2`.
+const FORMAT_DIRECTIVE: &str = "#!cli-spec";
+
+/// Every error found while parsing a spec. The parser recovers from broken fields and structs, so
+/// a single run reports all of them instead of stopping at the first one.
+#[derive(Debug)]
+pub(crate) struct ParserError {
+    pub errors: Vec<Diagnostic>,
+}
+
 pub(crate) struct Parser<'s> {
     source: &'s str,
+    source_map: SourceMap<'s>,
     lexer: LexerType<'s>,
+    errors: Vec<Diagnostic>,
 }
 
 struct ParserToken {
@@ -21,101 +36,177 @@ impl ParserToken {
     }
 }
 
+/// Replaces a `short_and_long = "v/verbose"` by the `short = v` and `long = verbose` it stands
+/// for, both spanning the whole attribute. The long name is kept as an identifier, `dry-run`
+/// becoming `dry_run` like `long = dry_run` is spelled. Malformed values are kept as they are for
+/// the semantic checks to report.
+fn desugar_short_and_long(attribute: Attribute) -> Vec<Attribute> {
+    let options = attribute
+        .value
+        .as_deref()
+        .filter(|_| attribute.ty == AttributeType::ShortAndLong)
+        .and_then(|value| value.split_once('/'))
+        .filter(|(short, long)| {
+            let mut short_chars = short.chars();
+            let is_short = short_chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && short_chars.next().is_none();
+            let is_long = long.starts_with(|c: char| c.is_ascii_alphabetic())
+                && long
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic() || c == '-' || c == '_');
+            is_short && is_long
+        });
+
+    let Some((short, long)) = options else {
+        return vec![attribute];
+    };
+
+    [(AttributeType::Short, short), (AttributeType::Long, long)]
+        .into_iter()
+        .map(|(ty, value)| Attribute {
+            ty,
+            value: Some(value.replace('-', "_")),
+            span: attribute.span.clone(),
+            value_span: attribute.value_span.clone(),
+        })
+        .collect()
+}
+
+/// The attributes of the field `name` as they are written, with `short` and `long` given their
+/// default value when they have none and `short_and_long` desugared.
+pub(crate) fn resolve_field_attributes(
+    name: &str,
+    mut attributes: Vec<Attribute>,
+) -> Vec<Attribute> {
+    for attribute in &mut attributes {
+        if matches!(attribute.ty, AttributeType::Short) && attribute.value.is_none() {
+            attribute.value = name.chars().next().map(String::from);
+        } else if matches!(attribute.ty, AttributeType::Long) && attribute.value.is_none() {
+            attribute.value = Some(name.to_string());
+        }
+    }
+
+    attributes
+        .into_iter()
+        .flat_map(desugar_short_and_long)
+        .collect()
+}
+
 impl<'s> Parser<'s> {
     pub fn new(source: &'s str) -> Self {
         Self {
             source,
+            source_map: SourceMap::new(source),
             lexer: Tokens::lexer(source).spanned().peekable(),
+            errors: Vec::new(),
         }
     }
 
     #[inline]
-    fn make_end_of_file_chic_error(&self) -> String {
-        chic::Error::new("Parser error")
-            .error(
-                1,
-                self.source.trim_end().len() - 1,
-                self.source.trim_end().len(),
-                self.source,
-                "Unexpected end of file",
-            )
-            .to_string()
+    fn make_end_of_file_error(&self) -> Diagnostic {
+        let end = self.source.trim_end().len();
+        Diagnostic::new(
+            "Parser error",
+            &(end.saturating_sub(1)..end),
+            "Unexpected end of file",
+        )
     }
 
     #[inline]
-    fn make_chic_error_for_lexer_error(&self, span: &Span) -> String {
-        chic::Error::new("Lexer error")
-            .error(1, span.start, span.end, self.source, "Unknown token")
-            .to_string()
+    fn make_lexer_error(&self, span: &Span) -> Diagnostic {
+        Diagnostic::new("Lexer error", span, "Unknown token")
     }
 
     #[inline]
-    fn make_chic_error_for_parse_error(
-        &self,
-        span: &Span,
-        message: &'s str,
-        help: Option<&'s str>,
-    ) -> String {
-        let mut err =
-            chic::Error::new("Parse error").error(1, span.start, span.end, self.source, message);
-
-        if let Some(help) = help {
-            err = err.help(help);
-        }
+    fn make_parse_error(&self, span: &Span, message: &'s str, help: Option<&'s str>) -> Diagnostic {
+        let err = Diagnostic::new("Parse error", span, message);
 
-        err.to_string()
+        match help {
+            Some(help) => err.help(help),
+            None => err,
+        }
     }
 
-    #[inline]
-    fn ensure_token_any_of(&self, token: &ParserToken, expected: &[Tokens]) -> Result<(), String> {
-        if expected.contains(&token.token) {
-            Ok(())
-        } else {
-            Err(chic::Error::new("Parser error")
-                .error(
-                    1,
-                    token.span.start,
-                    token.span.end,
-                    self.source,
-                    "Unexpected token",
-                )
-                .help(&format!(
+    fn make_unexpected_token_error(&self, token: &ParserToken, expected: &[Tokens]) -> Diagnostic {
+        Diagnostic::new("Parser error", &token.span, "Unexpected token").help(
+            &match Tokens::as_friendly_group_name(expected) {
+                Some(group_name) => format!("Expected {group_name}"),
+                None => format!(
                     "Tokens can be any of: {}",
                     expected
                         .iter()
                         .map(|v| v.as_token_literal())
                         .collect::<Vec<_>>()
                         .join(", ")
-                ))
-                .to_string())
+                ),
+            },
+        )
+    }
+
+    #[inline]
+    fn ensure_token_any_of(
+        &self,
+        token: &ParserToken,
+        expected: &[Tokens],
+    ) -> Result<(), Diagnostic> {
+        if expected.contains(&token.token) {
+            Ok(())
+        } else {
+            Err(self.make_unexpected_token_error(token, expected))
         }
     }
 
+    /// Records an error to report once parsing is done. Running out of input aborts every
+    /// construct being parsed, so the same error is only recorded once.
+    fn record_error(&mut self, err: Diagnostic) {
+        if self.errors.last() != Some(&err) {
+            self.errors.push(err);
+        }
+    }
+
+    /// Unexpected tokens are left in place, so that recovering can resume from them when they
+    /// start the next field.
     #[inline]
-    fn ensure_next_token_any_of(&mut self, tokens: &[Tokens]) -> Result<ParserToken, String> {
+    fn ensure_next_token_any_of(&mut self, tokens: &[Tokens]) -> Result<ParserToken, Diagnostic> {
         let next_token = self
-            .next_token()
-            .ok_or_else(|| self.make_end_of_file_chic_error())??;
+            .peek_token()
+            .ok_or_else(|| self.make_end_of_file_error())??;
 
         self.ensure_token_any_of(&next_token, tokens)?;
+        let _ = self.lexer.next();
 
         Ok(next_token)
     }
 
     #[inline]
-    fn ensure_next_token(&mut self, token: Tokens) -> Result<ParserToken, String> {
+    fn ensure_next_token(&mut self, token: Tokens) -> Result<ParserToken, Diagnostic> {
         self.ensure_next_token_any_of(&[token])
     }
 
+    /// Like `ensure_next_token(Tokens::Identifier)`, for the tokens that can be names as well, see
+    /// `Tokens::is_name`.
+    fn ensure_next_name(&mut self) -> Result<ParserToken, Diagnostic> {
+        let next_token = self
+            .peek_token()
+            .ok_or_else(|| self.make_end_of_file_error())??;
+
+        if !next_token.token.is_name() {
+            return Err(self.make_unexpected_token_error(&next_token, &[Tokens::Identifier]));
+        }
+        let _ = self.lexer.next();
+
+        Ok(ParserToken::new(Tokens::Identifier, next_token.span))
+    }
+
     #[inline]
-    fn next_token(&mut self) -> Option<Result<ParserToken, String>> {
+    fn next_token(&mut self) -> Option<Result<ParserToken, Diagnostic>> {
         let res = self.peek_token();
         let _ = self.lexer.next();
         res
     }
 
     #[inline]
-    fn peek_token(&mut self) -> Option<Result<ParserToken, String>> {
+    fn peek_token(&mut self) -> Option<Result<ParserToken, Diagnostic>> {
         let (token_res, span) = {
             let (token_res, span) = self.lexer.peek()?;
             (token_res.clone(), span.clone())
@@ -123,25 +214,84 @@ impl<'s> Parser<'s> {
 
         match token_res {
             Ok(token) => Some(Ok(ParserToken::new(token, span))),
-            Err(_) => Some(Err(self.make_chic_error_for_lexer_error(&span))),
+            Err(_) => Some(Err(self.make_lexer_error(&span))),
+        }
+    }
+
+    /// Whether only whitespace precedes the token on its line.
+    #[inline]
+    fn starts_line(&self, span: &Span) -> bool {
+        self.source[self.source_map.line_start(span.start)..span.start]
+            .trim()
+            .is_empty()
+    }
+
+    /// Skips tokens while `should_skip` holds for them, along with any unknown ones.
+    fn skip_tokens_while(&mut self, should_skip: impl Fn(&Self, &Tokens, &Span) -> bool) {
+        while let Some((token, span)) = self.lexer.peek().cloned() {
+            let skip = match token {
+                Ok(token) => should_skip(self, &token, &span),
+                Err(_) => true,
+            };
+
+            if !skip {
+                break;
+            }
+
+            let _ = self.lexer.next();
         }
     }
 
-    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, String> {
+    /// Skips the rest of a broken field, up to the identifier or attributes starting a line (most
+    /// likely the next field) or the `}` closing the struct. Since unexpected tokens are not
+    /// consumed, parsing resumes right at the next field when it is the one that was unexpected.
+    fn recover_to_next_field(&mut self) {
+        self.skip_tokens_while(|this, token, span| match token {
+            Tokens::RBrace => false,
+            Tokens::Pound => !this.starts_line(span),
+            token if token.is_name() => !this.starts_line(span),
+            _ => true,
+        });
+    }
+
+    /// Skips the rest of a broken struct, up to the next `struct` keyword.
+    fn recover_to_next_struct(&mut self) {
+        self.skip_tokens_while(|_, token, _| !matches!(token, Tokens::Struct));
+    }
+
+    /// Parses the `= <value>` part of an attribute, returning the value without any quotes along
+    /// with its span, quotes included.
+    fn parse_attribute_value(
+        &mut self,
+        expected: &[Tokens],
+    ) -> Result<(&'s str, Span), Diagnostic> {
+        self.ensure_next_token(Tokens::Equals)?;
+        let value_token = self.ensure_next_token_any_of(expected)?;
+        let value = &self.source[value_token.span.start..value_token.span.end];
+
+        if matches!(value_token.token, Tokens::StringLiteral) {
+            Ok((&value[1..value.len() - 1], value_token.span))
+        } else {
+            Ok((value, value_token.span))
+        }
+    }
+
+    /// Parses a `#[...]` block, returning its attributes along with the span of the whole block.
+    fn parse_attributes(&mut self) -> Result<(Vec<Attribute>, Span), Diagnostic> {
         let mut res = Vec::new();
 
         let attributes_start = self.ensure_next_token(Tokens::Pound)?;
         self.ensure_next_token(Tokens::LSquareBracket)?;
 
-        loop {
+        let attributes_end = loop {
             let Some(next_token) = self.next_token() else {
-                return Err(self.make_end_of_file_chic_error());
+                return Err(self.make_end_of_file_error());
             };
 
             let next_token = next_token?;
 
             if matches!(next_token.token, Tokens::RSquareBracket) {
-                break;
+                break next_token.span.end;
             }
 
             if matches!(next_token.token, Tokens::Comma) {
@@ -155,57 +305,150 @@ impl<'s> Parser<'s> {
             let value = match ty {
                 AttributeType::Short | AttributeType::Long => {
                     let Some(next_token) = self.peek_token() else {
-                        return Err(self.make_end_of_file_chic_error());
+                        return Err(self.make_end_of_file_error());
                     };
 
                     let next_token = next_token?;
 
                     if matches!(next_token.token, Tokens::Equals) {
                         let _ = self.next_token();
-                        let id_token = self.ensure_next_token(Tokens::Identifier)?;
-                        Some(&self.source[id_token.span.start..id_token.span.end])
+                        let id_token = self.ensure_next_name()?;
+                        Some((
+                            &self.source[id_token.span.start..id_token.span.end],
+                            id_token.span,
+                        ))
                     } else {
                         None
                     }
                 }
                 AttributeType::Alias => {
                     self.ensure_next_token(Tokens::Equals)?;
-                    let id_token = self.ensure_next_token(Tokens::Identifier)?;
-                    Some(&self.source[id_token.span.start..id_token.span.end])
+                    let id_token = self.ensure_next_name()?;
+                    Some((
+                        &self.source[id_token.span.start..id_token.span.end],
+                        id_token.span,
+                    ))
                 }
+                // The message is optional, a plain `deprecated` prints a generic warning.
+                AttributeType::Deprecated => {
+                    let Some(next_token) = self.peek_token() else {
+                        return Err(self.make_end_of_file_error());
+                    };
+
+                    if matches!(next_token?.token, Tokens::Equals) {
+                        Some(self.parse_attribute_value(&[Tokens::StringLiteral])?)
+                    } else {
+                        None
+                    }
+                }
+                AttributeType::TakesValue => {
+                    Some(self.parse_attribute_value(&[Tokens::True, Tokens::False])?)
+                }
+                AttributeType::Separator
+                | AttributeType::Prefix
+                | AttributeType::ValueSeparator
+                | AttributeType::Regex
+                | AttributeType::Doc
+                | AttributeType::About
+                | AttributeType::AfterHelp
+                | AttributeType::LongPrefix
+                | AttributeType::ParseFn
+                | AttributeType::AppendEnv
+                | AttributeType::ConfigFile
+                | AttributeType::Env
+                | AttributeType::DeprecatedIn
+                | AttributeType::RemovedIn
+                | AttributeType::EnvList
+                | AttributeType::ExclusiveGroup
+                | AttributeType::RequiresGroup
+                | AttributeType::ShortAndLong
+                | AttributeType::DefaultValueT
+                | AttributeType::EnvPrefix
+                | AttributeType::EnvCase
+                | AttributeType::LongShortConflict => {
+                    Some(self.parse_attribute_value(&[Tokens::StringLiteral])?)
+                }
+                AttributeType::MinOccurs
+                | AttributeType::MaxOccurs
+                | AttributeType::WordWrap
+                | AttributeType::ExitCode
+                | AttributeType::ParseErrorExit
+                | AttributeType::MissingArgExit
+                | AttributeType::UnknownFlagExit
+                | AttributeType::Index
+                | AttributeType::ParseOrder
+                | AttributeType::DisplayOrder => {
+                    Some(self.parse_attribute_value(&[Tokens::IntegerLiteral])?)
+                }
+                AttributeType::Min | AttributeType::Max => Some(
+                    self.parse_attribute_value(&[Tokens::IntegerLiteral, Tokens::FloatLiteral])?,
+                ),
                 _ => None,
             };
 
-            let value = value.map(String::from);
+            let (value, value_span) = match value {
+                Some((value, value_span)) => (Some(value.to_string()), Some(value_span)),
+                None => (None, None),
+            };
 
             let attribute = Attribute {
                 ty,
                 value,
                 span: next_token.span,
+                value_span,
             };
 
             res.push(attribute)
-        }
+        };
 
         if res.is_empty() {
-            return Err(self.make_chic_error_for_parse_error(
+            return Err(self.make_parse_error(
                 &attributes_start.span,
                 "Attributes cannot be empty",
                 None,
             ));
         }
 
-        Ok(res)
+        Ok((res, attributes_start.span.start..attributes_end))
     }
 
-    fn parse_field(&mut self) -> Result<Field, String> {
-        let id_token = self.ensure_next_token(Tokens::Identifier)?;
+    /// Parses a field. Once its name is there, a field whose type is broken is recorded and
+    /// skipped over, and the field is kept with an `Error` type for the tools working on specs
+    /// that are being written, see `parse_tolerant`. Such a field ends at its name.
+    fn parse_field(&mut self) -> Result<Field, Diagnostic> {
+        let id_token = self.ensure_next_name()?;
         let name = self.source[id_token.span.start..id_token.span.end].to_string();
 
+        let (ty, type_span, end) = match self.parse_field_type() {
+            Ok(ty) => ty,
+            Err(err) => {
+                self.record_error(err);
+                self.recover_to_next_field();
+                let end = id_token.span.end;
+                (FieldType::Error, end..end, end)
+            }
+        };
+
+        let res = Field {
+            name,
+            attributes: Vec::new(),
+            ty,
+            span: id_token.span.start..end,
+            name_span: id_token.span,
+            type_span,
+        };
+
+        Ok(res)
+    }
+
+    /// Parses the `: <type>` of a field, along with the comma after it, returning the type, the
+    /// span of its innermost type and where the field ends.
+    fn parse_field_type(&mut self) -> Result<(FieldType, Span, usize), Diagnostic> {
         self.ensure_next_token(Tokens::Colon)?;
 
         let mut ty_token = self.ensure_next_token_any_of(Tokens::type_tokens())?;
         let mut ty = ty_token.token.as_field_type();
+        let mut end = ty_token.span.end;
 
         if matches!(ty, FieldType::Vec(_) | FieldType::Optional(_)) {
             let inner = match &mut ty {
@@ -215,8 +458,8 @@ impl<'s> Parser<'s> {
             };
 
             self.ensure_next_token(Tokens::LAngleBracket)?;
-            let inner_ty_token = self.ensure_next_token_any_of(Tokens::type_tokens())?;
-            self.ensure_next_token(Tokens::RAngleBracket)?;
+            let inner_ty_token = self.ensure_next_token_any_of(Tokens::value_type_tokens())?;
+            end = self.ensure_next_token(Tokens::RAngleBracket)?.span.end;
 
             *inner.as_mut() = inner_ty_token.token.as_field_type();
 
@@ -237,113 +480,400 @@ impl<'s> Parser<'s> {
             let token = token?;
             if matches!(token.token, Tokens::Comma) {
                 let _ = self.next_token();
+                end = token.span.end;
             }
         }
 
-        let res = Field {
-            name,
-            attributes: Vec::new(),
-            ty,
-            name_span: id_token.span,
-            type_span: ty_token.span,
-        };
+        Ok((ty, ty_token.span, end))
+    }
 
-        Ok(res)
+    /// Parses a field of a struct along with its attributes, `token` being the first one.
+    fn parse_struct_field(&mut self, token: &ParserToken) -> Result<Field, Diagnostic> {
+        match token.token {
+            Tokens::Pound => {
+                let (attributes, attributes_span) = self.parse_attributes()?;
+                let mut field = self.parse_field()?;
+
+                field.attributes = resolve_field_attributes(&field.name, attributes);
+                field.span.start = attributes_span.start;
+                Ok(field)
+            }
+            token if token.is_name() => self.parse_field(),
+            _ => {
+                // Consumed, as recovering only stops at the start of a field.
+                let _ = self.lexer.next();
+                Err(self.make_unexpected_token_error(
+                    token,
+                    &[Tokens::Pound, Tokens::Identifier, Tokens::RBrace],
+                ))
+            }
+        }
     }
 
-    fn parse_struct(&mut self) -> Result<Struct, String> {
+    fn parse_struct(&mut self) -> Result<Struct, Diagnostic> {
         self.ensure_next_token(Tokens::Struct)?;
 
         let id_token = self.ensure_next_token(Tokens::Identifier)?;
         let name = self.source[id_token.span.start..id_token.span.end].to_string();
 
-        self.ensure_next_token(Tokens::LBrace)?;
+        let body_start = self.ensure_next_token(Tokens::LBrace)?.span.start;
 
         let mut fields = Vec::new();
 
         while let Some(token) = self.peek_token() {
-            let token = token?;
+            let token = match token {
+                Ok(token) => token,
+                Err(err) => {
+                    self.record_error(err);
+                    self.recover_to_next_field();
+                    continue;
+                }
+            };
             if matches!(token.token, Tokens::RBrace) {
                 break;
             }
 
-            match token.token {
-                Tokens::Pound => {
-                    let mut attributes = self.parse_attributes()?;
-                    let mut field = self.parse_field()?;
-
-                    for attribute in &mut attributes {
-                        if matches!(attribute.ty, AttributeType::Short) && attribute.value.is_none()
-                        {
-                            attribute.value =
-                                Some(String::from(field.name.chars().next().unwrap()));
-                        } else if matches!(attribute.ty, AttributeType::Long)
-                            && attribute.value.is_none()
-                        {
-                            attribute.value = Some(field.name.clone());
-                        }
-                    }
-
-                    field.attributes = attributes;
-                    fields.push(field);
-                }
-                Tokens::Identifier => {
-                    let field = self.parse_field()?;
-                    fields.push(field);
+            match self.parse_struct_field(&token) {
+                Ok(field) => fields.push(field),
+                Err(err) => {
+                    self.record_error(err);
+                    self.recover_to_next_field();
                 }
-                _ => unreachable!(),
             }
         }
 
-        self.ensure_next_token(Tokens::RBrace)?;
+        // Only missing at the end of the input, the struct is kept with the fields it has and its
+        // body runs to the end.
+        let body_end = match self.ensure_next_token(Tokens::RBrace) {
+            Ok(token) => token.span.end,
+            Err(err) => {
+                self.record_error(err);
+                self.source.trim_end().len()
+            }
+        };
 
         let strukt = Struct {
             attributes: Vec::new(),
             fields,
             name,
             name_span: id_token.span,
+            attributes_span: None,
+            body_span: body_start..body_end,
         };
 
         Ok(strukt)
     }
 
-    pub fn parse(&mut self) -> Result<Spec, String> {
-        let mut structs = Vec::new();
+    fn parse_struct_definition(&mut self, token: &ParserToken) -> Result<Struct, Diagnostic> {
+        match token.token {
+            Tokens::Pound => {
+                let (attributes, attributes_span) = self.parse_attributes()?;
 
-        while let Some(parser_token) = self.peek_token() {
-            let parser_token = parser_token?;
-            match parser_token.token {
-                Tokens::Pound => {
-                    let attributes = self.parse_attributes()?;
+                let Some(parser_token) = self.peek_token() else {
+                    return Err(self.make_end_of_file_error());
+                };
 
-                    let Some(parser_token) = self.peek_token() else {
-                        return Err(self.make_end_of_file_chic_error());
-                    };
+                self.ensure_token_any_of(&parser_token?, &[Tokens::Struct])?;
+
+                let mut strukt = self.parse_struct()?;
+                strukt.attributes.extend(attributes);
+                strukt.attributes_span = Some(attributes_span);
+                Ok(strukt)
+            }
+            Tokens::Struct => self.parse_struct(),
+            _ => {
+                // Consumed, as recovering only stops at the next struct.
+                let _ = self.lexer.next();
+                Err(self.make_unexpected_token_error(token, &[Tokens::Pound, Tokens::Struct]))
+            }
+        }
+    }
 
-                    let parser_token = parser_token?;
+    /// Parses the `#!cli-spec N` directive on the first line, if there's one. A newer format than
+    /// the supported ones is an error of its own, as the rest of the spec would only report what
+    /// this version doesn't know.
+    fn parse_format_directive(&mut self) -> Result<Option<u32>, Diagnostic> {
+        if !self.source.starts_with("#!") {
+            return Ok(None);
+        }
 
-                    match parser_token.token {
-                        Tokens::Struct => {
-                            let mut strukt = self.parse_struct()?;
-                            strukt.attributes.extend(attributes);
-                            structs.push(strukt);
-                        }
-                        _ => unreachable!(),
-                    }
-                }
-                Tokens::Struct => {
-                    let strukt = self.parse_struct()?;
-                    structs.push(strukt);
+        let line = self.source.lines().next().unwrap_or_default();
+        // The directive isn't made of tokens of the language, whatever it is they are skipped.
+        while self
+            .lexer
+            .peek()
+            .is_some_and(|(_, span)| span.start < line.len())
+        {
+            let _ = self.lexer.next();
+        }
+
+        let version = line
+            .strip_prefix(FORMAT_DIRECTIVE)
+            .filter(|version| version.starts_with([' ', '\t']))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .filter(|version| *version != 0);
+        let span = 0..line.trim_end().len();
+        let Some(version) = version else {
+            self.record_error(
+                Diagnostic::new("Parser error", &span, "Invalid format directive").help(&format!(
+                    "It is written `{FORMAT_DIRECTIVE} N`, N being a format version from 1 to {}",
+                    Spec::FORMAT_VERSION
+                )),
+            );
+            return Ok(None);
+        };
+
+        if version > Spec::FORMAT_VERSION {
+            return Err(Diagnostic::new(
+                "Unsupported format version",
+                &span,
+                &format!(
+                    "Spec requires format {version}, this tool supports up to {}",
+                    Spec::FORMAT_VERSION
+                ),
+            )
+            .help("Generate the code with a newer cli-generator"));
+        }
+
+        Ok(Some(version))
+    }
+
+    pub fn parse(&mut self) -> Result<Spec<'s>, ParserError> {
+        let (spec, errors) = self.parse_tolerant();
+        if !errors.is_empty() {
+            return Err(ParserError { errors });
+        }
+
+        Ok(spec)
+    }
+
+    /// Parses as much of the spec as can be, for the tools working on specs that are being
+    /// written. Along with the errors, the spec has every struct and field that could be parsed,
+    /// the fields with a broken type being given an `Error` one. The structs that are broken
+    /// before their `{` are left out.
+    pub fn parse_tolerant(&mut self) -> (Spec<'s>, Vec<Diagnostic>) {
+        let format_directive = match self.parse_format_directive() {
+            Ok(format_directive) => format_directive,
+            Err(err) => {
+                // A spec in a newer format means something else, none of it is parsed.
+                let spec = Spec {
+                    structs: Vec::new(),
+                    source: Cow::Borrowed(self.source),
+                    format_directive: None,
+                    is_built: false,
+                };
+                return (spec, vec![err]);
+            }
+        };
+        let mut structs = Vec::new();
+
+        while let Some(parser_token) = self.peek_token() {
+            let result = parser_token.and_then(|token| self.parse_struct_definition(&token));
+
+            match result {
+                Ok(strukt) => structs.push(strukt),
+                Err(err) => {
+                    self.record_error(err);
+                    self.recover_to_next_struct();
                 }
-                _ => unreachable!(),
             }
         }
 
         let res = Spec {
             structs,
-            source: self.source,
+            source: Cow::Borrowed(self.source),
+            format_directive,
+            is_built: false,
         };
 
-        Ok(res)
+        (res, std::mem::take(&mut self.errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_are_in_the_format_of_their_directive() {
+        let spec = "#[main]\nstruct Cli {\n    #[long]\n    port: u16,\n}\n";
+
+        assert_eq!(Parser::new(spec).parse().unwrap().format_version(), 1);
+        let source = format!("#!cli-spec {}\n{spec}", Spec::FORMAT_VERSION);
+        let parsed = Parser::new(&source).parse().unwrap();
+        assert_eq!(parsed.format_version(), Spec::FORMAT_VERSION);
+        assert_eq!(parsed.structs[0].fields[0].name, "port");
+
+        let source = format!("#!cli-spec {}\n{spec}", Spec::FORMAT_VERSION + 1);
+        let err = Parser::new(&source).parse().unwrap_err();
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(
+            err.errors[0].message,
+            format!(
+                "Spec requires format {}, this tool supports up to {}",
+                Spec::FORMAT_VERSION + 1,
+                Spec::FORMAT_VERSION
+            )
+        );
+
+        for directive in [
+            "#!cli-spec",
+            "#!cli-spec 0",
+            "#!cli-spec2",
+            "#!cli-spec two",
+        ] {
+            let source = format!("{directive}\n{spec}");
+            let err = Parser::new(&source).parse().unwrap_err();
+            assert_eq!(err.errors.len(), 1, "{directive}");
+            assert_eq!(err.errors[0].message, "Invalid format directive");
+        }
+    }
+
+    #[test]
+    fn attribute_names_name_fields_outside_of_attributes() {
+        let source = "#[main]\nstruct Cli {\n    #[long = output, alias = output_file]\n    output_file: string,\n    index: Optional<u32>,\n}\n";
+        let spec = Parser::new(source).parse().unwrap();
+        let fields = &spec.structs[0].fields;
+
+        assert_eq!(fields[0].name, "output_file");
+        assert_eq!(
+            fields[0].attribute_value(AttributeType::Alias),
+            Some("output_file")
+        );
+        assert_eq!(fields[1].name, "index");
+        assert!(
+            Parser::new("struct Cli { #[output_file = x] path: string }")
+                .parse()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn every_bad_field_is_reported() {
+        let source = "struct Cli {\n    a string,\n    #[long]\n    b: u32,\n    c: Vec<,\n}\n";
+        let err = Parser::new(source).parse().unwrap_err();
+        assert_eq!(err.errors.len(), 2);
+    }
+
+    #[test]
+    fn parsing_resumes_at_the_next_struct() {
+        let source = "struct Broken {\n    a: ,\n}\n\nstruct Other {\n    b u32\n}\n";
+        let err = Parser::new(source).parse().unwrap_err();
+        assert_eq!(err.errors.len(), 2);
+    }
+
+    #[test]
+    fn broken_fields_leave_the_rest_of_the_spec_parsed() {
+        let source = "#[main]\nstruct Cli {\n    #[long]\n    port: ,\n    #[short]\n    verbose: bool,\n}\n\nstruct Net {\n    host: string,\n";
+        let (spec, errors) = Parser::new(source).parse_tolerant();
+        assert_eq!(errors.len(), 2);
+
+        let cli = &spec.structs[0];
+        assert_eq!(cli.attributes[0].ty, AttributeType::Main);
+        let port = &cli.fields[0];
+        assert_eq!(port.ty, FieldType::Error);
+        assert_eq!(port.attributes[0].value.as_deref(), Some("port"));
+        assert_eq!(&source[port.name_span()], "port");
+        let verbose = &cli.fields[1];
+        assert_eq!(verbose.ty, FieldType::Bool);
+        assert_eq!(&source[verbose.name_span()], "verbose");
+        assert_eq!(&source[verbose.type_span()], "bool");
+        assert_eq!(&source[verbose.attributes[0].span.clone()], "short");
+
+        // Cut short by the end of the input.
+        let net = &spec.structs[1];
+        assert_eq!(&source[net.name_span.clone()], "Net");
+        assert_eq!(&source[net.fields[0].type_span()], "string");
+        assert_eq!(&source[net.body_span.clone()], "{\n    host: string,");
+    }
+
+    #[test]
+    fn declarations_span_their_attributes_and_values() {
+        let source = "#[main, about = \"Tool\"]\nstruct Cli {\n    #[long, alias = out]\n    output: Vec<string>,\n    #[min = 1] level: u32\n}\n";
+        let spec = Parser::new(source).parse().unwrap();
+
+        let cli = &spec.structs[0];
+        assert_eq!(
+            &source[cli.attributes_span.clone().unwrap()],
+            "#[main, about = \"Tool\"]"
+        );
+        assert_eq!(&source[cli.span()], source.trim_end());
+        assert!(source[cli.body_span.clone()].starts_with("{\n    #[long"));
+        assert_eq!(&source[cli.attributes[1].span.clone()], "about");
+        assert_eq!(
+            &source[cli.attributes[1].value_span.clone().unwrap()],
+            "\"Tool\""
+        );
+        assert_eq!(cli.attributes[0].value_span, None);
+
+        let output = &cli.fields[0];
+        assert_eq!(
+            &source[output.span()],
+            "#[long, alias = out]\n    output: Vec<string>,"
+        );
+        assert_eq!(&source[output.attributes[1].span.clone()], "alias");
+        assert_eq!(
+            &source[output.attributes[1].value_or_name_span().clone()],
+            "out"
+        );
+        // Named after the field, the long option has no value of its own.
+        assert_eq!(
+            &source[output.attributes[0].value_or_name_span().clone()],
+            "long"
+        );
+
+        // Without a comma, the field ends with its type.
+        let level = &cli.fields[1];
+        assert_eq!(&source[level.span()], "#[min = 1] level: u32");
+        assert_eq!(
+            &source[level.attributes[0].value_span.clone().unwrap()],
+            "1"
+        );
+    }
+
+    #[test]
+    fn running_out_of_input_is_reported_once() {
+        let source = "struct Cli {\n    a: u32,\n";
+        let err = Parser::new(source).parse().unwrap_err();
+        assert_eq!(err.errors.len(), 1);
+    }
+
+    #[test]
+    fn deprecated_takes_an_optional_message() {
+        let source = "struct Cli {\n    #[long, deprecated = \"use --b\"]\n    a: u32,\n    #[long, deprecated]\n    b: u32,\n}\n";
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let fields = &spec.structs[0].fields;
+        assert_eq!(fields[0].deprecation_message(), Some("use --b"));
+        assert!(fields[1].is_deprecated());
+        assert_eq!(fields[1].deprecation_message(), None);
+    }
+
+    #[test]
+    fn wrappers_cannot_be_nested() {
+        for source in [
+            "struct Cli { a: Vec<Vec<u32>> }",
+            "struct Cli { a: Optional<Vec> }",
+        ] {
+            let err = Parser::new(source).parse().unwrap_err();
+            assert_eq!(err.errors.len(), 1);
+            let help = err.errors[0].help.as_deref().unwrap();
+            assert!(help.contains("a type other than Vec and Optional"));
+        }
+    }
+
+    #[test]
+    fn short_and_long_stands_for_both_options() {
+        let source = "struct Cli {\n    #[short_and_long = \"v/dry-run\", doc = \"Doc\"]\n    a: bool,\n    #[short_and_long = \"vv/verbose\"]\n    b: bool,\n}\n";
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let fields = &spec.structs[0].fields;
+
+        assert_eq!(fields[0].short_value().as_deref(), Some("v"));
+        assert_eq!(fields[0].long_value().as_deref(), Some("dry-run"));
+        assert_eq!(fields[0].attributes[0].span, fields[0].attributes[1].span);
+        assert_eq!(fields[0].doc(), Some("Doc"));
+        assert_eq!(fields[1].attributes[0].ty, AttributeType::ShortAndLong);
+        assert_eq!(fields[1].short_value(), None);
     }
 }
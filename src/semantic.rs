@@ -1,175 +1,93 @@
-use crate::types::{AttributeType, Field, FieldType, Spec, SpecMetadata, Struct};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::generate::cpp::CppStandard;
+use crate::types::{
+    Attribute, AttributeType, Field, FieldType, OptionSyntax, Spec, SpecMetadata, Struct,
+    StructMetadata, StructOption, ValueKind,
+};
 use logos::Span;
-use std::cmp::{max, min};
-use std::collections::HashMap;
-
-fn get_line_with_span(source: &str, span: &Span) -> usize {
-    source[..span.start].lines().count()
-}
-
-fn get_line_span(source: &str, line: usize) -> Span {
-    let mut current_chars = 0;
-    for (i, l) in source.lines().enumerate() {
-        if i + 1 == line {
-            return Span::from(current_chars..current_chars + l.len());
-        }
-        current_chars += l.len() + 1;
-    }
-
-    unreachable!();
-}
-
-fn get_context(source: &str, span: &Span) -> (usize, Span) {
-    let line_start = get_line_with_span(source, span);
-
-    let previous_line = max(1, line_start - 1);
-    let previous_line_span = get_line_span(source, previous_line);
-
-    let next_line = min(line_start + 1, source.lines().count());
-    let next_line_span = get_line_span(source, next_line);
-
-    (
-        line_start,
-        Span::from(previous_line_span.start..next_line_span.end),
-    )
-}
-
-fn make_chic_error<'s>(
-    label: &'s str,
-    source: &'s str,
-    span: &'s Span,
-    error_msg: &'s str,
-) -> chic::Error<'s> {
-    let (line_start, context_span) = get_context(source, span);
-
-    chic::Error::new(label).error(
-        line_start,
-        span.start - context_span.start,
-        span.end - context_span.start,
-        &source[context_span.start..context_span.end],
-        error_msg,
-    )
-}
-
-fn make_chic_error_with_info<'s>(
-    label: &'s str,
-    source: &'s str,
-    error_span: &'s Span,
-    error_msg: &'s str,
-    info_span: &'s Span,
-    info_msg: &'s str,
-) -> chic::Report<'s> {
-    let (error_line_start, error_context_span) = get_context(source, error_span);
-    let (info_line_start, info_context_span) = get_context(source, info_span);
-
-    chic::Report::new_error(label)
-        .error(
-            error_line_start,
-            error_span.start - error_context_span.start,
-            error_span.end - error_context_span.start,
-            &source[error_context_span.start..error_context_span.end],
-            error_msg,
-        )
-        .info(
-            info_line_start,
-            info_span.start - info_context_span.start,
-            info_span.end - info_context_span.start,
-            &source[info_context_span.start..info_context_span.end],
-            info_msg,
-        )
-}
+use std::cmp::{max, min, Ordering};
+use std::collections::{BTreeMap, HashMap};
 
 fn check_for_multiple_struct_definitions<'s>(
     structs: &'s [Struct],
-    source: &'s str,
-) -> Result<HashMap<&'s str, &'s Struct>, String> {
+    errors: &mut Vec<Diagnostic>,
+) -> HashMap<&'s str, &'s Struct> {
     let mut id_to_struct = HashMap::with_capacity(structs.len());
 
     for strukt in structs {
         if id_to_struct.contains_key(strukt.name.as_str()) {
             let original_struct: &Struct = id_to_struct[strukt.name.as_str()];
-            let chic_error = make_chic_error_with_info(
+            let diagnostic = Diagnostic::with_info(
                 "Multiple type definition",
-                source,
                 &strukt.name_span,
                 "Redefinition of type",
                 &original_struct.name_span,
                 "Has already been defined here",
             );
 
-            return Err(chic_error.to_string());
+            errors.push(diagnostic);
+            continue;
         }
         id_to_struct.insert(strukt.name.as_str(), strukt);
     }
 
-    Ok(id_to_struct)
+    id_to_struct
 }
 
-fn check_for_multiple_field_definitions(fields: &[Field], source: &str) -> Result<(), String> {
+fn check_for_multiple_field_definitions(fields: &[Field], errors: &mut Vec<Diagnostic>) {
     let mut name_to_field = HashMap::with_capacity(fields.len());
 
     for field in fields {
         if name_to_field.contains_key(field.name.as_str()) {
             let original_field: &Field = name_to_field[field.name.as_str()];
-            let chic_error = make_chic_error_with_info(
+            let diagnostic = Diagnostic::with_info(
                 "Multiple field definition",
-                source,
                 &field.name_span,
                 "Redefinition of field",
                 &original_field.name_span,
                 "Has already been defined here",
             );
 
-            return Err(chic_error.to_string());
+            errors.push(diagnostic);
+            continue;
         }
 
         name_to_field.insert(field.name.as_str(), field);
     }
-
-    Ok(())
 }
 
 fn check_for_undefined_types(
     metadata: &SpecMetadata,
     fields: &[Field],
-    source: &str,
-) -> Result<(), String> {
+    errors: &mut Vec<Diagnostic>,
+) {
     for field in fields {
-        match &field.ty {
-            FieldType::Vec(inner) => match inner.as_ref() {
-                FieldType::Vec(_) => unreachable!(),
-                FieldType::Struct(name) => {
-                    if !metadata.identifier_to_struct.contains_key(name.as_str()) {
-                        return Err(make_chic_error(
-                            "Semantic error",
-                            source,
-                            &field.type_span,
-                            "Undefined type",
-                        )
-                        .to_string());
-                    }
-                }
-                _ => {}
-            },
-            FieldType::Struct(name) => {
-                if !metadata.identifier_to_struct.contains_key(name.as_str()) {
-                    return Err(make_chic_error(
-                        "Semantic error",
-                        source,
-                        &field.type_span,
-                        "Undefined type",
-                    )
-                    .to_string());
-                }
-            }
-            _ => {}
+        // Only specs parsed with errors have fields without a type, which can't be used either.
+        if field.ty == FieldType::Error {
+            errors.push(Diagnostic::new(
+                "Semantic error",
+                &field.type_span,
+                "Missing type",
+            ));
+            continue;
         }
-    }
 
-    Ok(())
+        // `Optional` structs have to be defined as much as the `Vec` and plain ones.
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+
+        if !metadata.identifier_to_struct.contains_key(name.as_str()) {
+            errors.push(Diagnostic::new(
+                "Semantic error",
+                &field.type_span,
+                "Undefined type",
+            ));
+        }
+    }
 }
 
-fn check_struct_attributes(strukt: &Struct, source: &str) -> Result<(), String> {
+fn check_struct_attributes(strukt: &Struct, errors: &mut Vec<Diagnostic>) {
     let mut main_span = Span::default();
     let mut subcommand_span = Span::default();
     let mut has_main = false;
@@ -177,10 +95,228 @@ fn check_struct_attributes(strukt: &Struct, source: &str) -> Result<(), String>
 
     for attribute in &strukt.attributes {
         match attribute.ty {
-            AttributeType::Short
-            | AttributeType::Long
-            | AttributeType::Alias
-            | AttributeType::Flatten => {
+            AttributeType::Main => {
+                has_main = true;
+                main_span = attribute.span.clone();
+            }
+            AttributeType::SubCommand => {
+                has_subcommand = true;
+                subcommand_span = attribute.span.clone();
+            }
+            AttributeType::AllowAbbrev
+            | AttributeType::CaseInsensitive
+            | AttributeType::NextLineHelp
+            | AttributeType::About
+            | AttributeType::AfterHelp
+            | AttributeType::ClapCompat => {}
+            AttributeType::WordWrap => {
+                if strukt.word_wrap().is_none_or(|width| width == 0) {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        "Invalid word wrap width",
+                    )
+                    .help("The width must be a positive number of characters");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::ExitCode
+            | AttributeType::ParseErrorExit
+            | AttributeType::MissingArgExit
+            | AttributeType::UnknownFlagExit => {
+                let code = attribute.value.as_ref().unwrap().parse::<u8>();
+                if code.is_err() || code == Ok(0) {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        "Invalid exit code",
+                    )
+                    .help("Exit codes of errors go from 1 to 255, 0 being a success");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::DocHidden => {
+                if strukt.has_attribute(AttributeType::Main) {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        &attribute.span,
+                        "Main struct can't be doc hidden",
+                    )
+                    .help("Doc hidden structs can only be used through flatten");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::Prefix | AttributeType::ValueSeparator => {
+                let (allowed_values, error_msg) = match attribute.ty {
+                    AttributeType::Prefix => (OptionSyntax::ALLOWED_PREFIXES, "Invalid prefix"),
+                    _ => (
+                        OptionSyntax::ALLOWED_VALUE_SEPARATORS,
+                        "Invalid value separator",
+                    ),
+                };
+
+                if !allowed_values.contains(&attribute.value.as_ref().unwrap().as_str()) {
+                    let help_msg = format!("Allowed values: {}", allowed_values.join(", "));
+
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        error_msg,
+                    )
+                    .help(help_msg.as_str());
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::Separator => {
+                let separator = attribute.value.as_ref().unwrap();
+                let looks_like_flag = separator.starts_with('-')
+                    && separator.chars().any(|c| c.is_ascii_alphanumeric());
+
+                if separator.is_empty() || looks_like_flag {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        "Invalid separator",
+                    )
+                    .help("The separator must be non empty and must not look like an option");
+
+                    errors.push(diagnostic);
+                } else if strukt.trailing_field().is_none() {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        &attribute.span,
+                        "Separator without trailing arguments",
+                    )
+                    .help("Add a trailing field to capture the arguments after the separator");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::ConfigFile => {
+                let is_path_option = strukt.config_file_field().is_some_and(|field| {
+                    let is_string = match &field.ty {
+                        FieldType::String => true,
+                        FieldType::Optional(inner) => matches!(inner.as_ref(), FieldType::String),
+                        _ => false,
+                    };
+                    is_string && (field.short_value().is_some() || field.long_value().is_some())
+                });
+
+                if !is_path_option {
+                    let diagnostic = Diagnostic::new("Semantic error", &attribute.span,
+                        "Config file should name a string field given by an option",
+                    )
+                    .help("The field holds the path of the config file, e.g. `config: Optional<string>`");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::EnvPrefix => {
+                let env_prefix = attribute.value.as_ref().unwrap();
+
+                if env_prefix.is_empty()
+                    || !env_prefix
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    let diagnostic =
+                        Diagnostic::new("Semantic error", attribute.value_or_name_span(), "Invalid env prefix")
+                            .help("The env prefix must be non empty and only made of letters, digits and underscores, e.g. `APP_`");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::EnvCase => {
+                if !Struct::ALLOWED_ENV_CASES.contains(&attribute.value.as_ref().unwrap().as_str())
+                {
+                    let help_msg =
+                        format!("Allowed values: {}", Struct::ALLOWED_ENV_CASES.join(", "));
+
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        "Invalid env case",
+                    )
+                    .help(help_msg.as_str());
+
+                    errors.push(diagnostic);
+                } else if !strukt.has_attribute(AttributeType::EnvPrefix) {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        &attribute.span,
+                        "Env case without env prefix",
+                    )
+                    .help("The env case applies to the names of the fields appended to the `env_prefix`");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::LongShortConflict => {
+                let allowed_values = Struct::ALLOWED_LONG_SHORT_CONFLICTS;
+
+                if !allowed_values.contains(&attribute.value.as_ref().unwrap().as_str()) {
+                    let help_msg = format!("Allowed values: {}", allowed_values.join(", "));
+
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        "Invalid long short conflict",
+                    )
+                    .help(help_msg.as_str());
+
+                    errors.push(diagnostic);
+                } else if !strukt.has_attribute(AttributeType::Main) {
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        &attribute.span,
+                        "Long short conflict outside of the main struct",
+                    )
+                    .help("It is set once on the main struct and applies to every struct");
+
+                    errors.push(diagnostic);
+                }
+            }
+            AttributeType::LongPrefix => {
+                let long_prefix = attribute.value.as_ref().unwrap();
+                let allowed_values = OptionSyntax::ALLOWED_LONG_PREFIXES;
+
+                if !allowed_values.contains(&long_prefix.as_str()) {
+                    let help_msg = format!("Allowed values: {}", allowed_values.join(", "));
+
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        attribute.value_or_name_span(),
+                        "Invalid long prefix",
+                    )
+                    .help(help_msg.as_str());
+
+                    errors.push(diagnostic);
+                } else if long_prefix == "/" && strukt.option_syntax().short_prefix == "-" {
+                    // `-x` next to `/flag` mixes two conventions, so the short options have to be
+                    // spelled with `/` as well.
+                    for field in strukt
+                        .fields
+                        .iter()
+                        .filter(|field| field.short_value().is_some())
+                    {
+                        let diagnostic = Diagnostic::with_info(
+                            "Semantic error",
+                            &field.name_span,
+                            "Short option with a `/` long prefix",
+                            &attribute.span,
+                            "Long prefix set here",
+                        )
+                        .help("Use `prefix = \"/\"` to spell the short options with `/` too");
+
+                        errors.push(diagnostic);
+                    }
+                }
+            }
+            _ => {
                 let help_msg = format!(
                     "Allowed attributes: {}",
                     AttributeType::allowed_struct_attribute_types()
@@ -190,52 +326,54 @@ fn check_struct_attributes(strukt: &Struct, source: &str) -> Result<(), String>
                         .join(", ")
                 );
 
-                let chic_error = make_chic_error(
-                    "Semantic error",
-                    source,
-                    &attribute.span,
-                    "Invalid attribute",
-                )
-                .help(help_msg.as_str());
+                let diagnostic =
+                    Diagnostic::new("Semantic error", &attribute.span, "Invalid attribute")
+                        .help(help_msg.as_str());
 
-                return Err(chic_error.to_string());
-            }
-            AttributeType::Main => {
-                has_main = true;
-                main_span = attribute.span.clone();
-            }
-            AttributeType::SubCommand => {
-                has_subcommand = true;
-                subcommand_span = attribute.span.clone();
+                errors.push(diagnostic);
             }
         }
     }
 
     if has_main && has_subcommand {
-        let error_span = Span::from(
-            min(main_span.start, subcommand_span.start)..max(main_span.end, subcommand_span.end),
-        );
+        // Both are in the attributes of the struct, which are pointed at as a whole.
+        let error_span = strukt.attributes_span.clone().unwrap_or_else(|| {
+            min(main_span.start, subcommand_span.start)..max(main_span.end, subcommand_span.end)
+        });
 
-        let chic_error = make_chic_error(
+        let diagnostic = Diagnostic::new(
             "Semantic error",
-            source,
             &error_span,
             "Invalid attribute combination",
         )
         .help("Only main or subcommand attributes are allowed");
 
-        return Err(chic_error.to_string());
+        errors.push(diagnostic);
     }
+}
 
-    Ok(())
+/// An option given to two fields, which is an error unless `long_short_conflict` makes it a
+/// warning. The last of the fields then takes the option.
+fn conflict_diagnostic(diagnostic: Diagnostic, severity: Severity) -> Diagnostic {
+    match severity {
+        Severity::Error => diagnostic,
+        _ => diagnostic
+            .severity(severity)
+            .help("The last field given the option takes it"),
+    }
 }
 
-fn check_field_attributes(fields: &[Field], source: &str) -> Result<(), String> {
-    let mut shorts = HashMap::new();
-    let mut longs = HashMap::new();
-    let mut aliases = HashMap::new();
+fn check_field_attributes(
+    strukt: &Struct,
+    conflict_severity: Severity,
+    errors: &mut Vec<Diagnostic>,
+) {
+    let mut shorts = BTreeMap::new();
+    let mut longs: BTreeMap<String, &Field> = BTreeMap::new();
+    let mut aliases: BTreeMap<String, &Field> = BTreeMap::new();
+    let mut trailing_field: Option<&Field> = None;
 
-    for field in fields {
+    for field in &strukt.fields {
         for attribute in &field.attributes {
             match attribute.ty {
                 AttributeType::Short => {
@@ -244,121 +382,2369 @@ fn check_field_attributes(fields: &[Field], source: &str) -> Result<(), String>
                     if shorts.contains_key(value) {
                         let original_field: &Field = shorts[value];
 
-                        let chic_error = make_chic_error_with_info(
+                        let diagnostic = Diagnostic::with_info(
                             "Invalid field attribute usage",
-                            source,
-                            &attribute.span,
+                            attribute.value_or_name_span(),
                             "There's already a field with the same starting character",
                             &original_field.name_span,
                             "Field with same starting letter",
                         );
 
-                        return Err(chic_error.to_string());
+                        errors.push(conflict_diagnostic(diagnostic, conflict_severity));
+                        if conflict_severity == Severity::Error {
+                            continue;
+                        }
                     }
 
                     shorts.insert(value, field);
                 }
                 AttributeType::Long => {
-                    let value = attribute.value.as_ref().unwrap().as_str();
+                    // With the prefix of the field, as that is the name it is given by.
+                    let value = field.long_value().unwrap();
 
-                    if longs.contains_key(value) && aliases.contains_key(value) {
-                        let original_field: &Field = longs[value];
-                        let chic_error = make_chic_error_with_info(
+                    if longs.contains_key(&value) || aliases.contains_key(&value) {
+                        let original_field = longs.get(&value).or(aliases.get(&value)).unwrap();
+                        let diagnostic = Diagnostic::with_info(
                             "Invalid field attribute usage",
-                            source,
-                            &attribute.span,
+                            attribute.value_or_name_span(),
                             "There's already a field with the same long name or alias",
                             &original_field.name_span,
                             "Field with same long or alias value",
                         );
 
-                        return Err(chic_error.to_string());
+                        errors.push(conflict_diagnostic(diagnostic, conflict_severity));
+                        if conflict_severity == Severity::Error {
+                            continue;
+                        }
                     }
 
                     longs.insert(value, field);
                 }
                 AttributeType::Alias => {
-                    let value = attribute.value.as_ref().unwrap().as_str();
+                    let value = attribute.value.as_ref().unwrap().replace('_', "-");
 
-                    if aliases.contains_key(value) && longs.contains_key(value) {
-                        let original_field: &Field = aliases[value];
-                        let chic_error = make_chic_error_with_info(
+                    if aliases.contains_key(&value) || longs.contains_key(&value) {
+                        let original_field = aliases.get(&value).or(longs.get(&value)).unwrap();
+                        let diagnostic = Diagnostic::with_info(
                             "Invalid field attribute usage",
-                            source,
-                            &attribute.span,
+                            attribute.value_or_name_span(),
                             "There's already a field with the same alias or long name",
                             &original_field.name_span,
                             "Field with same alias or long value",
                         );
 
-                        return Err(chic_error.to_string());
+                        errors.push(conflict_diagnostic(diagnostic, conflict_severity));
+                        if conflict_severity == Severity::Error {
+                            continue;
+                        }
                     }
 
                     aliases.insert(value, field);
                 }
                 AttributeType::Flatten => match &field.ty {
-                    FieldType::Vec(inner) => match inner.as_ref() {
+                    FieldType::Vec(inner) | FieldType::Optional(inner) => match inner.as_ref() {
                         FieldType::Vec(_) => unreachable!(),
                         FieldType::Struct(_) => {}
                         _ => {
-                            return Err(make_chic_error(
+                            errors.push(Diagnostic::new(
                                 "Invalid field attribute",
-                                source,
                                 &attribute.span,
                                 "Flatten should be used with a custom type",
-                            )
-                            .to_string());
+                            ));
                         }
                     },
                     FieldType::Struct(_) => {}
                     _ => {
-                        return Err(make_chic_error(
+                        errors.push(Diagnostic::new(
                             "Invalid field attribute",
-                            source,
                             &attribute.span,
                             "Flatten should be used with a custom type",
-                        )
-                        .to_string());
+                        ));
                     }
                 },
-                AttributeType::Main | AttributeType::SubCommand => {
-                    let help_msg = format!(
-                        "Valid field attributes are: {}",
-                        AttributeType::allowed_field_attribute_types()
-                            .iter()
-                            .map(|v| v.to_literal())
-                            .collect::<Vec<_>>()
-                            .join(", ")
+                AttributeType::ExplicitBool => {
+                    if !matches!(field.ty.value_type(), FieldType::Bool) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Explicit bool should be used with a bool field",
+                        ));
+                    }
+                }
+                AttributeType::FromStr => {
+                    if !matches!(field.ty.value_type(), FieldType::Struct(_)) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "From str should be used with a custom type",
+                        ));
+                    } else if field.has_attribute(AttributeType::Flatten) {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Conflicts with flatten",
+                            )
+                            .help("from_str parses the type from a single value, while flatten parses its options"),
+                        );
+                    }
+                }
+                AttributeType::DefaultValueT => {
+                    check_default_value(field, attribute, errors);
+                }
+                AttributeType::Index => {
+                    let is_string_or_numeric = matches!(
+                        field.ty.value_type(),
+                        FieldType::String
+                            | FieldType::I16
+                            | FieldType::U16
+                            | FieldType::I32
+                            | FieldType::U32
+                            | FieldType::I64
+                            | FieldType::U64
+                            | FieldType::F32
+                            | FieldType::F64
                     );
+                    let conflict = field.attributes.iter().find(|attr| {
+                        matches!(
+                            attr.ty,
+                            AttributeType::Short
+                                | AttributeType::Long
+                                | AttributeType::Alias
+                                | AttributeType::ShortAndLong
+                                | AttributeType::Trailing
+                                | AttributeType::Env
+                        )
+                    });
 
-                    let chic_error = make_chic_error(
-                        "Semantic error",
-                        source,
-                        &attribute.span,
-                        "Invalid field attribute",
-                    )
-                    .help(help_msg.as_str());
+                    if !is_string_or_numeric || matches!(field.ty, FieldType::Vec(_)) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Index should be used with a string or numeric field",
+                        ));
+                    } else if let Some(conflict) = conflict {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                &format!("Conflicts with {}", conflict.ty.to_literal()),
+                            )
+                            .help("Positional fields are only given by their position on the command line"),
+                        );
+                    }
+                }
+                AttributeType::ParseOrder => {
+                    let has_option = field.attributes.iter().any(|attr| {
+                        matches!(
+                            attr.ty,
+                            AttributeType::Short
+                                | AttributeType::Long
+                                | AttributeType::Alias
+                                | AttributeType::ShortAndLong
+                        )
+                    });
+
+                    if !has_option {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Parse order should be used with a field given by an option",
+                            )
+                            .help("It orders how the options of a struct are matched"),
+                        );
+                    }
+                }
+                AttributeType::DisplayOrder => {
+                    let has_option = field.attributes.iter().any(|attr| {
+                        matches!(
+                            attr.ty,
+                            AttributeType::Short
+                                | AttributeType::Long
+                                | AttributeType::Alias
+                                | AttributeType::ShortAndLong
+                        )
+                    });
 
-                    return Err(chic_error.to_string());
+                    if !has_option {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Display order should be used with a field given by an option",
+                            )
+                            .help("It orders how the options of a struct are listed in the help"),
+                        );
+                    } else if field.display_order().is_none() {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            "Invalid display order",
+                        ));
+                    }
                 }
-            }
-        }
-    }
+                AttributeType::TakesValue => {
+                    let takes_value = attribute.value.as_deref() == Some("true");
+                    let is_bool = matches!(field.ty.value_type(), FieldType::Bool);
 
-    Ok(())
-}
+                    if !takes_value && !is_bool {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Only bool fields can be used without a value",
+                        ));
+                    } else if !takes_value
+                        && field
+                            .attributes
+                            .iter()
+                            .any(|attr| matches!(attr.ty, AttributeType::ExplicitBool))
+                    {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Conflicts with explicit_bool",
+                            )
+                            .help("explicit_bool requires the field to take a value"),
+                        );
+                    }
+                }
+                AttributeType::Trailing => {
+                    let is_string_vec = matches!(
+                        &field.ty,
+                        FieldType::Vec(inner) if matches!(inner.as_ref(), FieldType::String)
+                    );
 
-pub(crate) fn check_semantics<'s>(spec: &'s Spec) -> Result<SpecMetadata<'s>, String> {
-    let identifier_to_struct = check_for_multiple_struct_definitions(&spec.structs, spec.source)?;
-    let mut spec_metadata = SpecMetadata::default();
-    spec_metadata.identifier_to_struct = identifier_to_struct;
+                    if !is_string_vec {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Trailing should be used with a Vec<string> field",
+                        ));
+                    } else if let Some(original_field) = trailing_field {
+                        let diagnostic = Diagnostic::with_info(
+                            "Invalid field attribute usage",
+                            &attribute.span,
+                            "There can only be one trailing field",
+                            &original_field.name_span,
+                            "Trailing field already defined here",
+                        );
 
-    for strukt in &spec.structs {
-        check_for_undefined_types(&spec_metadata, &strukt.fields, spec.source)?;
-        check_for_multiple_field_definitions(&strukt.fields, spec.source)?;
-        check_struct_attributes(strukt, spec.source)?;
-        check_field_attributes(&strukt.fields, spec.source)?;
-    }
+                        errors.push(diagnostic);
+                    } else {
+                        trailing_field = Some(field);
+                    }
+                }
+                AttributeType::AppendEnv | AttributeType::EnvList => {
+                    let name = if attribute.ty == AttributeType::AppendEnv {
+                        "Append env"
+                    } else {
+                        "Env list"
+                    };
+                    let is_string_vec = matches!(
+                        &field.ty,
+                        FieldType::Vec(inner) if matches!(inner.as_ref(), FieldType::String)
+                    );
+                    let first = field
+                        .attributes
+                        .iter()
+                        .find(|attr| attr.ty == attribute.ty)
+                        .unwrap();
 
-    Ok(spec_metadata)
+                    if first.span != attribute.span {
+                        errors.push(Diagnostic::with_info(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            &format!("{name} given more than once"),
+                            &first.span,
+                            "First given here",
+                        ));
+                    } else if !is_string_vec {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            &format!("{name} should be used with a Vec<string> field"),
+                        ));
+                    } else if attribute.value.as_ref().unwrap().is_empty() {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            &format!("{name} should be the name of an environment variable"),
+                        ));
+                    }
+                }
+                AttributeType::ExclusiveGroup | AttributeType::RequiresGroup => {
+                    let name = if attribute.ty == AttributeType::ExclusiveGroup {
+                        "Exclusive group"
+                    } else {
+                        "Requires group"
+                    };
+                    let is_option = field.attributes.iter().any(|attribute| {
+                        matches!(
+                            attribute.ty,
+                            AttributeType::Short | AttributeType::Long | AttributeType::Alias
+                        )
+                    });
+                    let first = field
+                        .attributes
+                        .iter()
+                        .find(|attr| attr.ty == attribute.ty)
+                        .unwrap();
+
+                    if first.span != attribute.span {
+                        errors.push(Diagnostic::with_info(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            &format!("{name} given more than once"),
+                            &first.span,
+                            "First given here",
+                        ));
+                    } else if !is_option {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            &format!("{name} should be used with a field given by an option"),
+                        ));
+                    } else if attribute.value.as_ref().unwrap().is_empty() {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            &format!("{name} should be the name of a group"),
+                        ));
+                    }
+                }
+                AttributeType::Env => {
+                    let is_single_value = match &field.ty {
+                        FieldType::Vec(_) | FieldType::Struct(_) => false,
+                        FieldType::Optional(inner) => {
+                            !matches!(inner.as_ref(), FieldType::Struct(_))
+                        }
+                        _ => true,
+                    };
+
+                    if !is_single_value || field.has_attribute(AttributeType::Trailing) {
+                        errors.push(
+                            Diagnostic::new("Invalid field attribute", &attribute.span,
+                                "Env should be used with a field holding a single value",
+                            )
+                            .help("Vec<string> fields are read from the environment with `append_env` or `env_list`"),
+                        );
+                    } else if attribute.value.as_ref().unwrap().is_empty() {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            "Env should be the name of an environment variable",
+                        ));
+                    }
+                }
+                AttributeType::EnvNoOverride => {
+                    if strukt.field_env(field).is_none() {
+                        errors.push(
+                            Diagnostic::new("Invalid field attribute", &attribute.span,
+                                "Env no override should be used along with env",
+                            )
+                            .help("Name the environment variable, e.g. `#[env = \"PORT\", env_no_override]`"),
+                        );
+                    }
+                }
+                AttributeType::Secret | AttributeType::ParseFn => {
+                    let is_string_or_numeric = matches!(
+                        field.ty.value_type(),
+                        FieldType::String
+                            | FieldType::I16
+                            | FieldType::U16
+                            | FieldType::I32
+                            | FieldType::U32
+                            | FieldType::I64
+                            | FieldType::U64
+                            | FieldType::F32
+                            | FieldType::F64
+                    );
+
+                    if !is_string_or_numeric {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            if attribute.ty == AttributeType::Secret {
+                                "Secret should be used with a string or numeric field"
+                            } else {
+                                "Parse fn should be used with a string or numeric field"
+                            },
+                        ));
+                    }
+
+                    // The function is called by name, possibly qualified, e.g. `net::parse_ipv4`.
+                    let is_identifier = |name: &str| {
+                        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    };
+                    if attribute.ty == AttributeType::ParseFn
+                        && !attribute
+                            .value
+                            .as_ref()
+                            .unwrap()
+                            .split("::")
+                            .all(is_identifier)
+                    {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            "Parse fn should be the name of a C++ function",
+                        ));
+                    }
+                }
+                AttributeType::MinOccurs | AttributeType::MaxOccurs => {
+                    let value = attribute.value.as_ref().unwrap();
+
+                    if !matches!(field.ty, FieldType::Vec(_)) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Occurrence constraints should be used with a Vec field",
+                        ));
+                    } else if value.parse::<usize>().is_err() {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            "Invalid number of occurrences",
+                        ));
+                    } else if attribute.ty == AttributeType::MaxOccurs {
+                        match (field.min_occurs(), field.max_occurs()) {
+                            (_, Some(0)) => errors.push(Diagnostic::new(
+                                "Invalid field attribute",
+                                attribute.value_or_name_span(),
+                                "max_occurs should be at least 1",
+                            )),
+                            (Some(min), Some(max)) if min > max => errors.push(Diagnostic::new(
+                                "Invalid field attribute",
+                                attribute.value_or_name_span(),
+                                "max_occurs is less than min_occurs",
+                            )),
+                            _ => {}
+                        }
+                    }
+                }
+                AttributeType::Min | AttributeType::Max => {
+                    let value = attribute.value.as_ref().unwrap();
+
+                    match field.ty.value_type().bounds() {
+                        None => errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Min and max should be used with a numeric field",
+                        )),
+                        Some(bounds) => {
+                            let bound = field.ty.value_type().parse_bound(value);
+                            if !bound.is_some_and(|bound| bounds.0 <= bound && bound <= bounds.1) {
+                                errors.push(Diagnostic::new(
+                                    "Invalid field attribute",
+                                    attribute.value_or_name_span(),
+                                    "Value is not representable by the field type",
+                                ));
+                            } else if attribute.ty == AttributeType::Max {
+                                let min = field
+                                    .attribute_value(AttributeType::Min)
+                                    .and_then(|min| field.ty.value_type().parse_bound(min));
+
+                                if min.is_some_and(|min| Some(min) > bound) {
+                                    errors.push(Diagnostic::new(
+                                        "Invalid field attribute",
+                                        attribute.value_or_name_span(),
+                                        "max is less than min",
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+                AttributeType::CaseInsensitive
+                | AttributeType::Doc
+                | AttributeType::NextLineHelp => {}
+                AttributeType::Deprecated
+                | AttributeType::DeprecatedIn
+                | AttributeType::RemovedIn
+                | AttributeType::HiddenFromCompletion => {
+                    let is_option = field.attributes.iter().any(|attribute| {
+                        matches!(
+                            attribute.ty,
+                            AttributeType::Short | AttributeType::Long | AttributeType::Alias
+                        )
+                    });
+
+                    if !is_option {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            if attribute.ty == AttributeType::HiddenFromCompletion {
+                                "Only fields given by an option can be hidden from completion"
+                            } else {
+                                "Only fields given by an option can be deprecated"
+                            },
+                        ));
+                    } else if attribute.ty == AttributeType::RemovedIn {
+                        check_removed_in(field, attribute, errors);
+                    }
+                }
+                AttributeType::InputFile | AttributeType::OutputFile | AttributeType::Directory => {
+                    let name = match attribute.ty {
+                        AttributeType::InputFile => "Input file",
+                        AttributeType::OutputFile => "Output file",
+                        _ => "Directory",
+                    };
+
+                    if !matches!(field.ty.value_type(), FieldType::String) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            &format!("{name} should be used with a string field"),
+                        ));
+                    } else if attribute.ty == AttributeType::OutputFile && field.is_input_file() {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Conflicts with input_file",
+                            )
+                            .help("A file is either read or written"),
+                        );
+                    } else if attribute.ty == AttributeType::Directory
+                        && (field.is_input_file() || field.is_output_file())
+                    {
+                        let file = if field.is_input_file() {
+                            "input_file"
+                        } else {
+                            "output_file"
+                        };
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                &format!("Conflicts with {file}"),
+                            )
+                            .help("A path is either a file or a directory"),
+                        );
+                    }
+                }
+                AttributeType::GlobalFlag => {
+                    if !matches!(field.ty, FieldType::Bool) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Global flag should be used with a bool field",
+                        ));
+                    } else if !strukt.has_attribute(AttributeType::Main) {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Global flag outside of the main struct",
+                            )
+                            .help("Only the arguments of the main struct precede a subcommand"),
+                        );
+                    }
+                }
+                AttributeType::PrefixFlag => {
+                    let is_option = field.attributes.iter().any(|attribute| {
+                        matches!(
+                            attribute.ty,
+                            AttributeType::Short | AttributeType::Long | AttributeType::Alias
+                        )
+                    });
+                    let takes_value = !matches!(
+                        field.ty.value_type(),
+                        FieldType::Bool | FieldType::Struct(_)
+                    );
+
+                    if !is_option || !takes_value {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                &attribute.span,
+                                "Prefix flag should be used with an option taking a value",
+                            )
+                            .help("bool options already accept an attached value"),
+                        );
+                    }
+                }
+                AttributeType::Prefix => {
+                    let prefix = attribute.value.as_ref().unwrap();
+
+                    if !field.has_attribute(AttributeType::Long) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Prefix should be used with a field given by a long option",
+                        ));
+                    } else if !prefix.starts_with(|c: char| c.is_ascii_alphanumeric())
+                        || !prefix
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                    {
+                        errors.push(
+                            Diagnostic::new(
+                                "Invalid field attribute",
+                                attribute.value_or_name_span(),
+                                "Invalid prefix of a long name",
+                            )
+                            .help("The prefix must start with a letter or a digit and only contain letters, digits, '-' and '_'"),
+                        );
+                    }
+                }
+                // Well formed ones are replaced by a `short` and a `long` by the parser.
+                AttributeType::ShortAndLong => {
+                    errors.push(
+                        Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            "Short and long should be a letter and a long name separated by '/'",
+                        )
+                        .help("e.g. `short_and_long = \"v/verbose\"`"),
+                    );
+                }
+                AttributeType::Default => {
+                    if matches!(field.ty, FieldType::Optional(_)) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Optional fields are already left out by default",
+                        ));
+                    }
+                }
+                AttributeType::Regex => {
+                    if !matches!(field.ty.value_type(), FieldType::String) {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            &attribute.span,
+                            "Regex should be used with a string field",
+                        ));
+                    } else if attribute.value.as_ref().unwrap().is_empty() {
+                        errors.push(Diagnostic::new(
+                            "Invalid field attribute",
+                            attribute.value_or_name_span(),
+                            "Regex pattern must not be empty",
+                        ));
+                    }
+                }
+                AttributeType::Main
+                | AttributeType::SubCommand
+                | AttributeType::AllowAbbrev
+                | AttributeType::Separator
+                | AttributeType::ValueSeparator
+                | AttributeType::DocHidden
+                | AttributeType::About
+                | AttributeType::AfterHelp
+                | AttributeType::LongPrefix
+                | AttributeType::WordWrap
+                | AttributeType::ConfigFile
+                | AttributeType::ClapCompat
+                | AttributeType::ExitCode
+                | AttributeType::ParseErrorExit
+                | AttributeType::MissingArgExit
+                | AttributeType::UnknownFlagExit
+                | AttributeType::EnvPrefix
+                | AttributeType::EnvCase
+                | AttributeType::LongShortConflict => {
+                    let help_msg = format!(
+                        "Valid field attributes are: {}",
+                        AttributeType::allowed_field_attribute_types()
+                            .iter()
+                            .map(|v| v.to_literal())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+
+                    let diagnostic = Diagnostic::new(
+                        "Semantic error",
+                        &attribute.span,
+                        "Invalid field attribute",
+                    )
+                    .help(help_msg.as_str());
+
+                    errors.push(diagnostic);
+                }
+            }
+        }
+    }
+}
+
+/// All the structs parsed as part of another struct must spell their options the same way.
+fn check_option_syntax_consistency(
+    metadata: &SpecMetadata,
+    strukt: &Struct,
+    errors: &mut Vec<Diagnostic>,
+) {
+    let syntax = strukt.option_syntax();
+
+    for field in &strukt.fields {
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+
+        let Some(inner_struct) = metadata.identifier_to_struct.get(name.as_str()) else {
+            continue;
+        };
+
+        let inner_syntax = inner_struct.option_syntax();
+        if syntax.short_prefix != inner_syntax.short_prefix
+            || syntax.long_prefix != inner_syntax.long_prefix
+            || syntax.value_separator != inner_syntax.value_separator
+        {
+            let diagnostic = Diagnostic::with_info(
+                "Semantic error",
+                &field.type_span,
+                "Mixed option prefixes",
+                &inner_struct.name_span,
+                "Type with different prefix or value separator",
+            );
+
+            errors.push(diagnostic);
+        }
+    }
+}
+
+/// Compares two versions made of dot separated components, numerically for the components that are
+/// numbers, e.g. `2.10` comes after `2.9`.
+fn compare_versions(lhs: &str, rhs: &str) -> Ordering {
+    let components = |version: &str| {
+        version
+            .split('.')
+            .map(|component| (component.parse::<u64>().ok(), component.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    components(lhs).cmp(&components(rhs))
+}
+
+/// A field can only be removed after it has been deprecated.
+fn check_removed_in(field: &Field, removed_in: &Attribute, errors: &mut Vec<Diagnostic>) {
+    if !field.is_deprecated() {
+        let diagnostic = Diagnostic::new(
+            "Invalid field attribute",
+            &removed_in.span,
+            "Removed in should be used along with deprecated or deprecated_in",
+        )
+        .help("Fields are deprecated before being removed, e.g. `deprecated_in = \"2.0\"`");
+
+        errors.push(diagnostic);
+        return;
+    }
+
+    let Some(deprecated_in) = field
+        .attributes
+        .iter()
+        .find(|attr| attr.ty == AttributeType::DeprecatedIn)
+    else {
+        return;
+    };
+
+    let (Some(deprecated_version), Some(removed_version)) =
+        (&deprecated_in.value, &removed_in.value)
+    else {
+        return;
+    };
+
+    // The versions are only printed in the deprecation warning, so the code is still generated.
+    if compare_versions(deprecated_version, removed_version) == Ordering::Greater {
+        let diagnostic = Diagnostic::with_info(
+            "Invalid field attribute",
+            removed_in.value_or_name_span(),
+            "Field removed before being deprecated",
+            deprecated_in.value_or_name_span(),
+            "Deprecated in this later version",
+        )
+        .severity(Severity::Warning);
+
+        errors.push(diagnostic);
+    }
+}
+
+/// A default value is written to the generated code as it is, so it has to be a value of the field
+/// type, within its `min` and `max` if any. Bools and `Vec` or `Optional` fields have an empty
+/// default already.
+fn check_default_value(field: &Field, default_value: &Attribute, errors: &mut Vec<Diagnostic>) {
+    if field.has_attribute(AttributeType::Default) {
+        errors.push(
+            Diagnostic::new(
+                "Invalid field attribute",
+                &default_value.span,
+                "Conflicts with default",
+            )
+            .help("default_value_t already lets the field be left out"),
+        );
+        return;
+    }
+    if field.ty == FieldType::String {
+        return;
+    }
+
+    let Some(bounds) = field.ty.bounds() else {
+        errors.push(Diagnostic::new(
+            "Invalid field attribute",
+            &default_value.span,
+            "Default value should be used with a numeric or string field",
+        ));
+        return;
+    };
+
+    let value = field
+        .ty
+        .parse_bound(default_value.value.as_ref().unwrap())
+        .filter(|value| bounds.0 <= *value && *value <= bounds.1);
+    let Some(value) = value else {
+        errors.push(Diagnostic::new(
+            "Invalid field attribute",
+            default_value.value_or_name_span(),
+            "Value is not representable by the field type",
+        ));
+        return;
+    };
+
+    let bound = |ty| {
+        field
+            .attribute_value(ty)
+            .and_then(|bound| field.ty.parse_bound(bound))
+    };
+    let below_min = bound(AttributeType::Min).is_some_and(|min| value < min);
+    let above_max = bound(AttributeType::Max).is_some_and(|max| value > max);
+    if below_min || above_max {
+        errors.push(Diagnostic::new(
+            "Invalid field attribute",
+            default_value.value_or_name_span(),
+            "Default value is out of the range of min and max",
+        ));
+    }
+}
+
+/// The options of a struct are matched in their parse order, which must tell them all apart.
+fn check_parse_orders(strukt: &Struct, errors: &mut Vec<Diagnostic>) {
+    let mut orders: HashMap<usize, &Field> = HashMap::new();
+
+    for field in &strukt.fields {
+        for attribute in field
+            .attributes
+            .iter()
+            .filter(|attr| attr.ty == AttributeType::ParseOrder)
+        {
+            let Ok(order) = attribute.value.as_ref().unwrap().parse::<usize>() else {
+                errors.push(Diagnostic::new(
+                    "Invalid field attribute",
+                    attribute.value_or_name_span(),
+                    "Invalid parse order",
+                ));
+                continue;
+            };
+
+            if let Some(original_field) = orders.get(&order) {
+                errors.push(Diagnostic::with_info(
+                    "Invalid field attribute usage",
+                    attribute.value_or_name_span(),
+                    "There's already a field with the same parse order",
+                    &original_field.name_span,
+                    "Field with same parse order",
+                ));
+                continue;
+            }
+
+            orders.insert(order, field);
+        }
+    }
+}
+
+/// The indices of the positional fields of a struct go from 0 to N-1, so that every field has its
+/// own position, and the mandatory ones come first since the fields after an optional one can only
+/// be given along with it.
+fn check_positional_indices(strukt: &Struct, errors: &mut Vec<Diagnostic>) {
+    let mut indices: BTreeMap<usize, &Field> = BTreeMap::new();
+    let mut attributes = Vec::new();
+
+    for field in &strukt.fields {
+        for attribute in field
+            .attributes
+            .iter()
+            .filter(|attr| attr.ty == AttributeType::Index)
+        {
+            let Ok(index) = attribute.value.as_ref().unwrap().parse::<usize>() else {
+                errors.push(Diagnostic::new(
+                    "Invalid field attribute",
+                    attribute.value_or_name_span(),
+                    "Invalid index",
+                ));
+                continue;
+            };
+
+            if let Some(original_field) = indices.get(&index) {
+                errors.push(Diagnostic::with_info(
+                    "Invalid field attribute usage",
+                    attribute.value_or_name_span(),
+                    "There's already a positional field with the same index",
+                    &original_field.name_span,
+                    "Field with same index",
+                ));
+                continue;
+            }
+
+            indices.insert(index, field);
+            attributes.push((index, attribute));
+        }
+    }
+
+    for (index, attribute) in &attributes {
+        if *index >= indices.len() {
+            errors.push(
+                Diagnostic::new(
+                    "Invalid field attribute",
+                    &attribute.span,
+                    "Indices of positional fields should go from 0 to N-1",
+                )
+                .help(&format!(
+                    "The struct has {} positional fields, so their indices go from 0 to {}",
+                    indices.len(),
+                    indices.len() - 1
+                )),
+            );
+        }
+    }
+
+    let mut optional_field: Option<&Field> = None;
+    for field in indices.values() {
+        match optional_field {
+            Some(optional_field) if field.is_required() => {
+                errors.push(Diagnostic::with_info(
+                    "Semantic error",
+                    &field.name_span,
+                    "Mandatory positional field after an optional one",
+                    &optional_field.name_span,
+                    "Optional positional field defined here",
+                ));
+            }
+            None if !field.is_required() => optional_field = Some(field),
+            _ => {}
+        }
+    }
+}
+
+/// Positional fields are given by their position among the arguments of the struct parsing them,
+/// which a flattened struct doesn't know about.
+fn check_flattened_positional_fields(
+    metadata: &SpecMetadata,
+    strukt: &Struct,
+    errors: &mut Vec<Diagnostic>,
+) {
+    for field in &strukt.fields {
+        if !field.has_attribute(AttributeType::Flatten) {
+            continue;
+        }
+
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+
+        let Some(inner_struct) = metadata.identifier_to_struct.get(name.as_str()) else {
+            continue;
+        };
+
+        if let Some(inner_field) = inner_struct.positional_fields().first() {
+            let diagnostic = Diagnostic::with_info(
+                "Semantic error",
+                &field.type_span,
+                "Flatten of a type with positional fields",
+                &inner_field.name_span,
+                "Positional field defined here",
+            );
+
+            errors.push(diagnostic);
+        }
+    }
+}
+
+/// Clap compat structs spell their options exactly like clap derive does, so that a clap CLI can be
+/// ported without breaking its users: `-s`, `--long` and `--long=value`, and no field without an
+/// option, which clap would take as a positional argument.
+fn check_clap_compat(strukt: &Struct, errors: &mut Vec<Diagnostic>) {
+    let Some(clap_compat) = strukt
+        .attributes
+        .iter()
+        .find(|attr| attr.ty == AttributeType::ClapCompat)
+    else {
+        return;
+    };
+
+    for attribute in &strukt.attributes {
+        let changes_syntax = match attribute.ty {
+            AttributeType::Prefix | AttributeType::LongPrefix => true,
+            AttributeType::ValueSeparator => attribute.value.as_deref() != Some("="),
+            _ => false,
+        };
+
+        if changes_syntax {
+            let diagnostic = Diagnostic::with_info(
+                "Semantic error",
+                &attribute.span,
+                "Option syntax of a clap compat struct changed",
+                &clap_compat.span,
+                "Clap compat set here",
+            )
+            .help("clap spells the options as `-s`, `--long` and `--long=value`");
+
+            errors.push(diagnostic);
+        }
+    }
+
+    for field in &strukt.fields {
+        if matches!(field.ty.value_type(), FieldType::Struct(_))
+            || field.has_attribute(AttributeType::Trailing)
+            || field.has_attribute(AttributeType::Index)
+        {
+            continue;
+        }
+
+        if field.short_value().is_none() && field.long_value().is_none() {
+            let diagnostic = Diagnostic::with_info(
+                "Semantic error",
+                &field.name_span,
+                "Field without an option in a clap compat struct",
+                &clap_compat.span,
+                "Clap compat set here",
+            )
+            .help("clap takes it as a positional argument, which is a field with an `index` here");
+
+            errors.push(diagnostic);
+        }
+    }
+}
+
+/// Long options that only differ in case can't be told apart once either of them is matched
+/// ignoring case.
+fn check_case_insensitive_collisions(
+    metadata: &SpecMetadata,
+    strukt: &Struct,
+    errors: &mut Vec<Diagnostic>,
+) {
+    let mut long_options: Vec<(String, &Field, bool)> = Vec::new();
+
+    for field in strukt.get_fields(metadata) {
+        let case_insensitive = strukt.is_case_insensitive(field, metadata);
+
+        for attribute in &field.attributes {
+            let value = match attribute.ty {
+                AttributeType::Long => field.long_value().unwrap(),
+                AttributeType::Alias => attribute.value.as_ref().unwrap().replace('_', "-"),
+                _ => continue,
+            };
+
+            let collision = long_options
+                .iter()
+                .find(|(other_value, _, other_case_insensitive)| {
+                    (case_insensitive || *other_case_insensitive)
+                        && *other_value != value
+                        && other_value.eq_ignore_ascii_case(&value)
+                });
+
+            if let Some((_, other_field, _)) = collision {
+                let diagnostic = Diagnostic::with_info(
+                    "Invalid field attribute usage",
+                    &attribute.span,
+                    "Long name or alias only differs in case from another one",
+                    &other_field.name_span,
+                    "Field with the same case insensitive long name or alias",
+                );
+
+                errors.push(diagnostic);
+                continue;
+            }
+
+            long_options.push((value, field, case_insensitive));
+        }
+    }
+}
+
+fn check_doc_hidden_usage(metadata: &SpecMetadata, strukt: &Struct, errors: &mut Vec<Diagnostic>) {
+    for field in &strukt.fields {
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+
+        let Some(inner_struct) = metadata.identifier_to_struct.get(name.as_str()) else {
+            continue;
+        };
+
+        if inner_struct.is_doc_hidden() && !field.has_attribute(AttributeType::Flatten) {
+            let diagnostic = Diagnostic::with_info(
+                "Semantic error",
+                &field.type_span,
+                "Doc hidden type used without flatten",
+                &inner_struct.name_span,
+                "Doc hidden type defined here",
+            );
+
+            errors.push(diagnostic);
+        }
+    }
+}
+
+/// The options of a struct flattened through a `Vec` start a new element whenever they are given
+/// again, which can't be told apart for the options of the structs it flattens itself.
+fn check_repeated_flatten_usage(
+    metadata: &SpecMetadata,
+    strukt: &Struct,
+    errors: &mut Vec<Diagnostic>,
+) {
+    for field in &strukt.fields {
+        if !matches!(field.ty, FieldType::Vec(_)) || !field.has_attribute(AttributeType::Flatten) {
+            continue;
+        }
+
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+
+        let Some(inner_struct) = metadata.identifier_to_struct.get(name.as_str()) else {
+            continue;
+        };
+
+        if let Some(inner_field) = inner_struct
+            .fields
+            .iter()
+            .find(|inner_field| inner_field.has_attribute(AttributeType::Flatten))
+        {
+            let diagnostic = Diagnostic::with_info(
+                "Semantic error",
+                &field.type_span,
+                "Repeated flatten of a type that flattens other types",
+                &inner_field.name_span,
+                "Flattened field defined here",
+            );
+
+            errors.push(diagnostic);
+        }
+    }
+}
+
+/// A struct flattening itself, directly or through the structs it flattens, would have endless
+/// fields.
+fn check_flatten_cycles(metadata: &SpecMetadata, strukt: &Struct, errors: &mut Vec<Diagnostic>) {
+    fn flattens<'s>(
+        metadata: &SpecMetadata<'s>,
+        from: &'s Struct,
+        to: &Struct,
+        visited: &mut Vec<&'s str>,
+    ) -> bool {
+        if visited.contains(&from.name.as_str()) {
+            return false;
+        }
+        visited.push(&from.name);
+
+        from.fields
+            .iter()
+            .filter(|field| field.has_attribute(AttributeType::Flatten))
+            .filter_map(|field| match field.ty.value_type() {
+                FieldType::Struct(name) => metadata.identifier_to_struct.get(name.as_str()),
+                _ => None,
+            })
+            .any(|inner| std::ptr::eq(*inner, to) || flattens(metadata, inner, to, visited))
+    }
+
+    for field in &strukt.fields {
+        if !field.has_attribute(AttributeType::Flatten) {
+            continue;
+        }
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+        let Some(inner_struct) = metadata.identifier_to_struct.get(name.as_str()) else {
+            continue;
+        };
+
+        if std::ptr::eq(*inner_struct, strukt)
+            || flattens(metadata, inner_struct, strukt, &mut Vec::new())
+        {
+            errors.push(Diagnostic::with_info(
+                "Semantic error",
+                &field.type_span,
+                "Flatten of a type that flattens this struct",
+                &strukt.name_span,
+                "Struct defined here",
+            ));
+        }
+    }
+}
+
+/// Finds the fields and options of `strukt` along with the ones of the structs it flattens, which
+/// must not flatten it back.
+fn collect_struct_metadata<'s>(
+    metadata: &SpecMetadata<'s>,
+    strukt: &'s Struct,
+) -> StructMetadata<'s> {
+    fn collect<'s>(
+        metadata: &SpecMetadata<'s>,
+        strukt: &'s Struct,
+        declaring_struct: &'s Struct,
+        required: bool,
+        struct_metadata: &mut StructMetadata<'s>,
+    ) {
+        let syntax = strukt.option_syntax();
+        let mut options: Vec<StructOption> = Vec::new();
+
+        for field in &declaring_struct.fields {
+            struct_metadata.fields.push(field);
+            if required && field.is_required() {
+                struct_metadata.mandatory_fields.push(field);
+            }
+
+            let case_insensitive = field.has_attribute(AttributeType::CaseInsensitive)
+                || strukt.has_attribute(AttributeType::CaseInsensitive)
+                || declaring_struct.has_attribute(AttributeType::CaseInsensitive);
+            let value = match field.ty.value_type() {
+                FieldType::Bool if field.is_explicit_bool() => ValueKind::ExplicitBool,
+                FieldType::Bool => ValueKind::Flag,
+                _ => ValueKind::Value,
+            };
+            for attribute in &field.attributes {
+                let (name, case_insensitive) = match attribute.ty {
+                    AttributeType::Short => {
+                        (syntax.short_option(&field.short_value().unwrap()), false)
+                    }
+                    AttributeType::Long => (
+                        syntax.long_option(&field.long_value().unwrap()),
+                        case_insensitive,
+                    ),
+                    AttributeType::Alias => (
+                        syntax.long_option(&attribute.value.as_ref().unwrap().replace('_', "-")),
+                        case_insensitive,
+                    ),
+                    _ => continue,
+                };
+
+                options.push(StructOption {
+                    name,
+                    ty: attribute.ty,
+                    field,
+                    case_insensitive,
+                    value,
+                });
+            }
+        }
+
+        // An option given to two fields of the struct, allowed by `long_short_conflict`, is taken
+        // by the last of them.
+        for (index, option) in options.iter().enumerate() {
+            if options[index + 1..]
+                .iter()
+                .all(|later_option| later_option.name != option.name)
+            {
+                struct_metadata.options.push(option.clone());
+            }
+        }
+
+        for field in &declaring_struct.fields {
+            if !field.has_attribute(AttributeType::Flatten) {
+                continue;
+            }
+            let FieldType::Struct(name) = field.ty.value_type() else {
+                continue;
+            };
+
+            // The fields of optional and repeated groups are only required once the group is given.
+            let required = required && matches!(field.ty, FieldType::Struct(_));
+            collect(
+                metadata,
+                strukt,
+                metadata.identifier_to_struct[name.as_str()],
+                required,
+                struct_metadata,
+            );
+        }
+    }
+
+    let mut struct_metadata = StructMetadata::default();
+    collect(metadata, strukt, strukt, true, &mut struct_metadata);
+    struct_metadata
+}
+
+/// Collects the fields of every `exclusive_group` into `option_groups`. A group belongs to a single
+/// struct, whose mandatory fields can't be part of it, and the group a field requires has to be
+/// one of its struct. Flattened fields are not tracked by the struct flattening them, so structs
+/// with groups can't be flattened.
+fn check_option_groups<'s>(
+    metadata: &mut SpecMetadata<'s>,
+    strukt: &'s Struct,
+    group_owners: &mut HashMap<&'s str, (&'s Struct, &'s Span)>,
+    errors: &mut Vec<Diagnostic>,
+) {
+    for (index, field) in strukt.fields.iter().enumerate() {
+        let Some(attribute) = field
+            .attributes
+            .iter()
+            .find(|attribute| attribute.ty == AttributeType::ExclusiveGroup)
+        else {
+            continue;
+        };
+        let group = attribute.value.as_deref().unwrap();
+
+        let (owner, first_span) = *group_owners
+            .entry(group)
+            .or_insert((strukt, &attribute.span));
+        if owner.name != strukt.name {
+            errors.push(Diagnostic::with_info(
+                "Invalid field attribute",
+                &attribute.span,
+                "Group already used by another struct",
+                first_span,
+                "Used here",
+            ));
+            continue;
+        }
+        if field.is_required() {
+            errors.push(
+                Diagnostic::new(
+                    "Invalid field attribute",
+                    &attribute.span,
+                    "Fields of an exclusive group can't be mandatory",
+                )
+                .help("Make the field Optional or give it a default"),
+            );
+        }
+
+        metadata
+            .option_groups
+            .entry(group.to_string())
+            .or_default()
+            .push(index);
+    }
+
+    for field in &strukt.fields {
+        let Some(attribute) = field
+            .attributes
+            .iter()
+            .find(|attribute| attribute.ty == AttributeType::RequiresGroup)
+        else {
+            continue;
+        };
+        let group = attribute.value.as_deref().unwrap();
+
+        let is_own_group = strukt
+            .fields
+            .iter()
+            .any(|other| other.exclusive_group() == Some(group));
+        if !group.is_empty() && !is_own_group {
+            errors.push(
+                Diagnostic::new(
+                    "Invalid field attribute",
+                    &attribute.span,
+                    "Undefined group",
+                )
+                .help("Groups are defined by the exclusive_group of the fields of the same struct"),
+            );
+        }
+    }
+
+    for field in &strukt.fields {
+        let FieldType::Struct(name) = field.ty.value_type() else {
+            continue;
+        };
+        let Some(inner_struct) = metadata.identifier_to_struct.get(name.as_str()) else {
+            continue;
+        };
+        if !field.has_attribute(AttributeType::Flatten) {
+            continue;
+        }
+
+        if let Some(grouped) = inner_struct
+            .fields
+            .iter()
+            .flat_map(|inner_field| &inner_field.attributes)
+            .find(|attribute| {
+                matches!(
+                    attribute.ty,
+                    AttributeType::ExclusiveGroup | AttributeType::RequiresGroup
+                )
+            })
+        {
+            errors.push(Diagnostic::with_info(
+                "Semantic error",
+                &field.type_span,
+                "Flatten of a type with option groups",
+                &grouped.span,
+                "Group given here",
+            ));
+        }
+    }
+}
+
+/// Runs every semantic check over the whole spec, collecting all the errors found instead of
+/// stopping at the first one.
+pub(crate) fn check_semantics<'s>(spec: &'s Spec) -> Result<SpecMetadata<'s>, Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+
+    let identifier_to_struct = check_for_multiple_struct_definitions(&spec.structs, &mut errors);
+    let mut spec_metadata = SpecMetadata::default();
+    spec_metadata.identifier_to_struct = identifier_to_struct;
+
+    let conflict_severity = spec
+        .structs
+        .iter()
+        .find(|strukt| strukt.has_attribute(AttributeType::Main))
+        .map_or(Severity::Error, Struct::long_short_conflict);
+    // Warnings are collected along with the errors, but don't keep the spec from being used.
+    let has_errors = |errors: &[Diagnostic]| {
+        errors
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    };
+
+    let mut group_owners = HashMap::new();
+    for strukt in &spec.structs {
+        check_for_undefined_types(&spec_metadata, &strukt.fields, &mut errors);
+        check_for_multiple_field_definitions(&strukt.fields, &mut errors);
+        check_struct_attributes(strukt, &mut errors);
+        check_clap_compat(strukt, &mut errors);
+        check_option_syntax_consistency(&spec_metadata, strukt, &mut errors);
+        check_doc_hidden_usage(&spec_metadata, strukt, &mut errors);
+        check_repeated_flatten_usage(&spec_metadata, strukt, &mut errors);
+        check_field_attributes(strukt, conflict_severity, &mut errors);
+        check_positional_indices(strukt, &mut errors);
+        check_parse_orders(strukt, &mut errors);
+        check_flattened_positional_fields(&spec_metadata, strukt, &mut errors);
+        check_flatten_cycles(&spec_metadata, strukt, &mut errors);
+        check_option_groups(&mut spec_metadata, strukt, &mut group_owners, &mut errors);
+    }
+
+    // Looking at the flattened fields requires every type to be defined, and no struct to flatten
+    // itself.
+    if !has_errors(&errors) {
+        for strukt in &spec.structs {
+            let struct_metadata = collect_struct_metadata(&spec_metadata, strukt);
+            spec_metadata
+                .structs
+                .insert(strukt.name.as_str(), struct_metadata);
+        }
+        for strukt in &spec.structs {
+            check_case_insensitive_collisions(&spec_metadata, strukt, &mut errors);
+        }
+    }
+
+    if has_errors(&errors) {
+        return Err(errors);
+    }
+
+    spec_metadata.warnings = errors;
+    Ok(spec_metadata)
+}
+
+/// The C and Go backends lack a few features of the C++ one. Specs using them are rejected up
+/// front instead of silently generating code that behaves differently. Regex attributes are only
+/// reported when `check_regex` is set.
+fn check_backend_support(
+    spec: &Spec,
+    language: &str,
+    check_regex: bool,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    let label = format!("Unsupported by the {language} backend");
+
+    for strukt in &spec.structs {
+        for attribute in &strukt.attributes {
+            if attribute.ty == AttributeType::AllowAbbrev {
+                errors.push(Diagnostic::new(
+                    &label,
+                    &attribute.span,
+                    &format!("Abbreviated options are not supported in {language}"),
+                ));
+            } else if attribute.ty == AttributeType::ConfigFile {
+                errors.push(Diagnostic::new(
+                    &label,
+                    &attribute.span,
+                    &format!("Config files are not supported in {language}"),
+                ));
+            } else if attribute.ty == AttributeType::EnvPrefix {
+                errors.push(Diagnostic::new(
+                    &label,
+                    &attribute.span,
+                    &format!("Environment values are not supported in {language}"),
+                ));
+            } else if matches!(
+                attribute.ty,
+                AttributeType::ExitCode
+                    | AttributeType::ParseErrorExit
+                    | AttributeType::MissingArgExit
+                    | AttributeType::UnknownFlagExit
+            ) {
+                errors.push(
+                    Diagnostic::new(
+                        &label,
+                        &attribute.span,
+                        &format!("Exit codes are not supported in {language}"),
+                    )
+                    .help("The parser returns the error instead of exiting"),
+                );
+            }
+        }
+
+        for field in &strukt.fields {
+            let nested = match &field.ty {
+                FieldType::Vec(inner) | FieldType::Optional(inner) => {
+                    matches!(inner.as_ref(), FieldType::Vec(_) | FieldType::Optional(_))
+                }
+                _ => false,
+            };
+            if nested {
+                errors.push(Diagnostic::new(
+                    &label,
+                    &field.type_span,
+                    &format!("Nested Vec and Optional types are not supported in {language}"),
+                ));
+            }
+
+            for attribute in &field.attributes {
+                match attribute.ty {
+                    AttributeType::Regex if check_regex => {
+                        errors.push(
+                            Diagnostic::new(
+                                &label,
+                                &attribute.span,
+                                &format!("Regex validation is not supported in {language}"),
+                            )
+                            .help("Pass --no-regex-validation to generate the field without it"),
+                        );
+                    }
+                    AttributeType::ParseFn => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Custom parse functions are not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::FromStr => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Parsing types from a string is not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::DefaultValueT => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Default values are not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::Index => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Positional arguments are not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::AppendEnv | AttributeType::Env | AttributeType::EnvList => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Environment values are not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::PrefixFlag => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Attached option values are not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::ExclusiveGroup | AttributeType::RequiresGroup => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Option groups are not supported in {language}"),
+                        ));
+                    }
+                    AttributeType::Flatten if matches!(field.ty, FieldType::Vec(_)) => {
+                        errors.push(Diagnostic::new(
+                            &label,
+                            &attribute.span,
+                            &format!("Flattening a Vec is not supported in {language}"),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+/// C structs hold the structs of their fields by value, `Optional` ones included, so a struct
+/// can't contain itself, directly or through the structs it contains.
+pub(crate) fn check_c_support(
+    spec: &Spec,
+    spec_metadata: &SpecMetadata,
+    no_regex_validation: bool,
+) -> Result<(), Vec<Diagnostic>> {
+    fn contains<'s>(
+        metadata: &SpecMetadata<'s>,
+        from: &'s Struct,
+        to: &Struct,
+        visiting: &mut Vec<&'s str>,
+    ) -> bool {
+        if visiting.contains(&from.name.as_str()) {
+            return false;
+        }
+        visiting.push(&from.name);
+
+        from.fields
+            .iter()
+            .filter_map(|field| held_struct(metadata, field))
+            .any(|inner| std::ptr::eq(inner, to) || contains(metadata, inner, to, visiting))
+    }
+
+    /// The struct a field holds by value, rather than in the heap allocated array of a `Vec`.
+    fn held_struct<'s>(metadata: &SpecMetadata<'s>, field: &Field) -> Option<&'s Struct> {
+        match &field.ty {
+            FieldType::Struct(name) => metadata.identifier_to_struct.get(name.as_str()).copied(),
+            FieldType::Optional(inner) => match inner.as_ref() {
+                FieldType::Struct(name) => {
+                    metadata.identifier_to_struct.get(name.as_str()).copied()
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    let mut errors = check_backend_support(spec, "C", !no_regex_validation)
+        .err()
+        .unwrap_or_default();
+    for strukt in &spec.structs {
+        for field in &strukt.fields {
+            let Some(inner) = held_struct(spec_metadata, field) else {
+                continue;
+            };
+            if std::ptr::eq(inner, strukt)
+                || contains(spec_metadata, inner, strukt, &mut Vec::new())
+            {
+                errors.push(
+                    Diagnostic::new(
+                        "Unsupported by the C backend",
+                        &field.type_span,
+                        "Structs containing themselves are not supported in C",
+                    )
+                    .help("Make the field a Vec, whose values are allocated separately"),
+                );
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+/// `--validate-directory` checks the directories with `std::filesystem`, which is only there from
+/// C++17.
+pub(crate) fn check_cpp_support(
+    spec: &Spec,
+    cpp_std: CppStandard,
+    validate_directory: bool,
+) -> Result<(), Vec<Diagnostic>> {
+    if !validate_directory || cpp_std.has_filesystem() {
+        return Ok(());
+    }
+
+    let errors = spec
+        .structs
+        .iter()
+        .flat_map(|strukt| &strukt.fields)
+        .flat_map(|field| &field.attributes)
+        .filter(|attribute| attribute.ty == AttributeType::Directory)
+        .map(|attribute| {
+            Diagnostic::new(
+                "Unsupported by the C++ backend",
+                &attribute.span,
+                "Directories are only validated from C++17, which has std::filesystem",
+            )
+            .help("Generate C++17 or later with --cpp-std, or leave out --validate-directory")
+        })
+        .collect::<Vec<_>>();
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(())
+}
+
+/// Go validates regex attributes with the `regexp` package, whose RE2 syntax covers the usual
+/// patterns but not backreferences or lookarounds.
+pub(crate) fn check_go_support(spec: &Spec) -> Result<(), Vec<Diagnostic>> {
+    check_backend_support(spec, "Go", false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+
+    /// The errors of the spec in `source` rendered against it, none when the spec is valid.
+    fn errors(source: &str) -> Vec<String> {
+        let spec = Parser::new(source).parse().unwrap();
+        check_semantics(&spec)
+            .err()
+            .unwrap_or_default()
+            .iter()
+            .map(|diagnostic| diagnostic.render(source))
+            .collect()
+    }
+
+    #[test]
+    fn attribute_collisions_are_reported_in_field_order() {
+        let source = "
+            #[main]
+            struct Cli {
+                #[short]
+                value: u32,
+                #[short]
+                verbose: bool,
+                #[short]
+                vex: bool,
+            }
+        ";
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let errors = check_semantics(&spec)
+            .unwrap_err()
+            .iter()
+            .map(|diagnostic| diagnostic.render(source))
+            .collect::<Vec<_>>();
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("verbose") && !errors[0].contains("vex"));
+        assert!(errors[1].contains("vex"));
+    }
+
+    #[test]
+    fn long_prefix_rejects_values_and_short_options_next_to_slashes() {
+        assert!(
+            errors(r#"#[main, long_prefix = "/"] struct Cli { #[long] port: u16 }"#).is_empty()
+        );
+        assert!(
+            errors(r#"#[main, long_prefix = "x-"] struct Cli { #[long] port: u16 }"#)[0]
+                .contains("Invalid long prefix")
+        );
+        assert!(
+            errors(r#"#[main, long_prefix = "%"] struct Cli { #[long] port: u16 }"#)[0]
+                .contains("Invalid long prefix")
+        );
+        assert!(
+            errors(r#"#[main, long_prefix = "--"] struct Cli { #[long] port: u16 }"#).is_empty()
+        );
+        assert!(
+            errors(r#"#[main, long_prefix = "/"] struct Cli { #[short, long] port: u16 }"#)[0]
+                .contains("Short option with a `/` long prefix")
+        );
+        assert!(errors(
+            r#"#[main, prefix = "/", long_prefix = "/"] struct Cli { #[short, long] port: u16 }"#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn config_file_names_a_string_option() {
+        assert!(errors(
+            r#"#[main, config_file = "config"] struct Cli { #[long] config: Optional<string> }"#
+        )
+        .is_empty());
+        for source in [
+            r#"#[main, config_file = "missing"] struct Cli { #[long] config: string }"#,
+            r#"#[main, config_file = "config"] struct Cli { #[long] config: u32 }"#,
+            r#"#[main, config_file = "config"] struct Cli { #[trailing] config: Vec<string> }"#,
+        ] {
+            assert!(errors(source)[0].contains("Config file should name a string field"));
+        }
+    }
+
+    #[test]
+    fn env_gives_a_single_value() {
+        assert!(errors(
+            r#"#[main] struct Cli { #[long, env = "PORT", env_no_override] port: Optional<u16> }"#
+        )
+        .is_empty());
+        for source in [
+            r#"#[main] struct Cli { #[long, env = "PATHS"] paths: Vec<string> }"#,
+            r#"#[main] struct Cli { #[long, env = ""] port: u16 }"#,
+            r#"#[main] struct Cli { #[long, env_no_override] port: u16 }"#,
+        ] {
+            assert!(errors(source)[0].contains("Env"));
+        }
+    }
+
+    #[test]
+    fn env_case_is_one_of_the_allowed_cases_of_a_prefix() {
+        for env_case in ["upper", "lower", "original"] {
+            let source = format!(
+                r#"#[main, env_prefix = "APP_", env_case = "{env_case}"] struct Cli {{ #[long, env_no_override] port: u16 }}"#
+            );
+            assert!(errors(&source).is_empty());
+        }
+        assert!(errors(
+            r#"#[main, env_prefix = "APP_", env_case = "camel"] struct Cli { #[long] port: u16 }"#
+        )[0]
+        .contains("Invalid env case"));
+        assert!(
+            errors(r#"#[main, env_case = "lower"] struct Cli { #[long] port: u16 }"#)[0]
+                .contains("Env case without env prefix")
+        );
+        assert!(
+            errors(r#"#[main, env_prefix = "APP-"] struct Cli { #[long] port: u16 }"#)[0]
+                .contains("Invalid env prefix")
+        );
+    }
+
+    #[test]
+    fn struct_metadata_resolves_the_flattened_structs() {
+        let source = r#"#[main] struct Cli {
+            #[short, long] name: string,
+            #[flatten] net: Net,
+            #[flatten] proxy: Optional<Proxy>,
+        }
+        #[case_insensitive] struct Net { #[long, alias = addr] host: string, #[flatten] tls: Tls }
+        struct Tls { #[long, explicit_bool] verify: bool }
+        struct Proxy { #[long] url: string }"#;
+        let spec = Parser::new(source).parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        let cli = metadata.struct_metadata(&spec.structs[0]);
+
+        let names = |fields: &[&Field]| {
+            fields
+                .iter()
+                .map(|field| field.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&cli.fields),
+            ["name", "net", "proxy", "host", "tls", "verify", "url"]
+        );
+        // The fields of the optional group are only required once it's given.
+        assert_eq!(names(&cli.mandatory_fields), ["name", "host", "verify"]);
+
+        let options = cli
+            .options
+            .iter()
+            .map(|option| {
+                (
+                    option.name.as_str(),
+                    option.field.name.as_str(),
+                    option.case_insensitive,
+                    option.value,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            options,
+            [
+                ("-n", "name", false, ValueKind::Value),
+                ("--name", "name", false, ValueKind::Value),
+                ("--host", "host", true, ValueKind::Value),
+                ("--addr", "host", true, ValueKind::Value),
+                ("--verify", "verify", false, ValueKind::ExplicitBool),
+                ("--url", "url", false, ValueKind::Value),
+            ]
+        );
+    }
+
+    #[test]
+    fn structs_flattening_themselves_are_rejected() {
+        let source = "#[main] struct Cli { #[long] name: string, #[flatten] net: Net }
+            struct Net { #[long] host: string, #[flatten] cli: Optional<Cli> }
+            struct Other { #[flatten] other: Other }";
+        let spec = Parser::new(source).parse().unwrap();
+        let errors = check_semantics(&spec).unwrap_err();
+
+        assert_eq!(errors.len(), 3);
+        assert!(errors
+            .iter()
+            .all(|error| error.message == "Flatten of a type that flattens this struct"));
+    }
+
+    #[test]
+    fn long_short_conflicts_are_warnings_when_asked() {
+        let source = |conflict: &str| {
+            format!(
+                "#[main{conflict}] struct Cli {{ #[short, long] port: u16, #[short, long = port] path: string }}"
+            )
+        };
+
+        let spec_source = source("");
+        let spec = Parser::new(&spec_source).parse().unwrap();
+        let errors = check_semantics(&spec).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|error| error.severity == Severity::Error));
+
+        let spec_source = source(r#", long_short_conflict = "warn""#);
+        let spec = Parser::new(&spec_source).parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        assert_eq!(metadata.warnings.len(), 2);
+        assert!(metadata.warnings[0]
+            .render(&spec_source)
+            .starts_with("warning: Invalid field attribute usage"));
+        // The last field takes the options given to both.
+        let options = &metadata.struct_metadata(&spec.structs[0]).options;
+        assert!(options.iter().all(|option| option.field.name == "path"));
+
+        for (source, message) in [
+            (
+                r#"#[main, long_short_conflict = "ignore"] struct Cli { #[long] port: u16 }"#,
+                "Invalid long short conflict",
+            ),
+            (
+                r#"#[main] struct Cli { #[flatten] net: Net }
+                #[long_short_conflict = "warn"] struct Net { #[long] port: u16 }"#,
+                "Long short conflict outside of the main struct",
+            ),
+        ] {
+            let spec = Parser::new(source).parse().unwrap();
+            assert_eq!(check_semantics(&spec).unwrap_err()[0].message, message);
+        }
+    }
+
+    #[test]
+    fn parse_orders_are_unique_within_a_struct() {
+        assert!(errors(
+            "#[main] struct Cli { #[long, parse_order = 1] port: u16, #[long, parse_order = 2] host: string, #[flatten] net: Net }
+             struct Net { #[long, parse_order = 1] timeout: u32 }"
+        )
+        .is_empty());
+        let duplicate = errors(
+            "#[main] struct Cli { #[long, parse_order = 1] port: u16, #[long, parse_order = 1] host: string }",
+        );
+        assert_eq!(duplicate.len(), 1);
+        assert!(duplicate[0].contains("There's already a field with the same parse order"));
+        assert!(
+            errors("#[main] struct Cli { #[index = 0, parse_order = 1] file: string }")[0]
+                .contains("Parse order should be used with a field given by an option")
+        );
+    }
+
+    #[test]
+    fn file_hints_are_given_to_string_fields() {
+        assert!(errors(
+            "#[main] struct Cli { #[long, input_file] input: Vec<string>, #[long, output_file] output: Optional<string> }"
+        )
+        .is_empty());
+        assert!(matches!(
+            errors("#[main] struct Cli { #[long, input_file] port: u16 }").as_slice(),
+            [error] if error.contains("Input file should be used with a string field")
+        ));
+        assert!(matches!(
+            errors(
+                "#[main] struct Cli { #[long, input_file, output_file] path: string }"
+            )
+            .as_slice(),
+            [error] if error.contains("Conflicts with input_file")
+        ));
+        assert!(errors("#[main] struct Cli { #[long, directory] dirs: Vec<string> }").is_empty());
+        assert!(matches!(
+            errors("#[main] struct Cli { #[long, directory] depth: u32 }").as_slice(),
+            [error] if error.contains("Directory should be used with a string field")
+        ));
+        assert!(matches!(
+            errors(
+                "#[main] struct Cli { #[long, output_file, directory] path: string }"
+            )
+            .as_slice(),
+            [error] if error.contains("Conflicts with output_file")
+        ));
+    }
+
+    #[test]
+    fn global_flags_are_bools_of_the_main_struct() {
+        assert!(
+            errors("#[main] struct Cli { #[short, long, global_flag] verbose: bool }").is_empty()
+        );
+        assert!(matches!(
+            errors("#[main] struct Cli { #[long, global_flag] dir: string }").as_slice(),
+            [error] if error.contains("Global flag should be used with a bool field")
+        ));
+        assert!(matches!(
+            errors(
+                "struct Common { #[long, global_flag] verbose: bool }
+                 #[main] struct Cli { #[flatten] common: Common }"
+            )
+            .as_slice(),
+            [error] if error.contains("Global flag outside of the main struct")
+        ));
+    }
+
+    #[test]
+    fn display_orders_are_given_to_options() {
+        assert!(errors(
+            "#[main] struct Cli { #[long, display_order = 1] port: u16, #[long, display_order = 1] host: string }"
+        )
+        .is_empty());
+        assert!(matches!(
+            errors(
+                "#[main] struct Cli { #[index = 0, display_order = 1] file: string }"
+            )
+            .as_slice(),
+            [error]
+                if error.contains("Display order should be used with a field given by an option")
+        ));
+        assert!(matches!(
+            errors(
+                "#[main] struct Cli { #[long, display_order = 99999999999999999999999] port: u16 }"
+            )
+            .as_slice(),
+            [error] if error.contains("Invalid display order")
+        ));
+    }
+
+    #[test]
+    fn clap_compat_structs_spell_options_like_clap() {
+        assert!(errors(
+            r#"#[main, clap_compat] struct Cli { #[short, long] dry_run: bool, #[long = out_dir] output: string, #[trailing] files: Vec<string> }"#
+        )
+        .is_empty());
+        for (source, error) in [
+            (
+                r#"#[main, clap_compat, prefix = "/"] struct Cli { #[long] port: u16 }"#,
+                "Option syntax of a clap compat struct changed",
+            ),
+            (
+                r#"#[main, clap_compat] struct Cli { #[long] port: u16, host: string }"#,
+                "Field without an option in a clap compat struct",
+            ),
+        ] {
+            assert!(errors(source)[0].contains(error));
+        }
+    }
+
+    #[test]
+    fn fields_are_removed_after_being_deprecated() {
+        assert!(errors(
+            r#"#[main] struct Cli { #[long, deprecated_in = "2.9", removed_in = "2.10"] port: u16 }"#
+        )
+        .is_empty());
+        for (source, error) in [
+            (
+                r#"#[main] struct Cli { #[long, removed_in = "3.0"] port: u16 }"#,
+                "Removed in should be used along with deprecated or deprecated_in",
+            ),
+            (
+                r#"#[main] struct Cli { #[deprecated_in = "2.0"] port: u16 }"#,
+                "Only fields given by an option can be deprecated",
+            ),
+        ] {
+            assert!(errors(source)[0].contains(error));
+        }
+
+        let source = r#"#[main] struct Cli { #[long, deprecated_in = "3.0", removed_in = "2.0"] port: u16 }"#;
+        let spec = Parser::new(source).parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        assert_eq!(metadata.warnings.len(), 1);
+        let warning = metadata.warnings[0].render(source);
+        assert!(warning.starts_with("warning: Invalid field attribute"));
+        assert!(warning.contains("Field removed before being deprecated"));
+    }
+
+    #[test]
+    fn env_list_is_given_once_to_a_string_vec() {
+        assert!(
+            errors(r#"#[main] struct Cli { #[long, env_list = "ITEMS"] items: Vec<string> }"#)
+                .is_empty()
+        );
+        for (source, error) in [
+            (
+                r#"#[main] struct Cli { #[long, env_list = "ITEMS"] items: Vec<u32> }"#,
+                "Env list should be used with a Vec<string> field",
+            ),
+            (
+                r#"#[main] struct Cli { #[long, env_list = "A", env_list = "B"] items: Vec<string> }"#,
+                "Env list given more than once",
+            ),
+        ] {
+            assert!(errors(source)[0].contains(error));
+        }
+    }
+
+    #[test]
+    fn option_groups_are_made_of_optional_fields_of_a_struct() {
+        let source = r#"#[main] struct Cli {
+            #[long, exclusive_group = "input"] file: Optional<string>,
+            #[long, exclusive_group = "input"] url: Optional<string>,
+            #[long, default, requires_group = "input"] verbose: bool,
+        }"#;
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+        assert_eq!(metadata.option_groups["input"], vec![0, 1]);
+
+        for (source, error) in [
+            (
+                r#"#[main] struct Cli { #[long, exclusive_group = "input"] file: string }"#,
+                "Fields of an exclusive group can't be mandatory",
+            ),
+            (
+                r#"#[main] struct Cli { #[long, requires_group = "output"] file: Optional<string> }"#,
+                "Undefined group",
+            ),
+            (
+                r#"#[main] struct Cli { #[exclusive_group = "input"] file: Optional<string> }"#,
+                "Exclusive group should be used with a field given by an option",
+            ),
+            (
+                r#"#[main] struct Cli { #[long, exclusive_group = "a"] a: Optional<u32> }
+                struct Net { #[long, exclusive_group = "a"] b: Optional<u32> }"#,
+                "Group already used by another struct",
+            ),
+            (
+                r#"#[main] struct Cli { #[flatten] net: Net }
+                struct Net { #[long, exclusive_group = "a"] b: Optional<u32> }"#,
+                "Flatten of a type with option groups",
+            ),
+        ] {
+            assert!(errors(source)[0].contains(error), "{source}");
+        }
+    }
+
+    #[test]
+    fn long_names_given_to_two_fields_collide() {
+        // A name used to be reported only when it was already both a long name and an alias.
+        for (source, error) in [
+            (
+                "#[main] struct Cli { #[long = port] a: u16, #[long = port] b: u16 }",
+                "There's already a field with the same long name or alias",
+            ),
+            (
+                "#[main] struct Cli { #[long, alias = port] other: u16, #[long] port: u16 }",
+                "There's already a field with the same long name or alias",
+            ),
+            (
+                "#[main] struct Cli { #[long] port: u16, #[long, alias = port] other: u16 }",
+                "There's already a field with the same alias or long name",
+            ),
+        ] {
+            assert!(errors(source)[0].contains(error), "{source}");
+        }
+    }
+
+    #[test]
+    fn prefixed_long_names_collide_with_other_long_names() {
+        assert!(errors(
+            r#"#[main] struct Cli { #[long, prefix = "my-"] file: string, #[long] my: string }"#
+        )
+        .is_empty());
+
+        for (source, error) in [
+            (
+                r#"#[main] struct Cli { #[long, prefix = "my_"] file: string, #[long] my_file: string }"#,
+                "There's already a field with the same long name or alias",
+            ),
+            (
+                r#"#[main] struct Cli { #[long, alias = my_file] path: string, #[long, prefix = "my-"] file: string }"#,
+                "There's already a field with the same long name or alias",
+            ),
+            (
+                r#"#[main] struct Cli { #[short, prefix = "my-"] file: string }"#,
+                "Prefix should be used with a field given by a long option",
+            ),
+            (
+                r#"#[main] struct Cli { #[long, prefix = "--"] file: string }"#,
+                "Invalid prefix of a long name",
+            ),
+        ] {
+            assert!(errors(source)[0].contains(error), "{source}");
+        }
+    }
+
+    #[test]
+    fn short_and_long_is_a_letter_and_a_long_name() {
+        assert!(
+            errors(r#"#[main] struct Cli { #[short_and_long = "v/verbose"] verbose: bool }"#)
+                .is_empty()
+        );
+        assert!(errors(
+            r#"#[main] struct Cli { #[short_and_long = "v/verbose"] a: bool, #[short] v: bool }"#
+        )[0]
+        .contains("There's already a field with the same starting character"));
+
+        for value in [
+            "verbose",
+            "v/",
+            "/verbose",
+            "vv/verbose",
+            "v/-verbose",
+            "v/ver bose",
+        ] {
+            let source =
+                format!(r#"#[main] struct Cli {{ #[short_and_long = "{value}"] a: bool }}"#);
+            assert!(
+                errors(&source)[0]
+                    .contains("Short and long should be a letter and a long name separated by '/'"),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_str_parses_a_custom_type_without_flattening_it() {
+        assert!(errors(
+            "#[main] struct Cli { #[long, from_str] origin: Optional<Point> } struct Point { x: i32 }"
+        )
+        .is_empty());
+        assert!(
+            errors("#[main] struct Cli { #[long, from_str] origin: string }")[0]
+                .contains("From str should be used with a custom type")
+        );
+        assert!(errors(
+            "#[main] struct Cli { #[flatten, from_str] origin: Point } struct Point { #[long] x: i32 }"
+        )[0]
+        .contains("Conflicts with flatten"));
+    }
+
+    #[test]
+    fn exit_codes_are_errors_a_process_can_exit_with() {
+        assert!(errors(
+            "#[main, exit_code = 2, missing_arg_exit = 255] struct Cli { #[long] port: u16 }"
+        )
+        .is_empty());
+        for attribute in ["exit_code = 0", "unknown_flag_exit = 256"] {
+            let source = format!("#[main, {attribute}] struct Cli {{ #[long] port: u16 }}");
+            assert!(
+                errors(&source)[0].contains("Invalid exit code"),
+                "{attribute}"
+            );
+        }
+        assert!(
+            errors("#[main] struct Cli { #[long, exit_code = 2] port: u16 }")[0]
+                .contains("Invalid field attribute")
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_pointed_at_rather_than_their_attribute() {
+        let source = r#"#[main, subcommand, env_case = "title"]
+            struct Cli {
+                #[long, alias = port]
+                port: u16,
+                #[long, max = 70000]
+                limit: u16,
+            }
+        "#;
+        let spec = Parser::new(source).parse().unwrap();
+        let errors = check_semantics(&spec).unwrap_err();
+        let spans = errors
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.message.as_str(),
+                    &source[diagnostic.span.clone()],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            [
+                ("Invalid env case", "\"title\""),
+                (
+                    "Invalid attribute combination",
+                    r#"#[main, subcommand, env_case = "title"]"#
+                ),
+                (
+                    "There's already a field with the same alias or long name",
+                    "port"
+                ),
+                ("Value is not representable by the field type", "70000"),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_values_are_values_of_the_field_type() {
+        assert!(errors(
+            r#"#[main] struct Cli {
+                #[long, default_value_t = "8080", max = 9000] port: u16,
+                #[long, default_value_t = "0.5"] ratio: f64,
+                #[long, default_value_t = "localhost"] host: string,
+            }"#
+        )
+        .is_empty());
+        for (field, error) in [
+            (
+                "#[long, default_value_t = \"-1\"] port: u16",
+                "Value is not representable",
+            ),
+            (
+                "#[long, default_value_t = \"0.5\"] port: u16",
+                "Value is not representable",
+            ),
+            (
+                "#[long, default_value_t = \"true\"] verbose: bool",
+                "numeric or string field",
+            ),
+            (
+                "#[long, default_value_t = \"a\"] names: Vec<string>",
+                "numeric or string field",
+            ),
+            (
+                "#[long, default_value_t = \"1\", min = 2] jobs: u32",
+                "out of the range",
+            ),
+            (
+                "#[long, default, default_value_t = \"1\"] jobs: u32",
+                "Conflicts with default",
+            ),
+        ] {
+            let source = format!("#[main] struct Cli {{ {field} }}");
+            assert!(errors(&source)[0].contains(error), "{field}");
+        }
+    }
+
+    #[test]
+    fn positional_indices_go_from_zero_without_gaps() {
+        assert!(errors(
+            r#"#[main] struct Cli {
+                #[index = 1] destination: string,
+                #[index = 0] source: string,
+                #[index = 2] copies: Optional<u32>,
+                #[long] verbose: bool,
+            }"#
+        )
+        .is_empty());
+        for (fields, error) in [
+            (
+                "#[index = 0] source: string, #[index = 0] destination: string",
+                "same index",
+            ),
+            (
+                "#[index = 0] source: string, #[index = 2] destination: string",
+                "from 0 to N-1",
+            ),
+            ("#[index = -1] source: string", "Invalid index"),
+            (
+                "#[index = 0] source: Optional<string>, #[index = 1] destination: string",
+                "Mandatory positional field after an optional one",
+            ),
+            (
+                "#[index = 0] sources: Vec<string>",
+                "string or numeric field",
+            ),
+            ("#[index = 0] verbose: bool", "string or numeric field"),
+            ("#[long, index = 0] source: string", "Conflicts with long"),
+        ] {
+            let source = format!("#[main] struct Cli {{ {fields} }}");
+            assert!(errors(&source)[0].contains(error), "{fields}");
+        }
+
+        let flattened = errors(
+            "struct Paths { #[index = 0] source: string }
+             #[main] struct Cli { #[flatten] paths: Paths }",
+        );
+        assert!(flattened[0].contains("Flatten of a type with positional fields"));
+    }
 }
@@ -0,0 +1,135 @@
+use crate::diagnostic::{Position, SourceSpan};
+use logos::Span;
+
+/// The lines of a source, found once so that spans are turned into lines and columns without
+/// scanning the source again. Lines end with `\n`, and a `\r` before it is left out of the span of
+/// the line like `str::lines` does.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceMap<'s> {
+    source: &'s str,
+    /// The byte offset every line starts at, the first one being 0. A source ending with a newline
+    /// has an empty line starting at its end.
+    line_starts: Vec<usize>,
+}
+
+impl<'s> SourceMap<'s> {
+    pub fn new(source: &'s str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(newline, _)| newline + 1))
+            .collect();
+
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The source the lines are found in.
+    pub fn source(&self) -> &'s str {
+        self.source
+    }
+
+    /// The number of lines, counted like `str::lines` does: the empty line after a final newline
+    /// doesn't count, but an empty source has a single empty line.
+    pub fn line_count(&self) -> usize {
+        let after_final_newline = usize::from(self.source.ends_with('\n'));
+        (self.line_starts.len() - after_final_newline).max(1)
+    }
+
+    /// The line of the byte `offset`, counted from 1.
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    /// The byte offset the line of the byte `offset` starts at.
+    pub fn line_start(&self, offset: usize) -> usize {
+        self.line_starts[self.line(offset) - 1]
+    }
+
+    /// The span of `line`, counted from 1, without its line ending.
+    pub fn line_span(&self, line: usize) -> Span {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next_start| next_start - 1);
+        let end = if self.source[start..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+
+        Span::from(start..end)
+    }
+
+    /// The position of the byte `offset`. Columns count characters rather than bytes, so that a
+    /// multi-byte character takes a single column.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.line(offset);
+
+        Position {
+            line,
+            column: self.source[self.line_starts[line - 1]..offset]
+                .chars()
+                .count()
+                + 1,
+        }
+    }
+
+    /// The positions `span` starts and ends at.
+    pub fn source_span(&self, span: &Span) -> SourceSpan {
+        SourceSpan {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_found_with_and_without_a_final_newline() {
+        let source_map = SourceMap::new("struct Cli {\n}\n");
+        assert_eq!(source_map.line_count(), 2);
+        assert_eq!(source_map.line_span(1), Span::from(0..12));
+        assert_eq!(source_map.line_span(2), Span::from(13..14));
+        assert_eq!(source_map.line(12), 1);
+        assert_eq!(source_map.line(13), 2);
+
+        let source_map = SourceMap::new("struct Cli {\n}");
+        assert_eq!(source_map.line_count(), 2);
+        assert_eq!(source_map.line_span(2), Span::from(13..14));
+        assert_eq!(source_map.position(14), Position { line: 2, column: 2 });
+
+        assert_eq!(SourceMap::new("").line_count(), 1);
+    }
+
+    #[test]
+    fn carriage_returns_are_left_out_of_the_lines() {
+        let source = "#[main]\r\nstruct Cli {\r\n}\r\n";
+        let source_map = SourceMap::new(source);
+
+        assert_eq!(source_map.line_count(), 3);
+        assert_eq!(&source[source_map.line_span(1)], "#[main]");
+        assert_eq!(&source[source_map.line_span(2)], "struct Cli {");
+        assert_eq!(&source[source_map.line_span(3)], "}");
+        assert_eq!(source_map.line_start(source.find('}').unwrap()), 23);
+    }
+
+    #[test]
+    fn columns_count_characters_rather_than_bytes() {
+        let source = "// ü😀\nx: u32";
+        let source_map = SourceMap::new(source);
+
+        let x = source.find('x').unwrap();
+        assert_eq!(source_map.position(x), Position { line: 2, column: 1 });
+        let emoji = source.find('😀').unwrap();
+        assert_eq!(source_map.position(emoji), Position { line: 1, column: 5 });
+        assert_eq!(
+            source_map.position(emoji + '😀'.len_utf8()),
+            Position { line: 1, column: 6 }
+        );
+    }
+}
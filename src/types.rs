@@ -1,17 +1,81 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::source_map::SourceMap;
 use logos::Span;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
-pub(crate) enum AttributeType {
+/// The kind of an attribute, e.g. `long` in `#[long = port]`. Spelled in the spec as returned by
+/// `to_literal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AttributeType {
     Short,
     Long,
     Alias,
     Flatten,
     Main,
+    #[serde(rename = "subcommand")]
     SubCommand,
+    ExplicitBool,
+    TakesValue,
+    AllowAbbrev,
+    Trailing,
+    Separator,
+    Prefix,
+    ValueSeparator,
+    Secret,
+    DocHidden,
+    MinOccurs,
+    MaxOccurs,
+    Min,
+    Max,
+    Regex,
+    CaseInsensitive,
+    Default,
+    Doc,
+    About,
+    AfterHelp,
+    LongPrefix,
+    WordWrap,
+    Deprecated,
+    HiddenFromCompletion,
+    ParseFn,
+    AppendEnv,
+    PrefixFlag,
+    ConfigFile,
+    Env,
+    EnvNoOverride,
+    ClapCompat,
+    DeprecatedIn,
+    RemovedIn,
+    EnvList,
+    ExclusiveGroup,
+    RequiresGroup,
+    NextLineHelp,
+    ShortAndLong,
+    FromStr,
+    DefaultValueT,
+    ExitCode,
+    ParseErrorExit,
+    MissingArgExit,
+    UnknownFlagExit,
+    Index,
+    EnvPrefix,
+    EnvCase,
+    ParseOrder,
+    DisplayOrder,
+    InputFile,
+    OutputFile,
+    Directory,
+    GlobalFlag,
+    LongShortConflict,
 }
 
 impl AttributeType {
+    /// The name of the attribute in the spec, e.g. `default_value_t`.
     pub const fn to_literal(&self) -> &'static str {
         match self {
             AttributeType::Short => "short",
@@ -20,32 +84,212 @@ impl AttributeType {
             AttributeType::Flatten => "flatten",
             AttributeType::Main => "main",
             AttributeType::SubCommand => "subcommand",
+            AttributeType::ExplicitBool => "explicit_bool",
+            AttributeType::TakesValue => "takes_value",
+            AttributeType::AllowAbbrev => "allow_abbrev",
+            AttributeType::Trailing => "trailing",
+            AttributeType::Separator => "separator",
+            AttributeType::Prefix => "prefix",
+            AttributeType::ValueSeparator => "value_separator",
+            AttributeType::Secret => "secret",
+            AttributeType::DocHidden => "doc_hidden",
+            AttributeType::MinOccurs => "min_occurs",
+            AttributeType::MaxOccurs => "max_occurs",
+            AttributeType::Min => "min",
+            AttributeType::Max => "max",
+            AttributeType::Regex => "regex",
+            AttributeType::CaseInsensitive => "case_insensitive",
+            AttributeType::Default => "default",
+            AttributeType::Doc => "doc",
+            AttributeType::About => "about",
+            AttributeType::AfterHelp => "after_help",
+            AttributeType::LongPrefix => "long_prefix",
+            AttributeType::WordWrap => "word_wrap",
+            AttributeType::Deprecated => "deprecated",
+            AttributeType::HiddenFromCompletion => "hidden_from_completion",
+            AttributeType::ParseFn => "parse_fn",
+            AttributeType::AppendEnv => "append_env",
+            AttributeType::PrefixFlag => "prefix_flag",
+            AttributeType::ConfigFile => "config_file",
+            AttributeType::Env => "env",
+            AttributeType::EnvNoOverride => "env_no_override",
+            AttributeType::ClapCompat => "clap_compat",
+            AttributeType::DeprecatedIn => "deprecated_in",
+            AttributeType::RemovedIn => "removed_in",
+            AttributeType::EnvList => "env_list",
+            AttributeType::ExclusiveGroup => "exclusive_group",
+            AttributeType::RequiresGroup => "requires_group",
+            AttributeType::NextLineHelp => "next_line_help",
+            AttributeType::ShortAndLong => "short_and_long",
+            AttributeType::FromStr => "from_str",
+            AttributeType::DefaultValueT => "default_value_t",
+            AttributeType::ExitCode => "exit_code",
+            AttributeType::ParseErrorExit => "parse_error_exit",
+            AttributeType::MissingArgExit => "missing_arg_exit",
+            AttributeType::UnknownFlagExit => "unknown_flag_exit",
+            AttributeType::Index => "index",
+            AttributeType::EnvPrefix => "env_prefix",
+            AttributeType::EnvCase => "env_case",
+            AttributeType::ParseOrder => "parse_order",
+            AttributeType::DisplayOrder => "display_order",
+            AttributeType::InputFile => "input_file",
+            AttributeType::OutputFile => "output_file",
+            AttributeType::Directory => "directory",
+            AttributeType::GlobalFlag => "global_flag",
+            AttributeType::LongShortConflict => "long_short_conflict",
         }
     }
 
+    /// The attributes that can be put on a struct.
     pub const fn allowed_struct_attribute_types() -> &'static [AttributeType] {
-        &[AttributeType::Main, AttributeType::SubCommand]
+        &[
+            AttributeType::Main,
+            AttributeType::SubCommand,
+            AttributeType::AllowAbbrev,
+            AttributeType::Separator,
+            AttributeType::Prefix,
+            AttributeType::ValueSeparator,
+            AttributeType::DocHidden,
+            AttributeType::CaseInsensitive,
+            AttributeType::About,
+            AttributeType::AfterHelp,
+            AttributeType::LongPrefix,
+            AttributeType::WordWrap,
+            AttributeType::ConfigFile,
+            AttributeType::ClapCompat,
+            AttributeType::NextLineHelp,
+            AttributeType::ExitCode,
+            AttributeType::ParseErrorExit,
+            AttributeType::MissingArgExit,
+            AttributeType::UnknownFlagExit,
+            AttributeType::EnvPrefix,
+            AttributeType::EnvCase,
+            AttributeType::LongShortConflict,
+        ]
     }
 
+    /// The attributes that can be put on a field.
     pub const fn allowed_field_attribute_types() -> &'static [AttributeType] {
         &[
             AttributeType::Short,
             AttributeType::Long,
             AttributeType::Alias,
             AttributeType::Flatten,
+            AttributeType::ExplicitBool,
+            AttributeType::TakesValue,
+            AttributeType::Trailing,
+            AttributeType::Secret,
+            AttributeType::MinOccurs,
+            AttributeType::MaxOccurs,
+            AttributeType::Min,
+            AttributeType::Max,
+            AttributeType::Regex,
+            AttributeType::CaseInsensitive,
+            AttributeType::Default,
+            AttributeType::Doc,
+            AttributeType::Deprecated,
+            AttributeType::HiddenFromCompletion,
+            AttributeType::ParseFn,
+            AttributeType::AppendEnv,
+            AttributeType::PrefixFlag,
+            AttributeType::Env,
+            AttributeType::EnvNoOverride,
+            AttributeType::DeprecatedIn,
+            AttributeType::RemovedIn,
+            AttributeType::EnvList,
+            AttributeType::ExclusiveGroup,
+            AttributeType::RequiresGroup,
+            AttributeType::Prefix,
+            AttributeType::NextLineHelp,
+            AttributeType::ShortAndLong,
+            AttributeType::FromStr,
+            AttributeType::DefaultValueT,
+            AttributeType::Index,
+            AttributeType::ParseOrder,
+            AttributeType::DisplayOrder,
+            AttributeType::InputFile,
+            AttributeType::OutputFile,
+            AttributeType::Directory,
+            AttributeType::GlobalFlag,
         ]
     }
 }
 
-#[derive(Debug)]
-pub(crate) struct Attribute {
+/// An attribute of a struct or a field, e.g. `long = port`. As JSON, it is `{"type": "long",
+/// "value": "port"}`, without its spans.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Attribute {
+    #[serde(rename = "type")]
     pub ty: AttributeType,
+    /// The value after the `=`, without the quotes of string literals and with their escape
+    /// sequences kept.
+    #[serde(default)]
     pub value: Option<String>,
+    /// Where the name of the attribute is in the spec.
+    #[serde(skip)]
     pub span: Span,
+    /// Where the value after the `=` is in the spec, quotes included.
+    #[serde(skip)]
+    pub value_span: Option<Span>,
+}
+
+impl Attribute {
+    /// The byte offset in the spec where the name of the attribute starts.
+    pub fn start_byte(&self) -> usize {
+        self.span.start
+    }
+
+    /// The byte offset in the spec right after the name of the attribute.
+    pub fn end_byte(&self) -> usize {
+        self.span.end
+    }
+
+    /// Where the value of the attribute is in the spec, or its name when it has none, e.g. a
+    /// `long` named after its field. What's wrong with a value is reported there.
+    pub fn value_or_name_span(&self) -> &Span {
+        self.value_span.as_ref().unwrap_or(&self.span)
+    }
+}
+
+/// Spelled like in the spec, e.g. `long = verbose` or `doc = "Print more output"`, with string
+/// values written as they are in the spec, escape sequences included.
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.ty.to_literal();
+        let Some(value) = &self.value else {
+            return write!(f, "{name}");
+        };
+
+        match self.ty {
+            AttributeType::Short
+            | AttributeType::Long
+            | AttributeType::Alias
+            | AttributeType::TakesValue
+            | AttributeType::MinOccurs
+            | AttributeType::MaxOccurs
+            | AttributeType::WordWrap
+            | AttributeType::ExitCode
+            | AttributeType::ParseErrorExit
+            | AttributeType::MissingArgExit
+            | AttributeType::UnknownFlagExit
+            | AttributeType::Index
+            | AttributeType::ParseOrder
+            | AttributeType::DisplayOrder
+            | AttributeType::Min
+            | AttributeType::Max => write!(f, "{name} = {value}"),
+            _ => write!(f, "{name} = \"{value}\""),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub(crate) enum FieldType {
+/// The type of a field, e.g. `Vec<u32>`. `Struct` is a custom type, named after one of the structs
+/// of the spec. `Error` stands for a type that couldn't be parsed, which only specs parsed with
+/// errors have.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FieldType {
     String,
     I16,
     U16,
@@ -59,18 +303,114 @@ pub(crate) enum FieldType {
     Vec(Box<FieldType>),
     Optional(Box<FieldType>),
     Struct(String),
+    #[serde(skip)]
+    Error,
+}
+
+impl FieldType {
+    /// The type of a single value of the field, looking through `Vec` and `Optional`.
+    pub fn value_type(&self) -> &FieldType {
+        match self {
+            FieldType::Vec(inner) | FieldType::Optional(inner) => inner.value_type(),
+            _ => self,
+        }
+    }
+
+    /// The smallest and largest values of a numeric type.
+    pub fn bounds(&self) -> Option<(NumericBound, NumericBound)> {
+        use NumericBound::{Float, Integer};
+
+        match self {
+            FieldType::I16 => Some((Integer(i16::MIN.into()), Integer(i16::MAX.into()))),
+            FieldType::U16 => Some((Integer(u16::MIN.into()), Integer(u16::MAX.into()))),
+            FieldType::I32 => Some((Integer(i32::MIN.into()), Integer(i32::MAX.into()))),
+            FieldType::U32 => Some((Integer(u32::MIN.into()), Integer(u32::MAX.into()))),
+            FieldType::I64 => Some((Integer(i64::MIN.into()), Integer(i64::MAX.into()))),
+            FieldType::U64 => Some((Integer(u64::MIN.into()), Integer(u64::MAX.into()))),
+            FieldType::F32 => Some((Float(f32::MIN.into()), Float(f32::MAX.into()))),
+            FieldType::F64 => Some((Float(f64::MIN), Float(f64::MAX))),
+            _ => None,
+        }
+    }
+
+    /// Parses a `min`/`max` literal in the representation of a numeric type. Float literals are
+    /// only accepted by float types.
+    pub fn parse_bound(&self, value: &str) -> Option<NumericBound> {
+        match self {
+            FieldType::F32 | FieldType::F64 => value
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(NumericBound::Float),
+            _ => value.parse::<i128>().ok().map(NumericBound::Integer),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub(crate) struct Field {
+/// Spelled like in the spec, e.g. `Vec<Optional<u32>>`.
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::String => write!(f, "string"),
+            FieldType::I16 => write!(f, "i16"),
+            FieldType::U16 => write!(f, "u16"),
+            FieldType::I32 => write!(f, "i32"),
+            FieldType::U32 => write!(f, "u32"),
+            FieldType::I64 => write!(f, "i64"),
+            FieldType::U64 => write!(f, "u64"),
+            FieldType::F32 => write!(f, "f32"),
+            FieldType::F64 => write!(f, "f64"),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::Vec(inner) => write!(f, "Vec<{inner}>"),
+            FieldType::Optional(inner) => write!(f, "Optional<{inner}>"),
+            FieldType::Struct(name) => write!(f, "{name}"),
+            FieldType::Error => write!(f, "<error>"),
+        }
+    }
+}
+
+/// A `min`/`max` value of a numeric field. Integers are kept exact so that the bounds of the 64
+/// bit types can be checked.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum NumericBound {
+    Integer(i128),
+    Float(f64),
+}
+
+/// A field of a struct, along with its attributes.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Field {
     pub name: String,
+    #[serde(default)]
     pub attributes: Vec<Attribute>,
+    #[serde(rename = "type")]
     pub ty: FieldType,
-    pub name_span: Span,
-    pub type_span: Span,
+    #[serde(skip)]
+    pub(crate) name_span: Span,
+    #[serde(skip)]
+    pub(crate) type_span: Span,
+    #[serde(skip)]
+    pub(crate) span: Span,
 }
 
 impl Field {
+    /// Where the whole field is in the spec, from its attributes to the comma after its type.
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// Where the name of the field is in the spec.
+    pub fn name_span(&self) -> Span {
+        self.name_span.clone()
+    }
+
+    /// Where the innermost type of the field is in the spec, e.g. `u32` in `Vec<u32>`.
+    pub fn type_span(&self) -> Span {
+        self.type_span.clone()
+    }
+
+    /// The letter of the short option of the field, e.g. `p` for `-p`.
     pub fn short_value(&self) -> Option<String> {
         self.attributes
             .iter()
@@ -79,62 +419,782 @@ impl Field {
             .map(|value| value.replace('_', "-"))
     }
 
+    /// The long name of the field, after its `prefix` if it has one, e.g. `my-file` for
+    /// `#[long, prefix = "my_"] file: String`.
     pub fn long_value(&self) -> Option<String> {
         self.attributes
             .iter()
             .find_map(|attr| matches!(attr.ty, AttributeType::Long).then(|| attr.value.as_ref()))
             .flatten()
-            .map(|value| value.replace('_', "-"))
+            .map(|value| {
+                let prefix = self
+                    .attribute_value(AttributeType::Prefix)
+                    .unwrap_or_default();
+                format!("{prefix}{value}").replace('_', "-")
+            })
+    }
+
+    /// Whether the field has an attribute of kind `ty`.
+    pub fn has_attribute(&self, ty: AttributeType) -> bool {
+        self.attributes.iter().any(|attr| attr.ty == ty)
+    }
+
+    /// The value of the first attribute of kind `ty`, if it has one.
+    pub fn attribute_value(&self, ty: AttributeType) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.ty == ty)
+            .and_then(|attr| attr.value.as_deref())
+    }
+
+    /// Whether the field has to be given for its struct to be parsed. `Optional`, `default` and
+    /// `default_value_t` fields can be left out, trailing fields and fields with `min_occurs` are
+    /// checked on their own, and flattened fields are never given themselves, only the fields of
+    /// the struct they flatten. A field read from an `env` variable is still required, the variable
+    /// giving it like its option does.
+    pub fn is_required(&self) -> bool {
+        !matches!(self.ty, FieldType::Optional(_))
+            && !self.has_attribute(AttributeType::Default)
+            && self.default_value().is_none()
+            && !self.has_attribute(AttributeType::Trailing)
+            && !self.has_attribute(AttributeType::MinOccurs)
+            && !self.has_attribute(AttributeType::Flatten)
+    }
+
+    /// The description of the field shown in the help.
+    pub fn doc(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::Doc)
+    }
+
+    /// Whether the completion scripts leave the field out. It is still parsed and shown in the help.
+    pub fn is_hidden_from_completion(&self) -> bool {
+        self.has_attribute(AttributeType::HiddenFromCompletion)
+    }
+
+    /// Whether the value of the field is an existing file to read, which the completion scripts
+    /// complete.
+    pub fn is_input_file(&self) -> bool {
+        self.has_attribute(AttributeType::InputFile)
+    }
+
+    /// Whether the value of the field is a file to write, which the completion scripts don't
+    /// complete so as not to suggest overwriting an existing one.
+    pub fn is_output_file(&self) -> bool {
+        self.has_attribute(AttributeType::OutputFile)
+    }
+
+    /// Whether the value of the field is the path of an existing directory, which the completion
+    /// scripts complete and the C++ checks with `--validate-directory`.
+    pub fn is_directory(&self) -> bool {
+        self.has_attribute(AttributeType::Directory)
+    }
+
+    /// Whether the field is a flag of the main struct taken before the other arguments, wherever
+    /// it is given, e.g. `--no-pager` in `git --no-pager log`.
+    pub fn is_global_flag(&self) -> bool {
+        self.has_attribute(AttributeType::GlobalFlag)
+    }
+
+    /// The user provided function the values of the field are converted with instead of the
+    /// built-in parsing, e.g. `parse_ipv4`.
+    pub fn parse_fn(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::ParseFn)
+    }
+
+    /// The environment variable giving the field its value when the option isn't given, e.g.
+    /// `PORT`.
+    pub fn env(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::Env)
+    }
+
+    /// The environment variable whose separated values are put before the ones given in the
+    /// arguments, e.g. `MY_PATHS`.
+    pub fn append_env(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::AppendEnv)
+    }
+
+    /// The environment variable whose lines are put before the values given in the arguments,
+    /// like `append_env` but separated by newlines.
+    pub fn env_list(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::EnvList)
+    }
+
+    /// The group of options of which at most one can be given, e.g. `input`.
+    pub fn exclusive_group(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::ExclusiveGroup)
+    }
+
+    /// The group of options of which one has to be given along with this field, if any.
+    pub fn requires_group(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::RequiresGroup)
+    }
+
+    /// Whether the value can also be attached to the option, e.g. `--port=8080`.
+    pub fn is_prefix_flag(&self) -> bool {
+        self.has_attribute(AttributeType::PrefixFlag)
+    }
+
+    /// Whether the custom type of the field is parsed from a single value by its `from_string`
+    /// function rather than from its own options.
+    pub fn is_from_str(&self) -> bool {
+        self.has_attribute(AttributeType::FromStr)
+    }
+
+    /// The value the field has when it isn't given, as it is spelled in C++, e.g. `8080`. The
+    /// generated struct keeps it in a constant, see `default_constant_name`.
+    pub fn default_value(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::DefaultValueT)
+    }
+
+    /// The name of the constant holding `default_value` in the generated struct, e.g.
+    /// `PORT_DEFAULT` for `port`.
+    pub fn default_constant_name(&self) -> String {
+        format!("{}_DEFAULT", self.name.to_uppercase())
+    }
+
+    /// The position of the field among the positional arguments of its struct, which are given
+    /// without an option, e.g. `0` for the first one.
+    pub fn positional_index(&self) -> Option<usize> {
+        self.attribute_value(AttributeType::Index)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Where the option of the field is matched among the options of its struct, smaller ones
+    /// being matched first. Fields without a parse order are matched after the ones with one.
+    pub fn parse_order(&self) -> Option<usize> {
+        self.attribute_value(AttributeType::ParseOrder)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Where the option of the field is listed in the help, smaller ones being listed first.
+    /// Fields without a display order are listed after the ones with one, in the order they are
+    /// declared.
+    pub fn display_order(&self) -> Option<usize> {
+        self.attribute_value(AttributeType::DisplayOrder)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Whether a warning is printed when the field is given, see `deprecation_message`.
+    pub fn is_deprecated(&self) -> bool {
+        self.has_attribute(AttributeType::Deprecated)
+            || self.has_attribute(AttributeType::DeprecatedIn)
+    }
+
+    /// The version the field was deprecated in, e.g. `2.0`.
+    pub fn deprecated_in(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::DeprecatedIn)
+    }
+
+    /// The version the field will be removed in, added to the deprecation warning.
+    pub fn removed_in(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::RemovedIn)
+    }
+
+    /// The message appended to the deprecation warning, e.g. `use --new-flag instead`.
+    pub fn deprecation_message(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::Deprecated)
+    }
+
+    /// The minimum number of values a `Vec` field must be given, if constrained.
+    pub fn min_occurs(&self) -> Option<usize> {
+        self.attribute_value(AttributeType::MinOccurs)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// The maximum number of values a `Vec` field can be given, if constrained.
+    pub fn max_occurs(&self) -> Option<usize> {
+        self.attribute_value(AttributeType::MaxOccurs)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Whether the value of a bool field must be given explicitly (`--flag true`), either through
+    /// `explicit_bool` or `takes_value = true`.
+    pub fn is_explicit_bool(&self) -> bool {
+        self.attributes.iter().any(|attr| match attr.ty {
+            AttributeType::ExplicitBool => true,
+            AttributeType::TakesValue => attr.value.as_deref() == Some("true"),
+            _ => false,
+        })
+    }
+}
+
+/// How the options of a struct are spelled on the command line, e.g. `-o`/`--output=value` by
+/// default or `/o`/`/output:value` for DOS style options.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionSyntax<'s> {
+    pub short_prefix: &'s str,
+    pub long_prefix: &'s str,
+    pub value_separator: &'s str,
+}
+
+impl OptionSyntax<'_> {
+    /// The values `prefix` can take.
+    pub const ALLOWED_PREFIXES: &'static [&'static str] = &["-", "/", "+"];
+    /// The values `long_prefix` can take, the prefixes along with the default `--`.
+    pub const ALLOWED_LONG_PREFIXES: &'static [&'static str] = &["-", "--", "/", "+"];
+    /// The values `value_separator` can take.
+    pub const ALLOWED_VALUE_SEPARATORS: &'static [&'static str] = &["=", ":"];
+
+    /// The short option of the letter `value`, e.g. `-p`.
+    pub fn short_option(&self, value: &str) -> String {
+        format!("{}{value}", self.short_prefix)
+    }
+
+    /// The long option of the name `value`, e.g. `--port`.
+    pub fn long_option(&self, value: &str) -> String {
+        format!("{}{value}", self.long_prefix)
     }
 }
 
-#[derive(Debug)]
-pub(crate) struct Struct {
+/// A struct of the spec, parsed from the arguments by the generated code.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Struct {
+    #[serde(default)]
     pub attributes: Vec<Attribute>,
+    #[serde(default)]
     pub fields: Vec<Field>,
     pub name: String,
+    #[serde(skip)]
     pub name_span: Span,
+    /// Where the `#[...]` before the struct is in the spec, if it has attributes.
+    #[serde(skip)]
+    pub attributes_span: Option<Span>,
+    /// Where the body of the struct is in the spec, from its `{` to its `}`.
+    #[serde(skip)]
+    pub body_span: Span,
 }
 
 impl Struct {
-    pub fn get_fields<'s>(
-        &'s self,
-        spec_metadata: &'s SpecMetadata,
-    ) -> impl Iterator<Item = &'s Field> {
-        let flattened_fields_iter = self
+    /// The values `env_case` can take.
+    pub const ALLOWED_ENV_CASES: &'static [&'static str] = &["upper", "lower", "original"];
+    /// The values `long_short_conflict` can take.
+    pub const ALLOWED_LONG_SHORT_CONFLICTS: &'static [&'static str] = &["warn", "error"];
+
+    /// Where the whole struct is in the spec, from its attributes, or its name when it has none,
+    /// to the `}` closing it.
+    pub fn span(&self) -> Span {
+        let start = self
+            .attributes_span
+            .as_ref()
+            .map_or(self.name_span.start, |span| {
+                span.start.min(self.name_span.start)
+            });
+
+        start..self.body_span.end
+    }
+
+    /// Whether the struct has an attribute of kind `ty`.
+    pub fn has_attribute(&self, ty: AttributeType) -> bool {
+        self.attributes.iter().any(|attr| attr.ty == ty)
+    }
+
+    /// Doc hidden structs are internal components that only exist to be flattened into others.
+    pub fn is_doc_hidden(&self) -> bool {
+        self.has_attribute(AttributeType::DocHidden)
+    }
+
+    /// The value of the first attribute of kind `ty`, if it has one.
+    pub fn attribute_value(&self, ty: AttributeType) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.ty == ty)
+            .and_then(|attr| attr.value.as_deref())
+    }
+
+    /// The description of the program shown at the top of the help.
+    pub fn about(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::About)
+    }
+
+    /// Extra text printed after the options in the help, e.g. examples.
+    pub fn after_help(&self) -> Option<&str> {
+        self.attribute_value(AttributeType::AfterHelp)
+    }
+
+    /// The width the help text is wrapped at, if any.
+    pub fn word_wrap(&self) -> Option<usize> {
+        self.attribute_value(AttributeType::WordWrap)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// The code `parse` exits with on the errors of `ty`, falling back to `exit_code` and then 1.
+    fn exit_code_of(&self, ty: AttributeType) -> u8 {
+        self.attribute_value(ty)
+            .or(self.attribute_value(AttributeType::ExitCode))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1)
+    }
+
+    /// The exit code of invalid values, e.g. a number out of range or a failed regex match.
+    pub fn parse_error_exit_code(&self) -> u8 {
+        self.exit_code_of(AttributeType::ParseErrorExit)
+    }
+
+    /// The exit code of options given without their value and of mandatory fields left out.
+    pub fn missing_arg_exit_code(&self) -> u8 {
+        self.exit_code_of(AttributeType::MissingArgExit)
+    }
+
+    /// The exit code of unknown and ambiguous options.
+    pub fn unknown_flag_exit_code(&self) -> u8 {
+        self.exit_code_of(AttributeType::UnknownFlagExit)
+    }
+
+    /// How options given to two fields of a struct are reported, which `long_short_conflict` on
+    /// the main struct sets for the whole spec. With a warning, the last of the fields takes the
+    /// option.
+    pub fn long_short_conflict(&self) -> Severity {
+        match self.attribute_value(AttributeType::LongShortConflict) {
+            Some("warn") => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// The literal that separates the options from the trailing arguments.
+    pub fn separator(&self) -> &str {
+        self.attribute_value(AttributeType::Separator)
+            .unwrap_or("--")
+    }
+
+    /// The environment variable giving `field`, one of the fields of the struct, its value when
+    /// its option isn't given. That's the one named by its `env` attribute, or with `env_prefix`
+    /// the prefix followed by the name of the field in the case of `env_case`, upper by default,
+    /// e.g. `APP_MY_FIELD` for `my_field`. Only options holding a single value get a prefixed one.
+    pub fn field_env(&self, field: &Field) -> Option<String> {
+        if let Some(env) = field.env() {
+            return Some(env.to_string());
+        }
+
+        let prefix = self.attribute_value(AttributeType::EnvPrefix)?;
+        let is_option = field.attributes.iter().any(|attribute| {
+            matches!(
+                attribute.ty,
+                AttributeType::Short
+                    | AttributeType::Long
+                    | AttributeType::Alias
+                    | AttributeType::ShortAndLong
+            )
+        });
+        let is_single_value = match &field.ty {
+            FieldType::Vec(_) | FieldType::Struct(_) => false,
+            FieldType::Optional(inner) => !matches!(inner.as_ref(), FieldType::Struct(_)),
+            _ => true,
+        };
+        if !is_option || !is_single_value || field.has_attribute(AttributeType::Trailing) {
+            return None;
+        }
+
+        let name = match self.attribute_value(AttributeType::EnvCase) {
+            Some("lower") => field.name.to_lowercase(),
+            Some("original") => field.name.clone(),
+            _ => field.name.to_uppercase(),
+        };
+        Some(format!("{prefix}{name}"))
+    }
+
+    /// How the options of the struct are spelled, set by its `prefix`, `long_prefix` and
+    /// `value_separator`.
+    pub fn option_syntax(&self) -> OptionSyntax<'_> {
+        let (short_prefix, long_prefix) = match self.attribute_value(AttributeType::Prefix) {
+            None | Some("-") => ("-", "--"),
+            Some(prefix) => (prefix, prefix),
+        };
+        // `long_prefix` only overrides the prefix of the long options, e.g. for `/flag`.
+        let long_prefix = self
+            .attribute_value(AttributeType::LongPrefix)
+            .unwrap_or(long_prefix);
+
+        OptionSyntax {
+            short_prefix,
+            long_prefix,
+            value_separator: self
+                .attribute_value(AttributeType::ValueSeparator)
+                .unwrap_or("="),
+        }
+    }
+
+    /// The fields that have to be given on the command line, see `Field::is_required`.
+    pub fn mandatory_fields(&self) -> Vec<&Field> {
+        self.fields
+            .iter()
+            .filter(|field| field.is_required())
+            .collect()
+    }
+
+    /// All the fields that aren't mandatory, see `mandatory_fields`.
+    pub fn optional_fields(&self) -> Vec<&Field> {
+        self.fields
+            .iter()
+            .filter(|field| !field.is_required())
+            .collect()
+    }
+
+    /// The field taking the arguments after the `separator`, if any.
+    pub fn trailing_field(&self) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.has_attribute(AttributeType::Trailing))
+    }
+
+    /// The positional arguments of the struct in the order they are given, see
+    /// `Field::positional_index`.
+    pub fn positional_fields(&self) -> Vec<&Field> {
+        let mut positional_fields = self
             .fields
             .iter()
-            .filter_map(|field| {
-                field.attributes.iter().find_map(|attr| match attr.ty {
-                    AttributeType::Flatten => match &field.ty {
-                        FieldType::Vec(inner) => match inner.as_ref() {
-                            FieldType::Vec(_) => unreachable!(),
-                            FieldType::Struct(name) => {
-                                Some(&spec_metadata.identifier_to_struct[name.as_str()].fields)
-                            }
-                            _ => None,
-                        },
-                        FieldType::Struct(name) => {
-                            Some(&spec_metadata.identifier_to_struct[name.as_str()].fields)
-                        }
-                        _ => None,
-                    },
-                    _ => None,
-                })
+            .filter(|field| field.positional_index().is_some())
+            .collect::<Vec<_>>();
+        positional_fields.sort_by_key(|field| field.positional_index());
+        positional_fields
+    }
+
+    /// The field holding the path of the config file the other fields are read from, named by
+    /// the `config_file` attribute.
+    pub fn config_file_field(&self) -> Option<&Field> {
+        let name = self.attribute_value(AttributeType::ConfigFile)?;
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// The fields that can be set from the config file along with their key, which is their long
+    /// option or their name without one. Only single values, `Optional` and `Vec` ones are read,
+    /// the trailing field, the positional fields and the config file itself are not.
+    pub fn config_fields(&self) -> Vec<(String, &Field)> {
+        let Some(config_file_field) = self.config_file_field() else {
+            return Vec::new();
+        };
+
+        self.fields
+            .iter()
+            .filter(|field| {
+                let is_single = match &field.ty {
+                    FieldType::Vec(inner) | FieldType::Optional(inner) => {
+                        !matches!(inner.as_ref(), FieldType::Vec(_) | FieldType::Optional(_))
+                    }
+                    _ => true,
+                };
+                is_single
+                    && !matches!(field.ty.value_type(), FieldType::Struct(_))
+                    && !field.has_attribute(AttributeType::Trailing)
+                    && field.positional_index().is_none()
+                    && field.name != config_file_field.name
+            })
+            .map(|field| (field.long_value().unwrap_or(field.name.clone()), field))
+            .collect()
+    }
+
+    /// Whether the long options and aliases of `field`, one of the fields of `get_fields`, are
+    /// matched ignoring case. That's the case when the field, this struct or the struct declaring
+    /// the field is `case_insensitive`. Short options are always case sensitive.
+    pub fn is_case_insensitive(&self, field: &Field, spec_metadata: &SpecMetadata) -> bool {
+        spec_metadata
+            .struct_metadata(self)
+            .options
+            .iter()
+            .any(|option| std::ptr::eq(option.field, field) && option.case_insensitive)
+    }
+
+    /// Whether the description of `field`, one of the fields of `get_fields`, is printed on the
+    /// line below its options in the help. That's the case when the field, this struct or the
+    /// struct declaring the field has `next_line_help`.
+    pub fn is_next_line_help(&self, field: &Field, spec_metadata: &SpecMetadata) -> bool {
+        field.has_attribute(AttributeType::NextLineHelp)
+            || self.has_attribute(AttributeType::NextLineHelp)
+            || spec_metadata.identifier_to_struct.values().any(|strukt| {
+                strukt.has_attribute(AttributeType::NextLineHelp)
+                    && strukt.fields.iter().any(|other| std::ptr::eq(other, field))
             })
-            .flatten();
+    }
 
-        self.fields.iter().chain(flattened_fields_iter)
+    /// The fields of the struct followed by the ones of the structs it flattens, which are parsed
+    /// along with its own, see `StructMetadata::fields`.
+    pub fn get_fields<'s>(
+        &self,
+        spec_metadata: &'s SpecMetadata<'s>,
+    ) -> impl Iterator<Item = &'s Field> {
+        spec_metadata.struct_metadata(self).fields.iter().copied()
     }
 }
 
+/// What follows an option on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueKind {
+    /// Nothing, the option is a bool flag, e.g. `--verbose`.
+    Flag,
+    /// `true` or `false`, see `Field::is_explicit_bool`.
+    ExplicitBool,
+    /// The value of the field, e.g. `--port 8080`.
+    Value,
+}
+
+/// An option accepted by a struct, as it is given on the command line.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct StructOption<'s> {
+    /// Spelled with the prefix of the struct, e.g. `--port` or `/p`.
+    pub name: String,
+    /// `Short`, `Long` or `Alias`.
+    pub ty: AttributeType,
+    /// The field given by the option, which belongs to the struct or one it flattens.
+    pub field: &'s Field,
+    /// Whether the option is matched ignoring case, see `Struct::is_case_insensitive`.
+    pub case_insensitive: bool,
+    pub value: ValueKind,
+}
+
+/// What the semantic checks learn about a struct, found once so that the generators don't walk
+/// its flattened structs again and all see them the same way.
 #[derive(Debug, Default)]
-pub(crate) struct SpecMetadata<'s> {
+#[non_exhaustive]
+pub struct StructMetadata<'s> {
+    /// The fields of the struct followed by the ones of every struct it flattens, which are
+    /// parsed along with its own. The fields of a flattened struct come after its own fields,
+    /// followed by the ones of the structs it flattens in turn.
+    pub fields: Vec<&'s Field>,
+    /// Every option accepted by the struct, in the order of `fields`. An option given to two
+    /// fields of the same struct is only accepted for the last of them.
+    pub options: Vec<StructOption<'s>>,
+    /// The fields of `fields` that have to be given, see `Field::is_required`. The fields of
+    /// structs flattened through an `Optional` or a `Vec` are left out, as they are only required
+    /// once their struct is given.
+    pub mandatory_fields: Vec<&'s Field>,
+}
+
+/// What the semantic checks learn about a valid spec, which the generators need along with it.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct SpecMetadata<'s> {
+    /// The structs of the spec by their name.
     pub identifier_to_struct: HashMap<&'s str, &'s Struct>,
+    /// The indices of the fields in every `exclusive_group`, by the name of the group. The fields
+    /// of a group all belong to the same struct.
+    pub option_groups: HashMap<String, Vec<usize>>,
+    /// What is known about every struct, by its name.
+    pub structs: HashMap<&'s str, StructMetadata<'s>>,
+    /// The problems of the spec that don't keep code from being generated, e.g. an option given
+    /// to two fields with `long_short_conflict = "warn"`.
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl StructMetadata<'_> {
+    /// Whether the option `name` of `field` gives it a value, rather than a later field of its
+    /// struct given the same option.
+    pub fn accepts(&self, field: &Field, name: &str) -> bool {
+        self.options
+            .iter()
+            .any(|option| std::ptr::eq(option.field, field) && option.name == name)
+    }
+}
+
+impl<'s> SpecMetadata<'s> {
+    /// What is known about `strukt`, one of the structs of the spec.
+    pub fn struct_metadata(&self, strukt: &Struct) -> &StructMetadata<'s> {
+        &self.structs[strukt.name.as_str()]
+    }
+
+    /// The field `field_name` of the struct `struct_name`, if the spec has both.
+    pub fn field_by_name(&self, struct_name: &str, field_name: &str) -> Option<&'s Field> {
+        let strukt = self.identifier_to_struct.get(struct_name).copied()?;
+        strukt.fields.iter().find(|field| field.name == field_name)
+    }
 }
 
-#[derive(Debug)]
-pub(crate) struct Spec<'s> {
+/// A parsed spec, borrowing the source it was parsed from, or a spec built with `SpecBuilder`.
+///
+/// As JSON, it is `{"structs": [...], "format_version": 1}`, the structs being like
+/// `{"name": "Cli", "attributes": [...], "fields": [{"name": "port", "type": "u16",
+/// "attributes": [...]}]}` and the types spelled like in `--emit-spec-json`. The spans are left out,
+/// and so is the format version of a spec without a `#!cli-spec` directive.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Spec<'s> {
+    /// The structs in declaration order.
     pub structs: Vec<Struct>,
-    pub source: &'s str,
+    /// The source of the spec, which the spans of its structs, fields and attributes point into.
+    /// A built spec has no source, the spans point into a line describing each of them instead,
+    /// e.g. `struct Args, field output`.
+    #[serde(skip)]
+    pub source: Cow<'s, str>,
+    /// The version of the `#!cli-spec N` directive the spec starts with, if it has one.
+    #[serde(
+        rename = "format_version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) format_directive: Option<u32>,
+    /// Whether the spec was built by `SpecBuilder`, so that its diagnostics describe what they are
+    /// about rather than show its source.
+    #[serde(skip)]
+    pub(crate) is_built: bool,
+}
+
+impl Spec<'_> {
+    /// The latest version of the spec format, bumped whenever a spec that was valid before means
+    /// something else. Specs in a newer format are rejected, and the ones in an older format are
+    /// generated the way they were back then.
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// The version of the format the spec is written in, given by a `#!cli-spec N` directive on
+    /// its first line. Specs without one are in format 1, the format before versions were given,
+    /// so that newer formats don't change what they mean.
+    pub fn format_version(&self) -> u32 {
+        self.format_directive.unwrap_or(1)
+    }
+
+    /// The structs of the spec as a Graphviz digraph, as written by `--emit-dot`: a node per struct
+    /// and an edge per struct typed field, labeled with the field. Fields referring to a struct
+    /// the spec doesn't have are left out, so the spec doesn't need to be checked.
+    pub fn to_dot_graph(&self) -> String {
+        let spec_metadata = SpecMetadata {
+            identifier_to_struct: self
+                .structs
+                .iter()
+                .map(|strukt| (strukt.name.as_str(), strukt))
+                .collect(),
+            ..Default::default()
+        };
+
+        crate::generate::dot::generate_dot(self, &spec_metadata, &Default::default(), None)
+    }
+}
+
+/// The tree of structs, fields and attributes printed by `--dump-ast`, with the span of each as
+/// `line:column-line:column`:
+///
+/// ```text
+/// struct Cli 2:8-2:11
+///   #[main] 1:3-1:7
+///   field jobs 3:13-3:17: Vec<u32> 3:23-3:26
+///     #[long = jobs] 3:7-3:11
+/// ```
+///
+/// Structs, fields and attributes are listed in declaration order, so the output only changes
+/// when the spec does. The span of a type is the one of the innermost type, e.g. `u32`.
+impl fmt::Display for Spec<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source_map = SourceMap::new(&self.source);
+        let span = |span: &Span| source_map.source_span(span);
+
+        for (index, strukt) in self.structs.iter().enumerate() {
+            if index != 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "struct {} {}", strukt.name, span(&strukt.name_span))?;
+            for attribute in &strukt.attributes {
+                writeln!(f, "  #[{attribute}] {}", span(&attribute.span))?;
+            }
+            for field in &strukt.fields {
+                writeln!(
+                    f,
+                    "  field {} {}: {} {}",
+                    field.name,
+                    span(&field.name_span),
+                    field.ty,
+                    span(&field.type_span)
+                )?;
+                for attribute in &field.attributes {
+                    writeln!(f, "    #[{attribute}] {}", span(&attribute.span))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::Parser;
+    use crate::semantic::check_semantics;
+
+    #[test]
+    fn spec_is_displayed_as_a_tree_with_spans() {
+        let source = r#"#[main, about = "A \"tool\""]
+struct Cli {
+    #[long, min_occurs = 1] jobs: Vec<u32>,
+    #[flatten] net: Optional<Net>,
+}
+
+struct Net { #[short] host: string }
+"#;
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+
+        assert_eq!(
+            spec.to_string(),
+            r#"struct Cli 2:8-2:11
+  #[main] 1:3-1:7
+  #[about = "A \"tool\""] 1:9-1:14
+  field jobs 3:29-3:33: Vec<u32> 3:39-3:42
+    #[long = jobs] 3:7-3:11
+    #[min_occurs = 1] 3:13-3:23
+  field net 4:16-4:19: Optional<Net> 4:30-4:33
+    #[flatten] 4:7-4:14
+
+struct Net 7:8-7:11
+  field host 7:23-7:27: string 7:29-7:35
+    #[short = h] 7:16-7:21
+"#
+        );
+    }
+
+    #[test]
+    fn fields_are_required_unless_they_can_be_left_out() {
+        let source = r#"#[main]
+struct Cli {
+    #[long] name: string,
+    #[long] port: u16,
+    #[long] ratio: f64,
+    #[long] verbose: bool,
+    #[long, env = "APP_USER"] user: string,
+    #[index = 0] file: string,
+    #[long] paths: Vec<string>,
+    #[flatten] net: Net,
+    #[long] host: Optional<string>,
+    #[long, default] jobs: u32,
+    #[long, default_value_t = "8"] threads: u32,
+    #[long, min_occurs = 1] inputs: Vec<string>,
+    #[flatten] log: Optional<Net>,
+    #[trailing] rest: Vec<string>,
+}
+
+struct Net { #[long] address: string }
+"#;
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+
+        let required = spec.structs[0]
+            .fields
+            .iter()
+            .filter(|field| field.is_required())
+            .map(|field| field.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            required,
+            ["name", "port", "ratio", "verbose", "user", "file", "paths"]
+        );
+        assert!(spec.structs[1].fields[0].is_required());
+    }
+
+    #[test]
+    fn fields_are_looked_up_by_the_name_of_their_struct() {
+        let source = r#"#[main]
+struct Cli {
+    #[long] port: u16,
+    #[flatten] net: Net,
+}
+
+struct Net { #[long] host: string }
+"#;
+        let mut parser = Parser::new(source);
+        let spec = parser.parse().unwrap();
+        let metadata = check_semantics(&spec).unwrap();
+
+        let host = metadata.field_by_name("Net", "host").unwrap();
+        assert!(std::ptr::eq(host, &spec.structs[1].fields[0]));
+        assert_eq!(metadata.field_by_name("Cli", "port").unwrap().name, "port");
+        assert!(metadata.field_by_name("Cli", "host").is_none());
+        assert!(metadata.field_by_name("Missing", "port").is_none());
+    }
 }
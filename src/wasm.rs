@@ -0,0 +1,14 @@
+use crate::{check, generate, parse_spec, Diagnostics, Error, GenOptions, Target};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub fn generate_cpp_from_spec(source: &str) -> Result<String, JsValue> {
+    let render = |diagnostics: &Diagnostics| JsValue::from_str(&diagnostics.render(source));
+
+    let spec = parse_spec(source).map_err(|diagnostics| render(&diagnostics))?;
+    let metadata = check(&spec).map_err(|diagnostics| render(&diagnostics))?;
+    let mut files = generate(&spec, &metadata, Target::Cpp, &GenOptions::default())
+        .map_err(|Error::Unsupported(diagnostics)| render(&diagnostics))?;
+
+    Ok(files.remove(0).contents)
+}
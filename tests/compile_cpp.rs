@@ -0,0 +1,412 @@
+//! Checks that the generated C++ and C compile without any warnings and that the tests generated
+//! along with the C++ pass. This needs a compiler for each, so it only runs with
+//! `cargo test --features compile-test`. The compilers are taken from `CXX` and `CC`, falling back
+//! to `c++` and `cc`. `--features compile-test-go` also checks that the generated Go is formatted
+//! and vetted, with the tools taken from `GO` and `GOFMT`, falling back to `go` and `gofmt`.
+#![cfg(feature = "compile-test")]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const COMPREHENSIVE_SPEC: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/specs/comprehensive.cli");
+/// The comprehensive spec without the features the C and Go backends don't support.
+const C99_SPEC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/specs/c99.cli");
+/// Specs exercising what the comprehensive one can't, e.g. structs without mandatory fields.
+const SMALL_SPECS: &[&str] = &[
+    // The `default_value_t` example of the README.
+    concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/specs/default_value_t.cli"
+    ),
+    // Only fields with a `default`, none of them mandatory.
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/specs/defaulted.cli"),
+];
+
+fn generate(name: &str, options: &[&str]) -> PathBuf {
+    generate_from(COMPREHENSIVE_SPEC, name, options)
+}
+
+fn generate_from(spec: &str, name: &str, options: &[&str]) -> PathBuf {
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.h"));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_cli-generator"))
+        .args(["-i", spec, "-o"])
+        .arg(&output)
+        .args(options)
+        .status()
+        .unwrap();
+    assert!(status.success(), "generation with {options:?} failed");
+
+    output
+}
+
+/// Compiles `header` as the C++ standard `std`, e.g. `c++11`.
+fn compile(header: &Path, std: &str) {
+    let compiler = std::env::var("CXX").unwrap_or_else(|_| String::from("c++"));
+
+    let output = Command::new(&compiler)
+        .arg(format!("-std={std}"))
+        .args(["-Wall", "-Wextra", "-Werror", "-fsyntax-only", "-x", "c++"])
+        .arg(header)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {compiler}: {err}"));
+
+    assert!(
+        output.status.success(),
+        "{} doesn't compile cleanly:\n{}",
+        header.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Generates the C++ of `spec`, whose main struct is `Cli`, with `options` and runs a program
+/// parsing `args` with it and printing what it parsed, or the exception thrown while parsing.
+/// With `--with-to-command-line`, the command line rebuilt from what it parsed is printed last.
+/// `prelude` comes before the generated header, e.g. to define the functions the spec refers to.
+fn run(name: &str, spec: &str, prelude: &str, options: &[&str], args: &[&str]) -> Output {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let spec_path = dir.join("cli.cli");
+    std::fs::write(&spec_path, spec).unwrap();
+    let header = generate_from(spec_path.to_str().unwrap(), name, options);
+    let print_command_line = if options.contains(&"--with-to-command-line") {
+        r#"printf("%s\n", cli.to_command_line().c_str());"#
+    } else {
+        ""
+    };
+
+    let program = dir.join("main.cpp");
+    std::fs::write(
+        &program,
+        format!(
+            r#"{prelude}
+#include <exception>
+#include "{}"
+
+int main(int argc, char* argv[]) {{
+    try {{
+        Cli cli = Cli::parse(argc, argv);
+        cli.print_debug();
+        {print_command_line}
+    }} catch (const std::exception& error) {{
+        printf("Parsing failed: %s\n", error.what());
+        return 1;
+    }}
+}}
+"#,
+            header.display()
+        ),
+    )
+    .unwrap();
+    let executable = dir.join("main");
+    let compiler = std::env::var("CXX").unwrap_or_else(|_| String::from("c++"));
+    let output = Command::new(&compiler)
+        .args(["-std=c++17", "-Wall", "-Wextra", "-Werror"])
+        .arg(&program)
+        .arg("-o")
+        .arg(&executable)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {compiler}: {err}"));
+    assert!(
+        output.status.success(),
+        "{} doesn't compile cleanly:\n{}",
+        program.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Command::new(&executable).args(args).output().unwrap()
+}
+
+#[test]
+fn generated_code_compiles_without_warnings() {
+    // Every configuration is compiled as the standard it is generated for.
+    let configurations: &[(&str, &str, &[&str])] = &[
+        ("default", "c++17", &[]),
+        ("accessors", "c++17", &["--accessors"]),
+        ("cpp11", "c++11", &[]),
+        (
+            "cpp11_accessors",
+            "c++11",
+            &["--accessors", "--with-to-command-line"],
+        ),
+        ("cpp14", "c++14", &[]),
+        ("cpp20", "c++20", &[]),
+        ("no_regex_validation", "c++17", &["--no-regex-validation"]),
+        ("validate_directory", "c++17", &["--validate-directory"]),
+        ("noexcept", "c++17", &["--cpp-noexcept"]),
+        ("doxygen", "c++17", &["--emit-doxygen"]),
+        (
+            "to_command_line",
+            "c++17",
+            &["--with-to-command-line", "--accessors", "--emit-doxygen"],
+        ),
+    ];
+
+    for (name, std, options) in configurations {
+        let mut options = options.to_vec();
+        options.extend(["--cpp-std", std]);
+        compile(&generate(name, &options), std);
+    }
+}
+
+#[test]
+fn small_specs_compile_without_warnings() {
+    for spec in SMALL_SPECS {
+        let name = Path::new(spec).file_stem().unwrap().to_str().unwrap();
+        compile(&generate_from(spec, name, &[]), "c++17");
+    }
+}
+
+#[test]
+fn generated_tests_pass() {
+    let tests = Path::new(env!("CARGO_TARGET_TMPDIR")).join("scenarios_tests.cpp");
+    let header = generate("scenarios", &["--emit-tests", tests.to_str().unwrap()]);
+    let executable = tests.with_extension("");
+    let compiler = std::env::var("CXX").unwrap_or_else(|_| String::from("c++"));
+
+    let output = Command::new(&compiler)
+        .args(["-std=c++17", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(header.parent().unwrap())
+        .arg(&tests)
+        .arg("-o")
+        .arg(&executable)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {compiler}: {err}"));
+    assert!(
+        output.status.success(),
+        "{} doesn't compile cleanly:\n{}",
+        tests.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = Command::new(&executable).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}",
+        executable.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn split_implementation_compiles_without_warnings() {
+    let header = generate(
+        "split",
+        &[
+            "--split-implementation",
+            "--accessors",
+            "--with-to-command-line",
+        ],
+    );
+    let implementation = header.with_extension("cpp");
+    let compiler = std::env::var("CXX").unwrap_or_else(|_| String::from("c++"));
+
+    let output = Command::new(&compiler)
+        .args(["-std=c++17", "-Wall", "-Wextra", "-Werror", "-fsyntax-only"])
+        .arg(&implementation)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {compiler}: {err}"));
+
+    assert!(
+        output.status.success(),
+        "{} doesn't compile cleanly:\n{}",
+        implementation.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn generated_c_compiles_without_warnings() {
+    let header = generate_from(C99_SPEC, "c99", &["--target", "c"]);
+    let source = header.with_extension("c");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| String::from("cc"));
+
+    let output = Command::new(&compiler)
+        .args([
+            "-std=c99",
+            "-pedantic",
+            "-Wall",
+            "-Wextra",
+            "-Werror",
+            "-fsyntax-only",
+        ])
+        .arg(&source)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {compiler}: {err}"));
+
+    assert!(
+        output.status.success(),
+        "{} doesn't compile cleanly:\n{}",
+        source.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+#[cfg(feature = "compile-test-go")]
+fn generated_go_is_formatted_and_vetted() {
+    let output = generate_from(C99_SPEC, "go", &["--target", "go", "--go-package", "cli"]);
+    let source = output.with_extension("go");
+    std::fs::rename(&output, &source).unwrap();
+
+    let gofmt = std::env::var("GOFMT").unwrap_or_else(|_| String::from("gofmt"));
+    let formatted = Command::new(&gofmt)
+        .arg(&source)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {gofmt}: {err}"));
+    assert!(formatted.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&formatted.stdout),
+        std::fs::read_to_string(&source).unwrap(),
+        "{} is not formatted like gofmt does",
+        source.display()
+    );
+
+    let go = std::env::var("GO").unwrap_or_else(|_| String::from("go"));
+    let vetted = Command::new(&go)
+        .arg("vet")
+        .arg(&source)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {go}: {err}"));
+    assert!(
+        vetted.status.success(),
+        "{} doesn't vet cleanly:\n{}",
+        source.display(),
+        String::from_utf8_lossy(&vetted.stderr)
+    );
+}
+
+#[test]
+fn the_separator_is_not_taken_for_an_abbreviation() {
+    let spec = "#[main, allow_abbrev]
+struct Cli {
+    #[long]
+    verbose: bool,
+    #[trailing]
+    rest: Vec<string>,
+}
+";
+    let output = run(
+        "abbrev_separator",
+        spec,
+        "",
+        &[],
+        &["--verb", "--", "--verbose"],
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("\t--verbose,"), "{stdout}");
+}
+
+#[test]
+fn a_throwing_parse_fn_is_reported_with_noexcept() {
+    let spec = r#"#[main]
+struct Cli {
+    #[long, parse_fn = "parse_mask"]
+    mask: u32,
+}
+"#;
+    let prelude = r#"#include <cstdint>
+#include <stdexcept>
+
+uint32_t parse_mask(const char*) {
+    throw std::invalid_argument("not a mask");
+}"#;
+    let output = run(
+        "throwing_parse_fn",
+        spec,
+        prelude,
+        &["--cpp-noexcept"],
+        &["--mask", "zz"],
+    );
+
+    // `std::terminate` would abort instead of returning to `main`.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert!(stdout.contains("Parsing failed: not a mask"), "{stdout}");
+}
+
+#[test]
+fn to_command_line_writes_floats_back_exactly() {
+    let spec = "#[main]
+struct Cli {
+    #[long]
+    ratio: f64,
+    #[long]
+    tiny: f32,
+}
+";
+    // Neither is representable, `std::to_string` would write `0.100000` and `0.000000`.
+    let output = run(
+        "float_command_line",
+        spec,
+        "",
+        &["--with-to-command-line"],
+        &["--ratio", "0.1", "--tiny", "1e-7"],
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert_eq!(
+        stdout.lines().last(),
+        Some("--ratio 0.1 --tiny 1e-07"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn floats_are_checked_like_from_chars_before_cpp17() {
+    let spec = "#[main]
+struct Cli {
+    #[long]
+    ratio: f64,
+}
+";
+    let parse = |value: &str| {
+        let output = run(
+            "stream_floats",
+            spec,
+            "",
+            &["--cpp-std", "c++11"],
+            &["--ratio", value],
+        );
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+
+    assert!(parse("0.25").0);
+    let (success, stdout) = parse(" 0.25");
+    assert!(!success);
+    assert!(
+        stdout.contains("is not a valid floating point number"),
+        "{stdout}"
+    );
+    let (success, stdout) = parse("1e400");
+    assert!(!success);
+    assert!(
+        stdout.contains("out of range for floating point type"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn the_value_of_an_option_is_not_taken_for_a_global_flag() {
+    let spec = "#[main]
+struct Cli {
+    #[short, long, default, global_flag]
+    verbose: bool,
+    #[long]
+    name: string,
+}
+";
+    let output = run("global_flag_value", spec, "", &[], &["--name", "-v"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("\tverbose: false\n"), "{stdout}");
+    assert!(stdout.contains("\tname: -v"), "{stdout}");
+}
@@ -0,0 +1,83 @@
+//! Checks that the options of a `cli-generator.toml` next to the spec, or given by `--config`,
+//! are used unless they are given on the command line.
+
+mod support;
+
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use support::{run, temp_dir};
+
+const SPEC: &str = "#[main] struct Cli { #[long] port: u16 }";
+
+/// A fresh directory for a test with a spec and a config file next to it.
+fn spec_dir(name: &str, config: &str) -> PathBuf {
+    temp_dir(
+        name,
+        &[
+            ("specs/cli.cli", SPEC),
+            ("specs/cli-generator.toml", config),
+        ],
+    )
+}
+
+fn generate(dir: &Path, args: &[&str]) -> (Output, String) {
+    let result = run(
+        dir,
+        ["-i", "specs/cli.cli", "-o", "cli.h"].iter().chain(args),
+    );
+    let generated = std::fs::read_to_string(dir.join("cli.h")).unwrap_or_default();
+    (result, generated)
+}
+
+#[test]
+fn the_config_next_to_the_spec_is_used_unless_overridden() {
+    let dir = spec_dir("config_next_to_spec", "indent = 2\nno-timestamp = true\n");
+
+    let (result, generated) = generate(&dir, &[]);
+    assert!(result.status.success());
+    assert!(generated.contains("\n  static Cli parse"), "{generated}");
+
+    let (result, generated) = generate(&dir, &["--indent", "8"]);
+    assert!(result.status.success());
+    assert!(
+        generated.contains("\n        static Cli parse"),
+        "{generated}"
+    );
+}
+
+#[test]
+fn unknown_keys_are_warned_about() {
+    let dir = spec_dir("config_unknown_keys", "namespace = \"app\"\n");
+
+    let (result, _) = generate(&dir, &[]);
+    assert!(result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(
+        stderr.contains("warning: unknown key 'namespace'"),
+        "{stderr}"
+    );
+
+    let (result, _) = generate(&dir, &["--quiet"]);
+    assert_eq!(String::from_utf8(result.stderr).unwrap(), "");
+}
+
+#[test]
+fn the_given_config_replaces_the_one_next_to_the_spec() {
+    let dir = spec_dir("config_given", "indent = 2\n");
+    std::fs::write(dir.join("other.toml"), "indent-tabs = true\n").unwrap();
+
+    let (result, generated) = generate(&dir, &["--config", "other.toml"]);
+    assert!(result.status.success());
+    assert!(generated.contains("\n\tstatic Cli parse"), "{generated}");
+
+    let (result, _) = generate(&dir, &["--config", "missing.toml"]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("cannot read 'missing.toml'"), "{stderr}");
+
+    std::fs::write(dir.join("invalid.toml"), "cpp-std = \"c++98\"\n").unwrap();
+    let (result, _) = generate(&dir, &["--config", "invalid.toml"]);
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("invalid config 'invalid.toml'"), "{stderr}");
+}
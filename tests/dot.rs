@@ -0,0 +1,42 @@
+//! Checks that the struct graph written by `--dot-output` is the one `Spec::to_dot_graph` draws.
+
+mod support;
+
+use cli_generator::parse_spec;
+use support::{run, temp_dir};
+
+const SPEC: &str = "struct Server {
+    #[long]
+    host: string,
+}
+
+#[main]
+struct Cli {
+    #[flatten]
+    servers: Vec<Server>,
+}
+";
+
+#[test]
+fn dot_output_writes_the_graph_of_the_spec() {
+    let dir = temp_dir("dot_output", &[("cli.cli", SPEC)]);
+
+    let result = run(&dir, ["-i", "cli.cli", "--dot-output", "spec.dot"]);
+    assert!(result.status.success());
+
+    let graph = parse_spec(SPEC).unwrap().to_dot_graph();
+    assert!(graph.contains(r#""Cli" -> "Server" [label="servers (Vec)", style=dashed];"#));
+    // The file also starts with the banner telling where it comes from.
+    let written = std::fs::read_to_string(dir.join("spec.dot")).unwrap();
+    assert!(written.ends_with(&graph), "{written}");
+}
+
+#[test]
+fn fields_of_unknown_structs_are_left_out() {
+    let spec = parse_spec("#[main] struct Cli { #[flatten] common: Common }").unwrap();
+
+    assert_eq!(
+        spec.to_dot_graph(),
+        "digraph spec {\n    node [shape=box];\n\n    \"Cli\" [style=bold];\n}\n"
+    );
+}
@@ -0,0 +1,100 @@
+//! Checks that every kind of failure exits with its own exit code, and the lists of the names the
+//! options take.
+
+mod support;
+
+use std::path::PathBuf;
+
+use support::{
+    run, temp_dir, EXIT_IO_ERROR, EXIT_PARSE_ERROR, EXIT_SEMANTIC_ERROR, EXIT_USAGE_ERROR,
+};
+
+/// A fresh directory for a test with `spec` in it.
+fn spec_dir(name: &str, spec: &str) -> PathBuf {
+    temp_dir(name, &[("cli.cli", spec)])
+}
+
+#[test]
+fn errors_of_the_spec_exit_with_their_kind() {
+    let dir = spec_dir("exit_parse_error", "#[main] struct Cli { port u16 }");
+    let result = run(&dir, ["-i", "cli.cli", "-o", "cli.h"]);
+    assert_eq!(result.status.code(), Some(EXIT_PARSE_ERROR));
+
+    let dir = spec_dir(
+        "exit_semantic_error",
+        "#[main] struct Cli { #[long] port: u16, #[long] port: u16 }",
+    );
+    let result = run(&dir, ["-i", "cli.cli", "-o", "cli.h"]);
+    assert_eq!(result.status.code(), Some(EXIT_SEMANTIC_ERROR));
+
+    let dir = spec_dir("exit_io_error", "#[main] struct Cli { #[long] port: u16 }");
+    let result = run(&dir, ["-i", "missing.cli", "-o", "cli.h"]);
+    assert_eq!(result.status.code(), Some(EXIT_IO_ERROR));
+}
+
+#[test]
+fn wrong_arguments_exit_with_the_usage_error() {
+    let dir = spec_dir(
+        "exit_usage_error",
+        "#[main] struct Cli { #[long] port: u16 }",
+    );
+
+    let result = run(&dir, ["-i", "cli.cli", "-o", "cli.h", "--target", "rust"]);
+    assert_eq!(result.status.code(), Some(EXIT_USAGE_ERROR));
+
+    let result = run(&dir, ["-i", "cli.cli", "-o", "a.h", "-o", "b.h"]);
+    assert_eq!(result.status.code(), Some(EXIT_USAGE_ERROR));
+
+    let result = run(&dir, ["--help"]);
+    assert!(result.status.success());
+}
+
+#[test]
+fn specs_read_from_json_exit_like_the_parsed_ones() {
+    let dir = spec_dir(
+        "exit_json_input",
+        "#[main] struct Cli { #[long] port: u16 }",
+    );
+    let result = run(&dir, ["-i", "cli.cli", "--emit-spec-json", "cli.json"]);
+    assert!(result.status.success());
+
+    let result = run(
+        &dir,
+        ["-i", "cli.json", "--input-format", "json", "-o", "cli.h"],
+    );
+    assert!(result.status.success());
+    assert!(std::fs::read_to_string(dir.join("cli.h"))
+        .unwrap()
+        .contains("struct Cli"));
+
+    std::fs::write(dir.join("broken.json"), r#"{"structs": [{"name": 1}]}"#).unwrap();
+    let result = run(
+        &dir,
+        ["-i", "broken.json", "--input-format", "json", "--check"],
+    );
+    assert_eq!(result.status.code(), Some(EXIT_PARSE_ERROR));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("$.structs[0].name"));
+
+    let result = run(
+        &dir,
+        ["-i", "cli.json", "--input-format", "json", "--dump-tokens"],
+    );
+    assert_eq!(result.status.code(), Some(EXIT_USAGE_ERROR));
+}
+
+#[test]
+fn targets_and_emitters_are_listed_one_per_line() {
+    let dir = spec_dir("list_names", "");
+
+    let result = run(&dir, ["--list-targets"]);
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "cpp\nc\ngo\n");
+
+    let result = run(&dir, ["--list-emitters"]);
+    assert!(result.status.success());
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        ["completions", "spec-json", "dot", "tests", "cmake"]
+    );
+}
@@ -0,0 +1,243 @@
+#ifndef _CLI_H_
+#define _CLI_H_
+
+#include <cctype>
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <cstdio>
+#include <cerrno>
+#include <string>
+#include <optional>
+
+inline bool equals_ignore_case(const char* lhs, const char* rhs) {
+    for (; *lhs != '\0' && *rhs != '\0'; ++lhs, ++rhs) {
+        if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {
+            return false;
+        }
+    }
+    return *lhs == *rhs;
+}
+
+inline bool parse_bool_value(const char* value, bool* res) {
+    if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {
+        *res = true;
+        return true;
+    }
+    if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {
+        *res = false;
+        return true;
+    }
+    return false;
+}
+
+struct Logging {
+    bool quiet;
+    std::optional<std::string> log_file;
+
+    void print_debug() {
+        printf("Logging {\n");
+        printf("\tquiet: %s\n", this->quiet ? "true" : "false");
+        if (this->log_file.has_value()) {
+            printf("\tlog_file: %s\n", this->log_file.value().c_str());
+        } else {
+            printf("\tlog_file: none\n");
+        }
+        printf("}\n");
+    }
+
+    static void help() {
+        printf("Usage: Logging [OPTIONS]\n"
+            "\n"
+            "Options:\n"
+            "    -h, --help\n"
+            "    -q, --quiet\n"
+            "    --log-file <LOG_FILE>\n"
+        );
+        exit(0);
+    }
+
+    static bool is_option(char* arg) {
+        static const char* valid_options[] = {
+            "-q",
+            "--quiet",
+            "--log-file",
+        };
+
+        for (size_t i = 0; i != 3; ++i) {
+            if (strcmp(arg, valid_options[i]) == 0) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    static Logging parse (int argc, char *args[]) {
+        const char* mandatory_field_names[] = { "quiet", };
+        bool mandatory_fields_seen[sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0])] = { false };
+
+        Logging res = {};
+        for (int i = 0; i != argc; ++i, ++args) {
+            const char *arg = args[0];
+            if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {
+                Logging::help();
+            } else if (strcmp(arg, "-q") == 0 || strncmp(arg, "-q=", 3) == 0 || strcmp(arg, "--quiet") == 0 || strncmp(arg, "--quiet=", 8) == 0) {
+                bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
+                res.quiet = arg_res;
+                mandatory_fields_seen[0] = true;
+            } else if (strcmp(arg, "--log-file") == 0) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.log_file = arg_res;
+            } else {
+                printf("Unknown option '%s'\n", arg);
+                exit(1);
+            }
+        }
+
+        bool not_seen_any = false;
+        for (size_t i = 0; i != sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0]); ++i) {
+            if (!mandatory_fields_seen[i]) {
+                printf("--%s was required but it was not provided\n", mandatory_field_names[i]);
+                not_seen_any = true;
+            }
+        }
+        if (not_seen_any) {
+            exit(1);
+        }
+        return res;
+    }
+};
+
+struct Cli {
+    std::optional<std::string> url;
+    std::optional<std::string> path;
+    Logging logging;
+
+    void print_debug() {
+        printf("Cli {\n");
+        if (this->url.has_value()) {
+            printf("\turl: %s\n", this->url.value().c_str());
+        } else {
+            printf("\turl: none\n");
+        }
+        if (this->path.has_value()) {
+            printf("\tpath: %s\n", this->path.value().c_str());
+        } else {
+            printf("\tpath: none\n");
+        }
+        this->logging.print_debug();
+        printf("}\n");
+    }
+
+    static void help() {
+        printf("Usage: Cli [OPTIONS]\n"
+            "\n"
+            "Options:\n"
+            "    -h, --help\n"
+            "    --url <URL>\n"
+            "    --path <PATH>\n"
+            "    -q, --quiet\n"
+            "    --log-file <LOG_FILE>\n"
+        );
+        exit(0);
+    }
+
+    static bool is_option(char* arg) {
+        static const char* valid_options[] = {
+            "--url",
+            "--path",
+            "-q",
+            "--quiet",
+            "--log-file",
+        };
+
+        for (size_t i = 0; i != 5; ++i) {
+            if (strcmp(arg, valid_options[i]) == 0) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    static Cli parse (int argc, char *args[]) {
+        --argc;
+        ++args;
+
+        bool fields_given[3] = { false };
+
+        Cli res = {};
+        for (int i = 0; i != argc; ++i, ++args) {
+            const char *arg = args[0];
+            if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {
+                Cli::help();
+            } else if (strcmp(arg, "--url") == 0) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.url = arg_res;
+                fields_given[0] = true;
+            } else if (strcmp(arg, "--path") == 0) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.path = arg_res;
+                fields_given[1] = true;
+            } else if (strcmp(arg, "-q") == 0 || strncmp(arg, "-q=", 3) == 0 || strcmp(arg, "--quiet") == 0 || strncmp(arg, "--quiet=", 8) == 0) {
+                bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
+                res.logging.quiet = arg_res;
+            } else if (strcmp(arg, "--log-file") == 0) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.logging.log_file = arg_res;
+            } else {
+                printf("Unknown option '%s'\n", arg);
+                exit(1);
+            }
+        }
+
+        if (fields_given[0] + fields_given[1] > 1) {
+            printf("Only one of --url and --path can be given\n");
+            exit(1);
+        }
+        return res;
+    }
+};
+
+#endif // _CLI_H_
@@ -0,0 +1,269 @@
+#ifndef _CLI_H_
+#define _CLI_H_
+
+#include <cctype>
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <cstdio>
+#include <cerrno>
+#include <string>
+#include <vector>
+#include <optional>
+
+inline bool equals_ignore_case(const char* lhs, const char* rhs) {
+    for (; *lhs != '\0' && *rhs != '\0'; ++lhs, ++rhs) {
+        if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {
+            return false;
+        }
+    }
+    return *lhs == *rhs;
+}
+
+inline bool starts_with_ignore_case(const char* str, const char* prefix) {
+    for (; *prefix != '\0'; ++str, ++prefix) {
+        if (tolower((unsigned char)*str) != tolower((unsigned char)*prefix)) {
+            return false;
+        }
+    }
+    return true;
+}
+
+inline bool parse_bool_value(const char* value, bool* res) {
+    if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {
+        *res = true;
+        return true;
+    }
+    if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {
+        *res = false;
+        return true;
+    }
+    return false;
+}
+
+struct Cli {
+    bool verbose;
+    bool color;
+    uint32_t jobs;
+    uint16_t retries;
+    std::optional<std::string> output;
+    std::vector<std::string> include;
+    std::optional<std::string> mode;
+
+    void print_debug() {
+        printf("Cli {\n");
+        printf("\tverbose: %s\n", this->verbose ? "true" : "false");
+        printf("\tcolor: %s\n", this->color ? "true" : "false");
+        printf("\tjobs: %u\n", this->jobs);
+        printf("\tretries: %d\n", this->retries);
+        if (this->output.has_value()) {
+            printf("\toutput: %s\n", this->output.value().c_str());
+        } else {
+            printf("\toutput: none\n");
+        }
+        printf("\tinclude: [\n");
+        for (size_t i = 0; i != this->include.size(); ++i) {
+            printf("\t%s,\n", this->include[i].c_str());
+        }
+        printf("\t]\n");
+        if (this->mode.has_value()) {
+            printf("\tmode: %s\n", this->mode.value().c_str());
+        } else {
+            printf("\tmode: none\n");
+        }
+        printf("}\n");
+    }
+
+    static void help() {
+        printf("Copies files around\n"
+            "\n"
+            "Usage: Cli [OPTIONS] --include <INCLUDE>...\n"
+            "\n"
+            "Options:\n"
+            "    -h, --help\n"
+            "    -v, --verbose  Print every file copied\n"
+            "    --color[=true|false]\n"
+            "    -j, --jobs <JOBS>\n"
+            "    --retries <RETRIES>\n"
+            "    --output <OUTPUT>\n"
+            "    --include <INCLUDE>\n"
+            "    --mode <MODE>\n"
+        );
+        exit(0);
+    }
+
+    static bool is_option(char* arg) {
+        static const char* valid_options[] = {
+            "-v",
+            "--verbose",
+            "--color",
+            "-j",
+            "--jobs",
+            "--retries",
+            "--output",
+            "--out",
+            "--include",
+        };
+
+        for (size_t i = 0; i != 9; ++i) {
+            if (strcmp(arg, valid_options[i]) == 0) {
+                return true;
+            }
+        }
+
+        static const char* case_insensitive_options[] = {
+            "--mode",
+        };
+
+        for (size_t i = 0; i != 1; ++i) {
+            if (equals_ignore_case(arg, case_insensitive_options[i])) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    static Cli parse (int argc, char *args[]) {
+        --argc;
+        ++args;
+
+        const char* mandatory_field_names[] = { "verbose", "color", "jobs", };
+        bool mandatory_fields_seen[sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0])] = { false };
+
+        Cli res = {};
+        for (int i = 0; i != argc; ++i, ++args) {
+            const char *arg = args[0];
+            if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {
+                Cli::help();
+            } else if (strcmp(arg, "-v") == 0 || strncmp(arg, "-v=", 3) == 0 || strcmp(arg, "--verbose") == 0 || strncmp(arg, "--verbose=", 10) == 0) {
+                bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
+                res.verbose = arg_res;
+                mandatory_fields_seen[0] = true;
+            } else if (strcmp(arg, "--color") == 0 || strncmp(arg, "--color=", 8) == 0) {
+                bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                } else {
+                    ++args;
+                    ++i;
+                    if (i == argc) {
+                        printf("Expected value for option '%s' but no value was provided", arg);
+                        exit(1);
+                    }
+                    bool_value = args[0];
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
+                res.color = arg_res;
+                mandatory_fields_seen[1] = true;
+            } else if (strcmp(arg, "-j") == 0 || strcmp(arg, "--jobs") == 0) {
+                ++args;
+                ++i;
+                if (i == argc || Cli::is_option(args[0])) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                char* arg_value = args[0];
+                errno = 0;
+                long long arg_number = std::strtoll(arg_value, nullptr, 10);
+                uint32_t arg_res = static_cast<uint32_t>(arg_number);
+
+                if (errno == ERANGE || arg_number < 0 || arg_number > UINT32_MAX) {
+                    printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
+                    exit(1);
+                }
+                if (arg_res == 0 && strcmp(arg_value, "0") != 0) {
+                    printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
+                    exit(1);
+                }
+                if (arg_res < 1 || arg_res > 64) {
+                    printf("Value '%s' of option '%s' is not between 1 and 64", arg_value, arg);
+                    exit(1);
+                }
+                res.jobs = arg_res;
+                mandatory_fields_seen[2] = true;
+            } else if (strcmp(arg, "--retries") == 0) {
+                ++args;
+                ++i;
+                if (i == argc || Cli::is_option(args[0])) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                char* arg_value = args[0];
+                errno = 0;
+                long long arg_number = std::strtoll(arg_value, nullptr, 10);
+                uint16_t arg_res = static_cast<uint16_t>(arg_number);
+
+                if (errno == ERANGE || arg_number < 0 || arg_number > UINT16_MAX) {
+                    printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
+                    exit(1);
+                }
+                if (arg_res == 0 && strcmp(arg_value, "0") != 0) {
+                    printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
+                    exit(1);
+                }
+                res.retries = arg_res;
+            } else if (strcmp(arg, "--output") == 0 || strcmp(arg, "--out") == 0) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.output = arg_res;
+            } else if (strcmp(arg, "--include") == 0) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.include.push_back(arg_res);
+            } else if (equals_ignore_case(arg, "--mode")) {
+                ++args;
+                ++i;
+                if (i == argc) {
+                    printf("Expected value for option '%s' but no value was provided", arg);
+                    exit(1);
+                }
+                std::string arg_res = args[0];
+                res.mode = arg_res;
+            } else {
+                printf("Unknown option '%s'\n", arg);
+                exit(1);
+            }
+        }
+
+        bool not_seen_any = false;
+        for (size_t i = 0; i != sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0]); ++i) {
+            if (!mandatory_fields_seen[i]) {
+                printf("--%s was required but it was not provided\n", mandatory_field_names[i]);
+                not_seen_any = true;
+            }
+        }
+        if (not_seen_any) {
+            exit(1);
+        }
+        if (res.include.size() < 1) {
+            printf("Expected at least 1 occurrences of --include but %zu were provided\n", res.include.size());
+            exit(1);
+        }
+        return res;
+    }
+};
+
+#endif // _CLI_H_
@@ -0,0 +1,161 @@
+#ifndef _CLI_H_
+#define _CLI_H_
+
+#include <cctype>
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <cstdio>
+#include <cerrno>
+#include <string>
+#include <optional>
+
+inline bool equals_ignore_case(const char* lhs, const char* rhs) {
+    for (; *lhs != '\0' && *rhs != '\0'; ++lhs, ++rhs) {
+        if (tolower((unsigned char)*lhs) != tolower((unsigned char)*rhs)) {
+            return false;
+        }
+    }
+    return *lhs == *rhs;
+}
+
+inline bool parse_bool_value(const char* value, bool* res) {
+    if (equals_ignore_case(value, "true") || equals_ignore_case(value, "1") || equals_ignore_case(value, "yes")) {
+        *res = true;
+        return true;
+    }
+    if (equals_ignore_case(value, "false") || equals_ignore_case(value, "0") || equals_ignore_case(value, "no")) {
+        *res = false;
+        return true;
+    }
+    return false;
+}
+
+struct Cli {
+    bool force;
+    std::string destination;
+    std::string source;
+    std::optional<uint32_t> copies;
+
+    void print_debug() {
+        printf("Cli {\n");
+        printf("\tforce: %s\n", this->force ? "true" : "false");
+        printf("\tdestination: %s\n", this->destination.c_str());
+        printf("\tsource: %s\n", this->source.c_str());
+        if (this->copies.has_value()) {
+            printf("\tcopies: %u\n", this->copies.value());
+        } else {
+            printf("\tcopies: none\n");
+        }
+        printf("}\n");
+    }
+
+    static void help() {
+        printf("Usage: Cli [OPTIONS] <SOURCE> <DESTINATION> [<COPIES>]\n"
+            "\n"
+            "Arguments:\n"
+            "    <SOURCE>\n"
+            "    <DESTINATION>\n"
+            "    <COPIES>\n"
+            "\n"
+            "Options:\n"
+            "    -h, --help\n"
+            "    -f, --force\n"
+        );
+        exit(0);
+    }
+
+    static bool is_option(char* arg) {
+        static const char* valid_options[] = {
+            "-f",
+            "--force",
+        };
+
+        for (size_t i = 0; i != 2; ++i) {
+            if (strcmp(arg, valid_options[i]) == 0) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    static Cli parse (int argc, char *args[]) {
+        --argc;
+        ++args;
+
+        const char* mandatory_field_names[] = { "force", };
+        bool mandatory_fields_seen[sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0])] = { false };
+
+        size_t positional_count = 0;
+        Cli res = {};
+        for (int i = 0; i != argc; ++i, ++args) {
+            const char *arg = args[0];
+            if (strcmp("-h", arg) == 0 || strcmp("--help", arg) == 0) {
+                Cli::help();
+            } else if (strcmp(arg, "-f") == 0 || strncmp(arg, "-f=", 3) == 0 || strcmp(arg, "--force") == 0 || strncmp(arg, "--force=", 8) == 0) {
+                bool arg_res = true;
+                const char* bool_value = strchr(arg, '=');
+                if (bool_value != nullptr) {
+                    ++bool_value;
+                }
+                if (bool_value != nullptr && !parse_bool_value(bool_value, &arg_res)) {
+                    printf("Value '%s' of option '%s' is not a valid boolean (expected true/false, 1/0 or yes/no)", bool_value, arg);
+                    exit(1);
+                }
+                res.force = arg_res;
+                mandatory_fields_seen[0] = true;
+            } else if (positional_count != 3 && (strncmp(arg, "-", 1) != 0 || strcmp(arg, "-") == 0) && (strncmp(arg, "--", 2) != 0 || strcmp(arg, "--") == 0)) {
+                if (positional_count == 0) {
+                    std::string arg_res = args[0];
+                    res.source = arg_res;
+                } else if (positional_count == 1) {
+                    std::string arg_res = args[0];
+                    res.destination = arg_res;
+                } else if (positional_count == 2) {
+                    const char* arg = "<COPIES>";
+                    char* arg_value = args[0];
+                    errno = 0;
+                    long long arg_number = std::strtoll(arg_value, nullptr, 10);
+                    uint32_t arg_res = static_cast<uint32_t>(arg_number);
+
+                    if (errno == ERANGE || arg_number < 0 || arg_number > UINT32_MAX) {
+                        printf("Value '%s' of option '%s' out of range for integer type", arg_value, arg);
+                        exit(1);
+                    }
+                    if (arg_res == 0 && strcmp(arg_value, "0") != 0) {
+                        printf("Value '%s' of option '%s' is not a valid integer", arg_value, arg);
+                        exit(1);
+                    }
+                    res.copies = arg_res;
+                }
+                ++positional_count;
+            } else {
+                printf("Unknown option '%s'\n", arg);
+                exit(1);
+            }
+        }
+
+        bool not_seen_any = false;
+        for (size_t i = 0; i != sizeof(mandatory_field_names)/sizeof(mandatory_field_names[0]); ++i) {
+            if (!mandatory_fields_seen[i]) {
+                printf("--%s was required but it was not provided\n", mandatory_field_names[i]);
+                not_seen_any = true;
+            }
+        }
+        if (positional_count <= 0) {
+            printf("<SOURCE> was required but it was not provided\n");
+            not_seen_any = true;
+        }
+        if (positional_count <= 1) {
+            printf("<DESTINATION> was required but it was not provided\n");
+            not_seen_any = true;
+        }
+        if (not_seen_any) {
+            exit(1);
+        }
+        return res;
+    }
+};
+
+#endif // _CLI_H_
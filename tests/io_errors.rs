@@ -0,0 +1,103 @@
+//! Checks that files that can't be read or written are reported with the OS error and the I/O
+//! exit code rather than a panic.
+
+mod support;
+
+use std::ffi::OsStr;
+
+use support::{run, temp_dir, EXIT_IO_ERROR};
+
+const SPEC: &str = "#[main] struct Cli { #[long] port: u16 }";
+
+#[test]
+fn missing_inputs_are_reported() {
+    let dir = temp_dir("missing_input", &[]);
+    let input = dir.join("speck.cli");
+    let output = dir.join("cli.h");
+
+    let result = run(
+        &dir,
+        [
+            OsStr::new("-i"),
+            input.as_ref(),
+            OsStr::new("-o"),
+            output.as_ref(),
+        ],
+    );
+
+    assert_eq!(result.status.code(), Some(EXIT_IO_ERROR));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.starts_with(&format!("error: cannot read '{}': ", input.display())),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert!(!output.exists());
+}
+
+#[test]
+fn unreadable_inputs_are_reported() {
+    // A directory can't be read as a file whatever the permissions of the user running the tests.
+    let dir = temp_dir("unreadable_input", &[]);
+
+    let result = run(
+        &dir,
+        [OsStr::new("-i"), dir.as_ref(), OsStr::new("--check")],
+    );
+
+    assert_eq!(result.status.code(), Some(EXIT_IO_ERROR));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.starts_with(&format!("error: cannot read '{}': ", dir.display())),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn missing_output_directories_are_created() {
+    let dir = temp_dir("missing_output_directory", &[("cli.in", SPEC)]);
+    let input = dir.join("cli.in");
+    let output = dir.join("generated").join("include").join("cli.h");
+
+    let result = run(
+        &dir,
+        [
+            OsStr::new("-i"),
+            input.as_ref(),
+            OsStr::new("-o"),
+            output.as_ref(),
+        ],
+    );
+
+    assert!(result.status.success());
+    assert!(output.is_file());
+}
+
+#[test]
+fn unwritable_outputs_are_reported() {
+    // The parent of the output being a file, its directory can't be created.
+    let dir = temp_dir("unwritable_output", &[("cli.in", SPEC)]);
+    let input = dir.join("cli.in");
+    let output = dir.join("cli.in").join("cli.h");
+
+    let result = run(
+        &dir,
+        [
+            OsStr::new("-i"),
+            input.as_ref(),
+            OsStr::new("-o"),
+            output.as_ref(),
+        ],
+    );
+
+    assert_eq!(result.status.code(), Some(EXIT_IO_ERROR));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.starts_with(&format!(
+            "error: cannot create the directory '{}' of '{}': ",
+            input.display(),
+            output.display()
+        )),
+        "{stderr}"
+    );
+}
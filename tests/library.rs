@@ -0,0 +1,212 @@
+//! Checks the library API: specs are parsed, checked and generated in memory, and every failure
+//! comes with the diagnostics the binary would print.
+
+use std::fs;
+use std::path::Path;
+
+use cli_generator::{
+    check, generate, parse_spec, parse_spec_json, parse_spec_tolerant, Error, FieldType,
+    GenOptions, Indent, SpecBuilder, Target,
+};
+
+const SPEC: &str = "#[main]
+struct Cli {
+    #[short, long]
+    port: u16,
+    #[long]
+    host: Optional<string>,
+}
+";
+
+#[test]
+fn every_target_is_generated_in_memory() {
+    let spec = parse_spec(SPEC).unwrap();
+    let metadata = check(&spec).unwrap();
+
+    let cpp = generate(&spec, &metadata, Target::Cpp, &GenOptions::default()).unwrap();
+    assert_eq!(cpp.len(), 1);
+    assert_eq!(cpp[0].path, "cli.h");
+    assert!(cpp[0].contents.contains("struct Cli"));
+
+    let c = generate(&spec, &metadata, Target::C, &GenOptions::default()).unwrap();
+    let paths = c.iter().map(|file| file.path.as_str()).collect::<Vec<_>>();
+    assert_eq!(paths, ["cli.h", "cli.c"]);
+
+    let go = generate(&spec, &metadata, Target::Go, &GenOptions::default()).unwrap();
+    assert_eq!(go[0].path, "cli.go");
+    assert!(go[0].contents.contains("\npackage main\n"));
+}
+
+#[test]
+fn generated_code_has_no_banner_and_follows_the_options() {
+    let spec = parse_spec(SPEC).unwrap();
+    let metadata = check(&spec).unwrap();
+
+    let mut options = GenOptions::default();
+    options.indent = Indent::Tabs;
+    let cpp = generate(&spec, &metadata, Target::Cpp, &options).unwrap();
+    assert!(!cpp[0].contents.contains("Spec hash"));
+    assert!(cpp[0].contents.contains("\n\t"));
+}
+
+#[test]
+fn failures_come_with_their_diagnostics() {
+    let source = "#[main] struct Cli { port u16 }";
+    let diagnostics = parse_spec(source).unwrap_err();
+    assert_eq!(diagnostics[0].label, "Parser error");
+    assert!(diagnostics.render(source).contains("port u16"));
+
+    let source = "#[main] struct Cli { #[long] port: u16, #[long] port: u16 }";
+    let spec = parse_spec(source).unwrap();
+    let diagnostics = check(&spec).unwrap_err();
+    assert_eq!(diagnostics[0].message, "Redefinition of field");
+    assert!(diagnostics
+        .to_string()
+        .starts_with("Multiple field definition: Redefinition of field"));
+
+    let source = "#[main] struct Cli { #[long] name: string, #[index = 0] file: string }";
+    let spec = parse_spec(source).unwrap();
+    let metadata = check(&spec).unwrap();
+    let Err(Error::Unsupported(diagnostics)) =
+        generate(&spec, &metadata, Target::Go, &GenOptions::default())
+    else {
+        panic!("positional arguments are generated in Go");
+    };
+    assert_eq!(
+        diagnostics[0].message,
+        "Positional arguments are not supported in Go"
+    );
+}
+
+#[test]
+fn spans_point_into_the_source() {
+    let spec = parse_spec(SPEC).unwrap();
+    let field = &spec.structs[0].fields[1];
+
+    let attribute = &field.attributes[0];
+    assert_eq!(&SPEC[attribute.start_byte()..attribute.end_byte()], "long");
+    assert_eq!(&SPEC[field.name_span()], "host");
+    assert_eq!(&SPEC[field.type_span()], "string");
+}
+
+#[test]
+fn built_specs_generate_the_code_of_the_same_spec_written_out() {
+    let built = SpecBuilder::new()
+        .strukt("Cli", |s| {
+            s.main()
+                .field("port", FieldType::U16, |f| f.short('p').long())
+                .field(
+                    "host",
+                    FieldType::Optional(Box::new(FieldType::String)),
+                    |f| f.long(),
+                )
+        })
+        .build()
+        .unwrap();
+    let parsed = parse_spec(SPEC).unwrap();
+
+    let built_metadata = check(&built).unwrap();
+    let parsed_metadata = check(&parsed).unwrap();
+    for target in [Target::Cpp, Target::C, Target::Go] {
+        assert_eq!(
+            generate(&built, &built_metadata, target, &GenOptions::default()).unwrap(),
+            generate(&parsed, &parsed_metadata, target, &GenOptions::default()).unwrap()
+        );
+    }
+}
+
+#[test]
+fn diagnostics_of_built_specs_tell_what_they_are_about() {
+    let spec = SpecBuilder::new()
+        .strukt("Args", |s| {
+            s.main()
+                .field("output", FieldType::String, |f| f.long())
+                .field("output", FieldType::String, |f| f.long())
+        })
+        .build()
+        .unwrap();
+
+    let diagnostics = check(&spec).unwrap_err();
+    assert_eq!(
+        diagnostics[0].render(&spec.source),
+        "error: Multiple field definition
+  --> in programmatically constructed spec, struct Args, field output
+   = Redefinition of field
+   = info: Has already been defined here, at struct Args, field output
+"
+    );
+}
+
+#[test]
+fn broken_specs_are_parsed_as_far_as_they_can_be() {
+    let source =
+        "#[main]\nstruct Cli {\n    #[long]\n    port: ,\n    #[long]\n    host: string,\n}\n";
+    let (spec, diagnostics) = parse_spec_tolerant(source);
+    assert_eq!(diagnostics.len(), 1);
+
+    let fields = &spec.structs[0].fields;
+    assert_eq!(fields[0].ty, FieldType::Error);
+    assert_eq!(&source[fields[1].type_span()], "string");
+    assert_eq!(check(&spec).unwrap_err()[0].message, "Missing type");
+
+    let (spec, diagnostics) = parse_spec_tolerant(SPEC);
+    assert!(diagnostics.is_empty());
+    assert_eq!(spec.structs[0].fields.len(), 2);
+}
+
+#[test]
+fn specs_serialized_as_json_generate_the_code_of_the_parsed_ones() {
+    let mut sources = vec![(String::from("SPEC"), SPEC.to_string())];
+    for dir in ["tests/specs", "tests/fixtures"] {
+        for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "cli") {
+                let source = fs::read_to_string(&path).unwrap();
+                sources.push((path.display().to_string(), source));
+            }
+        }
+    }
+
+    for (name, source) in &sources {
+        let Ok(parsed) = parse_spec(source) else {
+            continue;
+        };
+        let Ok(parsed_metadata) = check(&parsed) else {
+            continue;
+        };
+        let json = serde_json::to_string(&parsed).unwrap();
+        let read = parse_spec_json(&json)
+            .unwrap_or_else(|diagnostics| panic!("{name}: {}", diagnostics.render(&json)));
+        let read_metadata = check(&read)
+            .unwrap_or_else(|diagnostics| panic!("{name}: {}", diagnostics.render(&json)));
+
+        for target in [Target::Cpp, Target::C, Target::Go] {
+            assert_eq!(
+                generate(&read, &read_metadata, target, &GenOptions::default()).ok(),
+                generate(&parsed, &parsed_metadata, target, &GenOptions::default()).ok(),
+                "{name}, {target:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn json_that_is_not_a_spec_is_reported_at_its_path() {
+    let source = r#"{"structs": [{"name": "Cli", "fields": [{"name": "port", "type": "u8"}]}]}"#;
+    let diagnostics = parse_spec_json(source).unwrap_err();
+    assert!(diagnostics[0]
+        .message
+        .starts_with("$.structs[0].fields[0].type: unknown variant `u8`"));
+    assert_eq!(&source[diagnostics[0].span.clone()], r#""u8""#);
+
+    let source = r#"{"structs": [{"name": "Cli", "attributes": [{"type": "main"}], "fields": [
+        {"name": "port", "type": "u16", "attributes": [{"type": "long"}]},
+        {"name": "port", "type": "u16", "attributes": [{"type": "long"}]}
+    ]}]}"#;
+    let spec = parse_spec_json(source).unwrap();
+    let diagnostics = check(&spec).unwrap_err();
+    assert_eq!(diagnostics[0].message, "Redefinition of field");
+    assert!(diagnostics
+        .render(source)
+        .contains(r#"{"name": "port", "type": "u16""#));
+}
@@ -0,0 +1,176 @@
+//! Speaks the Language Server Protocol with `cli-generator lsp`, framing every message with its
+//! `Content-Length` header like editors do.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+const URI: &str = "file:///specs/cli.cli";
+
+fn send(stdin: &mut ChildStdin, message: Value) {
+    let content = message.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{content}", content.len()).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn receive(stdout: &mut BufReader<ChildStdout>) -> Value {
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        stdout.read_line(&mut header).unwrap();
+        match header.trim_end().split_once(": ") {
+            Some(("Content-Length", length)) => content_length = length.parse().unwrap(),
+            _ if header.trim_end().is_empty() => break,
+            _ => {}
+        }
+    }
+
+    let mut content = vec![0; content_length];
+    stdout.read_exact(&mut content).unwrap();
+    serde_json::from_slice(&content).unwrap()
+}
+
+#[test]
+fn diagnostics_and_symbols_follow_the_edits() {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_cli-generator"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = server.stdin.take().unwrap();
+    let mut stdout = BufReader::new(server.stdout.take().unwrap());
+
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+    );
+    let initialize = receive(&mut stdout);
+    assert_eq!(initialize["id"], 1);
+    assert_eq!(initialize["result"]["capabilities"]["textDocumentSync"], 1);
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+    );
+
+    // The field is defined twice, which the semantic checks find.
+    let text = "#[main]\nstruct Cli {\n    #[long] port: u16,\n    #[long] port: u16,\n}\n";
+    send(
+        &mut stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "cli", "version": 1, "text": text } },
+        }),
+    );
+    let published = receive(&mut stdout);
+    assert_eq!(published["method"], "textDocument/publishDiagnostics");
+    assert_eq!(published["params"]["uri"], URI);
+    let diagnostic = &published["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["message"], "Redefinition of field");
+    assert_eq!(
+        diagnostic["range"],
+        json!({ "start": { "line": 3, "character": 12 }, "end": { "line": 3, "character": 16 } })
+    );
+    assert_eq!(
+        diagnostic["relatedInformation"][0]["location"]["range"]["start"],
+        json!({ "line": 2, "character": 12 })
+    );
+
+    let text = "#[main]\nstruct Cli {\n    #[long] port: u16,\n    #[long] host: string,\n}\n";
+    send(
+        &mut stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": text }],
+            },
+        }),
+    );
+    let published = receive(&mut stdout);
+    assert_eq!(published["params"]["diagnostics"], json!([]));
+
+    send(
+        &mut stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": URI } },
+        }),
+    );
+    let symbols = receive(&mut stdout);
+    assert_eq!(symbols["id"], 2);
+    let strukt = &symbols["result"][0];
+    assert_eq!(strukt["name"], "Cli");
+    assert_eq!(strukt["kind"], 23);
+    // From the `#` of the attributes to the `}` closing the struct, and to the comma for fields.
+    assert_eq!(
+        strukt["range"],
+        json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 4, "character": 1 } })
+    );
+    assert_eq!(
+        strukt["children"][0]["range"],
+        json!({ "start": { "line": 2, "character": 4 }, "end": { "line": 2, "character": 22 } })
+    );
+    let fields = strukt["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(fields, ["port", "host"]);
+
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+    );
+    assert_eq!(receive(&mut stdout)["id"], 3);
+    send(&mut stdin, json!({ "jsonrpc": "2.0", "method": "exit" }));
+
+    assert!(server.wait().unwrap().success());
+}
+
+#[test]
+fn parse_errors_are_published_with_their_range() {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_cli-generator"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = server.stdin.take().unwrap();
+    let mut stdout = BufReader::new(server.stdout.take().unwrap());
+
+    send(
+        &mut stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "cli", "version": 1, "text": "#[main]\nstruct Cli {\n    port u16,\n}\n" } },
+        }),
+    );
+    let diagnostics = &receive(&mut stdout)["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+    assert_eq!(diagnostics[0]["severity"], 1);
+
+    // What could be parsed is still outlined.
+    send(
+        &mut stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": URI } },
+        }),
+    );
+    let symbols = receive(&mut stdout);
+    assert_eq!(symbols["result"][0]["name"], "Cli");
+    assert_eq!(symbols["result"][0]["children"][0]["name"], "port");
+
+    // Exiting without shutting down first is an error.
+    send(&mut stdin, json!({ "jsonrpc": "2.0", "method": "exit" }));
+    assert_eq!(server.wait().unwrap().code(), Some(1));
+}
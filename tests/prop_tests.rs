@@ -0,0 +1,462 @@
+//! Property based tests of the parser and the semantic analysis: they never panic, whatever the
+//! input, be it arbitrary bytes or specs broken in places, parsed specs print back to source that
+//! parses to the same spec, formatting them twice is formatting them once, and structurally valid
+//! specs pass the semantic checks. The inputs that used to panic are kept in `tests/fixtures/fuzz`.
+
+use cli_generator::internal::format_spec;
+use cli_generator::{
+    check, parse_spec, parse_spec_tolerant, Attribute, AttributeType, FieldType, Spec,
+};
+use proptest::prelude::*;
+
+const STRUCT_NAMES: &[&str] = &["Cli", "Net", "Log", "Status"];
+
+/// Attributes of every kind, with and without values, valid or not where they are put.
+const ATTRIBUTES: &[&str] = &[
+    "short",
+    "short = x",
+    "long",
+    "long = other_name",
+    "alias = other",
+    "short_and_long = \"y/other-name\"",
+    "short_and_long = \"yy\"",
+    "flatten",
+    "main",
+    "subcommand",
+    "explicit_bool",
+    "takes_value = false",
+    "trailing",
+    "secret",
+    "doc = \"The \\\"doc\\\"\"",
+    "about = \"About\"",
+    "after_help = \"After\"",
+    "min = 1",
+    "max = 2.5",
+    "min_occurs = 1",
+    "max_occurs = 2",
+    "regex = \"[a-z]+\"",
+    "case_insensitive",
+    "default",
+    "deprecated",
+    "deprecated = \"Use something else\"",
+    "deprecated_in = \"2.0\"",
+    "removed_in = \"3.0\"",
+    "hidden_from_completion",
+    "parse_fn = \"parse_value\"",
+    "append_env = \"PATHS\"",
+    "env_list = \"ITEMS\"",
+    "exclusive_group = \"input\"",
+    "requires_group = \"input\"",
+    "prefix_flag",
+    "next_line_help",
+    "from_str",
+    "default_value_t = \"8\"",
+    "index = 0",
+    "parse_order = 1",
+    "display_order = 2",
+    "input_file",
+    "output_file",
+    "directory",
+    "global_flag",
+    "env_prefix = \"APP_\"",
+    "env_case = \"lower\"",
+    "long_short_conflict = \"warn\"",
+    "doc_hidden",
+    "allow_abbrev",
+    "separator = \"--\"",
+    "prefix = \"/\"",
+    "value_separator = \":\"",
+    "long_prefix = \"+\"",
+    "word_wrap = 40",
+    "config_file = \"config\"",
+    "exit_code = 2",
+    "parse_error_exit = 3",
+    "missing_arg_exit = 4",
+    "unknown_flag_exit = 0",
+];
+
+const VALUE_TYPES: &[&str] = &[
+    "string", "i16", "u16", "i32", "u32", "i64", "u64", "f32", "f64", "bool",
+];
+
+fn field_type() -> impl Strategy<Value = String> {
+    let value_type = prop_oneof![
+        8 => prop::sample::select(VALUE_TYPES),
+        4 => prop::sample::select(STRUCT_NAMES),
+        // Syntax errors, bare or wrapped in each other.
+        1 => Just("Vec"),
+        1 => Just("Optional"),
+    ];
+
+    (value_type, 0..3usize).prop_map(|(value_type, wrapper)| match wrapper {
+        0 => value_type.to_string(),
+        1 => format!("Vec<{value_type}>"),
+        _ => format!("Optional<{value_type}>"),
+    })
+}
+
+fn attributes() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(ATTRIBUTES), 0..4).prop_map(|attributes| {
+        if attributes.is_empty() {
+            String::new()
+        } else {
+            format!("#[{}]\n", attributes.join(", "))
+        }
+    })
+}
+
+fn field() -> impl Strategy<Value = String> {
+    (attributes(), "[a-z][a-z_]{0,6}", field_type())
+        .prop_map(|(attributes, name, ty)| format!("{attributes}    {name}: {ty},\n"))
+}
+
+/// Specs made of the pieces of the language, put together without caring whether they make
+/// sense, e.g. `#[flatten, min = 1] port: Vec<bool>`.
+fn arbitrary_spec() -> impl Strategy<Value = String> {
+    let strukt = (
+        attributes(),
+        prop::sample::select(STRUCT_NAMES),
+        prop::collection::vec(field(), 0..5),
+    )
+        .prop_map(|(attributes, name, fields)| {
+            format!("{attributes}struct {name} {{\n{}}}\n", fields.concat())
+        });
+
+    prop::collection::vec(strukt, 1..4).prop_map(|structs| structs.join("\n"))
+}
+
+/// Pieces of the language and of what it doesn't expect, put in the middle of specs by
+/// `mutated_spec`.
+const SNIPPETS: &[&str] = &[
+    "#[",
+    "]",
+    "{",
+    "}",
+    "<",
+    ">",
+    "=",
+    ",",
+    ":",
+    "\"",
+    "\\",
+    "/",
+    "-",
+    "_",
+    "struct",
+    "Vec",
+    "Optional",
+    "main",
+    "flatten",
+    "short",
+    "long",
+    "index",
+    "min",
+    "max",
+    "0",
+    "-1",
+    "340282366920938463463374607431768211456",
+    "1e400",
+    "0.5",
+    "\"\"",
+    "\"a/\"",
+    "\"/b\"",
+    "\"é\"",
+    "é",
+    "\u{0}",
+    "\n",
+    " ",
+];
+
+/// What `mutated_spec` does to a spec at a position of it.
+#[derive(Debug, Clone)]
+enum Mutation {
+    Insert(&'static str),
+    Remove(usize),
+    Duplicate(usize),
+    Truncate,
+}
+
+/// Specs made of the pieces of the language, broken in places: snippets put in, parts removed or
+/// repeated, or the end cut off. Positions are fractions of the length, moved back to the start of
+/// a character.
+fn mutated_spec() -> impl Strategy<Value = String> {
+    let mutation = prop_oneof![
+        4 => prop::sample::select(SNIPPETS).prop_map(Mutation::Insert),
+        2 => (1..16usize).prop_map(Mutation::Remove),
+        1 => (1..32usize).prop_map(Mutation::Duplicate),
+        1 => Just(Mutation::Truncate),
+    ];
+    let source = prop_oneof![arbitrary_spec(), valid_spec()];
+
+    (source, prop::collection::vec((mutation, 0.0..1.0f64), 1..6)).prop_map(
+        |(mut source, mutations)| {
+            for (mutation, position) in mutations {
+                let mut at = (source.len() as f64 * position) as usize;
+                while !source.is_char_boundary(at) {
+                    at -= 1;
+                }
+                let end = |length: usize| {
+                    let mut end = (at + length).min(source.len());
+                    while !source.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    end
+                };
+                match mutation {
+                    Mutation::Insert(snippet) => source.insert_str(at, snippet),
+                    Mutation::Remove(length) => {
+                        source.replace_range(at..end(length), "");
+                    }
+                    Mutation::Duplicate(length) => {
+                        let repeated = source[at..end(length)].to_string();
+                        source.insert_str(at, &repeated);
+                    }
+                    Mutation::Truncate => source.truncate(at),
+                }
+            }
+            source
+        },
+    )
+}
+
+/// A name for the index, as identifiers can't have digits.
+fn letter(index: usize) -> String {
+    char::from(b'a' + index as u8).to_string()
+}
+
+/// Specs that are valid by construction: a main struct flattening the other structs, every
+/// field given by its own long option.
+fn valid_spec() -> impl Strategy<Value = String> {
+    let fields = prop::collection::vec(
+        (prop::sample::select(VALUE_TYPES), 0..3usize, any::<bool>()),
+        1..5,
+    );
+
+    prop::collection::vec(fields, 1..4).prop_map(|structs| {
+        let count = structs.len();
+        structs
+            .into_iter()
+            .enumerate()
+            .map(|(struct_index, fields)| {
+                let mut source = String::new();
+                let is_main = struct_index + 1 == count;
+                if is_main {
+                    source.push_str("#[main]\n");
+                }
+                let name = letter(struct_index);
+                source.push_str(&format!("struct Struct{} {{\n", name.to_uppercase()));
+                for (field_index, (value_type, wrapper, documented)) in fields.iter().enumerate() {
+                    let doc = if *documented { ", doc = \"Doc\"" } else { "" };
+                    let ty = match wrapper {
+                        0 => value_type.to_string(),
+                        1 => format!("Vec<{value_type}>"),
+                        _ => format!("Optional<{value_type}>"),
+                    };
+                    source.push_str(&format!(
+                        "    #[long{doc}]\n    {name}_{}: {ty},\n",
+                        letter(field_index)
+                    ));
+                }
+                if is_main {
+                    for flattened in 0..struct_index {
+                        let name = letter(flattened);
+                        source.push_str(&format!(
+                            "    #[flatten]\n    {name}: Struct{},\n",
+                            name.to_uppercase()
+                        ));
+                    }
+                }
+                source.push_str("}\n");
+                source
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+fn print_type(ty: &FieldType) -> String {
+    match ty {
+        FieldType::String => String::from("string"),
+        FieldType::I16 => String::from("i16"),
+        FieldType::U16 => String::from("u16"),
+        FieldType::I32 => String::from("i32"),
+        FieldType::U32 => String::from("u32"),
+        FieldType::I64 => String::from("i64"),
+        FieldType::U64 => String::from("u64"),
+        FieldType::F32 => String::from("f32"),
+        FieldType::F64 => String::from("f64"),
+        FieldType::Bool => String::from("bool"),
+        FieldType::Struct(name) => name.clone(),
+        FieldType::Vec(inner) => format!("Vec<{}>", print_type(inner)),
+        FieldType::Optional(inner) => format!("Optional<{}>", print_type(inner)),
+        _ => unreachable!("{ty:?} is not generated"),
+    }
+}
+
+fn print_attributes(attributes: &[Attribute]) -> String {
+    if attributes.is_empty() {
+        return String::new();
+    }
+
+    let attributes = attributes
+        .iter()
+        .map(|attribute| {
+            let name = attribute.ty.to_literal();
+            let Some(value) = &attribute.value else {
+                return name.to_string();
+            };
+            match attribute.ty {
+                AttributeType::Short
+                | AttributeType::Long
+                | AttributeType::Alias
+                | AttributeType::TakesValue
+                | AttributeType::MinOccurs
+                | AttributeType::MaxOccurs
+                | AttributeType::WordWrap
+                | AttributeType::ExitCode
+                | AttributeType::ParseErrorExit
+                | AttributeType::MissingArgExit
+                | AttributeType::UnknownFlagExit
+                | AttributeType::Index
+                | AttributeType::ParseOrder
+                | AttributeType::DisplayOrder
+                | AttributeType::Min
+                | AttributeType::Max => format!("{name} = {value}"),
+                _ => format!("{name} = \"{value}\""),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    format!("#[{}]\n", attributes.join(", "))
+}
+
+/// Prints the spec back to source, with the default values of `short` and `long` spelled out.
+fn print_spec(spec: &Spec) -> String {
+    spec.structs
+        .iter()
+        .map(|strukt| {
+            let fields = strukt
+                .fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        "{}{}: {},\n",
+                        print_attributes(&field.attributes),
+                        field.name,
+                        print_type(&field.ty)
+                    )
+                })
+                .collect::<String>();
+            format!(
+                "{}struct {} {{\n{fields}}}\n",
+                print_attributes(&strukt.attributes),
+                strukt.name
+            )
+        })
+        .collect()
+}
+
+type AttributesShape = Vec<(AttributeType, Option<String>)>;
+type FieldShape = (String, FieldType, AttributesShape);
+type StructShape = (String, AttributesShape, Vec<FieldShape>);
+
+/// The spec without its spans, which change when it is printed.
+fn shape(spec: &Spec) -> Vec<StructShape> {
+    let attributes = |attributes: &[Attribute]| {
+        attributes
+            .iter()
+            .map(|attribute| (attribute.ty, attribute.value.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    spec.structs
+        .iter()
+        .map(|strukt| {
+            let fields = strukt
+                .fields
+                .iter()
+                .map(|field| {
+                    (
+                        field.name.clone(),
+                        field.ty.clone(),
+                        attributes(&field.attributes),
+                    )
+                })
+                .collect();
+            (strukt.name.clone(), attributes(&strukt.attributes), fields)
+        })
+        .collect()
+}
+
+/// Parses and checks `source`, which gives a spec or diagnostics whatever it is.
+fn check_without_panicking(source: &str) {
+    if let Ok(spec) = parse_spec(source) {
+        let _ = check(&spec);
+    }
+
+    // Editors check what could be parsed of a broken spec as well.
+    let (spec, _) = parse_spec_tolerant(source);
+    let _ = check(&spec);
+}
+
+/// Inputs that used to panic, or that are at the edges of what the lexer and the parser take.
+const REGRESSIONS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fuzz");
+
+#[test]
+fn regressions_are_handled_without_panicking() {
+    for entry in std::fs::read_dir(REGRESSIONS).unwrap() {
+        let source = std::fs::read(entry.unwrap().path()).unwrap();
+        check_without_panicking(&String::from_utf8_lossy(&source));
+    }
+}
+
+proptest! {
+    #[test]
+    fn any_input_is_handled_without_panicking(source in "\\PC{0,64}") {
+        check_without_panicking(&source);
+    }
+
+    #[test]
+    fn any_bytes_are_handled_without_panicking(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        check_without_panicking(&String::from_utf8_lossy(&bytes));
+    }
+
+    #[test]
+    fn broken_specs_are_handled_without_panicking(source in mutated_spec()) {
+        check_without_panicking(&source);
+    }
+
+    #[test]
+    fn parsed_specs_print_back_to_the_same_spec(source in arbitrary_spec()) {
+        let Ok(spec) = parse_spec(&source) else {
+            return Ok(());
+        };
+        let _ = check(&spec);
+
+        let printed = print_spec(&spec);
+        let reparsed = parse_spec(&printed);
+        prop_assert!(reparsed.is_ok(), "{printed} doesn't parse");
+        prop_assert_eq!(shape(&reparsed.unwrap()), shape(&spec));
+    }
+
+    #[test]
+    fn formatted_specs_are_left_as_is_by_formatting(source in arbitrary_spec()) {
+        let Ok(spec) = parse_spec(&source) else {
+            return Ok(());
+        };
+
+        let formatted = format_spec(&spec);
+        let reparsed = parse_spec(&formatted);
+        prop_assert!(reparsed.is_ok(), "{formatted} doesn't parse");
+        prop_assert_eq!(format_spec(&reparsed.unwrap()), formatted);
+    }
+
+    #[test]
+    fn valid_specs_pass_the_semantic_checks(source in valid_spec()) {
+        let spec = parse_spec(&source);
+        prop_assert!(spec.is_ok(), "{source} doesn't parse");
+        let spec = spec.unwrap();
+        let metadata = check(&spec);
+        prop_assert!(metadata.is_ok(), "{source} fails the semantic checks: {:?}", metadata.err());
+    }
+}
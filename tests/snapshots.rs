@@ -0,0 +1,46 @@
+//! Locks the C++ generated from the specs in `tests/fixtures`, and the diagnostics of the ones that
+//! aren't valid, see `support::snapshot`. After a change to the generated code, run the tests with
+//! `UPDATE_SNAPSHOTS=1` and review the snapshots that changed.
+
+mod support;
+
+use std::path::Path;
+
+use support::snapshot;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+#[test]
+fn fixtures_match_their_snapshots() {
+    let fixtures = snapshot::fixtures(Path::new(FIXTURES));
+    assert!(!fixtures.is_empty(), "there are no fixtures in {FIXTURES}");
+
+    let failures = fixtures
+        .iter()
+        .filter_map(|fixture| snapshot::compare(fixture, &snapshot::snapshot(fixture)))
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn diffs_show_the_changed_lines_in_context() {
+    let expected = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let actual = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\n";
+
+    assert_eq!(
+        snapshot::diff(expected, actual),
+        "@@ line 2 @@
+     2 | b
+     3 | c
+     4 | d
+-    5 | e
++      | E
+     6 | f
+     7 | g
+     8 | h
+     9 | i
++      | j
+"
+    );
+}
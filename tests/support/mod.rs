@@ -0,0 +1,45 @@
+//! Helpers shared by the integration tests, which run the binary in a directory of their own.
+//! The snapshots of the fixtures are taken by `snapshot`.
+
+// Every test uses some of the helpers, and warns about the ones it doesn't otherwise.
+#![allow(dead_code)]
+
+pub mod snapshot;
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// The exit codes of the binary by kind of failure, see `Failure` in `src/main.rs`.
+pub const EXIT_PARSE_ERROR: i32 = 2;
+pub const EXIT_SEMANTIC_ERROR: i32 = 3;
+pub const EXIT_IO_ERROR: i32 = 4;
+pub const EXIT_USAGE_ERROR: i32 = 5;
+
+/// A fresh directory for a test, under the temporary directory of the target, with `files` in it
+/// given by their path in the directory and their contents.
+pub fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    dir
+}
+
+/// Runs the generator with `args` from `dir`, which the relative paths in them are relative to.
+pub fn run<I, S>(dir: &Path, args: I) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::new(env!("CARGO_BIN_EXE_cli-generator"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
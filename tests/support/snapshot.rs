@@ -0,0 +1,225 @@
+//! Snapshots of what the generator makes of the specs in `tests/fixtures`. A valid spec is
+//! snapshotted as the C++ generated with the default options in `<name>.expected.hpp`, and a spec
+//! that isn't as its diagnostics in `<name>.expected.txt`. Running the tests with
+//! `UPDATE_SNAPSHOTS=1` writes what was generated instead of comparing it.
+
+use std::fmt::Write;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use cli_generator::{check, generate, parse_spec, Diagnostic, GenOptions, Severity, Target};
+
+/// The lines of context kept around the changes of a diff.
+const CONTEXT: usize = 3;
+
+/// What a fixture turned into, along with where it's snapshotted.
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// The specs of `dir`, sorted by name so that the failures come in the same order every time.
+pub fn fixtures(dir: &Path) -> Vec<PathBuf> {
+    let mut fixtures = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("can't read {}: {err}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "cli"))
+        .collect::<Vec<_>>();
+    fixtures.sort();
+
+    fixtures
+}
+
+/// Parses, checks and generates the C++ of `fixture` in process.
+pub fn snapshot(fixture: &Path) -> Snapshot {
+    let source = fs::read_to_string(fixture).unwrap();
+    let diagnostics = match parse_spec(&source) {
+        Ok(spec) => match check(&spec) {
+            Ok(metadata) => {
+                let files = generate(&spec, &metadata, Target::Cpp, &GenOptions::default())
+                    .unwrap_or_else(|err| panic!("{}: {err}", fixture.display()));
+                return Snapshot {
+                    path: fixture.with_extension("expected.hpp"),
+                    contents: files[0].contents.clone(),
+                };
+            }
+            Err(diagnostics) => diagnostics.into_vec(),
+        },
+        Err(diagnostics) => diagnostics.into_vec(),
+    };
+
+    Snapshot {
+        path: fixture.with_extension("expected.txt"),
+        contents: render(&source, &diagnostics),
+    }
+}
+
+/// Compares `snapshot` against the one checked in, or writes it over when `UPDATE_SNAPSHOTS` is
+/// set. Returns what's wrong with it otherwise.
+pub fn compare(fixture: &Path, snapshot: &Snapshot) -> Option<String> {
+    // A spec that used to be valid and now isn't, or the other way around, is snapshotted in a
+    // file with the other extension.
+    let stale = ["expected.hpp", "expected.txt"]
+        .map(|extension| fixture.with_extension(extension))
+        .into_iter()
+        .find(|path| *path != snapshot.path && path.exists());
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|value| value == "1") {
+        fs::write(&snapshot.path, &snapshot.contents).unwrap();
+        if let Some(stale) = stale {
+            fs::remove_file(stale).unwrap();
+        }
+        return None;
+    }
+
+    if let Some(stale) = stale {
+        return Some(format!(
+            "{} was expected, but {} was generated",
+            stale.display(),
+            snapshot.path.display()
+        ));
+    }
+    let Ok(expected) = fs::read_to_string(&snapshot.path) else {
+        return Some(format!(
+            "{} is missing, run the tests with UPDATE_SNAPSHOTS=1 to write it",
+            snapshot.path.display()
+        ));
+    };
+    if expected == snapshot.contents {
+        return None;
+    }
+
+    Some(format!(
+        "{} doesn't match what was generated:\n{}",
+        snapshot.path.display(),
+        diff(&expected, &snapshot.contents)
+    ))
+}
+
+/// The lines of `expected` and `actual` that differ, as `-` and `+` lines, with a few lines of
+/// context around them. The lines are numbered after `expected`.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    // The length of the longest common subsequence of `expected[i..]` and `actual[j..]`.
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    // Every line along with its number in `expected`, and whether it was removed or added.
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push((i + 1, ' ', expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            lines.push((i + 1, '-', expected[i]));
+            i += 1;
+        } else {
+            lines.push((i + 1, '+', actual[j]));
+            j += 1;
+        }
+    }
+
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, kind, _))| *kind != ' ')
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for index in changed {
+        let hunk = index.saturating_sub(CONTEXT)..(index + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if last.end >= hunk.start => last.end = hunk.end,
+            _ => hunks.push(hunk),
+        }
+    }
+
+    let mut diff = String::new();
+    for hunk in hunks {
+        writeln!(diff, "@@ line {} @@", lines[hunk.start].0).unwrap();
+        for (line, kind, text) in &lines[hunk] {
+            match kind {
+                '+' => writeln!(diff, "+      | {text}").unwrap(),
+                _ => writeln!(diff, "{kind}{line:>5} | {text}").unwrap(),
+            }
+        }
+    }
+
+    diff
+}
+
+/// Renders the diagnostics like the binary does, with only the line each of them points at. chic
+/// isn't used so that the snapshots don't change along with its layout.
+fn render(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut rendered = String::new();
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        writeln!(rendered, "{severity}: {}", diagnostic.label).unwrap();
+        annotate(
+            &mut rendered,
+            source,
+            &diagnostic.span,
+            '^',
+            &diagnostic.message,
+        );
+        if let Some((span, message)) = diagnostic.info.as_deref() {
+            annotate(
+                &mut rendered,
+                source,
+                span,
+                '-',
+                &format!("info: {message}"),
+            );
+        }
+        if let Some(help) = &diagnostic.help {
+            writeln!(rendered, "  = help: {help}").unwrap();
+        }
+        rendered.push('\n');
+    }
+
+    rendered
+}
+
+/// Writes the line `span` starts on, underlined up to the end of the span or of the line.
+fn annotate(rendered: &mut String, source: &str, span: &Range<usize>, marker: char, message: &str) {
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |newline| start + newline);
+    let line = &source[line_start..line_end];
+    let number = source[..line_start].matches('\n').count() + 1;
+    let column = source[line_start..start].chars().count();
+    let width = source[start..span.end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    writeln!(rendered, "  --> {number}:{}", column + 1).unwrap();
+    writeln!(rendered, "   |").unwrap();
+    writeln!(rendered, "{number:>2} | {line}").unwrap();
+    writeln!(
+        rendered,
+        "   | {}{} {message}",
+        " ".repeat(column),
+        marker.to_string().repeat(width)
+    )
+    .unwrap();
+}
@@ -0,0 +1,54 @@
+//! Checks what `--quiet` and `--verbose` print about the generation of several inputs.
+
+mod support;
+
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use support::{run, temp_dir};
+
+const SPEC: &str = "#[main] struct Cli { #[short, long] port: u16, #[long] host: string }";
+
+/// A fresh directory for a test with two specs.
+fn specs_dir(name: &str) -> PathBuf {
+    temp_dir(name, &[("server.cli", SPEC), ("client.cli", SPEC)])
+}
+
+fn generate(dir: &Path, verbosity: &[&str]) -> Output {
+    let inputs = ["-i", "server.cli", "-i", "client.cli", "--out-dir", "out"];
+    run(dir, verbosity.iter().chain(&inputs))
+}
+
+#[test]
+fn quiet_leaves_out_the_status_of_every_input() {
+    let dir = specs_dir("quiet");
+
+    let stderr = String::from_utf8(generate(&dir, &[]).stderr).unwrap();
+    assert_eq!(stderr, "server.cli: ok\nclient.cli: ok\n");
+
+    let result = generate(&dir, &["--quiet"]);
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stderr).unwrap(), "");
+}
+
+#[test]
+fn verbose_reports_every_phase_of_every_input() {
+    let dir = specs_dir("verbose");
+
+    let stderr = String::from_utf8(generate(&dir, &["-v"]).stderr).unwrap();
+    for input in ["server.cli", "client.cli"] {
+        for message in ["parsed in ", "checked in ", "generated in "] {
+            assert!(stderr.contains(&format!("{input}: {message}")), "{stderr}");
+        }
+        assert!(stderr.contains(&format!("{input}: 1 structs, 2 fields, 3 options\n")));
+        assert!(stderr.contains(&format!("{input}: wrote ")), "{stderr}");
+        assert!(stderr.contains(&format!("{input}: ok\n")));
+    }
+
+    // Outputs that are up to date are not written again.
+    let stderr = String::from_utf8(generate(&dir, &["-v"]).stderr).unwrap();
+    assert!(
+        stderr.contains("server.cli: out/server.h is up to date\n"),
+        "{stderr}"
+    );
+}